@@ -9,7 +9,9 @@ use assets::*;
 use gpui::{App, AppContext as _, Application, KeyBinding, actions};
 use gpui_component::{ActiveTheme as _, Root, theme};
 use themes::*;
-use tracing_subscriber::{EnvFilter, fmt, layer::SubscriberExt as _, util::SubscriberInitExt as _};
+use tracing_subscriber::{
+    EnvFilter, Layer as _, fmt, layer::SubscriberExt as _, util::SubscriberInitExt as _,
+};
 use window::*;
 use workspace::*;
 
@@ -26,9 +28,11 @@ fn init_logging() {
         EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("warn"))
     };
 
+    // The filter applies to log output only, so server notices are still
+    // captured for the Messages tab
     tracing_subscriber::registry()
-        .with(fmt::layer().with_target(true))
-        .with(filter)
+        .with(fmt::layer().with_target(true).with_filter(filter))
+        .with(services::notice_layer())
         .init();
 }
 
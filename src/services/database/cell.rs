@@ -0,0 +1,706 @@
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime, NaiveTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use sqlx::error::BoxDynError;
+use sqlx::postgres::types::{Oid, PgInterval, PgMoney, PgRange, PgTimeTz};
+use sqlx::postgres::{PgTypeInfo, PgTypeKind, PgValueFormat, PgValueRef};
+use sqlx::types::Json;
+use sqlx::{Decode, Postgres, Type, TypeInfo, ValueRef};
+use uuid::Uuid;
+
+/// Fractional digits used when rendering `money` values.
+/// Postgres sends money as a scaled integer without the locale, and nearly
+/// every locale uses two digits.
+const MONEY_FRAC_DIGITS: u32 = 2;
+
+/// A decoded Postgres value
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "value", rename_all = "snake_case")]
+pub enum CellValue {
+    #[default]
+    Null,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    /// `numeric` and `money`, kept as text so no precision is lost
+    Numeric(String),
+    Text(String),
+    Json(JsonValue),
+    Bytes(Vec<u8>),
+    Uuid(Uuid),
+    Date(NaiveDate),
+    Time(NaiveTime),
+    Timestamp(NaiveDateTime),
+    TimestampTz(DateTime<Utc>),
+    Interval {
+        months: i32,
+        days: i32,
+        microseconds: i64,
+    },
+    Array(Vec<CellValue>),
+}
+
+impl CellValue {
+    pub fn is_null(&self) -> bool {
+        matches!(self, CellValue::Null)
+    }
+
+    /// Text form of the value, following the Postgres output format where
+    /// it matters (arrays, intervals, bytea, special floats)
+    pub fn to_text(&self) -> String {
+        match self {
+            CellValue::Null => "NULL".to_string(),
+            CellValue::Bool(v) => v.to_string(),
+            CellValue::Int(v) => v.to_string(),
+            CellValue::Float(v) => format_float(*v),
+            CellValue::Numeric(v) | CellValue::Text(v) => v.clone(),
+            CellValue::Json(v) => v.to_string(),
+            CellValue::Bytes(v) => format!("\\x{}", hex::encode(v)),
+            CellValue::Uuid(v) => v.to_string(),
+            CellValue::Date(v) => v.to_string(),
+            CellValue::Time(v) => v.to_string(),
+            CellValue::Timestamp(v) => v.to_string(),
+            CellValue::TimestampTz(v) => format!("{}+00", v.naive_utc()),
+            CellValue::Interval {
+                months,
+                days,
+                microseconds,
+            } => format_interval(*months, *days, *microseconds),
+            CellValue::Array(items) => format_array(items),
+        }
+    }
+
+    /// JSON form of the value used by the exporters
+    pub fn to_json(&self) -> JsonValue {
+        match self {
+            CellValue::Null => JsonValue::Null,
+            CellValue::Bool(v) => JsonValue::from(*v),
+            CellValue::Int(v) => JsonValue::from(*v),
+            CellValue::Float(v) => serde_json::Number::from_f64(*v)
+                .map(JsonValue::Number)
+                .unwrap_or_else(|| JsonValue::String(format_float(*v))),
+            CellValue::Json(v) => v.clone(),
+            CellValue::TimestampTz(v) => JsonValue::String(v.to_rfc3339()),
+            CellValue::Array(items) => {
+                JsonValue::Array(items.iter().map(CellValue::to_json).collect())
+            }
+            _ => JsonValue::String(self.to_text()),
+        }
+    }
+}
+
+/// Decode a raw column value into a [`CellValue`].
+///
+/// Values are dispatched on their Postgres type; domains resolve to their
+/// base type and enums decode as text. Types without a dedicated decoder
+/// fall back to their text form when it is readable, or to raw bytes.
+pub fn decode_value(value: PgValueRef<'_>) -> CellValue {
+    if value.is_null() {
+        return CellValue::Null;
+    }
+
+    let type_info = value.type_info().into_owned();
+    match decode_typed(value.clone(), &type_info) {
+        Ok(decoded) => decoded,
+        Err(e) => {
+            tracing::debug!("Falling back to raw value for {}: {}", type_info.name(), e);
+            fallback_value(&value)
+        }
+    }
+}
+
+fn decode_typed(value: PgValueRef<'_>, type_info: &PgTypeInfo) -> Result<CellValue, BoxDynError> {
+    match type_info.kind() {
+        PgTypeKind::Domain(base) => return decode_typed(value, base),
+        PgTypeKind::Enum(_) => return Ok(CellValue::Text(decode::<String>(value)?)),
+        PgTypeKind::Array(element) => return decode_array(value, element),
+        _ => {}
+    }
+
+    let decoded = match type_info.name() {
+        "BOOL" => CellValue::Bool(decode(value)?),
+        "INT2" => CellValue::Int(decode::<i16>(value)?.into()),
+        "INT4" => CellValue::Int(decode::<i32>(value)?.into()),
+        "INT8" => CellValue::Int(decode(value)?),
+        "OID" => CellValue::Int(decode::<Oid>(value)?.0.into()),
+        "FLOAT4" => CellValue::Float(widen_float4(decode(value)?)),
+        "FLOAT8" => CellValue::Float(decode(value)?),
+        "NUMERIC" => CellValue::Numeric(decode_numeric(&value)?),
+        "MONEY" => CellValue::Numeric(
+            decode::<PgMoney>(value)?
+                .to_decimal(MONEY_FRAC_DIGITS)
+                .to_string(),
+        ),
+        "TEXT" | "VARCHAR" | "CHAR" | "NAME" | "CITEXT" | "UNKNOWN" => {
+            CellValue::Text(decode(value)?)
+        }
+        "\"CHAR\"" => CellValue::Text(char::from(decode::<i8>(value)? as u8).to_string()),
+        "JSON" | "JSONB" => CellValue::Json(decode_json(&value, type_info)?),
+        "BYTEA" => CellValue::Bytes(decode(value)?),
+        "UUID" => CellValue::Uuid(decode(value)?),
+        "DATE" => CellValue::Date(decode(value)?),
+        "TIME" => CellValue::Time(decode(value)?),
+        "TIMETZ" => {
+            let timetz: PgTimeTz<NaiveTime, FixedOffset> = decode(value)?;
+            CellValue::Text(format!("{}{}", timetz.time, timetz.offset))
+        }
+        "TIMESTAMP" => CellValue::Timestamp(decode(value)?),
+        "TIMESTAMPTZ" => CellValue::TimestampTz(decode(value)?),
+        "INTERVAL" => {
+            let interval: PgInterval = decode(value)?;
+            CellValue::Interval {
+                months: interval.months,
+                days: interval.days,
+                microseconds: interval.microseconds,
+            }
+        }
+        "INET" | "CIDR" => CellValue::Text(decode_inet(&value)?),
+        "MACADDR" | "MACADDR8" => CellValue::Text(decode_macaddr(&value)?),
+        "INT4RANGE" => CellValue::Text(decode::<PgRange<i32>>(value)?.to_string()),
+        "INT8RANGE" => CellValue::Text(decode::<PgRange<i64>>(value)?.to_string()),
+        "NUMRANGE" => CellValue::Text(decode::<PgRange<rust_decimal::Decimal>>(value)?.to_string()),
+        "DATERANGE" => CellValue::Text(decode::<PgRange<NaiveDate>>(value)?.to_string()),
+        "TSRANGE" => CellValue::Text(decode::<PgRange<NaiveDateTime>>(value)?.to_string()),
+        "TSTZRANGE" => CellValue::Text(decode::<PgRange<DateTime<Utc>>>(value)?.to_string()),
+        "VOID" => CellValue::Null,
+        _ => fallback_value(&value),
+    };
+
+    Ok(decoded)
+}
+
+fn decode_array(value: PgValueRef<'_>, element: &PgTypeInfo) -> Result<CellValue, BoxDynError> {
+    match element.kind() {
+        PgTypeKind::Domain(base) => return decode_array(value, base),
+        PgTypeKind::Enum(_) => return array_of::<String>(value, CellValue::Text),
+        _ => {}
+    }
+
+    // sqlx only decodes one-dimensional arrays indexed from 1, and numerics
+    // through rust_decimal, which has no NaN and at most 28 digits
+    if value.format() == PgValueFormat::Binary {
+        let bytes = value.as_bytes()?;
+        let dims = array_dims(bytes)?;
+        if dims.len() > 1
+            || dims.iter().any(|&(_, lower)| lower != 1)
+            || element.name() == "NUMERIC"
+        {
+            return decode_binary_array(bytes, element.name());
+        }
+    }
+
+    match element.name() {
+        "BOOL" => array_of(value, CellValue::Bool),
+        "INT2" => array_of::<i16>(value, |v| CellValue::Int(v.into())),
+        "INT4" => array_of::<i32>(value, |v| CellValue::Int(v.into())),
+        "INT8" => array_of(value, CellValue::Int),
+        "FLOAT4" => array_of(value, |v| CellValue::Float(widen_float4(v))),
+        "FLOAT8" => array_of(value, CellValue::Float),
+        "NUMERIC" => {
+            array_of::<rust_decimal::Decimal>(value, |v| CellValue::Numeric(v.to_string()))
+        }
+        "TEXT" | "VARCHAR" | "CHAR" | "NAME" | "CITEXT" => array_of(value, CellValue::Text),
+        "JSON" | "JSONB" => array_of::<Json<JsonValue>>(value, |v| CellValue::Json(v.0)),
+        "BYTEA" => array_of(value, CellValue::Bytes),
+        "UUID" => array_of(value, CellValue::Uuid),
+        "DATE" => array_of(value, CellValue::Date),
+        "TIME" => array_of(value, CellValue::Time),
+        "TIMESTAMP" => array_of(value, CellValue::Timestamp),
+        "TIMESTAMPTZ" => array_of(value, CellValue::TimestampTz),
+        "INTERVAL" => array_of::<PgInterval>(value, |v| CellValue::Interval {
+            months: v.months,
+            days: v.days,
+            microseconds: v.microseconds,
+        }),
+        name => Err(format!("unsupported array element type {}", name).into()),
+    }
+}
+
+fn array_of<T>(
+    value: PgValueRef<'_>,
+    convert: impl Fn(T) -> CellValue,
+) -> Result<CellValue, BoxDynError>
+where
+    T: for<'a> Decode<'a, Postgres> + Type<Postgres>,
+{
+    let items: Vec<Option<T>> = Decode::decode(value)?;
+    Ok(CellValue::Array(
+        items
+            .into_iter()
+            .map(|item| item.map(&convert).unwrap_or(CellValue::Null))
+            .collect(),
+    ))
+}
+
+/// Length and lower bound of each dimension of a binary array
+fn array_dims(bytes: &[u8]) -> Result<Vec<(usize, i32)>, BoxDynError> {
+    let int = |ix: usize| -> Result<i32, BoxDynError> {
+        bytes
+            .get(ix * 4..ix * 4 + 4)
+            .map(|b| i32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+            .ok_or_else(|| "truncated array header".into())
+    };
+
+    // Dimension count, has-nulls flag and element type, then the dimensions
+    let ndim = usize::try_from(int(0)?)?;
+    (0..ndim)
+        .map(|dim| Ok((usize::try_from(int(3 + dim * 2)?)?, int(4 + dim * 2)?)))
+        .collect()
+}
+
+/// Binary arrays of any shape with common element types, nested one
+/// [`CellValue::Array`] per dimension
+fn decode_binary_array(bytes: &[u8], element: &str) -> Result<CellValue, BoxDynError> {
+    let dims = array_dims(bytes)?;
+    let mut rest = bytes
+        .get((3 + dims.len() * 2) * 4..)
+        .ok_or("truncated array header")?;
+    let count = if dims.is_empty() {
+        0
+    } else {
+        dims.iter().map(|&(len, _)| len).product()
+    };
+
+    let mut items = Vec::with_capacity(count);
+    for _ in 0..count {
+        let (len, tail) = rest
+            .split_first_chunk::<4>()
+            .ok_or("truncated array element")?;
+        let len = i32::from_be_bytes(*len);
+        if len < 0 {
+            items.push(CellValue::Null);
+            rest = tail;
+            continue;
+        }
+        let (raw, tail) = tail
+            .split_at_checked(len as usize)
+            .ok_or("truncated array element")?;
+        items.push(decode_binary_element(raw, element)?);
+        rest = tail;
+    }
+
+    let lengths: Vec<usize> = dims.iter().map(|&(len, _)| len).collect();
+    Ok(nest(items, &lengths))
+}
+
+fn nest(items: Vec<CellValue>, lengths: &[usize]) -> CellValue {
+    match lengths {
+        [] | [_] => CellValue::Array(items),
+        [_, inner @ ..] => {
+            let size = inner.iter().product::<usize>().max(1);
+            CellValue::Array(
+                items
+                    .chunks(size)
+                    .map(|chunk| nest(chunk.to_vec(), inner))
+                    .collect(),
+            )
+        }
+    }
+}
+
+fn decode_binary_element(raw: &[u8], element: &str) -> Result<CellValue, BoxDynError> {
+    let postgres_epoch = || {
+        NaiveDate::from_ymd_opt(2000, 1, 1)
+            .and_then(|date| date.and_hms_opt(0, 0, 0))
+            .ok_or("invalid epoch")
+    };
+    let timestamp = || -> Result<NaiveDateTime, BoxDynError> {
+        let micros = i64::from_be_bytes(raw.try_into()?);
+        postgres_epoch()?
+            .checked_add_signed(chrono::Duration::microseconds(micros))
+            .ok_or_else(|| "timestamp out of range".into())
+    };
+
+    Ok(match element {
+        "BOOL" => CellValue::Bool(raw.first().is_some_and(|&b| b != 0)),
+        "INT2" => CellValue::Int(i16::from_be_bytes(raw.try_into()?).into()),
+        "INT4" => CellValue::Int(i32::from_be_bytes(raw.try_into()?).into()),
+        "INT8" => CellValue::Int(i64::from_be_bytes(raw.try_into()?)),
+        "FLOAT4" => CellValue::Float(widen_float4(f32::from_be_bytes(raw.try_into()?))),
+        "FLOAT8" => CellValue::Float(f64::from_be_bytes(raw.try_into()?)),
+        "NUMERIC" => CellValue::Numeric(numeric_text(raw)?),
+        "TEXT" | "VARCHAR" | "CHAR" | "NAME" | "CITEXT" => {
+            CellValue::Text(std::str::from_utf8(raw)?.to_string())
+        }
+        "UUID" => CellValue::Uuid(Uuid::from_slice(raw)?),
+        "TIMESTAMP" => CellValue::Timestamp(timestamp()?),
+        "TIMESTAMPTZ" => CellValue::TimestampTz(timestamp()?.and_utc()),
+        name => return Err(format!("unsupported array element type {}", name).into()),
+    })
+}
+
+/// `numeric` as Postgres prints it, including `NaN`, the infinities and
+/// values too precise for a decimal type
+fn decode_numeric(value: &PgValueRef<'_>) -> Result<String, BoxDynError> {
+    match value.format() {
+        PgValueFormat::Text => Ok(value.as_str()?.to_string()),
+        PgValueFormat::Binary => numeric_text(value.as_bytes()?),
+    }
+}
+
+/// Binary `numeric`: digit count, weight of the first digit, sign and
+/// display scale, then base 10000 digits
+fn numeric_text(raw: &[u8]) -> Result<String, BoxDynError> {
+    let word = |ix: usize| -> Result<u16, BoxDynError> {
+        raw.get(ix * 2..ix * 2 + 2)
+            .map(|b| u16::from_be_bytes([b[0], b[1]]))
+            .ok_or_else(|| "truncated numeric".into())
+    };
+    let ndigits = i32::from(word(0)?);
+    let weight = i32::from(word(1)? as i16);
+    let (sign, scale) = (word(2)?, usize::from(word(3)?));
+    match sign {
+        0xC000 => return Ok("NaN".to_string()),
+        0xD000 => return Ok("Infinity".to_string()),
+        0xF000 => return Ok("-Infinity".to_string()),
+        _ => {}
+    }
+    // Digit `ix` is worth 10000^(weight - ix); missing ones are zero
+    let digit = |ix: i32| -> Result<u16, BoxDynError> {
+        if (0..ndigits).contains(&ix) {
+            word(4 + ix as usize)
+        } else {
+            Ok(0)
+        }
+    };
+
+    let mut text = if sign == 0x4000 { "-" } else { "" }.to_string();
+    if weight < 0 {
+        text.push('0');
+    }
+    for ix in 0..=weight {
+        let group = digit(ix)?;
+        if ix == 0 {
+            text.push_str(&group.to_string());
+        } else {
+            text.push_str(&format!("{:04}", group));
+        }
+    }
+    if scale > 0 {
+        let mut fraction = String::new();
+        let mut ix = weight + 1;
+        while fraction.len() < scale {
+            fraction.push_str(&format!("{:04}", digit(ix)?));
+            ix += 1;
+        }
+        fraction.truncate(scale);
+        text.push('.');
+        text.push_str(&fraction);
+    }
+    Ok(text)
+}
+
+fn decode<'r, T: Decode<'r, Postgres>>(value: PgValueRef<'r>) -> Result<T, BoxDynError> {
+    T::decode(value)
+}
+
+/// JSON is decoded by hand so domains over `jsonb` still have the binary
+/// version byte stripped
+fn decode_json(value: &PgValueRef<'_>, type_info: &PgTypeInfo) -> Result<JsonValue, BoxDynError> {
+    let mut bytes = value.as_bytes()?;
+    if value.format() == PgValueFormat::Binary && type_info.name() == "JSONB" {
+        match bytes.split_first() {
+            Some((1, rest)) => bytes = rest,
+            _ => return Err("unsupported jsonb format version".into()),
+        }
+    }
+    Ok(serde_json::from_slice(bytes)?)
+}
+
+/// Binary `inet`/`cidr`: family, netmask bits, is_cidr flag, address length,
+/// then the address bytes
+fn decode_inet(value: &PgValueRef<'_>) -> Result<String, BoxDynError> {
+    if value.format() == PgValueFormat::Text {
+        return Ok(value.as_str()?.to_string());
+    }
+
+    let bytes = value.as_bytes()?;
+    let [_family, bits, is_cidr, len, address @ ..] = bytes else {
+        return Err("invalid inet value".into());
+    };
+
+    let (ip, max_bits): (std::net::IpAddr, u8) = match (*len, address) {
+        (4, &[a, b, c, d]) => (std::net::Ipv4Addr::new(a, b, c, d).into(), 32),
+        (16, address) if address.len() == 16 => {
+            let mut octets = [0u8; 16];
+            octets.copy_from_slice(address);
+            (std::net::Ipv6Addr::from(octets).into(), 128)
+        }
+        _ => return Err("invalid inet address length".into()),
+    };
+
+    // Postgres omits the prefix for host addresses
+    if *is_cidr == 0 && *bits == max_bits {
+        Ok(ip.to_string())
+    } else {
+        Ok(format!("{}/{}", ip, bits))
+    }
+}
+
+fn decode_macaddr(value: &PgValueRef<'_>) -> Result<String, BoxDynError> {
+    if value.format() == PgValueFormat::Text {
+        return Ok(value.as_str()?.to_string());
+    }
+
+    Ok(value
+        .as_bytes()?
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect::<Vec<_>>()
+        .join(":"))
+}
+
+/// Readable text is kept as text (enums, xml, extension types sent as text);
+/// anything else is surfaced as bytes rather than dropped
+fn fallback_value(value: &PgValueRef<'_>) -> CellValue {
+    let Ok(bytes) = value.as_bytes() else {
+        return CellValue::Null;
+    };
+
+    match std::str::from_utf8(bytes) {
+        Ok(text)
+            if value.format() == PgValueFormat::Text
+                || !text.chars().any(|c| c.is_control() && !c.is_whitespace()) =>
+        {
+            CellValue::Text(text.to_string())
+        }
+        _ => CellValue::Bytes(bytes.to_vec()),
+    }
+}
+
+/// Widens a `real` through its shortest decimal form, so `0.1::real` stays
+/// 0.1 rather than becoming 0.10000000149011612
+fn widen_float4(v: f32) -> f64 {
+    v.to_string().parse().unwrap_or(v.into())
+}
+
+fn format_float(v: f64) -> String {
+    if v.is_nan() {
+        "NaN".to_string()
+    } else if v == f64::INFINITY {
+        "Infinity".to_string()
+    } else if v == f64::NEG_INFINITY {
+        "-Infinity".to_string()
+    } else {
+        v.to_string()
+    }
+}
+
+fn format_interval(months: i32, days: i32, microseconds: i64) -> String {
+    fn unit(value: i64, name: &str) -> String {
+        format!(
+            "{} {}{}",
+            value,
+            name,
+            if value.abs() == 1 { "" } else { "s" }
+        )
+    }
+
+    let mut parts = Vec::new();
+    let (years, months) = (months / 12, months % 12);
+
+    if years != 0 {
+        parts.push(unit(years.into(), "year"));
+    }
+    if months != 0 {
+        parts.push(unit(months.into(), "mon"));
+    }
+    if days != 0 {
+        parts.push(unit(days.into(), "day"));
+    }
+
+    if microseconds != 0 || parts.is_empty() {
+        let sign = if microseconds < 0 { "-" } else { "" };
+        let total = microseconds.unsigned_abs();
+        let (seconds, fraction) = (total / 1_000_000, total % 1_000_000);
+
+        let mut time = format!(
+            "{}{:02}:{:02}:{:02}",
+            sign,
+            seconds / 3600,
+            seconds % 3600 / 60,
+            seconds % 60
+        );
+        if fraction != 0 {
+            time.push_str(format!(".{:06}", fraction).trim_end_matches('0'));
+        }
+        parts.push(time);
+    }
+
+    parts.join(" ")
+}
+
+fn format_array(items: &[CellValue]) -> String {
+    let elements: Vec<String> = items
+        .iter()
+        .map(|item| match item {
+            CellValue::Null => "NULL".to_string(),
+            CellValue::Array(_) => item.to_text(),
+            _ => quote_array_element(&item.to_text()),
+        })
+        .collect();
+
+    format!("{{{}}}", elements.join(","))
+}
+
+fn quote_array_element(text: &str) -> String {
+    let needs_quotes = text.is_empty()
+        || text.eq_ignore_ascii_case("NULL")
+        || text
+            .chars()
+            .any(|c| matches!(c, ',' | '{' | '}' | '"' | '\\') || c.is_whitespace());
+
+    if needs_quotes {
+        format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+    } else {
+        text.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_interval_text() {
+        assert_eq!(format_interval(0, 0, 0), "00:00:00");
+        assert_eq!(format_interval(14, 3, 0), "1 year 2 mons 3 days");
+        assert_eq!(format_interval(0, 1, 3_723_500_000), "1 day 01:02:03.5");
+        assert_eq!(format_interval(0, 0, -90_000_000), "-00:01:30");
+    }
+
+    /// A binary array of the given dimensions, `None` for NULL elements
+    fn binary_array(dims: &[(i32, i32)], elements: &[Option<&[u8]>]) -> Vec<u8> {
+        let mut bytes = vec![];
+        for int in [dims.len() as i32, 0, 0] {
+            bytes.extend(int.to_be_bytes());
+        }
+        for &(len, lower) in dims {
+            bytes.extend(len.to_be_bytes());
+            bytes.extend(lower.to_be_bytes());
+        }
+        for element in elements {
+            match element {
+                Some(raw) => {
+                    bytes.extend((raw.len() as i32).to_be_bytes());
+                    bytes.extend(*raw);
+                }
+                None => bytes.extend((-1i32).to_be_bytes()),
+            }
+        }
+        bytes
+    }
+
+    #[test]
+    fn test_decode_binary_array() {
+        let ints = [1i32, 2, 3, 4].map(i32::to_be_bytes);
+        let bytes = binary_array(
+            &[(2, 1), (2, 1)],
+            &[Some(&ints[0]), Some(&ints[1]), Some(&ints[2]), None],
+        );
+        assert_eq!(
+            decode_binary_array(&bytes, "INT4").unwrap().to_text(),
+            "{{1,2},{3,NULL}}"
+        );
+
+        // Indexed from 0, e.g. '[0:1]={a,b c}'::text[]
+        let bytes = binary_array(&[(2, 0)], &[Some(b"a"), Some(b"b c")]);
+        assert_eq!(
+            decode_binary_array(&bytes, "TEXT").unwrap().to_text(),
+            "{a,\"b c\"}"
+        );
+
+        let uuid = Uuid::from_u128(1);
+        let micros = 86_400_000_000i64.to_be_bytes();
+        let bytes = binary_array(&[(1, 0)], &[Some(uuid.as_bytes())]);
+        assert_eq!(
+            decode_binary_array(&bytes, "UUID").unwrap(),
+            CellValue::Array(vec![CellValue::Uuid(uuid)])
+        );
+        let bytes = binary_array(&[(1, 0)], &[Some(&micros)]);
+        assert_eq!(
+            decode_binary_array(&bytes, "TIMESTAMP").unwrap().to_text(),
+            "{\"2000-01-02 00:00:00\"}"
+        );
+        let bytes = binary_array(&[(2, 0)], &[Some(&[1]), Some(&[0])]);
+        assert_eq!(
+            decode_binary_array(&bytes, "BOOL").unwrap().to_text(),
+            "{true,false}"
+        );
+    }
+
+    #[test]
+    fn test_numeric_text() {
+        // ndigits, weight, sign, dscale, then base 10000 digits
+        let numeric = |words: &[u16]| -> Vec<u8> {
+            words.iter().flat_map(|word| word.to_be_bytes()).collect()
+        };
+        assert_eq!(
+            numeric_text(&numeric(&[2, 1, 0, 2, 12, 3456])).unwrap(),
+            "123456.00"
+        );
+        assert_eq!(
+            numeric_text(&numeric(&[2, 0, 0x4000, 3, 1, 5000])).unwrap(),
+            "-1.500"
+        );
+        assert_eq!(
+            numeric_text(&numeric(&[1, 0xFFFE, 0, 6, 1200])).unwrap(),
+            "0.000012"
+        );
+        assert_eq!(numeric_text(&numeric(&[0, 0, 0, 0])).unwrap(), "0");
+        assert_eq!(numeric_text(&numeric(&[0, 0, 0xC000, 0])).unwrap(), "NaN");
+        assert_eq!(
+            numeric_text(&numeric(&[0, 0, 0xF000, 0])).unwrap(),
+            "-Infinity"
+        );
+
+        // 40 digits, beyond what rust_decimal holds
+        let digits = [1234, 5678, 9012, 3456, 7890, 1234, 5678, 9012, 3456, 7890];
+        let mut words = vec![10, 4, 0, 20];
+        words.extend(digits);
+        assert_eq!(
+            numeric_text(&numeric(&words)).unwrap(),
+            "12345678901234567890.12345678901234567890"
+        );
+    }
+
+    #[test]
+    fn test_real_keeps_its_digits() {
+        assert_eq!(CellValue::Float(widen_float4(0.1)).to_text(), "0.1");
+        assert_eq!(CellValue::Float(widen_float4(-2.5e-3)).to_text(), "-0.0025");
+        assert!(widen_float4(f32::NAN).is_nan());
+        assert_eq!(widen_float4(f32::INFINITY), f64::INFINITY);
+    }
+
+    #[test]
+    fn test_array_text() {
+        let array = CellValue::Array(vec![
+            CellValue::Text("plain".to_string()),
+            CellValue::Text("with space".to_string()),
+            CellValue::Text("null".to_string()),
+            CellValue::Null,
+            CellValue::Text("say \"hi\"".to_string()),
+        ]);
+        assert_eq!(
+            array.to_text(),
+            r#"{plain,"with space","null",NULL,"say \"hi\""}"#
+        );
+    }
+
+    #[test]
+    fn test_json_export_keeps_types() {
+        assert_eq!(CellValue::Int(42).to_json(), serde_json::json!(42));
+        assert_eq!(
+            CellValue::Float(f64::NAN).to_json(),
+            serde_json::json!("NaN")
+        );
+        assert_eq!(
+            CellValue::Numeric("1.10".to_string()).to_json(),
+            serde_json::json!("1.10")
+        );
+        assert_eq!(
+            CellValue::Bytes(vec![0xde, 0xad]).to_json(),
+            serde_json::json!("\\xdead")
+        );
+    }
+}
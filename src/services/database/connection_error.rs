@@ -0,0 +1,187 @@
+//! Why a connection could not be opened, sorted into the cases the UI
+//! explains differently.
+
+use std::fmt;
+use std::io;
+
+/// A failed connect, with a message meant for the user
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConnectionError {
+    /// The server asked for a password and the keyring has none for the
+    /// saved connection
+    MissingPassword,
+    /// The keyring could not be read
+    Keyring(String),
+    /// The server rejected the user or the password
+    Authentication(String),
+    /// The host name did not resolve
+    HostNotFound(String),
+    /// Nothing accepted the connection at the host and port
+    Unreachable(String),
+    /// The SSL handshake failed; the message says what to check
+    Tls(String),
+    /// The server did not answer in time
+    Timeout,
+    /// The SSH tunnel could not be opened
+    Tunnel(String),
+    /// Anything else, e.g. a database that doesn't exist
+    Other(String),
+}
+
+impl ConnectionError {
+    /// Sorts a connect error from sqlx. `hostname` is the server's name as
+    /// configured, which DNS and certificate errors refer to.
+    pub fn from_sqlx(error: sqlx::Error, hostname: &str) -> Self {
+        match error {
+            sqlx::Error::Database(e) => match e.code().as_deref() {
+                // invalid_password, invalid_authorization_specification
+                Some("28P01") | Some("28000") => Self::Authentication(e.message().to_string()),
+                _ => Self::Other(e.message().to_string()),
+            },
+            sqlx::Error::Tls(e) => Self::Tls(tls_failure(&e.to_string(), hostname)),
+            sqlx::Error::PoolTimedOut => Self::Timeout,
+            sqlx::Error::Io(e) => Self::from_io(&e, hostname),
+            e => Self::Other(e.to_string()),
+        }
+    }
+
+    fn from_io(error: &io::Error, hostname: &str) -> Self {
+        match error.kind() {
+            io::ErrorKind::TimedOut => Self::Timeout,
+            io::ErrorKind::ConnectionRefused
+            | io::ErrorKind::HostUnreachable
+            | io::ErrorKind::NetworkUnreachable => Self::Unreachable(error.to_string()),
+            _ => {
+                // Resolver errors carry no kind of their own; these are the
+                // glibc, macOS and Windows messages
+                let message = error.to_string().to_lowercase();
+                if [
+                    "failed to lookup address",
+                    "name or service not known",
+                    "nodename nor servname",
+                    "no such host",
+                ]
+                .iter()
+                .any(|needle| message.contains(needle))
+                {
+                    Self::HostNotFound(hostname.to_string())
+                } else {
+                    Self::Unreachable(error.to_string())
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Display for ConnectionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingPassword => {
+                write!(
+                    f,
+                    "The server asked for a password, and none is saved for this connection."
+                )
+            }
+            Self::Keyring(message) => write!(f, "Failed to read the keyring: {}", message),
+            Self::Authentication(message) => write!(f, "Authentication failed: {}", message),
+            Self::HostNotFound(hostname) => {
+                write!(f, "The host '{}' could not be found.", hostname)
+            }
+            Self::Unreachable(message) => write!(
+                f,
+                "The server could not be reached ({}). Check the host and port, and that it is running.",
+                message
+            ),
+            Self::Tls(message) => write!(f, "{}", message),
+            Self::Timeout => write!(f, "The server did not answer in time."),
+            Self::Tunnel(message) => write!(f, "SSH tunnel failed: {}", message),
+            Self::Other(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for ConnectionError {}
+
+/// Explains an SSL handshake failure, naming the usual fix for a server
+/// certificate that can't be verified
+fn tls_failure(message: &str, hostname: &str) -> String {
+    // native-tls reports OpenSSL, Security.framework and SChannel messages
+    let lower = message.to_lowercase();
+    let hint = if ["hostname mismatch", "principal name", "does not match"]
+        .iter()
+        .any(|needle| lower.contains(needle))
+    {
+        format!(
+            "The server certificate is not valid for '{}'. Connect with a name the certificate lists, or use Verify CA.",
+            hostname
+        )
+    } else if [
+        "certificate verify failed",
+        "self-signed",
+        "self signed",
+        "issuer",
+        "not trusted",
+    ]
+    .iter()
+    .any(|needle| lower.contains(needle))
+    {
+        "The server certificate could not be verified. Choose the CA certificate that signed it, or use Require to encrypt without verifying.".to_string()
+    } else {
+        "The SSL handshake failed. Check the SSL mode and the certificate files.".to_string()
+    };
+
+    format!("{}\n{}", hint, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::database::{ConnectionSettings, DatabaseManager};
+    use sqlx::postgres::PgConnectOptions;
+
+    #[test]
+    fn test_classify_connect_errors() {
+        let refused = sqlx::Error::Io(io::Error::from(io::ErrorKind::ConnectionRefused));
+        assert!(matches!(
+            ConnectionError::from_sqlx(refused, "db"),
+            ConnectionError::Unreachable(_)
+        ));
+
+        let dns = sqlx::Error::Io(io::Error::other(
+            "failed to lookup address information: Name or service not known",
+        ));
+        assert_eq!(
+            ConnectionError::from_sqlx(dns, "db.internal"),
+            ConnectionError::HostNotFound("db.internal".to_string())
+        );
+
+        assert_eq!(
+            ConnectionError::from_sqlx(sqlx::Error::PoolTimedOut, "db"),
+            ConnectionError::Timeout
+        );
+
+        let tls = sqlx::Error::Tls(
+            "error:0A000086:SSL routines::certificate verify failed:self-signed certificate".into(),
+        );
+        let message = ConnectionError::from_sqlx(tls, "db").to_string();
+        assert!(message.starts_with("The server certificate could not be verified"));
+    }
+
+    #[test]
+    fn test_refused_connect_is_unreachable() {
+        // A port that was just free has nothing listening on it
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let options = PgConnectOptions::new().host("127.0.0.1").port(port);
+
+        let error = smol::block_on(DatabaseManager::test_connection_options(
+            options,
+            None,
+            &ConnectionSettings::default(),
+        ));
+        assert!(matches!(error, Err(ConnectionError::Unreachable(_))));
+    }
+}
@@ -0,0 +1,186 @@
+//! Pool sizing and session setup chosen per connection.
+//!
+//! `application_name` and `search_path` go out as startup options with the
+//! connect options. The role and the startup `SET` statements need a session,
+//! so they run in the pool's `after_connect` hook on every new connection.
+
+use serde::{Deserialize, Serialize};
+use sqlx::PgConnection;
+use sqlx::postgres::{PgConnectOptions, PgPoolOptions};
+use std::time::Duration;
+
+pub const DEFAULT_MAX_CONNECTIONS: u32 = 5;
+/// One connection runs the user's query or holds the open transaction, the
+/// other cancels it, loads result metadata and checks the connection is alive
+pub const MIN_CONNECTIONS: u32 = 2;
+pub const DEFAULT_ACQUIRE_TIMEOUT_SECS: u64 = 5;
+/// sqlx's default
+pub const DEFAULT_IDLE_TIMEOUT_SECS: u64 = 10 * 60;
+
+/// Advanced settings of a connection. `None` keeps the default; an idle
+/// timeout of `Some(0)` keeps idle connections open.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConnectionSettings {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_connections: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub acquire_timeout_secs: Option<u64>,
+    /// How long a pooled connection may sit unused before it is closed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub idle_timeout_secs: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub application_name: Option<String>,
+    /// Comma separated schemas, as written after `SET search_path TO`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub search_path: Option<String>,
+    /// Role to `SET ROLE` to after logging in
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub role: Option<String>,
+    /// `SET` statements run on every new connection, in order
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub startup_statements: Vec<String>,
+}
+
+impl ConnectionSettings {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Adds the startup options to a connection's options
+    pub fn connect_options(&self, mut options: PgConnectOptions) -> PgConnectOptions {
+        if let Some(name) = &self.application_name {
+            options = options.application_name(name);
+        }
+        if let Some(search_path) = &self.search_path {
+            options = options.options([("search_path", escape_option(search_path))]);
+        }
+        options
+    }
+
+    /// How long to wait for a connection, from the pool or a fresh connect.
+    /// A timeout of 0 would fail every connect, so it keeps the default.
+    pub(crate) fn acquire_timeout(&self) -> Duration {
+        Duration::from_secs(
+            self.acquire_timeout_secs
+                .filter(|secs| *secs > 0)
+                .unwrap_or(DEFAULT_ACQUIRE_TIMEOUT_SECS),
+        )
+    }
+
+    pub(crate) fn pool_options(&self) -> PgPoolOptions {
+        let idle_timeout = match self.idle_timeout_secs {
+            Some(0) => None,
+            secs => Some(Duration::from_secs(
+                secs.unwrap_or(DEFAULT_IDLE_TIMEOUT_SECS),
+            )),
+        };
+        PgPoolOptions::new()
+            .max_connections(
+                self.max_connections
+                    .unwrap_or(DEFAULT_MAX_CONNECTIONS)
+                    .max(MIN_CONNECTIONS),
+            )
+            .acquire_timeout(self.acquire_timeout())
+            .idle_timeout(idle_timeout)
+    }
+
+    /// Sets the role and runs the startup statements on a new connection
+    pub(crate) async fn apply(&self, conn: &mut PgConnection) -> Result<(), sqlx::Error> {
+        if let Some(role) = &self.role {
+            sqlx::query("SELECT set_config('role', $1, false)")
+                .bind(role)
+                .execute(&mut *conn)
+                .await?;
+        }
+        for statement in &self.startup_statements {
+            sqlx::query(statement).execute(&mut *conn).await?;
+        }
+        Ok(())
+    }
+}
+
+/// Splits startup statements at semicolons and line breaks outside quotes.
+/// Blank statements are skipped and only `SET` statements are accepted, so a
+/// typo can't run a write on every connect.
+pub fn parse_startup_statements(text: &str) -> Result<Vec<String>, String> {
+    let mut statements = vec![];
+    let mut quote = None;
+    let mut start = 0;
+    for (ix, c) in text.char_indices() {
+        match (quote, c) {
+            (None, '\'' | '"') => quote = Some(c),
+            (Some(open), c) if c == open => quote = None,
+            (None, ';' | '\n') => {
+                statements.push(&text[start..ix]);
+                start = ix + 1;
+            }
+            _ => {}
+        }
+    }
+    statements.push(&text[start..]);
+
+    statements
+        .into_iter()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(|line| {
+            let is_set = line
+                .split_whitespace()
+                .next()
+                .is_some_and(|word| word.eq_ignore_ascii_case("set"));
+            if is_set {
+                Ok(line.to_string())
+            } else {
+                Err(format!("Only SET statements can run on connect: {}", line))
+            }
+        })
+        .collect()
+}
+
+/// Startup option values are split on spaces unless they are escaped with
+/// a backslash
+fn escape_option(value: &str) -> String {
+    value.replace('\\', "\\\\").replace(' ', "\\ ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_startup_statements() {
+        assert_eq!(
+            parse_startup_statements("SET work_mem = '64MB';\n\n  set timezone TO 'UTC'  \n"),
+            Ok(vec![
+                "SET work_mem = '64MB'".to_string(),
+                "set timezone TO 'UTC'".to_string(),
+            ])
+        );
+        assert_eq!(
+            parse_startup_statements("SET search_path TO 'a;b'; SET \"my.opt\" = 'x\ny'"),
+            Ok(vec![
+                "SET search_path TO 'a;b'".to_string(),
+                "SET \"my.opt\" = 'x\ny'".to_string(),
+            ])
+        );
+        assert!(parse_startup_statements("SET a = 1; DELETE FROM audit").is_err());
+        assert!(parse_startup_statements("DELETE FROM audit").is_err());
+        assert!(parse_startup_statements("SETTLE").is_err());
+        assert_eq!(escape_option("app, public"), "app,\\ public");
+    }
+
+    #[test]
+    fn test_pool_keeps_a_spare_connection() {
+        let single = ConnectionSettings {
+            max_connections: Some(1),
+            ..Default::default()
+        };
+        assert_eq!(single.pool_options().get_max_connections(), MIN_CONNECTIONS);
+        assert_eq!(
+            ConnectionSettings::default()
+                .pool_options()
+                .get_max_connections(),
+            DEFAULT_MAX_CONNECTIONS
+        );
+    }
+}
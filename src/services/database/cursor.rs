@@ -0,0 +1,190 @@
+use anyhow::Result;
+use async_lock::Mutex;
+use sqlx::pool::PoolConnection;
+use sqlx::postgres::PgRow;
+use sqlx::{PgConnection, Postgres};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Weak};
+use tracing::Instrument as _;
+
+use super::manager::DatabaseManager;
+use super::notices::NoticeScope;
+use super::query::convert_rows;
+use super::types::{ResultPage, TableMetadata};
+
+/// Prefix of the server-side cursors backing the results grids
+const RESULT_CURSOR_NAME: &str = "pgui_result_cursor";
+
+/// Number of rows fetched for the first page and on each scroll
+pub const RESULT_PAGE_SIZE: usize = 1_000;
+
+/// An open `DECLARE ... CURSOR` whose remaining rows can still be fetched.
+#[derive(Debug)]
+pub(crate) struct ResultCursor {
+    pub name: String,
+    /// Connection holding the cursor's transaction, or `None` when the cursor
+    /// lives inside the pinned explicit transaction
+    pub conn: Option<PoolConnection<Postgres>>,
+    pub backend_pid: Option<i32>,
+    pub metadata: TableMetadata,
+}
+
+/// Where a query handle keeps its open result cursor
+pub(crate) type CursorSlot = Arc<Mutex<Option<ResultCursor>>>;
+
+/// The cursor slots of the handles on one connection
+pub(crate) type CursorSlots = std::sync::Mutex<Vec<Weak<Mutex<Option<ResultCursor>>>>>;
+
+/// A cursor name no other handle in this process uses
+pub(crate) fn next_cursor_name() -> String {
+    static NEXT: AtomicU64 = AtomicU64::new(1);
+    format!(
+        "{}_{}",
+        RESULT_CURSOR_NAME,
+        NEXT.fetch_add(1, Ordering::Relaxed)
+    )
+}
+
+impl DatabaseManager {
+    /// Fetches the next page from the open result cursor, with the notices
+    /// the server sent meanwhile. Pass `None` to fetch every remaining row.
+    pub async fn fetch_more(&self, limit: Option<usize>) -> Result<ResultPage> {
+        let scope = NoticeScope::new();
+        let mut page = self
+            .fetch_cursor_page(limit)
+            .instrument(scope.span())
+            .await?;
+        page.notices = scope.take();
+        Ok(page)
+    }
+
+    async fn fetch_cursor_page(&self, limit: Option<usize>) -> Result<ResultPage> {
+        let mut cursor_guard = self.cursor.lock().await;
+        let Some(mut cursor) = cursor_guard.take() else {
+            anyhow::bail!("No more rows to fetch");
+        };
+
+        *self.running_backend_pid.write().await = cursor.backend_pid;
+
+        let fetched = match cursor.conn.as_mut() {
+            Some(conn) => fetch_page(conn, &cursor.name, limit).await,
+            None => {
+                let mut session = self.session.lock().await;
+                match session.as_mut() {
+                    Some(conn) => fetch_page(conn, &cursor.name, limit).await,
+                    None => {
+                        *self.running_backend_pid.write().await = None;
+                        anyhow::bail!("The transaction holding these results has ended");
+                    }
+                }
+            }
+        };
+
+        *self.running_backend_pid.write().await = None;
+
+        let rows = match fetched {
+            Ok(rows) => rows,
+            Err(e) => {
+                self.finish_cursor(cursor, false).await;
+                return Err(e.into());
+            }
+        };
+
+        let has_more = limit.is_some_and(|limit| rows.len() == limit);
+        let page = ResultPage {
+            rows: convert_rows(&rows, &cursor.metadata),
+            has_more,
+            notices: vec![],
+        };
+
+        if has_more {
+            *cursor_guard = Some(cursor);
+        } else {
+            self.finish_cursor(cursor, true).await;
+        }
+
+        Ok(page)
+    }
+
+    /// Whether the last SELECT still has unfetched rows
+    #[allow(dead_code)]
+    pub async fn has_open_cursor(&self) -> bool {
+        self.cursor.lock().await.is_some()
+    }
+
+    /// Closes the open result cursor, releasing its connection.
+    pub(crate) async fn close_cursor(&self) {
+        let cursor = self.cursor.lock().await.take();
+        if let Some(cursor) = cursor {
+            self.finish_cursor(cursor, true).await;
+        }
+    }
+
+    /// Closes the result cursors of every handle on this connection, or with
+    /// `pinned_only` just the ones inside the pinned transaction.
+    pub(crate) async fn close_all_cursors(&self, pinned_only: bool) {
+        for slot in self.all_cursor_slots() {
+            let mut slot = slot.lock().await;
+            if slot
+                .as_ref()
+                .is_some_and(|cursor| !pinned_only || cursor.conn.is_none())
+                && let Some(cursor) = slot.take()
+            {
+                self.finish_cursor(cursor, true).await;
+            }
+        }
+    }
+
+    /// The cursor slots of the handles still alive
+    pub(crate) fn all_cursor_slots(&self) -> Vec<CursorSlot> {
+        match self.cursor_slots.lock() {
+            Ok(slots) => slots.iter().filter_map(|slot| slot.upgrade()).collect(),
+            Err(_) => vec![self.cursor.clone()],
+        }
+    }
+
+    /// Ends a cursor: its own transaction is committed (or rolled back after
+    /// an error), while a cursor in the pinned transaction is simply closed.
+    async fn finish_cursor(&self, cursor: ResultCursor, succeeded: bool) {
+        match cursor.conn {
+            Some(mut conn) => {
+                let statement = if succeeded { "COMMIT" } else { "ROLLBACK" };
+                if sqlx::query(statement).execute(&mut *conn).await.is_err() {
+                    conn.close_on_drop();
+                }
+            }
+            None => {
+                let mut session = self.session.lock().await;
+                if let Some(conn) = session.as_mut() {
+                    let _ = close_result_cursor(conn, &cursor.name).await;
+                }
+            }
+        }
+    }
+}
+
+pub(crate) async fn fetch_page(
+    conn: &mut PgConnection,
+    name: &str,
+    limit: Option<usize>,
+) -> Result<Vec<PgRow>, sqlx::Error> {
+    let sql = match limit {
+        Some(limit) => format!("FETCH FORWARD {} FROM {}", limit, name),
+        None => format!("FETCH ALL FROM {}", name),
+    };
+
+    // Every cursor has a different row shape, so never cache the statement
+    sqlx::query(&sql).persistent(false).fetch_all(conn).await
+}
+
+pub(crate) async fn close_result_cursor(
+    conn: &mut PgConnection,
+    name: &str,
+) -> Result<(), sqlx::Error> {
+    let sql = format!("CLOSE {}", name);
+    sqlx::query(&sql)
+        .persistent(false)
+        .execute(conn)
+        .await
+        .map(|_| ())
+}
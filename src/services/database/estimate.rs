@@ -0,0 +1,106 @@
+use serde_json::Value as JsonValue;
+use sqlx::{PgConnection, Row};
+
+use super::manager::DatabaseManager;
+use super::params::{QueryParam, bind_values, resolve_params};
+use crate::services::sql::parameterize;
+
+impl DatabaseManager {
+    /// The planner's estimate of the rows an UPDATE or DELETE would touch,
+    /// taken from `EXPLAIN (FORMAT JSON)` without running the statement.
+    /// `None` when the statement can't be planned.
+    ///
+    /// Inside an open transaction the plan sees its uncommitted changes; a
+    /// savepoint keeps a failing EXPLAIN from aborting it.
+    pub async fn estimate_affected_rows(&self, sql: &str, params: &[QueryParam]) -> Option<u64> {
+        let statement = sql.trim();
+        let parameterized = parameterize(statement);
+        let executed = if params.is_empty() {
+            statement
+        } else {
+            parameterized.sql.as_str()
+        };
+        let explain = format!(
+            "EXPLAIN (FORMAT JSON) {}",
+            executed.trim_end_matches(|c: char| c == ';' || c.is_whitespace())
+        );
+
+        let mut session = self.session.lock().await;
+        if let Some(conn) = session.as_mut() {
+            sqlx::raw_sql("SAVEPOINT pgui_estimate")
+                .execute(&mut **conn)
+                .await
+                .ok()?;
+            let plan = explain_plan(conn, &explain, params).await;
+            let release = if plan.is_some() {
+                "RELEASE SAVEPOINT pgui_estimate"
+            } else {
+                "ROLLBACK TO SAVEPOINT pgui_estimate; RELEASE SAVEPOINT pgui_estimate"
+            };
+            if let Err(e) = sqlx::raw_sql(release).execute(&mut **conn).await {
+                tracing::warn!("Failed to release estimate savepoint: {}", e);
+            }
+            return planned_rows(&plan?);
+        }
+        drop(session);
+
+        let pool_guard = self.pool.read().await;
+        let mut conn = pool_guard.as_ref()?.acquire().await.ok()?;
+        let plan = explain_plan(&mut conn, &explain, params).await?;
+        planned_rows(&plan)
+    }
+}
+
+async fn explain_plan(
+    conn: &mut PgConnection,
+    explain: &str,
+    params: &[QueryParam],
+) -> Option<JsonValue> {
+    let values = resolve_params(conn, explain, params).await.ok()?;
+    match bind_values(sqlx::query(explain), &values)
+        .fetch_one(&mut *conn)
+        .await
+    {
+        Ok(row) => row.try_get::<JsonValue, _>(0).ok(),
+        Err(e) => {
+            tracing::debug!("Could not estimate affected rows: {}", e);
+            None
+        }
+    }
+}
+
+/// Rows planned for the node feeding the top-level ModifyTable, which
+/// itself reports 0 unless the statement has RETURNING
+fn planned_rows(explain: &JsonValue) -> Option<u64> {
+    let plan = explain.get(0)?.get("Plan")?;
+    let node = if plan.get("Node Type")?.as_str() == Some("ModifyTable") {
+        plan.get("Plans")?.get(0)?
+    } else {
+        plan
+    };
+    node.get("Plan Rows")?
+        .as_f64()
+        .map(|rows| rows.round() as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_planned_rows() {
+        let explain = serde_json::json!([{
+            "Plan": {
+                "Node Type": "ModifyTable",
+                "Operation": "Delete",
+                "Plan Rows": 0,
+                "Plans": [{ "Node Type": "Seq Scan", "Plan Rows": 1250 }]
+            }
+        }]);
+        assert_eq!(planned_rows(&explain), Some(1250));
+
+        let explain = serde_json::json!([{ "Plan": { "Node Type": "Result", "Plan Rows": 1 } }]);
+        assert_eq!(planned_rows(&explain), Some(1));
+        assert_eq!(planned_rows(&serde_json::json!([])), None);
+    }
+}
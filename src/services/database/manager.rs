@@ -1,60 +1,256 @@
 use anyhow::Result;
-use async_lock::RwLock;
-use sqlx::PgPool;
-use sqlx::postgres::{PgConnectOptions, PgPoolOptions};
+use async_lock::{Mutex, RwLock};
+use sqlx::pool::PoolConnection;
+use sqlx::postgres::PgConnectOptions;
+use sqlx::{Connection as _, PgConnection, PgPool, Postgres};
 use std::sync::Arc;
-use std::time::Duration;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use super::connection_error::ConnectionError;
+use super::connection_settings::ConnectionSettings;
+use super::cursor::{CursorSlot, CursorSlots, next_cursor_name};
+use super::reconnect::SessionSettings;
+use super::tunnel::{SshTunnel, SshTunnelConfig};
+use super::types::TransactionStatus;
 
 #[derive(Debug, Clone)]
 pub struct DatabaseManager {
     pub(crate) pool: Arc<RwLock<Option<PgPool>>>,
+    /// Backend PID of the connection currently running this handle's query
+    pub(crate) running_backend_pid: Arc<RwLock<Option<i32>>>,
+    /// Set when the user asked to cancel this handle's running query
+    pub(crate) cancel_requested: Arc<AtomicBool>,
+    /// Whether a `statement_timeout` applied to this handle's running query,
+    /// telling a timeout from a cancel sent by someone else
+    pub(crate) statement_timeout_active: Arc<AtomicBool>,
+    /// Connection pinned for an explicit transaction, if one is open
+    pub(crate) session: Arc<Mutex<Option<PoolConnection<Postgres>>>>,
+    pub(crate) transaction_status: Arc<RwLock<TransactionStatus>>,
+    /// Cursor holding the unfetched rows of this handle's last SELECT
+    pub(crate) cursor: CursorSlot,
+    /// Name this handle declares its result cursor under, so handles sharing
+    /// the pinned transaction don't close each other's cursors
+    pub(crate) cursor_name: String,
+    /// Cursor slots of every handle on this connection, closed together when
+    /// the transaction ends or the pool goes away
+    pub(crate) cursor_slots: Arc<CursorSlots>,
+    /// Refuse statements that would modify the database
+    pub(crate) read_only: Arc<AtomicBool>,
+    /// SSH tunnel the pool connects through, closed on disconnect
+    pub(crate) tunnel: Arc<Mutex<Option<SshTunnel>>>,
+    /// Settings left by the user's last `SET` or `RESET`, restored on
+    /// reconnect
+    pub(crate) session_settings: Arc<RwLock<Option<SessionSettings>>>,
+    /// A `SET` ran inside the open transaction; its settings are recorded
+    /// if the transaction commits
+    pub(crate) session_settings_pending: Arc<AtomicBool>,
 }
 
 impl DatabaseManager {
     pub fn new() -> Self {
+        let cursor: CursorSlot = Arc::new(Mutex::new(None));
         Self {
             pool: Arc::new(RwLock::new(None)),
+            running_backend_pid: Arc::new(RwLock::new(None)),
+            cancel_requested: Arc::new(AtomicBool::new(false)),
+            statement_timeout_active: Arc::new(AtomicBool::new(false)),
+            session: Arc::new(Mutex::new(None)),
+            transaction_status: Arc::new(RwLock::new(TransactionStatus::Idle)),
+            cursor_slots: Arc::new(std::sync::Mutex::new(vec![Arc::downgrade(&cursor)])),
+            cursor,
+            cursor_name: next_cursor_name(),
+            read_only: Arc::new(AtomicBool::new(false)),
+            tunnel: Arc::new(Mutex::new(None)),
+            session_settings: Arc::new(RwLock::new(None)),
+            session_settings_pending: Arc::new(AtomicBool::new(false)),
         }
     }
 
-    pub async fn connect_with_options(&self, options: PgConnectOptions) -> Result<()> {
-        let pool_opts = PgPoolOptions::new()
-            .max_connections(5)
-            .acquire_timeout(Duration::from_secs(5));
+    /// A handle for one query tab. It shares the pool, the pinned transaction
+    /// and the session settings with this manager, but keeps its own result
+    /// cursor, running query and cancel request, so tabs can't page through,
+    /// or cancel, each other's statements.
+    pub fn query_handle(&self) -> Self {
+        let cursor: CursorSlot = Arc::new(Mutex::new(None));
+        if let Ok(mut slots) = self.cursor_slots.lock() {
+            slots.retain(|slot| slot.strong_count() > 0);
+            slots.push(Arc::downgrade(&cursor));
+        }
+        Self {
+            running_backend_pid: Arc::new(RwLock::new(None)),
+            cancel_requested: Arc::new(AtomicBool::new(false)),
+            statement_timeout_active: Arc::new(AtomicBool::new(false)),
+            cursor,
+            cursor_name: next_cursor_name(),
+            ..self.clone()
+        }
+    }
 
-        let pool = pool_opts.connect_with(options).await;
+    /// Blocks modifying statements before they are sent. The session itself
+    /// should also be opened read-only, see [`ConnectionInfo::read_only`].
+    ///
+    /// [`ConnectionInfo::read_only`]: crate::services::ConnectionInfo::read_only
+    pub fn set_read_only(&self, read_only: bool) {
+        self.read_only.store(read_only, Ordering::SeqCst);
+    }
 
-        match pool {
-            Ok(p) => {
-                let mut pool_guard = self.pool.write().await;
-                *pool_guard = Some(p);
-            }
-            Err(e) => {
-                tracing::error!("Error Connecting: {}", e)
-            }
-        };
+    pub fn is_read_only(&self) -> bool {
+        self.read_only.load(Ordering::SeqCst)
+    }
 
-        Ok(())
+    /// Connects the pool, through an SSH tunnel to the options' host and
+    /// port when `ssh_tunnel` is set. `settings` size the pool and set up
+    /// each of its connections.
+    pub async fn connect_with_options(
+        &self,
+        options: PgConnectOptions,
+        ssh_tunnel: Option<SshTunnelConfig>,
+        settings: &ConnectionSettings,
+    ) -> Result<(), ConnectionError> {
+        *self.session_settings.write().await = None;
+        self.open_pool(options, ssh_tunnel, settings, None).await
     }
 
-    #[allow(dead_code)]
-    pub async fn test_connection_options(options: PgConnectOptions) -> Result<()> {
-        let pool = PgPoolOptions::new()
-            .max_connections(1)
-            .acquire_timeout(Duration::from_secs(5))
-            .connect_with(options)
-            .await?;
+    /// Replaces a pool whose connections dropped. The open transaction and
+    /// result cursor go with the old connections; the last seen
+    /// `search_path` and role are restored on the new ones.
+    pub async fn reconnect_with_options(
+        &self,
+        options: PgConnectOptions,
+        ssh_tunnel: Option<SshTunnelConfig>,
+        settings: &ConnectionSettings,
+    ) -> Result<(), ConnectionError> {
+        for slot in self.all_cursor_slots() {
+            slot.lock().await.take();
+        }
+        self.session.lock().await.take();
+        *self.transaction_status.write().await = TransactionStatus::Idle;
+        self.session_settings_pending.store(false, Ordering::SeqCst);
+
+        let restore = self.session_settings.read().await.clone();
+        self.open_pool(options, ssh_tunnel, settings, restore).await
+    }
 
-        sqlx::query("SELECT 1").fetch_one(&pool).await?;
-        pool.close().await;
+    /// Connects a pool whose new connections get the configured role and
+    /// startup statements, then the `restore`d session settings. Restoring
+    /// is best effort: a role dropped since it was recorded must not keep
+    /// every new connection from opening.
+    async fn open_pool(
+        &self,
+        options: PgConnectOptions,
+        ssh_tunnel: Option<SshTunnelConfig>,
+        settings: &ConnectionSettings,
+        restore: Option<SessionSettings>,
+    ) -> Result<(), ConnectionError> {
+        let hostname = options.get_host().to_string();
+        let (options, tunnel) = Self::open_tunnel(options, ssh_tunnel).await?;
+        Self::probe(&options, settings, &hostname).await?;
+        let settings = settings.clone();
+        let pool_opts = settings.pool_options().after_connect(move |conn, _meta| {
+            let settings = settings.clone();
+            let restore = restore.clone();
+            Box::pin(async move {
+                settings.apply(conn).await?;
+                if let Some(restore) = restore
+                    && let Err(e) = restore.apply(conn).await
+                {
+                    tracing::warn!("Failed to restore session settings: {}", e);
+                }
+                Ok(())
+            })
+        });
 
+        let pool = pool_opts
+            .connect_with(options)
+            .await
+            .map_err(|e| ConnectionError::from_sqlx(e, &hostname))?;
+        *self.pool.write().await = Some(pool);
+        // Dropping the old tunnel joins its thread, so keep it off the executor
+        let old_tunnel = std::mem::replace(&mut *self.tunnel.lock().await, tunnel);
+        if let Some(old_tunnel) = old_tunnel {
+            smol::unblock(move || drop(old_tunnel)).await;
+        }
         Ok(())
     }
 
+    /// Opens one connection with `settings` applied, so a role or startup
+    /// statement the server refuses fails the test
+    pub async fn test_connection_options(
+        options: PgConnectOptions,
+        ssh_tunnel: Option<SshTunnelConfig>,
+        settings: &ConnectionSettings,
+    ) -> Result<(), ConnectionError> {
+        let hostname = options.get_host().to_string();
+        // The tunnel, if any, lives until the test is done
+        let (options, _tunnel) = Self::open_tunnel(options, ssh_tunnel).await?;
+        Self::probe(&options, settings, &hostname).await
+    }
+
+    /// Opens and closes one connection with `settings` applied. A pool
+    /// retries a failed connect until its acquire timeout and then only
+    /// reports the timeout, so a refused connection, an unknown host or a
+    /// bad role is told apart here instead.
+    async fn probe(
+        options: &PgConnectOptions,
+        settings: &ConnectionSettings,
+        hostname: &str,
+    ) -> Result<(), ConnectionError> {
+        let connect = async {
+            let mut conn = PgConnection::connect_with(options).await?;
+            settings.apply(&mut conn).await?;
+            conn.close().await
+        };
+        let timeout = async {
+            smol::Timer::after(settings.acquire_timeout()).await;
+            Err(ConnectionError::Timeout)
+        };
+
+        smol::future::or(
+            async {
+                connect
+                    .await
+                    .map_err(|e| ConnectionError::from_sqlx(e, hostname))
+            },
+            timeout,
+        )
+        .await
+    }
+
+    /// Opens the tunnel off the async executor and points the options at
+    /// its local end. TLS then checks the certificate against `127.0.0.1`,
+    /// so Verify Full needs a certificate that lists it.
+    async fn open_tunnel(
+        options: PgConnectOptions,
+        ssh_tunnel: Option<SshTunnelConfig>,
+    ) -> Result<(PgConnectOptions, Option<SshTunnel>), ConnectionError> {
+        let Some(config) = ssh_tunnel else {
+            return Ok((options, None));
+        };
+
+        let host = options.get_host().to_string();
+        let port = options.get_port();
+        let tunnel = smol::unblock(move || SshTunnel::open(&config, &host, port))
+            .await
+            .map_err(|e| ConnectionError::Tunnel(format!("{:#}", e)))?;
+        let options = options.host("127.0.0.1").port(tunnel.local_port());
+        Ok((options, Some(tunnel)))
+    }
+
     pub async fn disconnect(&self) -> Result<()> {
+        // Closing the pool waits for every connection, including the ones
+        // held by an open result cursor or a pinned transaction
+        self.close_all_cursors(false).await;
+        let has_session = self.session.lock().await.is_some();
+        if has_session && let Err(e) = self.rollback_transaction().await {
+            tracing::warn!("Failed to roll back open transaction: {}", e);
+        }
+
         let mut pool_guard = self.pool.write().await;
         if let Some(pool) = pool_guard.take() {
             pool.close().await;
+            if let Some(tunnel) = self.tunnel.lock().await.take() {
+                smol::unblock(move || drop(tunnel)).await;
+            }
             Ok(())
         } else {
             Err(anyhow::anyhow!(
@@ -63,12 +259,49 @@ impl DatabaseManager {
         }
     }
 
+    /// Cancels this handle's running query, if any.
+    ///
+    /// Issues `pg_cancel_backend` from a second pooled connection against the
+    /// backend PID recorded when the query started. Returns `Ok(false)` when
+    /// nothing was running or the server refused the request.
+    pub async fn cancel_query(&self) -> Result<bool> {
+        let pool_guard = self.pool.read().await;
+        let pool = pool_guard
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("Database not connected"))?;
+
+        // Held until the cancel is sent, so it can only reach the statement
+        // that was running when it was asked for
+        let running = self.running_backend_pid.read().await;
+        let Some(pid) = *running else {
+            return Ok(false);
+        };
+
+        self.cancel_requested.store(true, Ordering::SeqCst);
+
+        let cancelled: bool = sqlx::query_scalar("SELECT pg_cancel_backend($1)")
+            .bind(pid)
+            .fetch_one(pool)
+            .await?;
+
+        tracing::debug!("Cancel requested for backend {}: {}", pid, cancelled);
+        Ok(cancelled)
+    }
+
     pub async fn is_connected(&self) -> bool {
         let pool_guard = self.pool.read().await;
-        if let Some(pool) = pool_guard.as_ref() {
-            sqlx::query("SELECT 1").fetch_one(pool).await.is_ok()
-        } else {
-            false
+        let Some(pool) = pool_guard.as_ref() else {
+            return false;
+        };
+        sqlx::query("SELECT 1").execute(pool).await.is_ok()
+    }
+
+    /// Records the settings a reconnect restores from the connection a
+    /// `SET` or `RESET` just ran on
+    pub(crate) async fn record_session_settings(&self, conn: &mut PgConnection) {
+        match SessionSettings::read(conn).await {
+            Ok(settings) => *self.session_settings.write().await = Some(settings),
+            Err(e) => tracing::warn!("Failed to read session settings: {}", e),
         }
     }
 
@@ -1,15 +1,39 @@
+mod cell;
+mod connection_error;
+mod connection_settings;
+mod cursor;
+mod estimate;
 mod manager;
+mod notices;
+mod params;
 mod query;
+mod reconnect;
 mod schema;
+mod timeouts;
+mod transaction;
+mod tunnel;
 mod types;
 
+pub use cell::{CellValue, decode_value};
+pub use connection_error::ConnectionError;
+pub use connection_settings::{
+    ConnectionSettings, DEFAULT_ACQUIRE_TIMEOUT_SECS, DEFAULT_IDLE_TIMEOUT_SECS,
+    DEFAULT_MAX_CONNECTIONS, MIN_CONNECTIONS, parse_startup_statements,
+};
+pub use cursor::RESULT_PAGE_SIZE;
 pub use manager::DatabaseManager;
+pub use notices::{NoticeSeverity, ServerNotice, notice_layer};
+pub use params::{ParamType, QueryParam};
+pub use reconnect::reconnect_delay;
+pub use timeouts::{QueryTimeouts, TimeoutKind};
+pub use tunnel::{KnownHostsPolicy, SshAuth, SshTunnelConfig};
 
 #[allow(unused_imports)]
 pub use types::{
-    ColumnDetail, ConstraintInfo, DatabaseInfo, DatabaseSchema, ErrorResult, ForeignKeyInfo,
-    IndexInfo, QueryExecutionResult, QueryResult, ResultCell, ResultColumnMetadata, ResultRow,
-    TableInfo, TableSchema,
+    CancelledResult, ColumnDetail, ConstraintInfo, DatabaseErrorDetails, DatabaseInfo,
+    DatabaseSchema, ErrorResult, ForeignKeyInfo, IndexInfo, QueryExecutionResult, QueryResult,
+    ResultCell, ResultColumnMetadata, ResultPage, ResultRow, TableInfo, TableSchema,
+    TransactionStatus,
 };
 
 // TableMetadata is internal only
@@ -0,0 +1,209 @@
+//! Capture of server notices (`RAISE NOTICE`, DDL warnings, ...).
+//!
+//! sqlx does not hand NoticeResponse messages to the caller; it only emits
+//! them as tracing events carrying the severity and message, not the SQLSTATE. The layer from [`notice_layer`] picks those events
+//! up and files them under the [`NoticeScope`] of the query that was running, so notices
+//! from concurrent work never mix.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{LazyLock, Mutex};
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Metadata, Span, Subscriber};
+use tracing_subscriber::filter::{LevelFilter, filter_fn};
+use tracing_subscriber::layer::{Context, Layer};
+use tracing_subscriber::registry::LookupSpan;
+
+/// Target sqlx logs NoticeResponse messages under
+const NOTICE_TARGET: &str = "sqlx::postgres::notice";
+/// Target of the span wrapping each user query
+const QUERY_SPAN_TARGET: &str = "pgui::query";
+
+/// Notices collected so far, keyed by the id of their query span
+static COLLECTED: LazyLock<Mutex<HashMap<u64, Vec<ServerNotice>>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Severity of a server notice
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NoticeSeverity {
+    Error,
+    Warning,
+    Notice,
+    /// `INFO` or `LOG`; sqlx reports both at the same level
+    Info,
+    Debug,
+}
+
+impl NoticeSeverity {
+    pub fn label(&self) -> &'static str {
+        match self {
+            NoticeSeverity::Error => "ERROR",
+            NoticeSeverity::Warning => "WARNING",
+            NoticeSeverity::Notice => "NOTICE",
+            NoticeSeverity::Info => "INFO",
+            NoticeSeverity::Debug => "DEBUG",
+        }
+    }
+}
+
+impl From<Level> for NoticeSeverity {
+    /// Inverse of the level mapping sqlx applies to notice severities
+    fn from(level: Level) -> Self {
+        match level {
+            Level::ERROR => NoticeSeverity::Error,
+            Level::WARN => NoticeSeverity::Warning,
+            Level::INFO => NoticeSeverity::Notice,
+            Level::DEBUG => NoticeSeverity::Debug,
+            Level::TRACE => NoticeSeverity::Info,
+        }
+    }
+}
+
+/// A notice or warning the server sent while a statement ran
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ServerNotice {
+    pub severity: NoticeSeverity,
+    pub message: String,
+}
+
+/// Collects the notices raised inside its span.
+///
+/// Run the query future instrumented with [`NoticeScope::span`], then call
+/// [`NoticeScope::take`].
+pub(crate) struct NoticeScope {
+    span: Span,
+}
+
+impl NoticeScope {
+    pub(crate) fn new() -> Self {
+        let span = tracing::info_span!(target: QUERY_SPAN_TARGET, "query");
+        if let Some(id) = span.id() {
+            lock_collected().insert(id.into_u64(), Vec::new());
+        }
+        Self { span }
+    }
+
+    pub(crate) fn span(&self) -> Span {
+        self.span.clone()
+    }
+
+    /// Notices collected so far, in the order the server sent them
+    pub(crate) fn take(&self) -> Vec<ServerNotice> {
+        self.span
+            .id()
+            .and_then(|id| lock_collected().get_mut(&id.into_u64()).map(std::mem::take))
+            .unwrap_or_default()
+    }
+}
+
+impl Drop for NoticeScope {
+    fn drop(&mut self) {
+        if let Some(id) = self.span.id() {
+            lock_collected().remove(&id.into_u64());
+        }
+    }
+}
+
+fn lock_collected() -> std::sync::MutexGuard<'static, HashMap<u64, Vec<ServerNotice>>> {
+    COLLECTED.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Tracing layer routing sqlx notice events to the running [`NoticeScope`].
+/// It must be registered for notices to be captured.
+struct NoticeLayer;
+
+/// The notice layer, filtered to its own span and sqlx notice events so it
+/// works whatever the log filter of other layers. sqlx logs `INFO` and `LOG`
+/// notices at `TRACE`, hence the level hint.
+pub fn notice_layer<S>() -> impl Layer<S>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    let filter = filter_fn(|metadata: &Metadata<'_>| {
+        matches!(metadata.target(), NOTICE_TARGET | QUERY_SPAN_TARGET)
+    });
+    NoticeLayer.with_filter(filter.with_max_level_hint(LevelFilter::TRACE))
+}
+
+impl<S> Layer<S> for NoticeLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        if event.metadata().target() != NOTICE_TARGET {
+            return;
+        }
+        let Some(scope) = ctx.event_scope(event) else {
+            return;
+        };
+
+        let mut collected = lock_collected();
+        for span in scope {
+            if let Some(notices) = collected.get_mut(&span.id().into_u64()) {
+                let mut visitor = MessageVisitor::default();
+                event.record(&mut visitor);
+                notices.push(ServerNotice {
+                    severity: (*event.metadata().level()).into(),
+                    message: visitor.message,
+                });
+                return;
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+struct MessageVisitor {
+    message: String,
+}
+
+impl Visit for MessageVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.message = value.to_string();
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "message" {
+            self.message = format!("{:?}", value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tracing_subscriber::layer::SubscriberExt as _;
+
+    #[test]
+    fn test_notices_go_to_their_scope() {
+        let subscriber = tracing_subscriber::registry().with(notice_layer());
+        tracing::subscriber::with_default(subscriber, || {
+            let outer = NoticeScope::new();
+            let inner = NoticeScope::new();
+
+            outer.span().in_scope(|| {
+                tracing::warn!(target: NOTICE_TARGET, message = "table \"t\" does not exist, skipping");
+            });
+            inner.span().in_scope(|| {
+                tracing::info!(target: NOTICE_TARGET, message = "hello");
+                tracing::info!(target: "other", message = "ignored");
+            });
+
+            assert_eq!(
+                outer.take(),
+                vec![ServerNotice {
+                    severity: NoticeSeverity::Warning,
+                    message: "table \"t\" does not exist, skipping".to_string(),
+                }]
+            );
+            let inner_notices = inner.take();
+            assert_eq!(inner_notices.len(), 1);
+            assert_eq!(inner_notices[0].severity, NoticeSeverity::Notice);
+            assert!(outer.take().is_empty());
+        });
+    }
+}
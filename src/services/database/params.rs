@@ -0,0 +1,278 @@
+use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use sqlx::postgres::PgArguments;
+use sqlx::query::Query;
+use sqlx::{Either, Executor as _, PgConnection, Postgres, Statement as _, TypeInfo as _};
+use uuid::Uuid;
+
+use super::query::query_failed;
+use super::types::ErrorResult;
+
+/// Type a bind parameter value is sent as
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ParamType {
+    /// Use the type the server infers for the placeholder
+    #[default]
+    Auto,
+    Text,
+    Integer,
+    Numeric,
+    Float,
+    Boolean,
+    Date,
+    Time,
+    Timestamp,
+    TimestampTz,
+    Uuid,
+    Json,
+}
+
+impl ParamType {
+    pub const ALL: [ParamType; 12] = [
+        ParamType::Auto,
+        ParamType::Text,
+        ParamType::Integer,
+        ParamType::Numeric,
+        ParamType::Float,
+        ParamType::Boolean,
+        ParamType::Date,
+        ParamType::Time,
+        ParamType::Timestamp,
+        ParamType::TimestampTz,
+        ParamType::Uuid,
+        ParamType::Json,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ParamType::Auto => "auto",
+            ParamType::Text => "text",
+            ParamType::Integer => "integer",
+            ParamType::Numeric => "numeric",
+            ParamType::Float => "float",
+            ParamType::Boolean => "boolean",
+            ParamType::Date => "date",
+            ParamType::Time => "time",
+            ParamType::Timestamp => "timestamp",
+            ParamType::TimestampTz => "timestamptz",
+            ParamType::Uuid => "uuid",
+            ParamType::Json => "json",
+        }
+    }
+}
+
+/// A value for one `$n` placeholder, in bind order
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct QueryParam {
+    /// Placeholder as written, e.g. `$1` or `:user_id`
+    pub name: String,
+    #[serde(default)]
+    pub param_type: ParamType,
+    /// `None` binds NULL
+    pub value: Option<String>,
+}
+
+/// A parameter parsed into the Rust type it is bound as
+#[derive(Debug, Clone)]
+pub(crate) enum BoundValue {
+    Text(Option<String>),
+    Int2(Option<i16>),
+    Int4(Option<i32>),
+    Int8(Option<i64>),
+    Numeric(Option<Decimal>),
+    Float4(Option<f32>),
+    Float8(Option<f64>),
+    Boolean(Option<bool>),
+    Date(Option<NaiveDate>),
+    Time(Option<NaiveTime>),
+    Timestamp(Option<NaiveDateTime>),
+    TimestampTz(Option<DateTime<Utc>>),
+    Uuid(Option<Uuid>),
+    Json(Option<JsonValue>),
+}
+
+/// Parses parameter values, asking the server for the types of `Auto`
+/// parameters. Errors name the offending placeholder.
+pub(crate) async fn resolve_params(
+    conn: &mut PgConnection,
+    sql: &str,
+    params: &[QueryParam],
+) -> Result<Vec<BoundValue>, ErrorResult> {
+    let invalid = |message: String| ErrorResult {
+        message,
+        ..Default::default()
+    };
+
+    let inferred: Vec<String> = if params.iter().any(|p| p.param_type == ParamType::Auto) {
+        let statement = conn
+            .prepare(sql)
+            .await
+            .map_err(|e| query_failed(&e, 0, false))?;
+        match statement.parameters() {
+            Some(Either::Left(types)) => types.iter().map(|t| t.name().to_string()).collect(),
+            _ => Vec::new(),
+        }
+    } else {
+        Vec::new()
+    };
+
+    if !inferred.is_empty() && inferred.len() != params.len() {
+        return Err(invalid(format!(
+            "Statement expects {} parameters but {} were given",
+            inferred.len(),
+            params.len()
+        )));
+    }
+
+    params
+        .iter()
+        .enumerate()
+        .map(|(ix, param)| {
+            let type_name = match param.param_type {
+                ParamType::Auto => inferred.get(ix).map(String::as_str).unwrap_or("TEXT"),
+                ParamType::Text => "TEXT",
+                ParamType::Integer => "INT8",
+                ParamType::Numeric => "NUMERIC",
+                ParamType::Float => "FLOAT8",
+                ParamType::Boolean => "BOOL",
+                ParamType::Date => "DATE",
+                ParamType::Time => "TIME",
+                ParamType::Timestamp => "TIMESTAMP",
+                ParamType::TimestampTz => "TIMESTAMPTZ",
+                ParamType::Uuid => "UUID",
+                ParamType::Json => "JSONB",
+            };
+            parse_value(type_name, param.value.as_deref()).map_err(|e| {
+                invalid(format!(
+                    "Invalid value for {} ({}): {}",
+                    param.name,
+                    type_name.to_lowercase(),
+                    e
+                ))
+            })
+        })
+        .collect()
+}
+
+/// Binds parsed parameters in order. With no parameters the query is left
+/// untouched, so it still goes through the simple protocol.
+pub(crate) fn bind_values<'q>(
+    mut query: Query<'q, Postgres, PgArguments>,
+    values: &[BoundValue],
+) -> Query<'q, Postgres, PgArguments> {
+    for value in values {
+        query = match value.clone() {
+            BoundValue::Text(v) => query.bind(v),
+            BoundValue::Int2(v) => query.bind(v),
+            BoundValue::Int4(v) => query.bind(v),
+            BoundValue::Int8(v) => query.bind(v),
+            BoundValue::Numeric(v) => query.bind(v),
+            BoundValue::Float4(v) => query.bind(v),
+            BoundValue::Float8(v) => query.bind(v),
+            BoundValue::Boolean(v) => query.bind(v),
+            BoundValue::Date(v) => query.bind(v),
+            BoundValue::Time(v) => query.bind(v),
+            BoundValue::Timestamp(v) => query.bind(v),
+            BoundValue::TimestampTz(v) => query.bind(v),
+            BoundValue::Uuid(v) => query.bind(v),
+            BoundValue::Json(v) => query.bind(v),
+        };
+    }
+    query
+}
+
+/// Parses a value for a Postgres type name; unknown types are sent as text
+fn parse_value(type_name: &str, value: Option<&str>) -> Result<BoundValue, String> {
+    fn parse<T, E: std::fmt::Display>(
+        value: Option<&str>,
+        f: impl Fn(&str) -> Result<T, E>,
+    ) -> Result<Option<T>, String> {
+        value
+            .map(|v| f(v.trim()))
+            .transpose()
+            .map_err(|e| e.to_string())
+    }
+
+    Ok(match type_name {
+        "INT2" => BoundValue::Int2(parse(value, str::parse)?),
+        "INT4" => BoundValue::Int4(parse(value, str::parse)?),
+        "INT8" | "OID" => BoundValue::Int8(parse(value, str::parse)?),
+        "NUMERIC" | "MONEY" => BoundValue::Numeric(parse(value, str::parse)?),
+        "FLOAT4" => BoundValue::Float4(parse(value, str::parse)?),
+        "FLOAT8" => BoundValue::Float8(parse(value, str::parse)?),
+        "BOOL" => BoundValue::Boolean(parse(value, parse_bool)?),
+        "DATE" => BoundValue::Date(parse(value, str::parse)?),
+        "TIME" => BoundValue::Time(parse(value, str::parse)?),
+        "TIMESTAMP" => BoundValue::Timestamp(parse(value, parse_timestamp)?),
+        "TIMESTAMPTZ" => BoundValue::TimestampTz(parse(value, parse_timestamptz)?),
+        "UUID" => BoundValue::Uuid(parse(value, str::parse)?),
+        "JSON" | "JSONB" => BoundValue::Json(parse(value, |v| serde_json::from_str(v))?),
+        _ => BoundValue::Text(value.map(str::to_string)),
+    })
+}
+
+fn parse_bool(value: &str) -> Result<bool, String> {
+    match value.to_lowercase().as_str() {
+        "t" | "true" | "y" | "yes" | "on" | "1" => Ok(true),
+        "f" | "false" | "n" | "no" | "off" | "0" => Ok(false),
+        _ => Err(format!("'{}' is not a boolean", value)),
+    }
+}
+
+fn parse_timestamp(value: &str) -> Result<NaiveDateTime, String> {
+    [
+        "%Y-%m-%d %H:%M:%S%.f",
+        "%Y-%m-%dT%H:%M:%S%.f",
+        "%Y-%m-%d %H:%M",
+    ]
+    .iter()
+    .find_map(|format| NaiveDateTime::parse_from_str(value, format).ok())
+    .or_else(|| {
+        NaiveDate::parse_from_str(value, "%Y-%m-%d")
+            .ok()
+            .and_then(|date| date.and_hms_opt(0, 0, 0))
+    })
+    .ok_or_else(|| format!("'{}' is not a timestamp", value))
+}
+
+fn parse_timestamptz(value: &str) -> Result<DateTime<Utc>, String> {
+    DateTime::parse_from_rfc3339(value)
+        .or_else(|_| DateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S%.f%#z"))
+        .map(|dt| dt.with_timezone(&Utc))
+        .or_else(|_| parse_timestamp(value).map(|dt| dt.and_utc()))
+        .map_err(|_| format!("'{}' is not a timestamp with time zone", value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_value_reports_bad_input() {
+        assert!(matches!(
+            parse_value("INT4", Some(" 42 ")),
+            Ok(BoundValue::Int4(Some(42)))
+        ));
+        assert!(matches!(
+            parse_value("INT4", None),
+            Ok(BoundValue::Int4(None))
+        ));
+        assert!(parse_value("INT4", Some("forty-two")).is_err());
+        assert!(parse_value("BOOL", Some("maybe")).is_err());
+        assert!(matches!(
+            parse_value("mood", Some("happy")),
+            Ok(BoundValue::Text(Some(_)))
+        ));
+    }
+
+    #[test]
+    fn test_parse_timestamps() {
+        assert!(parse_timestamp("2024-03-01").is_ok());
+        assert!(parse_timestamp("2024-03-01T10:30:00").is_ok());
+        let tz = parse_timestamptz("2024-03-01 10:30:00+02").unwrap();
+        assert_eq!(tz.to_rfc3339(), "2024-03-01T08:30:00+00:00");
+    }
+}
@@ -1,20 +1,110 @@
 use futures::StreamExt;
 use futures::stream::BoxStream;
+use sqlx::pool::PoolConnection;
 use sqlx::postgres::types::Oid;
-use sqlx::postgres::{PgColumn, PgRow};
+use sqlx::postgres::{PgColumn, PgDatabaseError, PgErrorPosition, PgRow};
 use sqlx::query::Query;
-use sqlx::{Column, Execute as _, PgPool, Row, TypeInfo, ValueRef};
+use sqlx::{Column, Execute as _, PgConnection, PgPool, Postgres, Row, TypeInfo};
 use std::collections::{HashMap, HashSet};
+use std::sync::atomic::Ordering;
+use tracing::Instrument as _;
 
-use crate::services::database::types::{ErrorResult, ModifiedResult};
+use crate::services::database::types::{CancelledResult, ErrorResult, ModifiedResult};
+use crate::services::sql::{classify_statement, enables_writes, parameterize, runs_code};
 
+use super::cell::{CellValue, decode_value};
+use super::cursor::{RESULT_PAGE_SIZE, ResultCursor, close_result_cursor, fetch_page};
 use super::manager::DatabaseManager;
+use super::notices::{NoticeScope, ServerNotice};
+use super::params::{BoundValue, QueryParam, bind_values, resolve_params};
+use super::reconnect::changes_session_settings;
+use super::timeouts::{
+    QueryTimeouts, reset_timeouts, set_local_timeouts, set_timeouts, timeout_kind,
+};
+use super::transaction::{TransactionControl, transaction_control};
 use super::types::{
-    QueryExecutionResult, QueryResult, ResultCell, ResultColumnMetadata, ResultRow, TableMetadata,
+    DatabaseErrorDetails, QueryExecutionResult, QueryResult, ResultCell, ResultColumnMetadata,
+    ResultRow, TableMetadata,
 };
 
 impl DatabaseManager {
     pub async fn execute_query_enhanced(&self, sql: &str) -> QueryExecutionResult {
+        self.execute_query_with_params(sql, &[], &QueryTimeouts::default())
+            .await
+            .0
+    }
+
+    /// Executes a statement with values for its `$n` / `:name` placeholders,
+    /// given in the order reported by [`parameterize`]. `timeouts` override
+    /// the connection's limits for this run only.
+    ///
+    /// Returns the notices the server sent meanwhile along with the result.
+    pub async fn execute_query_with_params(
+        &self,
+        sql: &str,
+        params: &[QueryParam],
+        timeouts: &QueryTimeouts,
+    ) -> (QueryExecutionResult, Vec<ServerNotice>) {
+        let statement = sql.trim();
+        let parameterized = (!params.is_empty()).then(|| parameterize(statement));
+        let executed = parameterized
+            .as_ref()
+            .map_or(statement, |parameterized| parameterized.sql.as_str());
+
+        let scope = NoticeScope::new();
+        let mut result = self
+            .run_query(executed, params, timeouts)
+            .instrument(scope.span())
+            .await;
+        let notices = scope.take();
+
+        // Report the error position against the text the caller passed in
+        if let QueryExecutionResult::Error(ErrorResult {
+            database_error: Some(details),
+            ..
+        }) = &mut result
+        {
+            details.position = details.position.and_then(|position| {
+                let offset = executed
+                    .char_indices()
+                    .map(|(ix, _)| ix)
+                    .chain(std::iter::once(executed.len()))
+                    .nth(position.checked_sub(1)?)?;
+                let offset = parameterized.as_ref().map_or(offset, |parameterized| {
+                    parameterized.original_offset(offset)
+                });
+                let leading = sql.len() - sql.trim_start().len();
+                Some(sql[..leading + offset].chars().count() + 1)
+            });
+        }
+
+        (result, notices)
+    }
+
+    /// Runs a trimmed statement whose placeholders are all `$n`
+    async fn run_query(
+        &self,
+        sql: &str,
+        params: &[QueryParam],
+        timeouts: &QueryTimeouts,
+    ) -> QueryExecutionResult {
+        if self.is_read_only()
+            && let Some(message) = read_only_violation(sql)
+        {
+            return QueryExecutionResult::Error(ErrorResult {
+                message,
+                ..Default::default()
+            });
+        }
+        if params.is_empty()
+            && let Some(control) = transaction_control(sql)
+            && let Some(result) = self
+                .execute_transaction_control(control, sql, timeouts)
+                .await
+        {
+            return result;
+        }
+
         let pool_guard = self.pool.read().await;
 
         let pool = match pool_guard.as_ref() {
@@ -22,29 +112,195 @@ impl DatabaseManager {
             None => {
                 return QueryExecutionResult::Error(ErrorResult {
                     message: "Database not connected".to_string(),
-                    execution_time_ms: 0,
+                    ..Default::default()
                 });
             }
         };
 
-        let sql = sql.trim();
         if sql.is_empty() {
             return QueryExecutionResult::Error(ErrorResult {
                 message: "Empty query".to_string(),
-                execution_time_ms: 0,
+                ..Default::default()
             });
         }
 
-        if is_select_query(sql) {
-            self.execute_select_query(sql, pool).await
+        // A new statement discards whatever is left of the previous result
+        self.close_cursor().await;
+
+        // Inside an explicit transaction every statement must use the pinned
+        // connection; otherwise take a dedicated one so its PID can be cancelled
+        let mut session = Some(self.session.lock().await);
+        let in_transaction = session.as_ref().is_some_and(|session| session.is_some());
+        if !in_transaction {
+            // Other handles may run their own statements meanwhile
+            session = None;
+        }
+        let mut pooled: Option<PoolConnection<Postgres>> = None;
+        let conn: &mut PgConnection = match session.as_deref_mut().and_then(Option::as_mut) {
+            Some(conn) => conn,
+            None => match pool.acquire().await {
+                Ok(conn) => pooled.insert(conn),
+                Err(e) => {
+                    return QueryExecutionResult::Error(query_failed(&e, 0, false));
+                }
+            },
+        };
+
+        let values = match resolve_params(conn, strip_trailing_semicolons(sql), params).await {
+            Ok(values) => values,
+            Err(error) => {
+                // A statement the server refused to prepare aborts the
+                // transaction; a client side check does not
+                if in_transaction && error.database_error.is_some() {
+                    self.note_transaction_statement(sql, false).await;
+                }
+                return QueryExecutionResult::Error(error);
+            }
+        };
+
+        // Outside a transaction a SELECT sets its limits inside the cursor's
+        // own transaction instead, so they end with it
+        let limits = timeouts.statement_limits();
+        let is_select = is_select_query(sql);
+        let session_limits = if in_transaction || !is_select {
+            limits
+        } else {
+            QueryTimeouts::default()
+        };
+        if let Err(e) = set_timeouts(conn, &session_limits).await {
+            if in_transaction {
+                self.note_transaction_statement(sql, false).await;
+            }
+            return QueryExecutionResult::Error(query_failed(&e, 0, false));
+        }
+
+        let (backend_pid, session_timeout) = sqlx::query_as::<_, (i32, bool)>(
+            "SELECT pg_backend_pid(), current_setting('statement_timeout') <> '0'",
+        )
+        .fetch_one(&mut *conn)
+        .await
+        .map_or((None, false), |(pid, timeout)| (Some(pid), timeout));
+        // A SELECT outside a transaction sets its own limit after this check
+        let statement_timeout = limits
+            .statement_timeout_ms
+            .map_or(session_timeout, |ms| ms > 0);
+        self.statement_timeout_active
+            .store(statement_timeout, Ordering::SeqCst);
+        {
+            let mut running = self.running_backend_pid.write().await;
+            self.cancel_requested.store(false, Ordering::SeqCst);
+            *running = backend_pid;
+        }
+
+        let (result, open_cursor) = if is_select {
+            self.execute_select_query(sql, &values, &limits, conn, pool, in_transaction)
+                .await
         } else {
-            self.execute_modification_query(sql, pool).await
+            (
+                self.execute_modification_query(sql, &values, conn).await,
+                None,
+            )
+        };
+
+        // Waits for a cancel being sent to this statement
+        *self.running_backend_pid.write().await = None;
+        reset_timeouts(conn, &session_limits).await;
+
+        let succeeded = matches!(
+            result,
+            QueryExecutionResult::Select(_) | QueryExecutionResult::Modified(_)
+        );
+        if in_transaction {
+            self.note_transaction_statement(sql, succeeded).await;
         }
+        if succeeded && changes_session_settings(sql) {
+            // Inside a transaction the change only sticks if it commits
+            if in_transaction {
+                self.session_settings_pending.store(true, Ordering::SeqCst);
+            } else {
+                self.record_session_settings(conn).await;
+            }
+        }
+
+        // Keep the cursor for later pages; it owns the pooled connection
+        // unless it lives in the pinned transaction
+        drop(session);
+        if let Some(metadata) = open_cursor {
+            *self.cursor.lock().await = Some(ResultCursor {
+                name: self.cursor_name.clone(),
+                conn: pooled.take(),
+                backend_pid,
+                metadata,
+            });
+        }
+
+        result
     }
 
-    async fn execute_modification_query(&self, sql: &str, pool: &PgPool) -> QueryExecutionResult {
+    /// Runs BEGIN / COMMIT / ROLLBACK against the pinned transaction.
+    /// Returns `None` when the statement should simply be sent to the server,
+    /// e.g. a nested BEGIN or a COMMIT with no open transaction.
+    ///
+    /// An idle in transaction override lasts for the transaction it begins.
+    async fn execute_transaction_control(
+        &self,
+        control: TransactionControl,
+        sql: &str,
+        timeouts: &QueryTimeouts,
+    ) -> Option<QueryExecutionResult> {
+        let in_transaction = self.session.lock().await.is_some();
         let start_time = std::time::Instant::now();
-        match sqlx::query(sql).execute(pool).await {
+
+        let result = match (control, in_transaction) {
+            (TransactionControl::Begin, false) => {
+                let idle_limit = QueryTimeouts {
+                    idle_in_transaction_timeout_ms: timeouts.idle_in_transaction_timeout_ms,
+                    ..Default::default()
+                };
+                match self.begin_transaction_with(sql).await {
+                    Ok(()) => match self.session.lock().await.as_mut() {
+                        Some(conn) => set_local_timeouts(conn, &idle_limit)
+                            .await
+                            .map_err(Into::into),
+                        None => Ok(()),
+                    },
+                    Err(e) => Err(e),
+                }
+            }
+            (TransactionControl::Commit, true) => self.commit_transaction().await,
+            (TransactionControl::Rollback, true) => self.rollback_transaction().await,
+            (TransactionControl::CommitAndChain, true) => self.chain_transaction(sql, true).await,
+            (TransactionControl::RollbackAndChain, true) => {
+                self.chain_transaction(sql, false).await
+            }
+            _ => return None,
+        };
+
+        let execution_time_ms = start_time.elapsed().as_millis();
+        Some(match result {
+            Ok(_) => QueryExecutionResult::Modified(ModifiedResult {
+                rows_affected: 0,
+                execution_time_ms,
+            }),
+            Err(e) => QueryExecutionResult::Error(match e.downcast_ref::<sqlx::Error>() {
+                Some(error) => query_failed(error, execution_time_ms, false),
+                None => ErrorResult {
+                    message: format!("Query failed: {}", e),
+                    execution_time_ms,
+                    ..Default::default()
+                },
+            }),
+        })
+    }
+
+    async fn execute_modification_query(
+        &self,
+        sql: &str,
+        values: &[BoundValue],
+        conn: &mut PgConnection,
+    ) -> QueryExecutionResult {
+        let start_time = std::time::Instant::now();
+        match bind_values(sqlx::query(sql), values).execute(conn).await {
             Ok(result) => {
                 let execution_time_ms = start_time.elapsed().as_millis();
                 QueryExecutionResult::Modified(ModifiedResult {
@@ -52,13 +308,23 @@ impl DatabaseManager {
                     execution_time_ms,
                 })
             }
-            Err(e) => {
-                let execution_time_ms = start_time.elapsed().as_millis();
-                QueryExecutionResult::Error(ErrorResult {
-                    message: format!("Query failed: {}", e),
-                    execution_time_ms,
-                })
-            }
+            Err(e) => self.failed_result(e, start_time.elapsed().as_millis()),
+        }
+    }
+
+    /// Maps a query error to a result, distinguishing user cancellation.
+    fn failed_result(&self, error: sqlx::Error, execution_time_ms: u128) -> QueryExecutionResult {
+        let was_cancelled = self.cancel_requested.swap(false, Ordering::SeqCst)
+            && error
+                .as_database_error()
+                .and_then(|db_err| db_err.code())
+                .is_some_and(|code| code == QUERY_CANCELED_SQLSTATE);
+
+        if was_cancelled {
+            QueryExecutionResult::Cancelled(CancelledResult { execution_time_ms })
+        } else {
+            let statement_timeout = self.statement_timeout_active.load(Ordering::SeqCst);
+            QueryExecutionResult::Error(query_failed(&error, execution_time_ms, statement_timeout))
         }
     }
 
@@ -81,6 +347,7 @@ impl DatabaseManager {
                         rows: vec![],
                         row_count: 0,
                         execution_time_ms: execution_time,
+                        has_more: false,
                     });
                 }
 
@@ -94,66 +361,164 @@ impl DatabaseManager {
                     rows: result_rows,
                     row_count: rows.len(),
                     execution_time_ms: execution_time,
+                    has_more: false,
                 })
             }
             Err(e) => {
                 let execution_time_ms = start_time.elapsed().as_millis();
-                QueryExecutionResult::Error(ErrorResult {
-                    message: format!("Query failed: {}", e),
-                    execution_time_ms,
-                })
+                QueryExecutionResult::Error(query_failed(&e, execution_time_ms, false))
             }
         }
     }
 
+    /// Runs a SELECT through a server-side cursor and fetches the first page.
+    ///
+    /// Outside an explicit transaction the cursor gets its own transaction on
+    /// this connection. Returns the table metadata when rows remain, meaning
+    /// the caller must keep the cursor (and its connection) open.
     pub(crate) async fn execute_select_query(
         &self,
         sql: &str,
+        values: &[BoundValue],
+        limits: &QueryTimeouts,
+        conn: &mut PgConnection,
         pool: &PgPool,
-    ) -> QueryExecutionResult {
+        in_transaction: bool,
+    ) -> (QueryExecutionResult, Option<TableMetadata>) {
         let start_time = std::time::Instant::now();
-        let original_query = sql.to_string();
 
-        let limited_sql = if !sql.to_lowercase().contains(" limit ") {
-            format!("{} LIMIT {}", sql.trim_end_matches(';'), 1_000)
-        } else {
-            sql.to_string()
-        };
+        if !in_transaction {
+            let begun = match sqlx::query("BEGIN").execute(&mut *conn).await {
+                Ok(_) => set_local_timeouts(conn, limits).await,
+                Err(e) => Err(e),
+            };
+            if let Err(e) = begun {
+                let _ = sqlx::query("ROLLBACK").execute(&mut *conn).await;
+                return (
+                    self.failed_result(e, start_time.elapsed().as_millis()),
+                    None,
+                );
+            }
+        }
 
-        match sqlx::query(limited_sql.as_ref()).fetch_all(pool).await {
-            Ok(rows) => {
-                let execution_time = start_time.elapsed().as_millis();
+        let declare_prefix = format!("DECLARE {} NO SCROLL CURSOR FOR ", self.cursor_name);
+        let declare = format!("{}{}", declare_prefix, strip_trailing_semicolons(sql));
 
-                if rows.is_empty() {
-                    return QueryExecutionResult::Select(QueryResult {
-                        original_query,
-                        columns: vec![],
-                        rows: vec![],
-                        row_count: 0,
-                        execution_time_ms: execution_time,
-                    });
-                }
+        // Inside the user's transaction a refused DECLARE must not abort it
+        if in_transaction && let Err(e) = sqlx::query(DECLARE_SAVEPOINT).execute(&mut *conn).await {
+            return (
+                self.failed_result(e, start_time.elapsed().as_millis()),
+                None,
+            );
+        }
 
-                let metadata = fetch_table_metadata(&rows, pool).await;
-                let columns = build_column_metadata(&rows[0], &metadata);
-                let result_rows = convert_rows(&rows, &metadata);
+        let declared = bind_values(sqlx::query(&declare).persistent(false), values)
+            .execute(&mut *conn)
+            .await;
+        if let Err(e) = declared {
+            if !is_cursor_refused(&e) {
+                // The statement itself failed: report it, leaving the user's
+                // transaction aborted as running it directly would
+                if !in_transaction {
+                    let _ = sqlx::query("ROLLBACK").execute(&mut *conn).await;
+                }
+                let mut result = self.failed_result(e, start_time.elapsed().as_millis());
+                unwrap_error_position(&mut result, &declare_prefix);
+                return (result, None);
+            }
 
-                QueryExecutionResult::Select(QueryResult {
-                    original_query,
-                    columns,
-                    rows: result_rows,
-                    row_count: rows.len(),
-                    execution_time_ms: execution_time,
-                })
+            // Statements such as SELECT ... INTO or data-modifying CTEs cannot
+            // be wrapped in a cursor, so run them as written instead
+            let undone = if in_transaction {
+                sqlx::raw_sql(ROLLBACK_DECLARE_SAVEPOINT)
+                    .execute(&mut *conn)
+                    .await
+                    .map(|_| ())
+            } else {
+                match sqlx::query("ROLLBACK").execute(&mut *conn).await {
+                    Ok(_) => set_timeouts(conn, limits).await,
+                    Err(e) => Err(e),
+                }
+            };
+            if let Err(e) = undone {
+                return (
+                    self.failed_result(e, start_time.elapsed().as_millis()),
+                    None,
+                );
             }
+            let result = self
+                .execute_unpaged_query(sql, values, conn, pool, start_time)
+                .await;
+            if !in_transaction {
+                reset_timeouts(conn, limits).await;
+            }
+            return (result, None);
+        }
+
+        if in_transaction
+            && let Err(e) = sqlx::query(RELEASE_DECLARE_SAVEPOINT)
+                .execute(&mut *conn)
+                .await
+        {
+            return (
+                self.failed_result(e, start_time.elapsed().as_millis()),
+                None,
+            );
+        }
+
+        let rows = match fetch_page(&mut *conn, &self.cursor_name, Some(RESULT_PAGE_SIZE)).await {
+            Ok(rows) => rows,
             Err(e) => {
-                let execution_time_ms = start_time.elapsed().as_millis();
-                QueryExecutionResult::Error(ErrorResult {
-                    message: format!("Query failed: {}", e),
-                    execution_time_ms,
-                })
+                if !in_transaction {
+                    let _ = sqlx::query("ROLLBACK").execute(&mut *conn).await;
+                }
+                return (
+                    self.failed_result(e, start_time.elapsed().as_millis()),
+                    None,
+                );
+            }
+        };
+
+        let has_more = rows.len() == RESULT_PAGE_SIZE;
+        if !has_more {
+            let finished = if in_transaction {
+                close_result_cursor(&mut *conn, &self.cursor_name).await
+            } else {
+                sqlx::query("COMMIT").execute(&mut *conn).await.map(|_| ())
+            };
+            if let Err(e) = finished {
+                return (
+                    self.failed_result(e, start_time.elapsed().as_millis()),
+                    None,
+                );
             }
         }
+
+        let (result, metadata) =
+            build_select_result(&rows, pool, sql.to_string(), start_time, has_more).await;
+
+        (
+            QueryExecutionResult::Select(result),
+            if has_more { Some(metadata) } else { None },
+        )
+    }
+
+    async fn execute_unpaged_query(
+        &self,
+        sql: &str,
+        values: &[BoundValue],
+        conn: &mut PgConnection,
+        pool: &PgPool,
+        start_time: std::time::Instant,
+    ) -> QueryExecutionResult {
+        match bind_values(sqlx::query(sql), values).fetch_all(conn).await {
+            Ok(rows) => {
+                let (result, _) =
+                    build_select_result(&rows, pool, sql.to_string(), start_time, false).await;
+                QueryExecutionResult::Select(result)
+            }
+            Err(e) => self.failed_result(e, start_time.elapsed().as_millis()),
+        }
     }
 
     /// Returns a stream of rows for large dataset exports
@@ -180,6 +545,157 @@ impl DatabaseManager {
 // Free functions for query processing
 // ============================================================================
 
+/// SQLSTATE reported by Postgres when a statement is cancelled
+const QUERY_CANCELED_SQLSTATE: &str = "57014";
+
+/// Errors DECLARE raises for statements that can't back a cursor: a
+/// data-modifying WITH (`feature_not_supported`) and SELECT ... INTO
+/// (`syntax_error`). Both come before anything runs, so running the
+/// statement as written instead doesn't run it twice.
+const CURSOR_REFUSED_SQLSTATES: [&str; 2] = ["0A000", "42601"];
+
+const DECLARE_SAVEPOINT: &str = "SAVEPOINT pgui_declare";
+const RELEASE_DECLARE_SAVEPOINT: &str = "RELEASE SAVEPOINT pgui_declare";
+const ROLLBACK_DECLARE_SAVEPOINT: &str =
+    "ROLLBACK TO SAVEPOINT pgui_declare; RELEASE SAVEPOINT pgui_declare";
+
+fn is_cursor_refused(error: &sqlx::Error) -> bool {
+    error
+        .as_database_error()
+        .and_then(|db_err| db_err.code())
+        .is_some_and(|code| CURSOR_REFUSED_SQLSTATES.contains(&code.as_ref()))
+}
+
+/// Error result for a failed statement, keeping the fields the server reported.
+/// `statement_timeout` says whether that limit applied to the statement.
+pub(crate) fn query_failed(
+    error: &sqlx::Error,
+    execution_time_ms: u128,
+    statement_timeout: bool,
+) -> ErrorResult {
+    let pg_error = error
+        .as_database_error()
+        .and_then(|db_err| db_err.try_downcast_ref::<PgDatabaseError>());
+    let timeout = pg_error.and_then(|pg_error| timeout_kind(pg_error, statement_timeout));
+
+    ErrorResult {
+        message: match timeout {
+            Some(kind) => format!("{} exceeded: {}", kind.label(), error),
+            None => format!("Query failed: {}", error),
+        },
+        execution_time_ms,
+        database_error: pg_error.map(|db_err| Box::new(database_error_details(db_err))),
+        timeout,
+    }
+}
+
+fn database_error_details(error: &PgDatabaseError) -> DatabaseErrorDetails {
+    let (position, internal_position, internal_query) = match error.position() {
+        Some(PgErrorPosition::Original(position)) => (Some(position), None, None),
+        Some(PgErrorPosition::Internal { position, query }) => {
+            (None, Some(position), Some(query.to_string()))
+        }
+        None => (None, None, None),
+    };
+
+    DatabaseErrorDetails {
+        severity: format!("{:?}", error.severity()).to_uppercase(),
+        code: error.code().to_string(),
+        message: error.message().to_string(),
+        detail: error.detail().map(str::to_string),
+        hint: error.hint().map(str::to_string),
+        position,
+        internal_position,
+        internal_query,
+        context: error.r#where().map(str::to_string),
+        schema: error.schema().map(str::to_string),
+        table: error.table().map(str::to_string),
+        column: error.column().map(str::to_string),
+        data_type: error.data_type().map(str::to_string),
+        constraint: error.constraint().map(str::to_string),
+        file: error.file().map(str::to_string),
+        line: error.line(),
+        routine: error.routine().map(str::to_string),
+    }
+}
+
+/// Makes the error position of a statement run wrapped in `prefix` relative
+/// to the statement itself
+fn unwrap_error_position(result: &mut QueryExecutionResult, prefix: &str) {
+    if let QueryExecutionResult::Error(ErrorResult {
+        database_error: Some(details),
+        ..
+    }) = result
+    {
+        let prefix_chars = prefix.chars().count();
+        details.position = details
+            .position
+            .and_then(|position| position.checked_sub(prefix_chars))
+            .filter(|position| *position > 0);
+    }
+}
+
+/// Why a statement may not run on a read-only connection, if it may not
+fn read_only_violation(sql: &str) -> Option<String> {
+    if classify_statement(sql).modifies_data() || runs_code(sql) {
+        let keyword = sql.split_whitespace().next().unwrap_or_default();
+        Some(format!(
+            "Blocked: this connection is read-only and {} statements can modify the database",
+            keyword.to_uppercase()
+        ))
+    } else if enables_writes(sql) {
+        Some("Blocked: this connection is read-only and cannot be switched to read-write".into())
+    } else {
+        None
+    }
+}
+
+/// Removes trailing semicolons so the statement can be embedded in DECLARE
+fn strip_trailing_semicolons(sql: &str) -> &str {
+    let mut sql = sql.trim_end();
+    while let Some(stripped) = sql.strip_suffix(';') {
+        sql = stripped.trim_end();
+    }
+    sql
+}
+
+async fn build_select_result(
+    rows: &[PgRow],
+    pool: &PgPool,
+    original_query: String,
+    start_time: std::time::Instant,
+    has_more: bool,
+) -> (QueryResult, TableMetadata) {
+    let execution_time_ms = start_time.elapsed().as_millis();
+
+    if rows.is_empty() {
+        let metadata = TableMetadata {
+            oid_to_table_name: HashMap::new(),
+            column_nullable_map: HashMap::new(),
+        };
+        let result = QueryResult {
+            original_query,
+            columns: vec![],
+            rows: vec![],
+            row_count: 0,
+            execution_time_ms,
+            has_more,
+        };
+        return (result, metadata);
+    }
+
+    let metadata = fetch_table_metadata(rows, pool).await;
+    let result = QueryResult {
+        original_query,
+        columns: build_column_metadata(&rows[0], &metadata),
+        rows: convert_rows(rows, &metadata),
+        row_count: rows.len(),
+        execution_time_ms,
+        has_more,
+    };
+    (result, metadata)
+}
+
 fn is_select_query(sql: &str) -> bool {
     let lower = sql.to_lowercase();
     let trimmed = lower.trim_start();
@@ -283,7 +799,7 @@ fn build_column_metadata(first_row: &PgRow, metadata: &TableMetadata) -> Vec<Res
         .collect()
 }
 
-fn convert_rows(rows: &[PgRow], metadata: &TableMetadata) -> Vec<ResultRow> {
+pub(crate) fn convert_rows(rows: &[PgRow], metadata: &TableMetadata) -> Vec<ResultRow> {
     rows.iter().map(|row| convert_row(row, metadata)).collect()
 }
 
@@ -323,47 +839,13 @@ fn build_cell_column_metadata(
     }
 }
 
-fn decode_cell_value(row: &PgRow, column: &PgColumn, index: usize) -> (String, bool) {
-    // Try to decode as String first - Postgres can convert most types to text
-    if let Ok(v) = row.try_get::<String, _>(index) {
-        return (v, false);
-    }
-
-    // If string decoding fails, try type-specific decoding
-    match column.type_info().name() {
-        "BOOL" => row
-            .try_get::<bool, _>(index)
-            .map(|v| (v.to_string(), false))
-            .unwrap_or_else(|_| ("NULL".to_string(), true)),
-        "INT2" | "INT4" => row
-            .try_get::<i32, _>(index)
-            .map(|v| (v.to_string(), false))
-            .unwrap_or_else(|_| ("NULL".to_string(), true)),
-        "INT8" => row
-            .try_get::<i64, _>(index)
-            .map(|v| (v.to_string(), false))
-            .unwrap_or_else(|_| ("NULL".to_string(), true)),
-        "FLOAT4" => row
-            .try_get::<f32, _>(index)
-            .map(|v| (v.to_string(), false))
-            .unwrap_or_else(|_| ("NULL".to_string(), true)),
-        "FLOAT8" => row
-            .try_get::<f64, _>(index)
-            .map(|v| (v.to_string(), false))
-            .unwrap_or_else(|_| ("NULL".to_string(), true)),
-        "NUMERIC" => row
-            .try_get::<rust_decimal::Decimal, _>(index)
-            .map(|v| (v.to_string(), false))
-            .unwrap_or_else(|_| ("NULL".to_string(), true)),
-        _ => ("NULL".to_string(), true),
-    }
-}
-
-fn extract_cell_value(row: &PgRow, column: &PgColumn, index: usize) -> (String, bool) {
+fn extract_cell_value(row: &PgRow, index: usize) -> CellValue {
     match row.try_get_raw(index) {
-        Ok(raw_value) if raw_value.is_null() => ("NULL".to_string(), true),
-        Ok(_) => decode_cell_value(row, column, index),
-        Err(_) => ("ERROR".to_string(), false),
+        Ok(raw_value) => decode_value(raw_value),
+        Err(e) => {
+            tracing::error!("Failed to read column {}: {}", index, e);
+            CellValue::Text("ERROR".to_string())
+        }
     }
 }
 
@@ -374,11 +856,12 @@ fn convert_cell(
     metadata: &TableMetadata,
 ) -> ResultCell {
     let column_metadata = build_cell_column_metadata(column, index, metadata);
-    let (value, is_null) = extract_cell_value(row, column, index);
+    let typed_value = extract_cell_value(row, index);
 
     ResultCell {
-        value,
-        is_null,
+        value: typed_value.to_text(),
+        is_null: typed_value.is_null(),
+        typed_value,
         column_metadata,
     }
 }
@@ -0,0 +1,157 @@
+//! Getting a dropped session back.
+//!
+//! After a user's `SET` or `RESET` the manager records the `search_path` and
+//! role of the connection it ran on. A reconnect replays them on each new
+//! pooled connection, so queries keep resolving the same way after a network
+//! blip.
+
+use sqlx::PgConnection;
+use std::time::Duration;
+
+use crate::services::sql::{Token, TokenKind, tokenize};
+
+const FIRST_RETRY_DELAY: Duration = Duration::from_millis(500);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(30);
+
+/// Wait before reconnect `attempt`, counting from 1: doubles from half a
+/// second up to thirty seconds
+pub fn reconnect_delay(attempt: u32) -> Duration {
+    FIRST_RETRY_DELAY
+        .saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)))
+        .min(MAX_RETRY_DELAY)
+}
+
+/// Settings a user typically changes with `SET` during a session
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct SessionSettings {
+    pub search_path: String,
+    /// Set when `SET ROLE` switched away from the login role
+    pub role: Option<String>,
+}
+
+impl SessionSettings {
+    /// Reads the settings of the connection a statement just ran on
+    pub(crate) async fn read(conn: &mut PgConnection) -> Result<Self, sqlx::Error> {
+        let (search_path, role): (String, Option<String>) = sqlx::query_as(
+            "SELECT current_setting('search_path'), \
+             CASE WHEN current_user <> session_user THEN current_user::text END",
+        )
+        .fetch_one(conn)
+        .await?;
+        Ok(Self { search_path, role })
+    }
+
+    /// Applies the settings to a new connection. `set_config` takes the
+    /// values as they were read, so nothing needs quoting. Without a role
+    /// the session goes back to the login role, undoing a configured
+    /// `SET ROLE` the user had reset.
+    pub(crate) async fn apply(&self, conn: &mut PgConnection) -> Result<(), sqlx::Error> {
+        sqlx::query("SELECT set_config('search_path', $1, false)")
+            .bind(&self.search_path)
+            .execute(&mut *conn)
+            .await?;
+        sqlx::query("SELECT set_config('role', coalesce($1, 'none'), false)")
+            .bind(&self.role)
+            .execute(&mut *conn)
+            .await?;
+        Ok(())
+    }
+}
+
+/// Whether `sql` may change the recorded settings for the rest of the
+/// session: `SET`, `RESET` or a `set_config` call that isn't local. `SET
+/// LOCAL` and the transaction forms end with the transaction.
+pub(crate) fn changes_session_settings(sql: &str) -> bool {
+    let tokens = tokenize(sql);
+    match tokens.first() {
+        Some(first) if first.is_word("reset") => true,
+        Some(first) if first.is_word("set") => tokens.get(1).is_some_and(|next| {
+            !(next.is_word("local") || next.is_word("transaction") || next.is_word("constraints"))
+        }),
+        _ => tokens
+            .iter()
+            .enumerate()
+            .filter(|(_, token)| token.is_word("set_config"))
+            .any(|(ix, _)| set_config_is_local(&tokens[ix + 1..]) == Some(false)),
+    }
+}
+
+/// Whether the `set_config(...)` call whose arguments start `tokens` sets
+/// `is_local`; `None` when the tokens aren't such a call
+fn set_config_is_local(tokens: &[Token]) -> Option<bool> {
+    let is_symbol =
+        |token: &Token, symbol: &str| token.kind == TokenKind::Symbol && token.text == symbol;
+    if !is_symbol(tokens.first()?, "(") {
+        return None;
+    }
+
+    // The tokens of the third argument, up to the closing parenthesis
+    let mut depth = 0usize;
+    let mut commas = 0;
+    let mut is_local = vec![];
+    for token in &tokens[1..] {
+        if is_symbol(token, "(") {
+            depth += 1;
+        } else if is_symbol(token, ")") {
+            if depth == 0 {
+                break;
+            }
+            depth -= 1;
+        } else if depth == 0 && is_symbol(token, ",") {
+            commas += 1;
+            continue;
+        }
+        if commas == 2 {
+            is_local.push(token);
+        }
+    }
+
+    // Anything but a literal true might be false
+    Some(match is_local.as_slice() {
+        [value] => {
+            value.is_word("true")
+                || matches!(
+                    value.text.to_ascii_lowercase().as_str(),
+                    "'true'" | "'t'" | "'on'" | "'yes'" | "'1'"
+                )
+        }
+        _ => false,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reconnect_delay() {
+        assert_eq!(reconnect_delay(1), Duration::from_millis(500));
+        assert_eq!(reconnect_delay(2), Duration::from_secs(1));
+        assert_eq!(reconnect_delay(4), Duration::from_secs(4));
+        assert_eq!(reconnect_delay(7), Duration::from_secs(30));
+        assert_eq!(reconnect_delay(u32::MAX), Duration::from_secs(30));
+    }
+
+    #[test]
+    fn test_changes_session_settings() {
+        assert!(changes_session_settings("SET search_path TO app, public"));
+        assert!(changes_session_settings("set role reporting"));
+        assert!(changes_session_settings("RESET ALL"));
+        assert!(!changes_session_settings("SET LOCAL search_path TO app"));
+        assert!(!changes_session_settings("SET TRANSACTION READ ONLY"));
+        assert!(!changes_session_settings("SELECT 'set role'"));
+        assert!(!changes_session_settings("set local role reporting"));
+        assert!(changes_session_settings(
+            "SELECT set_config('search_path', 'app', false)"
+        ));
+        assert!(changes_session_settings(
+            "select pg_catalog.set_config('role', lower('Reporting'), $1)"
+        ));
+        assert!(!changes_session_settings(
+            "SELECT set_config('search_path', concat('app', ','), true)"
+        ));
+        assert!(!changes_session_settings(
+            "SELECT 'set_config(x, y, false)'"
+        ));
+    }
+}
@@ -0,0 +1,165 @@
+//! Server-side time limits for user statements.
+//!
+//! Connection defaults are sent as startup options, so every pooled
+//! connection starts with them. Per-run overrides are `SET` on the connection
+//! running the statement and `RESET` afterwards, which restores the defaults.
+
+use serde::{Deserialize, Serialize};
+use sqlx::PgConnection;
+use sqlx::postgres::PgDatabaseError;
+
+/// Limits in milliseconds. `None` keeps the inherited value and `Some(0)`
+/// disables the limit.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QueryTimeouts {
+    #[serde(default)]
+    pub statement_timeout_ms: Option<u64>,
+    #[serde(default)]
+    pub lock_timeout_ms: Option<u64>,
+    #[serde(default)]
+    pub idle_in_transaction_timeout_ms: Option<u64>,
+}
+
+impl QueryTimeouts {
+    pub fn is_empty(&self) -> bool {
+        self.settings().is_empty()
+    }
+
+    /// Server settings that have a value, as `(name, milliseconds)`
+    pub fn settings(&self) -> Vec<(&'static str, u64)> {
+        [
+            (TimeoutKind::Statement, self.statement_timeout_ms),
+            (TimeoutKind::Lock, self.lock_timeout_ms),
+            (
+                TimeoutKind::IdleInTransaction,
+                self.idle_in_transaction_timeout_ms,
+            ),
+        ]
+        .into_iter()
+        .filter_map(|(kind, ms)| ms.map(|ms| (kind.setting(), ms)))
+        .collect()
+    }
+
+    /// Limits that apply to a single statement; the idle limit only makes
+    /// sense for a whole transaction
+    pub(crate) fn statement_limits(&self) -> QueryTimeouts {
+        QueryTimeouts {
+            idle_in_transaction_timeout_ms: None,
+            ..*self
+        }
+    }
+}
+
+/// The server limit that stopped a statement or session
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TimeoutKind {
+    Statement,
+    Lock,
+    IdleInTransaction,
+}
+
+impl TimeoutKind {
+    pub fn label(&self) -> &'static str {
+        match self {
+            TimeoutKind::Statement => "Statement timeout",
+            TimeoutKind::Lock => "Lock timeout",
+            TimeoutKind::IdleInTransaction => "Idle in transaction timeout",
+        }
+    }
+
+    /// Name of the server setting
+    pub fn setting(&self) -> &'static str {
+        match self {
+            TimeoutKind::Statement => "statement_timeout",
+            TimeoutKind::Lock => "lock_timeout",
+            TimeoutKind::IdleInTransaction => "idle_in_transaction_session_timeout",
+        }
+    }
+}
+
+/// Recognises the errors Postgres raises when one of the limits is hit.
+///
+/// Only the SQLSTATE is checked, since messages follow the server's
+/// `lc_messages`. A statement timeout shares its code with any cancel, so it
+/// is only reported when `statement_timeout` was in effect for the run. Cancels
+/// the user asked for are reported before this is reached; a `NOWAIT` lock
+/// failure shares its code with the lock timeout.
+pub(crate) fn timeout_kind(
+    error: &PgDatabaseError,
+    statement_timeout: bool,
+) -> Option<TimeoutKind> {
+    match error.code() {
+        "57014" if statement_timeout => Some(TimeoutKind::Statement),
+        "55P03" => Some(TimeoutKind::Lock),
+        "25P03" => Some(TimeoutKind::IdleInTransaction),
+        _ => None,
+    }
+}
+
+/// Applies per-run overrides for the rest of the session
+pub(crate) async fn set_timeouts(
+    conn: &mut PgConnection,
+    timeouts: &QueryTimeouts,
+) -> Result<(), sqlx::Error> {
+    for (setting, ms) in timeouts.settings() {
+        sqlx::query(&format!("SET {} = {}", setting, ms))
+            .execute(&mut *conn)
+            .await?;
+    }
+    Ok(())
+}
+
+/// Like [`set_timeouts`], but only until the current transaction ends
+pub(crate) async fn set_local_timeouts(
+    conn: &mut PgConnection,
+    timeouts: &QueryTimeouts,
+) -> Result<(), sqlx::Error> {
+    for (setting, ms) in timeouts.settings() {
+        sqlx::query(&format!("SET LOCAL {} = {}", setting, ms))
+            .execute(&mut *conn)
+            .await?;
+    }
+    Ok(())
+}
+
+/// Restores the connection defaults after [`set_timeouts`].
+///
+/// This fails in an aborted transaction, but the rollback that must follow
+/// undoes the `SET` as well.
+pub(crate) async fn reset_timeouts(conn: &mut PgConnection, timeouts: &QueryTimeouts) {
+    for (setting, _) in timeouts.settings() {
+        if let Err(e) = sqlx::query(&format!("RESET {}", setting))
+            .execute(&mut *conn)
+            .await
+        {
+            tracing::debug!("Could not reset {}: {}", setting, e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_settings_skip_unset_limits() {
+        let timeouts = QueryTimeouts {
+            statement_timeout_ms: Some(5_000),
+            lock_timeout_ms: None,
+            idle_in_transaction_timeout_ms: Some(0),
+        };
+        assert_eq!(
+            timeouts.settings(),
+            vec![
+                ("statement_timeout", 5_000),
+                ("idle_in_transaction_session_timeout", 0)
+            ]
+        );
+        assert_eq!(
+            timeouts.statement_limits().settings(),
+            vec![("statement_timeout", 5_000)]
+        );
+        assert!(QueryTimeouts::default().is_empty());
+    }
+}
@@ -0,0 +1,209 @@
+use anyhow::Result;
+use std::sync::atomic::Ordering;
+
+use super::manager::DatabaseManager;
+use super::types::TransactionStatus;
+
+/// Transaction control statements that are handled by the manager itself
+/// rather than being sent to whichever pooled connection is free.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum TransactionControl {
+    Begin,
+    Commit,
+    Rollback,
+    /// `COMMIT AND CHAIN`: the next transaction starts on the same connection
+    CommitAndChain,
+    RollbackAndChain,
+}
+
+impl DatabaseManager {
+    /// Starts an explicit transaction on a connection that stays pinned to
+    /// this manager until it is committed or rolled back.
+    pub async fn begin_transaction(&self) -> Result<()> {
+        self.begin_transaction_with("BEGIN").await
+    }
+
+    /// Starts a pinned transaction using the given statement, so options such
+    /// as `BEGIN ISOLATION LEVEL SERIALIZABLE` are preserved.
+    pub(crate) async fn begin_transaction_with(&self, statement: &str) -> Result<()> {
+        let mut session = self.session.lock().await;
+        if session.is_some() {
+            anyhow::bail!("A transaction is already open");
+        }
+
+        let pool_guard = self.pool.read().await;
+        let pool = pool_guard
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("Database not connected"))?;
+
+        let mut conn = pool.acquire().await?;
+        sqlx::raw_sql(statement).execute(&mut *conn).await?;
+
+        *session = Some(conn);
+        *self.transaction_status.write().await = TransactionStatus::InTransaction;
+        Ok(())
+    }
+
+    /// Commits the open transaction and returns its connection to the pool.
+    /// Postgres rolls back instead if the transaction had failed.
+    pub async fn commit_transaction(&self) -> Result<()> {
+        self.end_transaction("COMMIT").await
+    }
+
+    /// Rolls back the open transaction and returns its connection to the pool.
+    pub async fn rollback_transaction(&self) -> Result<()> {
+        self.end_transaction("ROLLBACK").await
+    }
+
+    /// Ends the open transaction with `COMMIT AND CHAIN` or `ROLLBACK AND
+    /// CHAIN`, keeping the connection pinned for the one that follows. Even a
+    /// failed transaction chains into a working one.
+    pub(crate) async fn chain_transaction(&self, statement: &str, commit: bool) -> Result<()> {
+        self.close_all_cursors(true).await;
+
+        let mut session = self.session.lock().await;
+        let Some(conn) = session.as_mut() else {
+            anyhow::bail!("No open transaction");
+        };
+
+        let mut status = self.transaction_status.write().await;
+        let settings_changed = self.session_settings_pending.swap(false, Ordering::SeqCst)
+            && *status == TransactionStatus::InTransaction;
+
+        if let Err(e) = sqlx::raw_sql(statement).execute(&mut **conn).await {
+            // A commit that fails ends the transaction without chaining
+            if let Some(mut conn) = session.take() {
+                conn.close_on_drop();
+            }
+            *status = TransactionStatus::Idle;
+            return Err(e.into());
+        }
+        *status = TransactionStatus::InTransaction;
+        drop(status);
+
+        if settings_changed && commit {
+            self.record_session_settings(conn).await;
+        }
+        Ok(())
+    }
+
+    pub async fn transaction_status(&self) -> TransactionStatus {
+        *self.transaction_status.read().await
+    }
+
+    async fn end_transaction(&self, statement: &str) -> Result<()> {
+        // A cursor declared inside the transaction does not survive it,
+        // whichever handle it belongs to
+        self.close_all_cursors(true).await;
+
+        let mut session = self.session.lock().await;
+        let Some(mut conn) = session.take() else {
+            anyhow::bail!("No open transaction");
+        };
+
+        let status = std::mem::take(&mut *self.transaction_status.write().await);
+        // A failed transaction rolls back even on COMMIT
+        let settings_changed = self.session_settings_pending.swap(false, Ordering::SeqCst)
+            && status == TransactionStatus::InTransaction;
+
+        if let Err(e) = sqlx::query(statement).execute(&mut *conn).await {
+            // Never hand a connection in an unknown transaction state back to the pool
+            conn.close_on_drop();
+            return Err(e.into());
+        }
+
+        // A rolled back SET is undone, so there is nothing new to record
+        if settings_changed && statement == "COMMIT" {
+            self.record_session_settings(&mut conn).await;
+        }
+
+        Ok(())
+    }
+
+    /// Records the outcome of a statement that ran on the pinned connection.
+    pub(crate) async fn note_transaction_statement(&self, sql: &str, succeeded: bool) {
+        let mut status = self.transaction_status.write().await;
+        if !succeeded {
+            *status = TransactionStatus::Failed;
+        } else if is_rollback_to_savepoint(sql) {
+            *status = TransactionStatus::InTransaction;
+        }
+    }
+}
+
+/// Detects BEGIN / COMMIT / ROLLBACK statements so they can drive the
+/// pinned transaction instead of leaking a transaction into the pool.
+pub(crate) fn transaction_control(sql: &str) -> Option<TransactionControl> {
+    let lower = sql.trim().trim_end_matches(';').to_lowercase();
+    let words: Vec<&str> = lower.split_whitespace().collect();
+    let chain = words.windows(2).any(|pair| pair == ["and", "chain"]);
+
+    match words.first().copied()? {
+        "begin" if words.get(1).is_none_or(|w| is_transaction_word(w)) => {
+            Some(TransactionControl::Begin)
+        }
+        "start" if words.get(1) == Some(&"transaction") => Some(TransactionControl::Begin),
+        "commit" | "end" if !words.contains(&"prepared") => Some(if chain {
+            TransactionControl::CommitAndChain
+        } else {
+            TransactionControl::Commit
+        }),
+        "rollback" | "abort" if !words.contains(&"to") && !words.contains(&"prepared") => {
+            Some(if chain {
+                TransactionControl::RollbackAndChain
+            } else {
+                TransactionControl::Rollback
+            })
+        }
+        _ => None,
+    }
+}
+
+fn is_transaction_word(word: &str) -> bool {
+    matches!(word, "transaction" | "work" | "isolation" | "read")
+}
+
+fn is_rollback_to_savepoint(sql: &str) -> bool {
+    let lower = sql.trim().to_lowercase();
+    (lower.starts_with("rollback") || lower.starts_with("abort"))
+        && lower.split_whitespace().any(|w| w == "to")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transaction_control() {
+        let cases = [
+            ("BEGIN", Some(TransactionControl::Begin)),
+            (
+                "start transaction read only",
+                Some(TransactionControl::Begin),
+            ),
+            ("COMMIT;", Some(TransactionControl::Commit)),
+            ("commit and no chain", Some(TransactionControl::Commit)),
+            ("COMMIT AND CHAIN", Some(TransactionControl::CommitAndChain)),
+            (
+                "end work and chain",
+                Some(TransactionControl::CommitAndChain),
+            ),
+            ("ROLLBACK", Some(TransactionControl::Rollback)),
+            (
+                "rollback and chain",
+                Some(TransactionControl::RollbackAndChain),
+            ),
+            (
+                "ABORT AND CHAIN",
+                Some(TransactionControl::RollbackAndChain),
+            ),
+            ("ROLLBACK TO SAVEPOINT a", None),
+            ("COMMIT PREPARED 'tx'", None),
+            ("ROLLBACK PREPARED 'tx'", None),
+            ("BEGIN; SELECT 1", None),
+        ];
+        for (sql, control) in cases {
+            assert_eq!(transaction_control(sql), control, "{}", sql);
+        }
+    }
+}
@@ -0,0 +1,533 @@
+//! SSH tunnels to servers that are only reachable through a bastion.
+//!
+//! A tunnel listens on a free local port and forwards every accepted
+//! connection over one SSH session as a `direct-tcpip` channel, like
+//! `ssh -L`. libssh2 is blocking, so the session runs on its own thread in
+//! non-blocking mode and polls the local sockets and the channels in turn,
+//! sleeping longer the longer nothing moves.
+
+use anyhow::{Context as _, Result};
+use base64::Engine as _;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use serde::{Deserialize, Serialize};
+use ssh2::{Channel, CheckResult, HostKeyType, KnownHostFileKind, Session};
+use std::collections::VecDeque;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Read, Write};
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+const DEFAULT_SSH_PORT: u16 = 22;
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+/// Pause of the forwarding thread once nothing moved; it doubles while the
+/// tunnel stays idle, up to `MAX_IDLE_POLL`
+const MIN_IDLE_POLL: Duration = Duration::from_millis(1);
+const MAX_IDLE_POLL: Duration = Duration::from_millis(25);
+const KEEPALIVE_INTERVAL_SECS: u32 = 30;
+const BUFFER_SIZE: usize = 32 * 1024;
+
+/// How the jump host is reached and how its identity is checked
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SshTunnelConfig {
+    pub host: String,
+    #[serde(default = "default_ssh_port")]
+    pub port: u16,
+    pub user: String,
+    #[serde(default)]
+    pub auth: SshAuth,
+    #[serde(default)]
+    pub known_hosts: KnownHostsPolicy,
+    /// Password, or passphrase of the key file. Kept in the keyring and
+    /// never serialized.
+    #[serde(skip)]
+    pub secret: String,
+}
+
+fn default_ssh_port() -> u16 {
+    DEFAULT_SSH_PORT
+}
+
+impl SshTunnelConfig {
+    pub fn new(host: String, user: String) -> Self {
+        Self {
+            host,
+            port: DEFAULT_SSH_PORT,
+            user,
+            auth: SshAuth::default(),
+            known_hosts: KnownHostsPolicy::default(),
+            secret: String::new(),
+        }
+    }
+}
+
+/// How to log in to the jump host
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(tag = "method", rename_all = "snake_case")]
+pub enum SshAuth {
+    /// Keys loaded in the running `ssh-agent`
+    #[default]
+    Agent,
+    /// A private key file, `~/` allowed; the secret is its passphrase
+    KeyFile { path: String },
+    /// The secret is the account password
+    Password,
+}
+
+/// What to do with the jump host's key, checked against
+/// `~/.ssh/known_hosts`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KnownHostsPolicy {
+    /// Refuse hosts that are not listed yet
+    Strict,
+    /// Trust and record hosts seen for the first time, refuse changed keys
+    #[default]
+    AcceptNew,
+    /// Don't check the host key
+    Off,
+}
+
+impl KnownHostsPolicy {
+    pub const ALL: [KnownHostsPolicy; 3] = [Self::Strict, Self::AcceptNew, Self::Off];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Strict => "Strict",
+            Self::AcceptNew => "Accept new",
+            Self::Off => "Off",
+        }
+    }
+}
+
+/// A running tunnel; dropping it stops forwarding and closes the session
+pub(crate) struct SshTunnel {
+    local_port: u16,
+    shutdown: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl std::fmt::Debug for SshTunnel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SshTunnel")
+            .field("local_port", &self.local_port)
+            .finish()
+    }
+}
+
+impl SshTunnel {
+    /// Logs in to the jump host and forwards a local port to
+    /// `target_host:target_port` as the jump host resolves it. Blocks until
+    /// the session is authenticated.
+    pub(crate) fn open(
+        config: &SshTunnelConfig,
+        target_host: &str,
+        target_port: u16,
+    ) -> Result<Self> {
+        let address = (config.host.as_str(), config.port)
+            .to_socket_addrs()
+            .with_context(|| format!("Failed to resolve SSH host '{}'", config.host))?
+            .next()
+            .with_context(|| format!("Failed to resolve SSH host '{}'", config.host))?;
+        let tcp = TcpStream::connect_timeout(&address, CONNECT_TIMEOUT)
+            .with_context(|| format!("Failed to reach SSH host {}:{}", config.host, config.port))?;
+
+        let mut session = Session::new().context("Failed to start an SSH session")?;
+        session.set_timeout(CONNECT_TIMEOUT.as_millis() as u32);
+        session.set_tcp_stream(tcp);
+        session.handshake().context("SSH handshake failed")?;
+        check_host_key(&session, config)?;
+        authenticate(&session, config)?;
+        session.set_keepalive(true, KEEPALIVE_INTERVAL_SECS);
+
+        let listener =
+            TcpListener::bind(("127.0.0.1", 0)).context("Failed to open a local tunnel port")?;
+        listener.set_nonblocking(true)?;
+        let local_port = listener.local_addr()?.port();
+
+        let shutdown = Arc::new(AtomicBool::new(false));
+        let thread = thread::Builder::new()
+            .name(format!("ssh-tunnel-{}", local_port))
+            .spawn({
+                let shutdown = shutdown.clone();
+                let target_host = target_host.to_string();
+                move || forward(session, listener, target_host, target_port, shutdown)
+            })
+            .context("Failed to start the SSH tunnel")?;
+
+        tracing::info!(
+            "SSH tunnel via {}@{}:{} listening on 127.0.0.1:{}",
+            config.user,
+            config.host,
+            config.port,
+            local_port
+        );
+
+        Ok(Self {
+            local_port,
+            shutdown,
+            thread: Some(thread),
+        })
+    }
+
+    pub(crate) fn local_port(&self) -> u16 {
+        self.local_port
+    }
+}
+
+impl Drop for SshTunnel {
+    fn drop(&mut self) {
+        self.shutdown.store(true, Ordering::SeqCst);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+fn check_host_key(session: &Session, config: &SshTunnelConfig) -> Result<()> {
+    if config.known_hosts == KnownHostsPolicy::Off {
+        return Ok(());
+    }
+
+    let (key, key_type) = session
+        .host_key()
+        .context("The SSH server sent no host key")?;
+    let path = known_hosts_path()?;
+    let mut known_hosts = session.known_hosts()?;
+    if path.exists() {
+        known_hosts
+            .read_file(&path, KnownHostFileKind::OpenSSH)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+    }
+
+    match known_hosts.check_port(&config.host, config.port, key) {
+        CheckResult::Match => Ok(()),
+        CheckResult::Mismatch => anyhow::bail!(
+            "The host key of '{}' has changed. If that is expected, remove its old key from {}.",
+            config.host,
+            path.display()
+        ),
+        CheckResult::NotFound if config.known_hosts == KnownHostsPolicy::AcceptNew => {
+            append_known_host(&path, &config.host, config.port, key, key_type)
+        }
+        CheckResult::NotFound => anyhow::bail!(
+            "'{}' is not in {}. Connect once with ssh to trust its host key.",
+            config.host,
+            path.display()
+        ),
+        CheckResult::Failure => {
+            anyhow::bail!("Failed to check the host key of '{}'", config.host)
+        }
+    }
+}
+
+/// Records a first-seen host the way OpenSSH does, without rewriting the
+/// entries already in the file
+fn append_known_host(
+    path: &Path,
+    host: &str,
+    port: u16,
+    key: &[u8],
+    key_type: HostKeyType,
+) -> Result<()> {
+    let key_name = match key_type {
+        HostKeyType::Rsa => "ssh-rsa",
+        HostKeyType::Dss => "ssh-dss",
+        HostKeyType::Ecdsa256 => "ecdsa-sha2-nistp256",
+        HostKeyType::Ecdsa384 => "ecdsa-sha2-nistp384",
+        HostKeyType::Ecdsa521 => "ecdsa-sha2-nistp521",
+        HostKeyType::Ed25519 => "ssh-ed25519",
+        HostKeyType::Unknown => anyhow::bail!("'{}' sent an unknown type of host key", host),
+    };
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("Failed to open {}", path.display()))?;
+    writeln!(
+        file,
+        "{} {} {}",
+        known_host_name(host, port),
+        key_name,
+        BASE64.encode(key)
+    )?;
+    tracing::info!("Added '{}' to {}", host, path.display());
+    Ok(())
+}
+
+/// `host`, or `[host]:port` off the default port
+fn known_host_name(host: &str, port: u16) -> String {
+    if port == DEFAULT_SSH_PORT {
+        host.to_string()
+    } else {
+        format!("[{}]:{}", host, port)
+    }
+}
+
+fn known_hosts_path() -> Result<PathBuf> {
+    let home = dirs::home_dir().context("Failed to find the home directory")?;
+    Ok(home.join(".ssh").join("known_hosts"))
+}
+
+fn authenticate(session: &Session, config: &SshTunnelConfig) -> Result<()> {
+    match &config.auth {
+        SshAuth::Agent => session
+            .userauth_agent(&config.user)
+            .context("SSH agent authentication failed. Is the key added with ssh-add?")?,
+        SshAuth::KeyFile { path } => {
+            let path = match path.strip_prefix("~/") {
+                Some(rest) => dirs::home_dir()
+                    .context("Failed to find the home directory")?
+                    .join(rest),
+                None => PathBuf::from(path),
+            };
+            let passphrase = (!config.secret.is_empty()).then_some(config.secret.as_str());
+            session
+                .userauth_pubkey_file(&config.user, None, &path, passphrase)
+                .with_context(|| format!("SSH authentication with {} failed", path.display()))?
+        }
+        SshAuth::Password => session
+            .userauth_password(&config.user, &config.secret)
+            .context("SSH password authentication failed")?,
+    }
+
+    anyhow::ensure!(
+        session.authenticated(),
+        "The SSH server rejected user '{}'",
+        config.user
+    );
+    Ok(())
+}
+
+/// The tunnel thread: accepts local connections and moves bytes until
+/// `shutdown` is set
+fn forward(
+    session: Session,
+    listener: TcpListener,
+    target_host: String,
+    target_port: u16,
+    shutdown: Arc<AtomicBool>,
+) {
+    session.set_blocking(false);
+    // Accepted connections whose channel is still being opened, oldest
+    // first; libssh2 opens one channel at a time
+    let mut accepted: VecDeque<TcpStream> = VecDeque::new();
+    let mut forwards: Vec<Forward> = Vec::new();
+    let mut buf = vec![0u8; BUFFER_SIZE];
+    let mut idle_poll = MIN_IDLE_POLL;
+
+    while !shutdown.load(Ordering::SeqCst) {
+        let mut busy = false;
+
+        match listener.accept() {
+            Ok((client, _)) => {
+                busy = true;
+                accepted.push_back(client);
+            }
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => {}
+            Err(e) => {
+                tracing::error!("SSH tunnel stopped accepting connections: {}", e);
+                break;
+            }
+        }
+
+        while let Some(client) = accepted.pop_front() {
+            match session.channel_direct_tcpip(&target_host, target_port, None) {
+                Ok(channel) => {
+                    busy = true;
+                    match Forward::new(client, channel) {
+                        Ok(forward) => forwards.push(forward),
+                        Err(e) => tracing::debug!("SSH tunnel connection closed: {}", e),
+                    }
+                }
+                Err(e) => {
+                    let e = io::Error::from(e);
+                    if e.kind() == io::ErrorKind::WouldBlock {
+                        accepted.push_front(client);
+                        break;
+                    }
+                    busy = true;
+                    tracing::error!(
+                        "SSH tunnel failed to reach {}:{}: {}",
+                        target_host,
+                        target_port,
+                        e
+                    );
+                }
+            }
+        }
+
+        forwards.retain_mut(|forward| match forward.pump(&mut buf) {
+            Ok(moved) => {
+                busy |= moved;
+                !forward.is_done()
+            }
+            Err(e) => {
+                tracing::debug!("SSH tunnel connection closed: {}", e);
+                false
+            }
+        });
+
+        let _ = session.keepalive_send();
+        if busy {
+            idle_poll = MIN_IDLE_POLL;
+        } else {
+            thread::sleep(idle_poll);
+            idle_poll = (idle_poll * 2).min(MAX_IDLE_POLL);
+        }
+    }
+
+    let _ = session.disconnect(None, "pgui disconnected", None);
+}
+
+/// One local connection and the channel it is forwarded over, with the
+/// bytes read from one side that the other hasn't taken yet
+struct Forward {
+    client: TcpStream,
+    channel: Channel,
+    to_server: Vec<u8>,
+    to_client: Vec<u8>,
+    client_closed: bool,
+    server_closed: bool,
+}
+
+impl Forward {
+    fn new(client: TcpStream, channel: Channel) -> io::Result<Self> {
+        client.set_nonblocking(true)?;
+        let _ = client.set_nodelay(true);
+        Ok(Self {
+            client,
+            channel,
+            to_server: Vec::new(),
+            to_client: Vec::new(),
+            client_closed: false,
+            server_closed: false,
+        })
+    }
+
+    /// Moves what is ready in both directions; returns whether anything
+    /// moved
+    fn pump(&mut self, buf: &mut [u8]) -> io::Result<bool> {
+        let mut moved = false;
+
+        if !self.client_closed && self.to_server.is_empty() {
+            match self.client.read(buf) {
+                Ok(0) => {
+                    self.client_closed = true;
+                    let _ = self.channel.send_eof();
+                    moved = true;
+                }
+                Ok(n) => {
+                    self.to_server.extend_from_slice(&buf[..n]);
+                    moved = true;
+                }
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => {}
+                Err(e) => return Err(e),
+            }
+        }
+        moved |= flush(&mut self.channel, &mut self.to_server)?;
+
+        if !self.server_closed && self.to_client.is_empty() {
+            match self.channel.read(buf) {
+                Ok(0) => {
+                    if self.channel.eof() {
+                        self.server_closed = true;
+                        moved = true;
+                    }
+                }
+                Ok(n) => {
+                    self.to_client.extend_from_slice(&buf[..n]);
+                    moved = true;
+                }
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => {}
+                Err(e) => return Err(e),
+            }
+        }
+        moved |= flush(&mut self.client, &mut self.to_client)?;
+
+        Ok(moved)
+    }
+
+    /// Either side hung up and everything read has been passed on
+    fn is_done(&self) -> bool {
+        (self.client_closed || self.server_closed)
+            && self.to_server.is_empty()
+            && self.to_client.is_empty()
+    }
+}
+
+/// Writes as much of `pending` as `writer` takes without blocking
+fn flush(writer: &mut impl Write, pending: &mut Vec<u8>) -> io::Result<bool> {
+    if pending.is_empty() {
+        return Ok(false);
+    }
+    match writer.write(pending) {
+        Ok(n) => {
+            pending.drain(..n);
+            Ok(n > 0)
+        }
+        Err(e) if e.kind() == io::ErrorKind::WouldBlock => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_keeps_secret_out() {
+        let mut config = SshTunnelConfig::new("bastion".to_string(), "deploy".to_string());
+        config.auth = SshAuth::KeyFile {
+            path: "~/.ssh/id_ed25519".to_string(),
+        };
+        config.secret = "passphrase".to_string();
+
+        let json = serde_json::to_string(&config).unwrap();
+        assert!(!json.contains("passphrase"));
+        let parsed: SshTunnelConfig = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.auth, config.auth);
+        assert_eq!(parsed.port, 22);
+        assert!(parsed.secret.is_empty());
+
+        assert_eq!(known_host_name("bastion", 22), "bastion");
+        assert_eq!(known_host_name("bastion", 2222), "[bastion]:2222");
+    }
+
+    /// Needs an sshd on localhost that accepts a key loaded in ssh-agent
+    /// for the current user: `cargo test -- --ignored test_tunnel_local_sshd`
+    #[test]
+    #[ignore]
+    fn test_tunnel_local_sshd() {
+        let echo = TcpListener::bind(("127.0.0.1", 0)).unwrap();
+        let echo_port = echo.local_addr().unwrap().port();
+        thread::spawn(move || {
+            let (mut stream, _) = echo.accept().unwrap();
+            let mut buf = [0u8; 64];
+            let n = stream.read(&mut buf).unwrap();
+            stream.write_all(&buf[..n]).unwrap();
+        });
+
+        let user = std::env::var("USER").unwrap();
+        let mut config = SshTunnelConfig::new("localhost".to_string(), user);
+        config.known_hosts = KnownHostsPolicy::Off;
+        let tunnel = SshTunnel::open(&config, "127.0.0.1", echo_port).unwrap();
+        let local_port = tunnel.local_port();
+
+        let mut client = TcpStream::connect(("127.0.0.1", local_port)).unwrap();
+        client.write_all(b"ping").unwrap();
+        let mut reply = [0u8; 4];
+        client.read_exact(&mut reply).unwrap();
+        assert_eq!(&reply, b"ping");
+
+        drop(tunnel);
+        assert!(TcpStream::connect(("127.0.0.1", local_port)).is_err());
+    }
+}
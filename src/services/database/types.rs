@@ -2,6 +2,10 @@ use serde::{Deserialize, Serialize};
 use sqlx::postgres::types::Oid;
 use std::collections::HashMap;
 
+use super::cell::CellValue;
+use super::notices::ServerNotice;
+use super::timeouts::TimeoutKind;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TableInfo {
     pub table_name: String,
@@ -88,6 +92,9 @@ pub struct ResultColumnMetadata {
 pub struct ResultCell {
     /// String representation of the value
     pub value: String,
+    /// Decoded value, used wherever the original type matters
+    #[serde(default)]
+    pub typed_value: CellValue,
     /// Whether the value is NULL
     pub is_null: bool,
     /// Column metadata for this cell
@@ -108,6 +115,18 @@ pub struct QueryResult {
     pub row_count: usize,
     pub execution_time_ms: u128,
     pub original_query: String,
+    /// More rows are waiting in the server-side cursor
+    #[serde(default)]
+    pub has_more: bool,
+}
+
+/// A further page of rows read from the open result cursor
+#[derive(Debug, Clone)]
+pub struct ResultPage {
+    pub rows: Vec<ResultRow>,
+    pub has_more: bool,
+    /// Notices the server sent while the page was fetched
+    pub notices: Vec<ServerNotice>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -116,10 +135,48 @@ pub struct ModifiedResult {
     pub execution_time_ms: u128,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct ErrorResult {
     pub message: String,
     pub execution_time_ms: u128,
+    /// Fields reported by Postgres when the server rejected the statement
+    #[serde(default)]
+    pub database_error: Option<Box<DatabaseErrorDetails>>,
+    /// Set when a server time limit stopped the statement
+    #[serde(default)]
+    pub timeout: Option<TimeoutKind>,
+}
+
+/// The fields of a Postgres ErrorResponse
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DatabaseErrorDetails {
+    pub severity: String,
+    /// SQLSTATE code, e.g. `42P01`
+    pub code: String,
+    pub message: String,
+    pub detail: Option<String>,
+    pub hint: Option<String>,
+    /// 1-based character position in the SQL text that was executed
+    pub position: Option<usize>,
+    /// Position inside `internal_query`, e.g. a statement run by PL/pgSQL
+    pub internal_position: Option<usize>,
+    pub internal_query: Option<String>,
+    /// Call stack context (`CONTEXT:` in psql)
+    pub context: Option<String>,
+    pub schema: Option<String>,
+    pub table: Option<String>,
+    pub column: Option<String>,
+    pub data_type: Option<String>,
+    pub constraint: Option<String>,
+    pub file: Option<String>,
+    pub line: Option<usize>,
+    pub routine: Option<String>,
+}
+
+/// A query that was stopped by the user before it finished
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CancelledResult {
+    pub execution_time_ms: u128,
 }
 
 /// Result of an query execution
@@ -128,9 +185,53 @@ pub enum QueryExecutionResult {
     Select(QueryResult),
     Modified(ModifiedResult),
     Error(ErrorResult),
+    Cancelled(CancelledResult),
+}
+
+impl QueryExecutionResult {
+    /// One-line outcome, e.g. "42 rows in 12 ms"
+    pub fn summary(&self) -> String {
+        let plural = |n: u64| if n == 1 { "" } else { "s" };
+        match self {
+            QueryExecutionResult::Select(result) => format!(
+                "{}{} row{} in {} ms",
+                result.row_count,
+                if result.has_more { "+" } else { "" },
+                plural(result.row_count as u64),
+                result.execution_time_ms
+            ),
+            QueryExecutionResult::Modified(result) => format!(
+                "{} row{} affected in {} ms",
+                result.rows_affected,
+                plural(result.rows_affected),
+                result.execution_time_ms
+            ),
+            QueryExecutionResult::Error(error) => {
+                format!(
+                    "Error: {}",
+                    error.message.lines().next().unwrap_or_default()
+                )
+            }
+            QueryExecutionResult::Cancelled(cancelled) => {
+                format!("Cancelled after {} ms", cancelled.execution_time_ms)
+            }
+        }
+    }
+}
+
+/// State of the connection pinned for an explicit transaction
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum TransactionStatus {
+    /// Statements run in autocommit mode on any pooled connection
+    #[default]
+    Idle,
+    InTransaction,
+    /// A statement failed; only ROLLBACK (or ROLLBACK TO SAVEPOINT) will succeed
+    Failed,
 }
 
 /// Internal metadata for table/column resolution
+#[derive(Debug)]
 pub(crate) struct TableMetadata {
     pub oid_to_table_name: HashMap<Oid, String>,
     pub column_nullable_map: HashMap<(Oid, String), bool>,
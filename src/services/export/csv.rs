@@ -1,9 +1,9 @@
-use crate::services::QueryResult;
+use crate::services::{CellValue, QueryResult, decode_value};
 use anyhow::Result;
 use csv::Writer;
 use futures::StreamExt;
 use sqlx::postgres::PgRow;
-use sqlx::{Column, Row, ValueRef};
+use sqlx::{Column, Row};
 use std::path::Path;
 
 /// Stream rows directly to a CSV file without holding everything in memory
@@ -34,7 +34,7 @@ where
             .columns()
             .iter()
             .enumerate()
-            .map(|(i, col)| extract_value(&row, i, col))
+            .map(|(i, _)| extract_value(&row, i))
             .collect();
         wtr.write_record(&values)?;
 
@@ -50,20 +50,11 @@ where
     Ok(row_count)
 }
 
-fn extract_value(row: &PgRow, index: usize, _col: &sqlx::postgres::PgColumn) -> String {
-    // Check for NULL first
-    if let Ok(raw) = row.try_get_raw(index) {
-        if raw.is_null() {
-            return String::new(); // CSV NULL representation
-        }
+fn extract_value(row: &PgRow, index: usize) -> String {
+    match row.try_get_raw(index).map(decode_value) {
+        Ok(CellValue::Null) | Err(_) => String::new(), // CSV NULL representation
+        Ok(value) => value.to_text(),
     }
-
-    // Try string first, then specific types
-    row.try_get::<String, _>(index)
-        .or_else(|_| row.try_get::<i64, _>(index).map(|v| v.to_string()))
-        .or_else(|_| row.try_get::<f64, _>(index).map(|v| v.to_string()))
-        .or_else(|_| row.try_get::<bool, _>(index).map(|v| v.to_string()))
-        .unwrap_or_default()
 }
 
 pub fn export_to_csv(result: &QueryResult) -> Result<String> {
@@ -75,7 +66,11 @@ pub fn export_to_csv(result: &QueryResult) -> Result<String> {
 
     // Data rows
     for row in &result.rows {
-        let values: Vec<&str> = row.cells.iter().map(|c| c.value.as_str()).collect();
+        let values: Vec<&str> = row
+            .cells
+            .iter()
+            .map(|c| if c.is_null { "" } else { c.value.as_str() })
+            .collect();
         wtr.write_record(&values)?;
     }
 
@@ -1,9 +1,9 @@
-use crate::services::QueryResult;
+use crate::services::{QueryResult, decode_value};
 use anyhow::Result;
 use futures::StreamExt;
 use serde_json::{Map, Value};
 use sqlx::postgres::PgRow;
-use sqlx::{Column, Row, ValueRef};
+use sqlx::{Column, Row};
 use std::io::{BufWriter, Write};
 use std::path::Path;
 
@@ -24,7 +24,7 @@ where
 
         let mut obj = Map::new();
         for (i, col) in row.columns().iter().enumerate() {
-            let value = extract_json_value(&row, i, col);
+            let value = extract_json_value(&row, i);
             obj.insert(col.name().to_string(), value);
         }
 
@@ -44,28 +44,10 @@ where
     Ok(row_count)
 }
 
-fn extract_json_value(row: &PgRow, index: usize, _col: &sqlx::postgres::PgColumn) -> Value {
-    if let Ok(raw) = row.try_get_raw(index) {
-        if raw.is_null() {
-            return Value::Null;
-        }
-    }
-
-    // Try types in order of likelihood
-    if let Ok(v) = row.try_get::<i64, _>(index) {
-        return Value::from(v);
-    }
-    if let Ok(v) = row.try_get::<f64, _>(index) {
-        return Value::from(v);
-    }
-    if let Ok(v) = row.try_get::<bool, _>(index) {
-        return Value::from(v);
-    }
-    if let Ok(v) = row.try_get::<String, _>(index) {
-        return Value::String(v);
-    }
-
-    Value::Null
+fn extract_json_value(row: &PgRow, index: usize) -> Value {
+    row.try_get_raw(index)
+        .map(|raw| decode_value(raw).to_json())
+        .unwrap_or(Value::Null)
 }
 
 pub fn export_to_json(result: &QueryResult) -> Result<String> {
@@ -75,16 +57,7 @@ pub fn export_to_json(result: &QueryResult) -> Result<String> {
         .map(|row| {
             let mut obj = Map::new();
             for cell in &row.cells {
-                let value = if cell.is_null {
-                    Value::Null
-                } else {
-                    // Try to parse as number, otherwise keep as string
-                    cell.value
-                        .parse::<i64>()
-                        .map(Value::from)
-                        .or_else(|_| cell.value.parse::<f64>().map(Value::from))
-                        .unwrap_or_else(|_| Value::String(cell.value.clone()))
-                };
+                let value = cell.typed_value.to_json();
                 obj.insert(cell.column_metadata.name.clone(), value);
             }
             Value::Object(obj)
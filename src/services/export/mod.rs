@@ -1,5 +1,7 @@
 mod csv;
 mod json;
+mod sql_files;
 
 pub use csv::*;
 pub use json::*;
+pub use sql_files::*;
@@ -0,0 +1,169 @@
+use crate::services::storage::SavedQuery;
+use anyhow::Result;
+use std::collections::HashSet;
+use std::path::Path;
+use uuid::Uuid;
+
+/// Write each query to `<name>.sql` in `dir`, with its name, description,
+/// tags and connection in leading `-- key: value` comments
+pub fn export_saved_queries(queries: &[SavedQuery], dir: &Path) -> Result<usize> {
+    std::fs::create_dir_all(dir)?;
+
+    let mut used = HashSet::new();
+    for query in queries {
+        let stem = file_stem(&query.name);
+        let mut file_name = format!("{}.sql", stem);
+        let mut n = 2;
+        while !used.insert(file_name.to_lowercase()) {
+            file_name = format!("{}_{}.sql", stem, n);
+            n += 1;
+        }
+        std::fs::write(dir.join(file_name), saved_query_to_sql(query))?;
+    }
+
+    Ok(queries.len())
+}
+
+/// Read every `.sql` file in `dir`; files without a `-- name:` header are
+/// named after the file
+pub fn import_saved_queries(dir: &Path) -> Result<Vec<SavedQuery>> {
+    let mut paths: Vec<_> = std::fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.is_file()
+                && path
+                    .extension()
+                    .is_some_and(|ext| ext.eq_ignore_ascii_case("sql"))
+        })
+        .collect();
+    paths.sort();
+
+    paths
+        .iter()
+        .map(|path| {
+            let stem = path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            Ok(saved_query_from_sql(&stem, &std::fs::read_to_string(path)?))
+        })
+        .collect()
+}
+
+fn saved_query_to_sql(query: &SavedQuery) -> String {
+    let mut out = format!("-- name: {}\n", single_line(&query.name));
+    if let Some(description) = &query.description {
+        out.push_str(&format!("-- description: {}\n", single_line(description)));
+    }
+    if !query.tags.is_empty() {
+        out.push_str(&format!("-- tags: {}\n", query.tags.join(", ")));
+    }
+    if let Some(connection_id) = query.connection_id {
+        out.push_str(&format!("-- connection: {}\n", connection_id));
+    }
+    out.push_str(query.sql.trim_end());
+    out.push('\n');
+    out
+}
+
+fn saved_query_from_sql(stem: &str, contents: &str) -> SavedQuery {
+    let mut query = SavedQuery::new(stem, "");
+    let mut body_start = 0;
+
+    for line in contents.split_inclusive('\n') {
+        let Some((key, value)) = line
+            .trim()
+            .strip_prefix("--")
+            .and_then(|comment| comment.split_once(':'))
+        else {
+            break;
+        };
+        let value = value.trim();
+        match key.trim().to_lowercase().as_str() {
+            "name" if !value.is_empty() => query.name = value.to_string(),
+            "description" if !value.is_empty() => query.description = Some(value.to_string()),
+            "tags" => {
+                query.tags = value
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|tag| !tag.is_empty())
+                    .map(str::to_string)
+                    .collect();
+            }
+            "connection" => query.connection_id = Uuid::parse_str(value).ok(),
+            _ => break,
+        }
+        body_start += line.len();
+    }
+
+    query.sql = contents[body_start..].trim().to_string();
+    query
+}
+
+/// A file name for the query: letters, digits, `-` and `_`, spaces as `_`
+fn file_stem(name: &str) -> String {
+    let stem: String = name
+        .trim()
+        .chars()
+        .filter_map(|c| match c {
+            c if c.is_alphanumeric() || c == '-' || c == '_' => Some(c),
+            c if c.is_whitespace() => Some('_'),
+            _ => None,
+        })
+        .collect();
+    if stem.is_empty() {
+        "query".to_string()
+    } else {
+        stem
+    }
+}
+
+fn single_line(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_saved_queries_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let mut daily = SavedQuery::new("Daily checks", "SELECT count(*)\nFROM orders;");
+        daily.description = Some("Row counts\nof the main tables".to_string());
+        daily.tags = vec!["ops".to_string(), "daily".to_string()];
+        daily.connection_id = Some(Uuid::new_v4());
+        let duplicate = SavedQuery::new("Daily checks", "SELECT 2");
+        let plain = SavedQuery::new("a/b", "-- not a header\nSELECT 1");
+
+        let queries = vec![daily.clone(), duplicate, plain];
+        assert_eq!(export_saved_queries(&queries, dir.path()).unwrap(), 3);
+        assert!(dir.path().join("Daily_checks_2.sql").exists());
+        assert!(dir.path().join("ab.sql").exists());
+
+        let imported = import_saved_queries(dir.path()).unwrap();
+        assert_eq!(imported.len(), 3);
+
+        let first = &imported[0];
+        assert_eq!(first.name, "Daily checks");
+        assert_eq!(
+            first.description.as_deref(),
+            Some("Row counts of the main tables")
+        );
+        assert_eq!(first.tags, daily.tags);
+        assert_eq!(first.connection_id, daily.connection_id);
+        assert_eq!(first.sql, daily.sql);
+
+        assert_eq!(imported[2].name, "a/b");
+        assert_eq!(imported[2].sql, "-- not a header\nSELECT 1");
+    }
+
+    #[test]
+    fn test_import_without_header() {
+        let query = saved_query_from_sql("report", "\nSELECT 1;\n");
+        assert_eq!(query.name, "report");
+        assert_eq!(query.sql, "SELECT 1;");
+        assert!(query.tags.is_empty());
+    }
+}
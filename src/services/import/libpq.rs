@@ -0,0 +1,652 @@
+//! Connections from the places libpq reads them: `postgres://` URIs,
+//! `key=value` connection strings, `~/.pgpass` and `pg_service.conf`.
+
+use crate::services::storage::{ConnectionInfo, SslMode};
+use anyhow::{Context as _, Result};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+const DEFAULT_HOST: &str = "localhost";
+const DEFAULT_PORT: usize = 5432;
+
+/// A connection found in a libpq source
+#[derive(Debug, Clone)]
+pub struct ImportedConnection {
+    pub connection: ConnectionInfo,
+    /// Where it was read from, e.g. `~/.pgpass entry 3`
+    pub source: String,
+}
+
+/// A line of a password file; any field may be the `*` wildcard
+#[derive(Debug, Clone, PartialEq)]
+pub struct PgPassEntry {
+    pub hostname: String,
+    pub port: String,
+    pub database: String,
+    pub username: String,
+    pub password: String,
+}
+
+impl PgPassEntry {
+    /// Whether libpq would use this password for `connection`
+    pub fn matches(&self, connection: &ConnectionInfo) -> bool {
+        let field = |pattern: &str, value: &str| pattern == "*" || pattern == value;
+        field(&self.hostname, &connection.hostname)
+            && field(&self.port, &connection.port.to_string())
+            && field(&self.database, &connection.database)
+            && field(&self.username, &connection.username)
+    }
+
+    /// The connection this line describes, with wildcards replaced by
+    /// libpq's defaults
+    pub fn to_connection(&self) -> Result<ConnectionInfo> {
+        let concrete = |value: &str| (value != "*").then(|| value.to_string());
+        let username = concrete(&self.username).unwrap_or_else(default_user);
+        let mut params = vec![
+            ("user".to_string(), username),
+            ("password".to_string(), self.password.clone()),
+        ];
+        for (key, value) in [
+            ("host", &self.hostname),
+            ("port", &self.port),
+            ("dbname", &self.database),
+        ] {
+            if let Some(value) = concrete(value) {
+                params.push((key.to_string(), value));
+            }
+        }
+        connection_from_params(&params)
+    }
+}
+
+/// Parse a `postgres://` URI or a `host=... dbname=...` connection string
+pub fn parse_connection_string(input: &str) -> Result<ConnectionInfo> {
+    let input = input.trim();
+    let params = if let Some(rest) = input
+        .strip_prefix("postgresql://")
+        .or_else(|| input.strip_prefix("postgres://"))
+    {
+        uri_params(rest)?
+    } else if input.contains('=') {
+        keyword_params(input)?
+    } else {
+        anyhow::bail!("Expected a postgres:// URL or key=value pairs");
+    };
+    connection_from_params(&params)
+}
+
+/// Parse the lines of a password file: `hostname:port:database:username:password`
+pub fn parse_pgpass(contents: &str) -> Vec<PgPassEntry> {
+    contents
+        .lines()
+        .filter(|line| !line.trim().is_empty() && !line.trim_start().starts_with('#'))
+        .filter_map(|line| {
+            let mut fields = vec![String::new()];
+            let mut chars = line.chars();
+            while let Some(c) = chars.next() {
+                match c {
+                    '\\' => fields.last_mut()?.extend(chars.next()),
+                    ':' if fields.len() < 5 => fields.push(String::new()),
+                    c => fields.last_mut()?.push(c),
+                }
+            }
+            let [hostname, port, database, username, password]: [String; 5] =
+                fields.try_into().ok()?;
+            Some(PgPassEntry {
+                hostname,
+                port,
+                database,
+                username,
+                password,
+            })
+        })
+        .collect()
+}
+
+/// Parse a connection service file into `(service name, parameters)`
+pub fn parse_pg_service(contents: &str) -> Vec<(String, Vec<(String, String)>)> {
+    let mut services: Vec<(String, Vec<(String, String)>)> = vec![];
+    for line in contents.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            services.push((name.trim().to_string(), vec![]));
+        } else if let Some((key, value)) = line.split_once('=')
+            && let Some((_, params)) = services.last_mut()
+        {
+            params.push((key.trim().to_string(), value.trim().to_string()));
+        }
+    }
+    services
+}
+
+/// The connections of the user's password and service files, at the
+/// locations libpq uses. Service entries get their password from the
+/// password file, as libpq would.
+pub fn discover_connections() -> Vec<ImportedConnection> {
+    let passwords = pgpass_path()
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .map(|contents| parse_pgpass(&contents))
+        .unwrap_or_default();
+
+    let mut found = vec![];
+    for path in service_paths() {
+        if let Ok(contents) = std::fs::read_to_string(&path) {
+            found.extend(services_from(&path, &contents, &passwords));
+        }
+    }
+    if let Some(path) = pgpass_path()
+        && let Ok(contents) = std::fs::read_to_string(&path)
+    {
+        found.extend(pgpass_from(&path, &contents));
+    }
+    found
+}
+
+/// Read a password file or a service file picked by the user
+pub fn import_file(path: &Path) -> Result<Vec<ImportedConnection>> {
+    let contents = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+
+    let is_service_file = contents
+        .lines()
+        .any(|line| line.trim_start().starts_with('['));
+    if is_service_file {
+        Ok(services_from(path, &contents, &[]))
+    } else {
+        Ok(pgpass_from(path, &contents))
+    }
+}
+
+/// `name`, or `name (2)`, `name (3)`... when it is already taken
+pub fn unique_connection_name(name: &str, taken: &HashSet<String>) -> String {
+    let mut candidate = name.to_string();
+    let mut n = 2;
+    while taken.contains(&candidate) {
+        candidate = format!("{} ({})", name, n);
+        n += 1;
+    }
+    candidate
+}
+
+fn services_from(
+    path: &Path,
+    contents: &str,
+    passwords: &[PgPassEntry],
+) -> Vec<ImportedConnection> {
+    parse_pg_service(contents)
+        .into_iter()
+        .filter_map(|(service, params)| {
+            let mut connection = match connection_from_params(&params) {
+                Ok(connection) => connection,
+                Err(e) => {
+                    tracing::warn!("Skipping service {} in {}: {}", service, path.display(), e);
+                    return None;
+                }
+            };
+            connection.name = service.clone();
+            if connection.password.is_empty()
+                && let Some(entry) = passwords.iter().find(|entry| entry.matches(&connection))
+            {
+                connection.password = entry.password.clone();
+            }
+            Some(ImportedConnection {
+                connection,
+                source: format!("{} [{}]", display_path(path), service),
+            })
+        })
+        .collect()
+}
+
+fn pgpass_from(path: &Path, contents: &str) -> Vec<ImportedConnection> {
+    parse_pgpass(contents)
+        .iter()
+        .enumerate()
+        .filter_map(|(ix, entry)| match entry.to_connection() {
+            Ok(connection) => Some(ImportedConnection {
+                connection,
+                source: format!("{} entry {}", display_path(path), ix + 1),
+            }),
+            Err(e) => {
+                tracing::warn!("Skipping entry {} in {}: {}", ix + 1, path.display(), e);
+                None
+            }
+        })
+        .collect()
+}
+
+/// Build a connection from libpq keywords; later keywords win
+fn connection_from_params(params: &[(String, String)]) -> Result<ConnectionInfo> {
+    let mut connection = ConnectionInfo::new(
+        String::new(),
+        DEFAULT_HOST.to_string(),
+        String::new(),
+        String::new(),
+        String::new(),
+        DEFAULT_PORT,
+        SslMode::default(),
+    );
+
+    for (key, value) in params {
+        match key.as_str() {
+            // Only the first of several hosts is kept
+            "host" | "hostaddr" => {
+                if let Some(host) = value.split(',').next().filter(|host| !host.is_empty()) {
+                    connection.hostname = host.to_string();
+                }
+            }
+            "port" => {
+                if let Some(port) = value.split(',').next().filter(|port| !port.is_empty()) {
+                    connection.port = port
+                        .parse()
+                        .ok()
+                        .filter(|port| (1..=65_535).contains(port))
+                        .with_context(|| format!("Invalid port '{}'", port))?;
+                }
+            }
+            "user" => connection.username = value.clone(),
+            "password" => connection.password = value.clone(),
+            "dbname" => connection.database = value.clone(),
+            "sslmode" => connection.ssl_mode = ssl_mode(value)?,
+            "sslrootcert" => connection.ssl_root_cert = Some(value.clone()),
+            "sslcert" => connection.ssl_client_cert = Some(value.clone()),
+            "sslkey" => connection.ssl_client_key = Some(value.clone()),
+            "application_name" => connection.settings.application_name = Some(value.clone()),
+            "options" => apply_options(&mut connection, value),
+            _ => {}
+        }
+    }
+
+    if connection.username.is_empty() {
+        connection.username = default_user();
+    }
+    if connection.database.is_empty() {
+        connection.database = connection.username.clone();
+    }
+    connection.name = format!("{}@{}", connection.database, connection.hostname);
+    Ok(connection)
+}
+
+fn ssl_mode(value: &str) -> Result<SslMode> {
+    Ok(match value {
+        "disable" => SslMode::Disable,
+        // Closest mode; the connection still works against either server
+        "allow" | "prefer" => SslMode::Prefer,
+        "require" => SslMode::Require,
+        "verify-ca" => SslMode::VerifyCa,
+        "verify-full" => SslMode::VerifyFull,
+        _ => anyhow::bail!("Unknown sslmode '{}'", value),
+    })
+}
+
+/// Picks the timeouts, read-only default, search path and role out of
+/// `-c name=value` startup options. Other settings become startup `SET`
+/// statements.
+fn apply_options(connection: &mut ConnectionInfo, options: &str) {
+    let mut tokens = option_words(options).into_iter();
+    while let Some(token) = tokens.next() {
+        let setting = match token.as_str() {
+            "-c" => tokens.next(),
+            token => token
+                .strip_prefix("--")
+                .or_else(|| token.strip_prefix("-c"))
+                .map(str::to_string),
+        };
+        let Some((name, value)) = setting
+            .as_deref()
+            .and_then(|setting| setting.split_once('='))
+        else {
+            continue;
+        };
+
+        let name = name.replace('-', "_");
+        let timeouts = &mut connection.timeouts;
+        let timeout = match name.as_str() {
+            "statement_timeout" => &mut timeouts.statement_timeout_ms,
+            "lock_timeout" => &mut timeouts.lock_timeout_ms,
+            "idle_in_transaction_session_timeout" => &mut timeouts.idle_in_transaction_timeout_ms,
+            "default_transaction_read_only" => {
+                connection.read_only = matches!(value, "on" | "true" | "yes" | "1");
+                continue;
+            }
+            "search_path" => {
+                connection.settings.search_path = Some(value.to_string());
+                continue;
+            }
+            "role" => {
+                connection.settings.role = Some(value.to_string());
+                continue;
+            }
+            _ => {
+                set_on_connect(connection, &name, value);
+                continue;
+            }
+        };
+        // Durations in units the timeouts can't hold are set as written
+        match duration_ms(value) {
+            Some(ms) => *timeout = Some(ms),
+            None => set_on_connect(connection, &name, value),
+        }
+    }
+}
+
+/// Keeps a startup setting as a `SET` statement run on every connect
+fn set_on_connect(connection: &mut ConnectionInfo, name: &str, value: &str) {
+    let is_name = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.');
+    if !is_name {
+        return;
+    }
+    let value = value.trim_matches('\'').replace('\'', "''");
+    connection
+        .settings
+        .startup_statements
+        .push(format!("SET {} = '{}'", name, value));
+}
+
+/// Splits `options` on whitespace, where a backslash escapes the next
+/// character, e.g. the space in `-c search_path=a,\ b`
+fn option_words(options: &str) -> Vec<String> {
+    let mut words = vec![];
+    let mut chars = options.chars().peekable();
+    loop {
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        if chars.peek().is_none() {
+            return words;
+        }
+
+        let mut word = String::new();
+        while let Some(c) = chars.next_if(|c| !c.is_whitespace()) {
+            if c == '\\' {
+                word.extend(chars.next());
+            } else {
+                word.push(c);
+            }
+        }
+        words.push(word);
+    }
+}
+
+/// A server duration such as `5000`, `5s` or `2min` in milliseconds
+fn duration_ms(value: &str) -> Option<u64> {
+    let value = value.trim_matches('\'');
+    let split = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let factor = match unit.trim() {
+        "" | "ms" => 1,
+        "s" => 1_000,
+        "min" => 60_000,
+        "h" => 3_600_000,
+        "d" => 86_400_000,
+        _ => return None,
+    };
+    number
+        .parse::<u64>()
+        .ok()
+        .and_then(|n| n.checked_mul(factor))
+}
+
+/// Keywords of `[userspec@][hostspec][/dbname][?paramspec]`
+fn uri_params(rest: &str) -> Result<Vec<(String, String)>> {
+    let (rest, query) = rest.split_once('?').unwrap_or((rest, ""));
+    let (authority, dbname) = rest.split_once('/').unwrap_or((rest, ""));
+    let (userspec, hostspec) = authority.rsplit_once('@').unwrap_or(("", authority));
+
+    let mut params = vec![];
+    if !userspec.is_empty() {
+        let (user, password) = userspec.split_once(':').unwrap_or((userspec, ""));
+        params.push(("user".to_string(), percent_decode(user)?));
+        if !password.is_empty() {
+            params.push(("password".to_string(), percent_decode(password)?));
+        }
+    }
+
+    let first_host = hostspec.split(',').next().unwrap_or_default();
+    let (host, port) = if let Some(bracketed) = first_host.strip_prefix('[') {
+        // IPv6 address
+        let (host, after) = bracketed
+            .split_once(']')
+            .context("Unclosed '[' in the host")?;
+        (host, after.strip_prefix(':').unwrap_or_default())
+    } else {
+        first_host.split_once(':').unwrap_or((first_host, ""))
+    };
+    if !host.is_empty() {
+        params.push(("host".to_string(), percent_decode(host)?));
+    }
+    if !port.is_empty() {
+        params.push(("port".to_string(), port.to_string()));
+    }
+    if !dbname.is_empty() {
+        params.push(("dbname".to_string(), percent_decode(dbname)?));
+    }
+
+    for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+        let (key, value) = pair
+            .split_once('=')
+            .with_context(|| format!("Missing '=' in '{}'", pair))?;
+        params.push((percent_decode(key)?, percent_decode(value)?));
+    }
+    Ok(params)
+}
+
+/// Keywords of `key=value key='quoted \' value'`
+fn keyword_params(input: &str) -> Result<Vec<(String, String)>> {
+    let mut params = vec![];
+    let mut chars = input.chars().peekable();
+    loop {
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        if chars.peek().is_none() {
+            return Ok(params);
+        }
+
+        let key: String =
+            std::iter::from_fn(|| chars.next_if(|c| *c != '=' && !c.is_whitespace())).collect();
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        anyhow::ensure!(chars.next() == Some('='), "Missing '=' after '{}'", key);
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+
+        let mut value = String::new();
+        if chars.next_if_eq(&'\'').is_some() {
+            loop {
+                match chars.next() {
+                    Some('\\') => value.extend(chars.next()),
+                    Some('\'') => break,
+                    Some(c) => value.push(c),
+                    None => anyhow::bail!("Unterminated quote in the value of '{}'", key),
+                }
+            }
+        } else {
+            while let Some(c) = chars.next_if(|c| !c.is_whitespace()) {
+                if c == '\\' {
+                    value.extend(chars.next());
+                } else {
+                    value.push(c);
+                }
+            }
+        }
+        params.push((key, value));
+    }
+}
+
+fn percent_decode(value: &str) -> Result<String> {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = value
+                .get(i + 1..i + 3)
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                .with_context(|| format!("Invalid percent-encoding in '{}'", value))?;
+            decoded.push(hex);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(decoded).context("Percent-encoded value is not UTF-8")
+}
+
+/// libpq falls back to the operating system user name
+fn default_user() -> String {
+    std::env::var("PGUSER")
+        .or_else(|_| std::env::var("USER"))
+        .or_else(|_| std::env::var("USERNAME"))
+        .unwrap_or_else(|_| "postgres".to_string())
+}
+
+fn pgpass_path() -> Option<PathBuf> {
+    if let Some(path) = std::env::var_os("PGPASSFILE") {
+        return Some(PathBuf::from(path));
+    }
+    if cfg!(windows) {
+        dirs::config_dir().map(|dir| dir.join("postgresql").join("pgpass.conf"))
+    } else {
+        dirs::home_dir().map(|home| home.join(".pgpass"))
+    }
+}
+
+/// The user's service file, then the system-wide one
+fn service_paths() -> Vec<PathBuf> {
+    let user = std::env::var_os("PGSERVICEFILE")
+        .map(PathBuf::from)
+        .or_else(|| dirs::home_dir().map(|home| home.join(".pg_service.conf")));
+    let system =
+        std::env::var_os("PGSYSCONFDIR").map(|dir| PathBuf::from(dir).join("pg_service.conf"));
+    user.into_iter().chain(system).collect()
+}
+
+/// The path with the home directory shortened to `~`
+fn display_path(path: &Path) -> String {
+    match dirs::home_dir().and_then(|home| path.strip_prefix(home).ok().map(Path::to_path_buf)) {
+        Some(relative) => format!("~/{}", relative.display()),
+        None => path.display().to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_uri() {
+        let connection = parse_connection_string(
+            "postgresql://app%40corp:s%3Acret@[::1]:6432/orders?sslmode=verify-full&options=-c%20statement_timeout%3D5s%20-c%20default_transaction_read_only%3Don",
+        )
+        .unwrap();
+        assert_eq!(connection.username, "app@corp");
+        assert_eq!(connection.password, "s:cret");
+        assert_eq!(connection.hostname, "::1");
+        assert_eq!(connection.port, 6432);
+        assert_eq!(connection.database, "orders");
+        assert_eq!(connection.ssl_mode, SslMode::VerifyFull);
+        assert_eq!(connection.timeouts.statement_timeout_ms, Some(5_000));
+        assert!(connection.read_only);
+        assert_eq!(connection.name, "orders@::1");
+
+        // Query parameters override the authority, e.g. a socket directory
+        let connection =
+            parse_connection_string("postgres://me@/db?host=%2Fvar%2Frun%2Fpostgresql&port=5433")
+                .unwrap();
+        assert_eq!(connection.hostname, "/var/run/postgresql");
+        assert_eq!(connection.port, 5433);
+
+        // An escaped space stays in the value, and a duration too large for
+        // the timeouts is set as written
+        let connection = parse_connection_string(
+            "postgres://me@host/db?options=-c%20search_path%3Da,%5C%20b%20-c%20lock_timeout%3D99999999999999999d",
+        )
+        .unwrap();
+        assert_eq!(connection.settings.search_path.as_deref(), Some("a, b"));
+        assert_eq!(connection.timeouts.lock_timeout_ms, None);
+        assert_eq!(
+            connection.settings.startup_statements,
+            vec!["SET lock_timeout = '99999999999999999d'".to_string()]
+        );
+
+        assert!(parse_connection_string("postgres://me@host:99999/db").is_err());
+        assert!(parse_connection_string("postgres://me@host/db?sslmode=sometimes").is_err());
+        assert!(parse_connection_string("mysql://me@host/db").is_err());
+    }
+
+    #[test]
+    fn test_parse_keyword_string() {
+        let connection = parse_connection_string(
+            "host=db.internal port = 5433 user=app dbname=orders password='a \\'quoted\\' secret' options='-c lock_timeout=250 -c search_path=app -c work_mem=64MB --jit=off' application_name=reports sslrootcert=/etc/ssl/ca.pem sslcert=client.crt sslkey=client.key",
+        )
+        .unwrap();
+        assert_eq!(connection.hostname, "db.internal");
+        assert_eq!(connection.port, 5433);
+        assert_eq!(connection.password, "a 'quoted' secret");
+        assert_eq!(connection.timeouts.lock_timeout_ms, Some(250));
+        assert_eq!(connection.settings.search_path.as_deref(), Some("app"));
+        assert_eq!(
+            connection.settings.startup_statements,
+            vec![
+                "SET work_mem = '64MB'".to_string(),
+                "SET jit = 'off'".to_string(),
+            ]
+        );
+        assert_eq!(
+            connection.settings.application_name.as_deref(),
+            Some("reports")
+        );
+        assert_eq!(connection.ssl_root_cert.as_deref(), Some("/etc/ssl/ca.pem"));
+        assert_eq!(connection.ssl_client_cert.as_deref(), Some("client.crt"));
+        assert_eq!(connection.ssl_client_key.as_deref(), Some("client.key"));
+    }
+
+    #[test]
+    fn test_parse_pgpass() {
+        let entries = parse_pgpass(
+            "# comment\n\ndb.internal:5432:orders:app:pa\\:ss\\\\word\n*:*:*:reporter:secret\nbroken:line\n",
+        );
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].password, "pa:ss\\word");
+
+        let connection = entries[1].to_connection().unwrap();
+        assert_eq!(connection.hostname, "localhost");
+        assert_eq!(connection.port, 5432);
+        assert_eq!(connection.database, "reporter");
+        assert_eq!(connection.password, "secret");
+
+        let mut other = entries[0].to_connection().unwrap();
+        assert!(entries[0].matches(&other));
+        other.database = "billing".to_string();
+        assert!(!entries[0].matches(&other));
+        other.username = "reporter".to_string();
+        assert!(entries[1].matches(&other));
+    }
+
+    #[test]
+    fn test_services_take_pgpass_passwords() {
+        let passwords = parse_pgpass("db.internal:*:*:app:from-pgpass\n");
+        let found = services_from(
+            Path::new("/etc/pg_service.conf"),
+            "# services\n[prod]\nhost=db.internal\nuser=app\ndbname=orders\nsslmode=require\n\n[local]\ndbname=scratch\nuser=me\n",
+            &passwords,
+        );
+
+        assert_eq!(found.len(), 2);
+        let prod = &found[0].connection;
+        assert_eq!(prod.name, "prod");
+        assert_eq!(prod.password, "from-pgpass");
+        assert_eq!(prod.ssl_mode, SslMode::Require);
+        assert_eq!(found[0].source, "/etc/pg_service.conf [prod]");
+        assert!(found[1].connection.password.is_empty());
+    }
+
+    #[test]
+    fn test_unique_connection_name() {
+        let taken: HashSet<String> = ["prod".to_string(), "prod (2)".to_string()].into();
+        assert_eq!(unique_connection_name("prod", &taken), "prod (3)");
+        assert_eq!(unique_connection_name("staging", &taken), "staging");
+    }
+}
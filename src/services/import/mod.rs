@@ -0,0 +1,3 @@
+mod libpq;
+
+pub use libpq::*;
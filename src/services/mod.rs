@@ -1,6 +1,7 @@
 pub mod agent;
 pub mod database;
 pub mod export;
+pub mod import;
 pub mod sql;
 pub mod storage;
 pub mod updates;
@@ -10,7 +11,8 @@ pub use export::{export_to_csv, export_to_json};
 pub use sql::SqlCompletionProvider;
 #[allow(unused_imports)]
 pub use storage::{
-    AppStore, ConnectionInfo, ConnectionsRepository, QueryHistoryRepository, SslMode,
+    AppStore, ConnectionColor, ConnectionInfo, ConnectionsRepository, Environment,
+    QueryHistoryRepository, SslMode,
 };
 
 pub use updates::check_for_update;
@@ -1,5 +1,7 @@
 use tree_sitter::{Parser, Tree};
 
+use super::tokens::{Token, TokenKind, tokenize};
+
 /// Represents a detected SQL query with position information
 #[allow(dead_code)]
 #[derive(Debug)]
@@ -152,3 +154,604 @@ fn byte_to_char_offset(text: &str, byte_offset: usize) -> usize {
         .position(|(i, _)| i >= byte_offset)
         .unwrap_or(text.chars().count())
 }
+
+/// What a statement does, judged from its keywords
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StatementKind {
+    /// Only reads: SELECT, VALUES, SHOW, plain EXPLAIN, COPY ... TO
+    Read,
+    /// Changes rows or runs maintenance: INSERT, UPDATE, DELETE, MERGE,
+    /// TRUNCATE, COPY ... FROM, VACUUM, ...
+    Write,
+    /// Changes the schema or privileges: CREATE, ALTER, DROP, GRANT, ...
+    /// `SELECT ... INTO` creates a table, so it counts as well.
+    Definition,
+    /// Transaction control and session settings
+    Session,
+    /// Anything whose effect depends on the code it runs, e.g. CALL or DO
+    Other,
+}
+
+impl StatementKind {
+    /// Whether the statement changes data or schema
+    pub fn modifies_data(&self) -> bool {
+        matches!(self, StatementKind::Write | StatementKind::Definition)
+    }
+}
+
+/// Classifies a single statement
+pub fn classify_statement(sql: &str) -> StatementKind {
+    let words = top_level_words(sql);
+    let Some((first, rest)) = words.split_first() else {
+        return StatementKind::Other;
+    };
+    let has = |keywords: &[&str]| rest.iter().any(|w| keywords.contains(&w.as_str()));
+
+    match first.as_str() {
+        "select" | "values" | "table" if has(&["into"]) => StatementKind::Definition,
+        "select" | "values" | "table" | "show" | "fetch" | "move" | "close" | "declare" => {
+            StatementKind::Read
+        }
+        "with" => {
+            // Data-modifying CTEs hide inside parentheses
+            let all_words = words_of(sql);
+            if all_words
+                .iter()
+                .any(|w| matches!(w.as_str(), "insert" | "update" | "delete" | "merge"))
+            {
+                StatementKind::Write
+            } else if has(&["into"]) {
+                StatementKind::Definition
+            } else {
+                StatementKind::Read
+            }
+        }
+        "explain" => {
+            // EXPLAIN ANALYZE runs the statement
+            let analyzed = words_of(sql)
+                .iter()
+                .skip(1)
+                .take_while(|w| !is_statement_keyword(w))
+                .any(|w| w == "analyze" || w == "analyse");
+            let explained = tokenize(sql).into_iter().skip(1).find(|t| {
+                t.kind == TokenKind::Word && is_statement_keyword(&t.text.to_ascii_lowercase())
+            });
+            match explained {
+                Some(token) if analyzed => classify_statement(&sql[token.start..]),
+                _ => StatementKind::Read,
+            }
+        }
+        "copy" if has(&["to"]) => StatementKind::Read,
+        "insert" | "update" | "delete" | "merge" | "truncate" | "copy" | "vacuum" | "analyze"
+        | "analyse" | "cluster" | "reindex" | "refresh" => StatementKind::Write,
+        "create" | "alter" | "drop" | "grant" | "revoke" | "comment" | "security" | "reassign"
+        | "import" => StatementKind::Definition,
+        "begin" | "start" | "commit" | "end" | "rollback" | "abort" | "savepoint" | "release"
+        | "set" | "reset" | "discard" => StatementKind::Session,
+        _ => StatementKind::Other,
+    }
+}
+
+/// Whether a statement runs server-side code, i.e. `DO` or `CALL`, which may
+/// write anything, including the session's read-only settings
+pub fn runs_code(sql: &str) -> bool {
+    tokenize(sql)
+        .first()
+        .is_some_and(|t| t.is_word("do") || t.is_word("call"))
+}
+
+/// Whether a statement tries to make the session or transaction writable:
+/// `BEGIN`, `START TRANSACTION` or `SET [SESSION] TRANSACTION` with
+/// `READ WRITE`, turning `default_transaction_read_only` off, or calling
+/// `set_config` on a read-only setting
+pub fn enables_writes(sql: &str) -> bool {
+    let tokens = tokenize(sql);
+    let Some(first) = tokens.first() else {
+        return false;
+    };
+    if calls_set_config_on_read_only(&tokens) {
+        return true;
+    }
+    if first.is_word("begin") || first.is_word("start") {
+        return has_read_write(&tokens[1..]);
+    }
+    if first.is_word("reset") {
+        return tokens
+            .get(1)
+            .is_some_and(|t| t.is_word("all") || is_read_only_setting(t));
+    }
+    if !first.is_word("set") {
+        return false;
+    }
+
+    let ix = skip_words(&tokens, 1, &["session", "local", "characteristics", "as"]);
+    match tokens.get(ix) {
+        Some(t) if t.is_word("transaction") => has_read_write(&tokens[ix + 1..]),
+        Some(t) if is_read_only_setting(t) => {
+            let ix = skip_symbol(&tokens, skip_words(&tokens, ix + 1, &["to"]), "=");
+            tokens.get(ix).is_none_or(|value| !is_true(value))
+        }
+        _ => false,
+    }
+}
+
+/// Whether any `set_config(name, ...)` call may change a read-only setting.
+/// A name other than a single plain literal could be anything, so it counts too.
+fn calls_set_config_on_read_only(tokens: &[Token]) -> bool {
+    let is_symbol =
+        |token: &Token, symbol: &str| token.kind == TokenKind::Symbol && token.text == symbol;
+    tokens.iter().enumerate().any(|(ix, token)| {
+        token.is_word("set_config")
+            && tokens.get(ix + 1).is_some_and(|t| is_symbol(t, "("))
+            && tokens
+                .get(ix + 2)
+                .filter(|_| tokens.get(ix + 3).is_some_and(|t| is_symbol(t, ",")))
+                .and_then(string_literal)
+                .is_none_or(|name| {
+                    name.eq_ignore_ascii_case("default_transaction_read_only")
+                        || name.eq_ignore_ascii_case("transaction_read_only")
+                })
+    })
+}
+
+/// The contents of a plain `'...'` literal without escapes
+fn string_literal<'a>(token: &Token<'a>) -> Option<&'a str> {
+    let text = token.text.strip_prefix('\'')?.strip_suffix('\'')?;
+    (token.kind == TokenKind::String && !text.contains('\'')).then_some(text)
+}
+
+fn has_read_write(tokens: &[Token]) -> bool {
+    tokens
+        .windows(2)
+        .any(|pair| pair[0].is_word("read") && pair[1].is_word("write"))
+}
+
+fn is_read_only_setting(token: &Token) -> bool {
+    token.is_word("default_transaction_read_only") || token.is_word("transaction_read_only")
+}
+
+/// A boolean setting value Postgres reads as true, e.g. `on` or `'yes'`
+fn is_true(value: &Token) -> bool {
+    let text = value.text.trim_matches('\'').to_ascii_lowercase();
+    text == "on" || text == "1" || text.starts_with('t') || text.starts_with('y')
+}
+
+fn is_statement_keyword(word: &str) -> bool {
+    matches!(
+        word,
+        "select"
+            | "with"
+            | "values"
+            | "table"
+            | "insert"
+            | "update"
+            | "delete"
+            | "merge"
+            | "create"
+            | "execute"
+            | "declare"
+    )
+}
+
+/// Lowercased keywords and identifiers, skipping literals and comments
+fn words_of(sql: &str) -> Vec<String> {
+    tokenize(sql)
+        .into_iter()
+        .filter(|t| t.kind == TokenKind::Word)
+        .map(|t| t.text.to_ascii_lowercase())
+        .collect()
+}
+
+/// Like [`words_of`], but only outside parentheses
+fn top_level_words(sql: &str) -> Vec<String> {
+    top_level_tokens(sql)
+        .into_iter()
+        .filter(|t| t.kind == TokenKind::Word)
+        .map(|t| t.text.to_ascii_lowercase())
+        .collect()
+}
+
+/// Tokens outside parentheses; the parentheses themselves are dropped
+fn top_level_tokens(sql: &str) -> Vec<Token<'_>> {
+    let mut depth = 0usize;
+    let mut tokens = Vec::new();
+    for token in tokenize(sql) {
+        match (token.kind, token.text) {
+            (TokenKind::Symbol, "(") => depth += 1,
+            (TokenKind::Symbol, ")") => depth = depth.saturating_sub(1),
+            _ if depth == 0 => tokens.push(token),
+            _ => {}
+        }
+    }
+    tokens
+}
+
+/// What makes a statement worth confirming before it runs
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DestructiveAction {
+    UpdateWithoutWhere,
+    DeleteWithoutWhere,
+    /// `DROP` of any object, with its lowercased type, e.g. "materialized view"
+    Drop(String),
+    Truncate,
+    DropColumn,
+}
+
+impl DestructiveAction {
+    /// Whether `EXPLAIN` can estimate how many rows the statement touches
+    pub fn affects_rows(&self) -> bool {
+        matches!(
+            self,
+            DestructiveAction::UpdateWithoutWhere | DestructiveAction::DeleteWithoutWhere
+        )
+    }
+
+    pub fn label(&self) -> String {
+        match self {
+            DestructiveAction::UpdateWithoutWhere => "UPDATE without WHERE".to_string(),
+            DestructiveAction::DeleteWithoutWhere => "DELETE without WHERE".to_string(),
+            DestructiveAction::Drop(object_type) => {
+                format!("DROP {}", object_type.to_uppercase())
+            }
+            DestructiveAction::Truncate => "TRUNCATE".to_string(),
+            DestructiveAction::DropColumn => "DROP COLUMN".to_string(),
+        }
+    }
+}
+
+/// A destructive statement and the objects it would affect, as written
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DestructiveStatement {
+    pub action: DestructiveAction,
+    pub objects: Vec<String>,
+}
+
+/// Object types after `DROP`, longest first so prefixes don't shadow them
+const DROP_OBJECT_TYPES: [&str; 12] = [
+    "foreign data wrapper",
+    "text search configuration",
+    "text search dictionary",
+    "text search parser",
+    "text search template",
+    "materialized view",
+    "foreign table",
+    "event trigger",
+    "access method",
+    "operator class",
+    "operator family",
+    "user mapping",
+];
+
+/// Detects UPDATE/DELETE without WHERE, DROP, TRUNCATE and
+/// `ALTER TABLE ... DROP COLUMN`
+pub fn destructive_statement(sql: &str) -> Option<DestructiveStatement> {
+    let tokens = top_level_tokens(sql);
+    // Data-modifying CTEs run whatever the main statement does
+    if tokens.first()?.is_word("with")
+        && let Some(found) = cte_bodies(sql).into_iter().find_map(destructive_statement)
+    {
+        return Some(found);
+    }
+    // Skip the CTEs of a WITH query to reach the main statement
+    let start = if tokens.first()?.is_word("with") {
+        tokens.iter().skip(1).position(|t| {
+            t.kind == TokenKind::Word && is_statement_keyword(&t.text.to_ascii_lowercase())
+        })? + 1
+    } else {
+        0
+    };
+    let tokens = &tokens[start..];
+    let first = tokens.first()?;
+    let has_where = || tokens.iter().any(|t| t.is_word("where"));
+
+    let (action, objects) = if first.is_word("update") {
+        if has_where() {
+            return None;
+        }
+        let ix = skip_words(tokens, 1, &["only"]);
+        (
+            DestructiveAction::UpdateWithoutWhere,
+            object_names(tokens, ix, false),
+        )
+    } else if first.is_word("delete") {
+        if has_where() {
+            return None;
+        }
+        let ix = skip_words(tokens, 1, &["from", "only"]);
+        (
+            DestructiveAction::DeleteWithoutWhere,
+            object_names(tokens, ix, false),
+        )
+    } else if first.is_word("truncate") {
+        let ix = skip_words(tokens, 1, &["table", "only"]);
+        (DestructiveAction::Truncate, object_names(tokens, ix, true))
+    } else if first.is_word("drop") {
+        let (object_type, ix) = drop_object_type(tokens)?;
+        let ix = skip_words(tokens, ix, &["concurrently", "if", "exists"]);
+        (
+            DestructiveAction::Drop(object_type),
+            object_names(tokens, ix, true),
+        )
+    } else if first.is_word("alter") && tokens.get(1).is_some_and(|t| t.is_word("table")) {
+        let ix = skip_words(tokens, 2, &["if", "exists", "only"]);
+        let (table, ix) = qualified_name(tokens, ix)?;
+        let columns = dropped_columns(tokens, ix);
+        if columns.is_empty() {
+            return None;
+        }
+        (
+            DestructiveAction::DropColumn,
+            columns
+                .into_iter()
+                .map(|column| format!("{}.{}", table, column))
+                .collect(),
+        )
+    } else {
+        return None;
+    };
+
+    Some(DestructiveStatement { action, objects })
+}
+
+/// Queries of the CTEs of a `WITH` statement, i.e. the parenthesized
+/// bodies after `AS` or `[NOT] MATERIALIZED`
+fn cte_bodies(sql: &str) -> Vec<&str> {
+    let tokens = tokenize(sql);
+    let mut bodies = Vec::new();
+    let mut depth = 0usize;
+    let mut body_start = None;
+    for (ix, token) in tokens.iter().enumerate() {
+        match (token.kind, token.text) {
+            (TokenKind::Symbol, "(") => {
+                let after_as = ix.checked_sub(1).is_some_and(|prev| {
+                    tokens[prev].is_word("as") || tokens[prev].is_word("materialized")
+                });
+                if depth == 0 && after_as {
+                    body_start = Some(token.end());
+                }
+                depth += 1;
+            }
+            (TokenKind::Symbol, ")") => {
+                depth = depth.saturating_sub(1);
+                if depth == 0
+                    && let Some(start) = body_start.take()
+                {
+                    bodies.push(&sql[start..token.start]);
+                }
+            }
+            _ => {}
+        }
+    }
+    bodies
+}
+
+/// The lowercased object type after `DROP` and the index following it
+fn drop_object_type(tokens: &[Token]) -> Option<(String, usize)> {
+    let words: Vec<String> = tokens
+        .iter()
+        .skip(1)
+        .take(3)
+        .map(|t| t.text.to_ascii_lowercase())
+        .collect();
+    for object_type in DROP_OBJECT_TYPES {
+        let len = object_type.split(' ').count();
+        if words.len() >= len && words[..len].join(" ") == object_type {
+            return Some((object_type.to_string(), 1 + len));
+        }
+    }
+    let word = tokens.get(1).filter(|t| t.kind == TokenKind::Word)?;
+    Some((word.text.to_ascii_lowercase(), 2))
+}
+
+/// Columns removed by the `DROP [COLUMN]` actions of an ALTER TABLE, whose
+/// actions start at `ix`
+fn dropped_columns(tokens: &[Token], ix: usize) -> Vec<String> {
+    tokens[ix.min(tokens.len())..]
+        .split(|t| t.kind == TokenKind::Symbol && t.text == ",")
+        .filter(|action| action.first().is_some_and(|t| t.is_word("drop")))
+        .filter(|action| !action.get(1).is_some_and(|t| t.is_word("constraint")))
+        .filter_map(|action| {
+            let ix = skip_words(action, 1, &["column", "if", "exists"]);
+            qualified_name(action, ix).map(|(name, _)| name)
+        })
+        .collect()
+}
+
+/// Names starting at `ix`, several of them when `list` is set and they are
+/// separated by commas
+fn object_names(tokens: &[Token], mut ix: usize, list: bool) -> Vec<String> {
+    let mut names = Vec::new();
+    while let Some((name, next)) = qualified_name(tokens, ix) {
+        names.push(name);
+        ix = skip_symbol(tokens, next, "*");
+        if !list || tokens.get(ix).is_none_or(|t| t.text != ",") {
+            break;
+        }
+        ix = skip_words(tokens, ix + 1, &["only"]);
+    }
+    names
+}
+
+/// A possibly schema-qualified name at `ix`, and the index after it
+fn qualified_name(tokens: &[Token], ix: usize) -> Option<(String, usize)> {
+    let is_name = |t: &Token| matches!(t.kind, TokenKind::Word | TokenKind::QuotedIdent);
+    let mut name = tokens.get(ix).filter(|t| is_name(t))?.text.to_string();
+    let mut ix = ix + 1;
+    while tokens.get(ix).is_some_and(|t| t.text == ".")
+        && let Some(part) = tokens.get(ix + 1).filter(|t| is_name(t))
+    {
+        name.push('.');
+        name.push_str(part.text);
+        ix += 2;
+    }
+    Some((name, ix))
+}
+
+fn skip_words(tokens: &[Token], mut ix: usize, words: &[&str]) -> usize {
+    while tokens
+        .get(ix)
+        .is_some_and(|t| words.iter().any(|w| t.is_word(w)))
+    {
+        ix += 1;
+    }
+    ix
+}
+
+fn skip_symbol(tokens: &[Token], ix: usize, symbol: &str) -> usize {
+    match tokens.get(ix) {
+        Some(t) if t.kind == TokenKind::Symbol && t.text == symbol => ix + 1,
+        _ => ix,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify_statement() {
+        let cases = [
+            ("SELECT * FROM users", StatementKind::Read),
+            ("-- note\nselect 1", StatementKind::Read),
+            ("SELECT * INTO backup FROM users", StatementKind::Definition),
+            ("SELECT 'insert into t'", StatementKind::Read),
+            ("WITH x AS (SELECT 1) SELECT * FROM x", StatementKind::Read),
+            (
+                "WITH gone AS (DELETE FROM t RETURNING *) SELECT * FROM gone",
+                StatementKind::Write,
+            ),
+            ("EXPLAIN UPDATE t SET a = 1", StatementKind::Read),
+            (
+                "EXPLAIN (ANALYZE, BUFFERS) UPDATE t SET a = 1",
+                StatementKind::Write,
+            ),
+            ("COPY (SELECT a FROM t) TO STDOUT", StatementKind::Read),
+            ("COPY t FROM STDIN", StatementKind::Write),
+            ("update t set a = 1", StatementKind::Write),
+            ("DROP TABLE t", StatementKind::Definition),
+            ("BEGIN", StatementKind::Session),
+            ("CALL do_things()", StatementKind::Other),
+        ];
+        for (sql, kind) in cases {
+            assert_eq!(classify_statement(sql), kind, "{}", sql);
+        }
+    }
+
+    #[test]
+    fn test_destructive_statement() {
+        let detect = |sql| destructive_statement(sql).map(|d| (d.action, d.objects));
+
+        assert_eq!(
+            detect("DELETE FROM orders;"),
+            Some((
+                DestructiveAction::DeleteWithoutWhere,
+                vec!["orders".to_string()]
+            ))
+        );
+        assert_eq!(detect("DELETE FROM orders WHERE id = 1"), None);
+        assert_eq!(
+            detect("update only public.users set active = false"),
+            Some((
+                DestructiveAction::UpdateWithoutWhere,
+                vec!["public.users".to_string()]
+            ))
+        );
+        assert_eq!(
+            detect("UPDATE t SET a = (SELECT b FROM u WHERE u.id = 1)"),
+            Some((DestructiveAction::UpdateWithoutWhere, vec!["t".to_string()]))
+        );
+        assert_eq!(
+            detect("WITH x AS (SELECT 1) DELETE FROM t"),
+            Some((DestructiveAction::DeleteWithoutWhere, vec!["t".to_string()]))
+        );
+        assert_eq!(
+            detect("WITH d AS (DELETE FROM t RETURNING *) SELECT * FROM d"),
+            Some((DestructiveAction::DeleteWithoutWhere, vec!["t".to_string()]))
+        );
+        assert_eq!(
+            detect("WITH s AS (SELECT 1), u AS NOT MATERIALIZED (UPDATE t SET a = 1) SELECT 1"),
+            Some((DestructiveAction::UpdateWithoutWhere, vec!["t".to_string()]))
+        );
+        assert_eq!(
+            detect("WITH d AS (DELETE FROM t WHERE id = 1 RETURNING *) SELECT * FROM d"),
+            None
+        );
+        assert_eq!(detect("SELECT * FROM t FOR UPDATE"), None);
+        assert_eq!(
+            detect("DROP TABLE IF EXISTS a, \"B\".c CASCADE"),
+            Some((
+                DestructiveAction::Drop("table".to_string()),
+                vec!["a".to_string(), "\"B\".c".to_string()]
+            ))
+        );
+        assert_eq!(
+            detect("drop materialized view stats"),
+            Some((
+                DestructiveAction::Drop("materialized view".to_string()),
+                vec!["stats".to_string()]
+            ))
+        );
+        assert_eq!(
+            detect("TRUNCATE TABLE a, ONLY b RESTART IDENTITY"),
+            Some((
+                DestructiveAction::Truncate,
+                vec!["a".to_string(), "b".to_string()]
+            ))
+        );
+        assert_eq!(
+            detect("ALTER TABLE users DROP COLUMN email, DROP CONSTRAINT pk, DROP IF EXISTS age"),
+            Some((
+                DestructiveAction::DropColumn,
+                vec!["users.email".to_string(), "users.age".to_string()]
+            ))
+        );
+        assert_eq!(
+            detect("ALTER TABLE users ALTER COLUMN email DROP NOT NULL"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_enables_writes() {
+        assert!(enables_writes("BEGIN READ WRITE"));
+        assert!(enables_writes(
+            "SET SESSION CHARACTERISTICS AS TRANSACTION READ WRITE"
+        ));
+        assert!(enables_writes("set default_transaction_read_only = off"));
+        assert!(!enables_writes("BEGIN READ ONLY"));
+        assert!(!enables_writes("SELECT 'read write'"));
+        assert!(!enables_writes("SELECT read, write FROM t"));
+        assert!(!enables_writes("UPDATE t SET read = 1, write = 2"));
+        assert!(enables_writes("SET TRANSACTION READ WRITE"));
+        assert!(enables_writes(
+            "set session transaction isolation level serializable, read write"
+        ));
+        assert!(!enables_writes("SET TRANSACTION READ ONLY"));
+        assert!(enables_writes(
+            "SET default_transaction_read_only TO 'false'"
+        ));
+        assert!(!enables_writes("SET default_transaction_read_only = on"));
+        assert!(enables_writes("RESET default_transaction_read_only"));
+        assert!(enables_writes(
+            "SELECT set_config('default_transaction_read_only', 'off', false)"
+        ));
+        assert!(enables_writes(
+            "select pg_catalog.set_config('transaction_read_only', 'off', true)"
+        ));
+        assert!(enables_writes(
+            "SELECT set_config('default_transaction_' || 'read_only', 'off', false)"
+        ));
+        assert!(!enables_writes(
+            "SELECT set_config('search_path', 'public', false)"
+        ));
+        assert!(!enables_writes("SELECT 'set_config(x)'"));
+    }
+
+    #[test]
+    fn test_runs_code() {
+        assert!(runs_code("DO $$ BEGIN PERFORM 1; END $$"));
+        assert!(runs_code("call refresh_totals()"));
+        assert!(!runs_code("SELECT do_things()"));
+        assert!(!runs_code("-- call me\nSELECT 1"));
+    }
+}
@@ -1,16 +1,22 @@
 //! SQL editing support module.
 //!
 //! This module provides:
-//! - `analyzer` - SQL query detection and parsing with tree-sitter
+//! - `analyzer` - SQL query detection and parsing with tree-sitter, statement classification
 //! - `completions` - LSP-style completion provider for SQL
 //! - `completion_agent` - Agent-powered inline completions
 //! - `code_action_agent` - Agent-powered code actions (Complete, Explain, Optimize)
+//! - `tokens` - Comment- and literal-aware tokenizer, bind placeholder detection
 
 mod analyzer;
 mod code_action_agent;
 mod completion_agent;
 mod completions;
+mod tokens;
 
-pub use analyzer::{SqlQuery, SqlQueryAnalyzer};
+pub use analyzer::{
+    DestructiveAction, DestructiveStatement, SqlQuery, SqlQueryAnalyzer, StatementKind,
+    classify_statement, destructive_statement, enables_writes, runs_code,
+};
 pub use code_action_agent::SqlCodeActionProvider;
 pub use completions::SqlCompletionProvider;
+pub use tokens::{ParameterizedSql, Token, TokenKind, parameterize, tokenize};
@@ -0,0 +1,381 @@
+//! Lightweight SQL tokenizer.
+//!
+//! Splits a statement into words, placeholders and symbols while skipping
+//! whitespace, comments, string literals and quoted identifiers, so callers
+//! can inspect the code without being fooled by text inside literals.
+
+/// Kind of a SQL token
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenKind {
+    /// Keyword or unquoted identifier
+    Word,
+    /// `"quoted identifier"`
+    QuotedIdent,
+    /// String literal, including escape and dollar-quoted strings
+    String,
+    Number,
+    /// `$1` or `:name` bind placeholder
+    Placeholder,
+    /// Operator or punctuation; `::` is a single symbol
+    Symbol,
+}
+
+/// A token and its byte range in the source
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Token<'a> {
+    pub kind: TokenKind,
+    pub text: &'a str,
+    pub start: usize,
+}
+
+impl Token<'_> {
+    pub fn end(&self) -> usize {
+        self.start + self.text.len()
+    }
+
+    /// Case-insensitive keyword comparison
+    pub fn is_word(&self, keyword: &str) -> bool {
+        self.kind == TokenKind::Word && self.text.eq_ignore_ascii_case(keyword)
+    }
+}
+
+/// Tokenize SQL, dropping whitespace and comments
+pub fn tokenize(sql: &str) -> Vec<Token<'_>> {
+    let bytes = sql.as_bytes();
+    let mut tokens = Vec::new();
+    let mut pos = 0;
+    // Inside `[...]` a colon is an array slice, e.g. `a[1:n]`
+    let mut bracket_depth = 0usize;
+
+    while pos < bytes.len() {
+        let start = pos;
+        let c = bytes[pos];
+
+        let kind = match c {
+            c if c.is_ascii_whitespace() => {
+                pos += 1;
+                continue;
+            }
+            b'-' if bytes.get(pos + 1) == Some(&b'-') => {
+                pos = sql[pos..].find('\n').map_or(bytes.len(), |ix| pos + ix + 1);
+                continue;
+            }
+            b'/' if bytes.get(pos + 1) == Some(&b'*') => {
+                pos = skip_block_comment(bytes, pos);
+                continue;
+            }
+            b'\'' => {
+                pos = skip_quoted(bytes, pos, b'\'', false);
+                TokenKind::String
+            }
+            b'"' => {
+                pos = skip_quoted(bytes, pos, b'"', false);
+                TokenKind::QuotedIdent
+            }
+            b'e' | b'E' if bytes.get(pos + 1) == Some(&b'\'') => {
+                pos = skip_quoted(bytes, pos + 1, b'\'', true);
+                TokenKind::String
+            }
+            b'$' => match dollar_quote_tag(sql, pos) {
+                Some(tag) => {
+                    pos = sql[pos + tag.len()..]
+                        .find(tag)
+                        .map_or(bytes.len(), |ix| pos + tag.len() + ix + tag.len());
+                    TokenKind::String
+                }
+                None if bytes.get(pos + 1).is_some_and(u8::is_ascii_digit) => {
+                    pos = take_while(bytes, pos + 1, |b| b.is_ascii_digit());
+                    TokenKind::Placeholder
+                }
+                None => {
+                    pos += 1;
+                    TokenKind::Symbol
+                }
+            },
+            b':' if bytes.get(pos + 1) == Some(&b':') => {
+                pos += 2;
+                TokenKind::Symbol
+            }
+            b':' if bracket_depth == 0
+                && bytes.get(pos + 1).is_some_and(|&b| is_ident_start(b))
+                && !pos
+                    .checked_sub(1)
+                    .is_some_and(|prev| bytes[prev].is_ascii_digit() || bytes[prev] == b']') =>
+            {
+                pos = take_while(bytes, pos + 1, is_ident_char);
+                TokenKind::Placeholder
+            }
+            c if c.is_ascii_digit() => {
+                pos = take_while(bytes, pos, |b| b.is_ascii_digit() || b == b'.');
+                TokenKind::Number
+            }
+            c if is_ident_start(c) => {
+                pos = take_while(bytes, pos, is_ident_char);
+                TokenKind::Word
+            }
+            _ => {
+                // Step over a whole UTF-8 character
+                pos += sql[pos..].chars().next().map_or(1, char::len_utf8);
+                TokenKind::Symbol
+            }
+        };
+
+        match &sql[start..pos] {
+            "[" => bracket_depth += 1,
+            "]" => bracket_depth = bracket_depth.saturating_sub(1),
+            _ => {}
+        }
+        tokens.push(Token {
+            kind,
+            text: &sql[start..pos],
+            start,
+        });
+    }
+
+    tokens
+}
+
+/// Placeholders of a statement in bind order, with the SQL to send
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParameterizedSql {
+    /// The statement with `:name` placeholders rewritten to `$n`
+    pub sql: String,
+    /// Placeholder labels for `$1..$n`, e.g. `"$1"` or `":user_id"`
+    pub names: Vec<String>,
+    /// Rewritten placeholders, in order
+    rewrites: Vec<Rewrite>,
+}
+
+/// A `:name` replaced by `$n`: byte offset and length in both texts
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Rewrite {
+    at: usize,
+    len: usize,
+    original_at: usize,
+    original_len: usize,
+}
+
+impl ParameterizedSql {
+    /// Maps a byte offset in the rewritten SQL back to the original statement
+    pub fn original_offset(&self, offset: usize) -> usize {
+        let mut original = offset;
+        for rewrite in &self.rewrites {
+            if offset < rewrite.at {
+                break;
+            }
+            if offset < rewrite.at + rewrite.len {
+                return rewrite.original_at;
+            }
+            original =
+                offset - (rewrite.at + rewrite.len) + rewrite.original_at + rewrite.original_len;
+        }
+        original
+    }
+}
+
+/// Find the bind placeholders of a statement.
+///
+/// Positional placeholders keep their numbers (gaps are filled so every
+/// `$n` up to the highest one gets a value); each distinct `:name` is
+/// numbered after them, in order of first use.
+pub fn parameterize(sql: &str) -> ParameterizedSql {
+    let placeholders: Vec<Token<'_>> = tokenize(sql)
+        .into_iter()
+        .filter(|t| t.kind == TokenKind::Placeholder)
+        .collect();
+
+    let max_positional = placeholders
+        .iter()
+        .filter_map(|t| t.text.strip_prefix('$'))
+        .filter_map(|n| n.parse::<usize>().ok())
+        .max()
+        .unwrap_or(0);
+
+    let mut names: Vec<String> = (1..=max_positional).map(|n| format!("${}", n)).collect();
+    let mut rewritten = String::with_capacity(sql.len());
+    let mut rewrites = Vec::new();
+    let mut last = 0;
+
+    for token in placeholders.iter().filter(|t| t.text.starts_with(':')) {
+        let index = match names.iter().position(|name| name == token.text) {
+            Some(ix) => ix,
+            None => {
+                names.push(token.text.to_string());
+                names.len() - 1
+            }
+        };
+
+        rewritten.push_str(&sql[last..token.start]);
+        let placeholder = format!("${}", index + 1);
+        rewrites.push(Rewrite {
+            at: rewritten.len(),
+            len: placeholder.len(),
+            original_at: token.start,
+            original_len: token.text.len(),
+        });
+        rewritten.push_str(&placeholder);
+        last = token.end();
+    }
+    rewritten.push_str(&sql[last..]);
+
+    ParameterizedSql {
+        sql: rewritten,
+        names,
+        rewrites,
+    }
+}
+
+fn is_ident_start(b: u8) -> bool {
+    b.is_ascii_alphabetic() || b == b'_' || !b.is_ascii()
+}
+
+fn is_ident_char(b: u8) -> bool {
+    is_ident_start(b) || b.is_ascii_digit() || b == b'$'
+}
+
+fn take_while(bytes: &[u8], mut pos: usize, pred: impl Fn(u8) -> bool) -> usize {
+    while pos < bytes.len() && pred(bytes[pos]) {
+        pos += 1;
+    }
+    pos
+}
+
+/// Skips a quoted run starting at `pos`; doubled quotes (and backslashes in
+/// escape strings) do not end it
+fn skip_quoted(bytes: &[u8], pos: usize, quote: u8, backslash_escapes: bool) -> usize {
+    let mut pos = pos + 1;
+    while pos < bytes.len() {
+        match bytes[pos] {
+            b'\\' if backslash_escapes => pos += 2,
+            b if b == quote => {
+                if bytes.get(pos + 1) == Some(&quote) {
+                    pos += 2;
+                } else {
+                    return pos + 1;
+                }
+            }
+            _ => pos += 1,
+        }
+    }
+    bytes.len()
+}
+
+/// Block comments nest in Postgres
+fn skip_block_comment(bytes: &[u8], pos: usize) -> usize {
+    let mut depth = 0;
+    let mut pos = pos;
+    while pos < bytes.len() {
+        match (bytes[pos], bytes.get(pos + 1)) {
+            (b'/', Some(b'*')) => {
+                depth += 1;
+                pos += 2;
+            }
+            (b'*', Some(b'/')) => {
+                depth -= 1;
+                pos += 2;
+                if depth == 0 {
+                    return pos;
+                }
+            }
+            _ => pos += 1,
+        }
+    }
+    bytes.len()
+}
+
+/// Returns `$tag$` (or `$$`) when a dollar quote opens at `pos`
+fn dollar_quote_tag(sql: &str, pos: usize) -> Option<&str> {
+    let bytes = sql.as_bytes();
+    let tag_end = take_while(bytes, pos + 1, |b| {
+        b.is_ascii_alphanumeric() || b == b'_' || !b.is_ascii()
+    });
+
+    let tag_starts_well = tag_end == pos + 1 || !bytes[pos + 1].is_ascii_digit();
+    (bytes.get(tag_end) == Some(&b'$') && tag_starts_well).then(|| &sql[pos..=tag_end])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(sql: &str) -> Vec<(TokenKind, &str)> {
+        tokenize(sql)
+            .into_iter()
+            .map(|t| (t.kind, t.text))
+            .collect()
+    }
+
+    #[test]
+    fn test_skips_comments_and_literals() {
+        let tokens = kinds("SELECT 'a '' :b', \"x:y\" -- :c\n/* $1 /* nested */ */ FROM t");
+        assert_eq!(
+            tokens,
+            vec![
+                (TokenKind::Word, "SELECT"),
+                (TokenKind::String, "'a '' :b'"),
+                (TokenKind::Symbol, ","),
+                (TokenKind::QuotedIdent, "\"x:y\""),
+                (TokenKind::Word, "FROM"),
+                (TokenKind::Word, "t"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_dollar_quotes_and_casts() {
+        let tokens = kinds("SELECT $fn$ :a $1 $fn$, x::int, $2, E'\\' :b'");
+        assert_eq!(
+            tokens,
+            vec![
+                (TokenKind::Word, "SELECT"),
+                (TokenKind::String, "$fn$ :a $1 $fn$"),
+                (TokenKind::Symbol, ","),
+                (TokenKind::Word, "x"),
+                (TokenKind::Symbol, "::"),
+                (TokenKind::Word, "int"),
+                (TokenKind::Symbol, ","),
+                (TokenKind::Placeholder, "$2"),
+                (TokenKind::Symbol, ","),
+                (TokenKind::String, "E'\\' :b'"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_array_slices_are_not_placeholders() {
+        let tokens = kinds("SELECT a[1:n], b[:m], c[2]:d, :e");
+        let placeholders: Vec<_> = tokens
+            .iter()
+            .filter(|(kind, _)| *kind == TokenKind::Placeholder)
+            .map(|(_, text)| *text)
+            .collect();
+        assert_eq!(placeholders, vec![":e"]);
+    }
+
+    #[test]
+    fn test_parameterize_named() {
+        let parsed =
+            parameterize("SELECT * FROM users WHERE id = :id OR parent = :id AND org = :org");
+        assert_eq!(
+            parsed.sql,
+            "SELECT * FROM users WHERE id = $1 OR parent = $1 AND org = $2"
+        );
+        assert_eq!(parsed.names, vec![":id", ":org"]);
+
+        // Text before, between and inside rewritten placeholders
+        assert_eq!(parsed.original_offset(10), 10);
+        let and = parsed.sql.find("AND").unwrap();
+        assert_eq!(parsed.original_offset(and), 51);
+        assert_eq!(parsed.original_offset(parsed.sql.len() - 1), 61);
+        assert_eq!(parsed.original_offset(parsed.sql.len()), 65);
+    }
+
+    #[test]
+    fn test_parameterize_positional() {
+        let parsed = parameterize("SELECT $3::int, $1, ':skip'");
+        assert_eq!(parsed.sql, "SELECT $3::int, $1, ':skip'");
+        assert_eq!(parsed.names, vec!["$1", "$2", "$3"]);
+
+        assert!(parameterize("SELECT 1").names.is_empty());
+    }
+}
@@ -2,13 +2,44 @@
 
 use anyhow::{Context, Result};
 use keyring::Entry;
-use sqlx::SqlitePool;
+use sqlx::{SqliteConnection, SqlitePool};
+use std::collections::{HashMap, HashSet};
 use uuid::Uuid;
 
-use super::types::{ConnectionInfo, SslMode};
+use super::connections_file::{ConnectionsFile, ConnectionsImport, NameConflict};
+use super::types::{ConnectionColor, ConnectionInfo, Environment, SslMode};
+use crate::services::import::unique_connection_name;
 
 const KEYRING_SERVICE: &str = "pgui";
 
+/// Columns read for a connection, matching [`ConnectionRow`]
+const CONNECTION_COLUMNS: &str = "id, name, hostname, username, database, port, ssl_mode, \
+    ssl_root_cert, ssl_client_cert, ssl_client_key, ssh_tunnel, timeouts, read_only, settings, \
+    group_name, color, environment";
+
+/// A `connections` row; JSON columns are parsed in
+/// [`ConnectionsRepository::connection_from_row`]
+#[derive(sqlx::FromRow)]
+struct ConnectionRow {
+    id: String,
+    name: String,
+    hostname: String,
+    username: String,
+    database: String,
+    port: i64,
+    ssl_mode: String,
+    ssl_root_cert: Option<String>,
+    ssl_client_cert: Option<String>,
+    ssl_client_key: Option<String>,
+    ssh_tunnel: Option<String>,
+    timeouts: Option<String>,
+    read_only: bool,
+    settings: Option<String>,
+    group_name: Option<String>,
+    color: Option<String>,
+    environment: Option<String>,
+}
+
 /// Repository for connection CRUD operations.
 ///
 /// Passwords are stored securely in the system keyring, while connection
@@ -50,36 +81,72 @@ impl ConnectionsRepository {
         Ok(())
     }
 
+    /// The SSH password or key passphrase sits next to the database
+    /// password, under `<id>:ssh`
+    fn get_ssh_keyring_entry(connection_id: &Uuid) -> Result<Entry> {
+        Entry::new(KEYRING_SERVICE, &format!("{}:ssh", connection_id))
+            .context("Failed to create keyring entry")
+    }
+
+    fn store_ssh_secret(connection_id: &Uuid, secret: &str) -> Result<()> {
+        let entry = Self::get_ssh_keyring_entry(connection_id)?;
+        entry
+            .set_password(secret)
+            .context("Failed to store SSH secret in keyring")
+    }
+
+    fn delete_ssh_secret(connection_id: &Uuid) -> Result<()> {
+        let entry = Self::get_ssh_keyring_entry(connection_id)?;
+        let _ = entry.delete_credential();
+        Ok(())
+    }
+
     // ========== CRUD Methods ==========
 
     /// Load all saved connections from the database
     pub async fn load_all(&self) -> Result<Vec<ConnectionInfo>> {
-        let rows = sqlx::query_as::<_, (String, String, String, String, String, i64, String)>(
-            "SELECT id, name, hostname, username, database, port, ssl_mode
-             FROM connections
-             ORDER BY name",
-        )
+        let rows = sqlx::query_as::<_, ConnectionRow>(&format!(
+            "SELECT {} FROM connections ORDER BY name",
+            CONNECTION_COLUMNS
+        ))
         .fetch_all(&self.pool)
         .await?;
 
-        let mut connections = Vec::new();
-        for (id_str, name, hostname, username, database, port, ssl_mode_str) in rows {
-            let id = Uuid::parse_str(&id_str).context("Invalid UUID in database")?;
-            let password = String::new(); // Load on-demand to avoid keychain prompts
-
-            connections.push(ConnectionInfo {
-                id,
-                name,
-                hostname,
-                username,
-                password,
-                database,
-                port: port as usize,
-                ssl_mode: SslMode::from_db_str(&ssl_mode_str),
-            });
-        }
+        rows.into_iter().map(Self::connection_from_row).collect()
+    }
 
-        Ok(connections)
+    fn connection_from_row(row: ConnectionRow) -> Result<ConnectionInfo> {
+        let id = Uuid::parse_str(&row.id).context("Invalid UUID in database")?;
+
+        Ok(ConnectionInfo {
+            id,
+            name: row.name,
+            hostname: row.hostname,
+            username: row.username,
+            password: String::new(), // Load on-demand to avoid keychain prompts
+            database: row.database,
+            port: row.port as usize,
+            ssl_mode: SslMode::from_db_str(&row.ssl_mode),
+            ssl_root_cert: row.ssl_root_cert,
+            ssl_client_cert: row.ssl_client_cert,
+            ssl_client_key: row.ssl_client_key,
+            ssh_tunnel: row.ssh_tunnel.and_then(|t| serde_json::from_str(&t).ok()),
+            timeouts: row
+                .timeouts
+                .and_then(|t| serde_json::from_str(&t).ok())
+                .unwrap_or_default(),
+            read_only: row.read_only,
+            settings: row
+                .settings
+                .and_then(|s| serde_json::from_str(&s).ok())
+                .unwrap_or_default(),
+            group: row.group_name,
+            color: row.color.as_deref().and_then(ConnectionColor::from_db_str),
+            environment: row
+                .environment
+                .as_deref()
+                .and_then(Environment::from_db_str),
+        })
     }
 
     /// Create a new connection
@@ -91,27 +158,8 @@ impl ConnectionsRepository {
             );
         }
 
-        if !connection.password.is_empty() {
-            Self::store_password(&connection.id, &connection.password)?;
-        }
-
-        sqlx::query(
-            r#"
-            INSERT INTO connections (id, name, hostname, username, database, port, ssl_mode, updated_at)
-            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, CURRENT_TIMESTAMP)
-            "#,
-        )
-        .bind(connection.id.to_string())
-        .bind(&connection.name)
-        .bind(&connection.hostname)
-        .bind(&connection.username)
-        .bind(&connection.database)
-        .bind(connection.port as i64)
-        .bind(connection.ssl_mode.to_db_str())
-        .execute(&self.pool)
-        .await?;
-
-        Ok(())
+        Self::store_secrets(connection)?;
+        Self::insert_row(&mut *self.pool.acquire().await?, connection).await
     }
 
     /// Update an existing connection
@@ -131,15 +179,73 @@ impl ConnectionsRepository {
             );
         }
 
+        Self::store_secrets(connection)?;
+        Self::update_row(&mut *self.pool.acquire().await?, connection).await
+    }
+
+    /// Keeps the keyring in step with a saved connection. An empty password
+    /// leaves the stored one alone.
+    fn store_secrets(connection: &ConnectionInfo) -> Result<()> {
         if !connection.password.is_empty() {
             Self::store_password(&connection.id, &connection.password)?;
         }
+        match &connection.ssh_tunnel {
+            Some(tunnel) if !tunnel.secret.is_empty() => {
+                Self::store_ssh_secret(&connection.id, &tunnel.secret)
+            }
+            Some(_) => Ok(()),
+            None => Self::delete_ssh_secret(&connection.id),
+        }
+    }
+
+    async fn insert_row(conn: &mut SqliteConnection, connection: &ConnectionInfo) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO connections (id, name, hostname, username, database, port, ssl_mode,
+                ssl_root_cert, ssl_client_cert, ssl_client_key, ssh_tunnel, timeouts, read_only,
+                settings, group_name, color, environment, updated_at)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17,
+                CURRENT_TIMESTAMP)
+            "#,
+        )
+        .bind(connection.id.to_string())
+        .bind(&connection.name)
+        .bind(&connection.hostname)
+        .bind(&connection.username)
+        .bind(&connection.database)
+        .bind(connection.port as i64)
+        .bind(connection.ssl_mode.to_db_str())
+        .bind(&connection.ssl_root_cert)
+        .bind(&connection.ssl_client_cert)
+        .bind(&connection.ssl_client_key)
+        .bind(
+            connection
+                .ssh_tunnel
+                .as_ref()
+                .map(serde_json::to_string)
+                .transpose()?,
+        )
+        .bind(serde_json::to_string(&connection.timeouts)?)
+        .bind(connection.read_only)
+        .bind(serde_json::to_string(&connection.settings)?)
+        .bind(&connection.group)
+        .bind(connection.color.map(|color| color.to_db_str()))
+        .bind(connection.environment.map(|env| env.to_db_str()))
+        .execute(conn)
+        .await?;
 
+        Ok(())
+    }
+
+    async fn update_row(conn: &mut SqliteConnection, connection: &ConnectionInfo) -> Result<()> {
         sqlx::query(
             r#"
             UPDATE connections
             SET name = ?2, hostname = ?3, username = ?4, database = ?5,
-                port = ?6, ssl_mode = ?7, updated_at = CURRENT_TIMESTAMP
+                port = ?6, ssl_mode = ?7, ssl_root_cert = ?8, ssl_client_cert = ?9,
+                ssl_client_key = ?10, ssh_tunnel = ?11, timeouts = ?12, read_only = ?13,
+                settings = ?14, group_name = ?15, color = ?16, environment = ?17,
+                updated_at = CURRENT_TIMESTAMP
             WHERE id = ?1
             "#,
         )
@@ -150,7 +256,23 @@ impl ConnectionsRepository {
         .bind(&connection.database)
         .bind(connection.port as i64)
         .bind(connection.ssl_mode.to_db_str())
-        .execute(&self.pool)
+        .bind(&connection.ssl_root_cert)
+        .bind(&connection.ssl_client_cert)
+        .bind(&connection.ssl_client_key)
+        .bind(
+            connection
+                .ssh_tunnel
+                .as_ref()
+                .map(serde_json::to_string)
+                .transpose()?,
+        )
+        .bind(serde_json::to_string(&connection.timeouts)?)
+        .bind(connection.read_only)
+        .bind(serde_json::to_string(&connection.settings)?)
+        .bind(&connection.group)
+        .bind(connection.color.map(|color| color.to_db_str()))
+        .bind(connection.environment.map(|env| env.to_db_str()))
+        .execute(conn)
         .await?;
 
         Ok(())
@@ -159,6 +281,7 @@ impl ConnectionsRepository {
     /// Delete a connection by ID
     pub async fn delete(&self, id: &Uuid) -> Result<()> {
         Self::delete_password(id)?;
+        Self::delete_ssh_secret(id)?;
         sqlx::query("DELETE FROM connections WHERE id = ?1")
             .bind(id.to_string())
             .execute(&self.pool)
@@ -169,26 +292,15 @@ impl ConnectionsRepository {
     /// Get a single connection by ID
     #[allow(dead_code)]
     pub async fn get(&self, id: &Uuid) -> Result<Option<ConnectionInfo>> {
-        let result = sqlx::query_as::<_, (String, String, String, String, String, i64, String)>(
-            "SELECT id, name, hostname, username, database, port, ssl_mode
-             FROM connections WHERE id = ?1",
-        )
+        let result = sqlx::query_as::<_, ConnectionRow>(&format!(
+            "SELECT {} FROM connections WHERE id = ?1",
+            CONNECTION_COLUMNS
+        ))
         .bind(id.to_string())
         .fetch_optional(&self.pool)
         .await?;
 
-        Ok(result.map(
-            |(id_str, name, hostname, username, database, port, ssl_mode_str)| ConnectionInfo {
-                id: Uuid::parse_str(&id_str).unwrap_or_else(|_| Uuid::new_v4()),
-                name,
-                hostname,
-                username,
-                password: String::new(),
-                database,
-                port: port as usize,
-                ssl_mode: SslMode::from_db_str(&ssl_mode_str),
-            },
-        ))
+        result.map(Self::connection_from_row).transpose()
     }
 
     /// Get password for a connection from keyring (on-demand)
@@ -196,6 +308,82 @@ impl ConnectionsRepository {
         Self::get_password(connection_id)
     }
 
+    /// Get the SSH tunnel password or key passphrase from keyring
+    pub fn get_ssh_secret(connection_id: &Uuid) -> Result<String> {
+        Self::get_ssh_keyring_entry(connection_id)?
+            .get_password()
+            .context("Failed to retrieve SSH secret from keyring")
+    }
+
+    /// The saved connections as a shareable file. Passwords are read from
+    /// the keyring and encrypted only when a passphrase is given.
+    pub async fn export(&self, passphrase: Option<&str>) -> Result<ConnectionsFile> {
+        let mut connections = self.load_all().await?;
+        if passphrase.is_some() {
+            for connection in &mut connections {
+                connection.password = Self::get_password(&connection.id).unwrap_or_default();
+            }
+        }
+        ConnectionsFile::new(&connections, passphrase)
+    }
+
+    /// Add the connections of a shared file. A replaced connection keeps
+    /// its id, and its password when the file has none. The rows are written
+    /// in one transaction, so a failed import leaves the saved list as it was.
+    pub async fn import(
+        &self,
+        file: &ConnectionsFile,
+        passphrase: Option<&str>,
+        on_conflict: NameConflict,
+    ) -> Result<ConnectionsImport> {
+        let imported = file.connections(passphrase)?;
+        // Names in use, by saved connections and by those already imported
+        let mut taken: HashMap<String, Uuid> = self
+            .load_all()
+            .await?
+            .iter()
+            .map(|conn| (conn.name.clone(), conn.id))
+            .collect();
+        let mut summary = ConnectionsImport::default();
+        let mut written = vec![];
+
+        let mut tx = self.pool.begin().await?;
+        for mut connection in imported {
+            match (taken.get(&connection.name).copied(), on_conflict) {
+                (None, _) => {
+                    Self::insert_row(&mut tx, &connection).await?;
+                    summary.added += 1;
+                }
+                (Some(_), NameConflict::Skip) => {
+                    summary.skipped += 1;
+                    continue;
+                }
+                (Some(id), NameConflict::Replace) => {
+                    connection.id = id;
+                    Self::update_row(&mut tx, &connection).await?;
+                    summary.replaced += 1;
+                }
+                (Some(_), NameConflict::Rename) => {
+                    let names: HashSet<String> = taken.keys().cloned().collect();
+                    connection.name = unique_connection_name(&connection.name, &names);
+                    Self::insert_row(&mut tx, &connection).await?;
+                    summary.renamed += 1;
+                }
+            }
+            taken.insert(connection.name.clone(), connection.id);
+            written.push(connection);
+        }
+        tx.commit().await?;
+
+        // The keyring can't join the transaction, so secrets are stored once
+        // the rows are saved
+        for connection in &written {
+            Self::store_secrets(connection)?;
+        }
+
+        Ok(summary)
+    }
+
     /// Check if a connection with the given name exists
     pub async fn exists_by_name(&self, name: &str) -> Result<bool> {
         let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM connections WHERE name = ?1")
@@ -205,3 +393,87 @@ impl ConnectionsRepository {
         Ok(count > 0)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::storage::AppStore;
+
+    fn connection(name: &str, hostname: &str) -> ConnectionInfo {
+        ConnectionInfo::new(
+            name.to_string(),
+            hostname.to_string(),
+            "app".to_string(),
+            String::new(),
+            "orders".to_string(),
+            5432,
+            SslMode::Require,
+        )
+    }
+
+    #[test]
+    fn test_import_name_conflicts() {
+        smol::block_on(async {
+            let dir = tempfile::tempdir().unwrap();
+            let store = AppStore::from_path(dir.path().join("pgui.db"))
+                .await
+                .unwrap();
+            let repository = store.connections();
+            let prod = connection("prod", "old.internal");
+            repository.create(&prod).await.unwrap();
+
+            let file = ConnectionsFile::new(
+                &[
+                    connection("prod", "new.internal"),
+                    connection("staging", "staging.internal"),
+                ],
+                None,
+            )
+            .unwrap();
+
+            let summary = repository
+                .import(&file, None, NameConflict::Skip)
+                .await
+                .unwrap();
+            assert_eq!((summary.added, summary.skipped), (1, 1));
+
+            let summary = repository
+                .import(&file, None, NameConflict::Rename)
+                .await
+                .unwrap();
+            assert_eq!(summary.renamed, 2);
+            let names: Vec<String> = repository
+                .load_all()
+                .await
+                .unwrap()
+                .iter()
+                .map(|conn| conn.name.clone())
+                .collect();
+            assert_eq!(names, ["prod", "prod (2)", "staging", "staging (2)"]);
+
+            let summary = repository
+                .import(&file, None, NameConflict::Replace)
+                .await
+                .unwrap();
+            assert_eq!(summary.replaced, 2);
+            let replaced = repository.get(&prod.id).await.unwrap().unwrap();
+            assert_eq!(replaced.hostname, "new.internal");
+
+            // A name repeated within the file conflicts with its first copy
+            let file = ConnectionsFile::new(
+                &[
+                    connection("qa", "qa1.internal"),
+                    connection("qa", "qa2.internal"),
+                ],
+                None,
+            )
+            .unwrap();
+            let summary = repository
+                .import(&file, None, NameConflict::Rename)
+                .await
+                .unwrap();
+            assert_eq!((summary.added, summary.renamed), (1, 1));
+            assert!(repository.exists_by_name("qa (2)").await.unwrap());
+        });
+    }
+}
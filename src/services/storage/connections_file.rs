@@ -0,0 +1,397 @@
+//! Connection lists shared as JSON or TOML files.
+//!
+//! Passwords are left out unless the user gives a passphrase. They are then
+//! encrypted with ChaCha20-Poly1305 under a key derived from the passphrase
+//! with Argon2id, so the file can be checked into a repository. Each password
+//! is bound to its connection's name, so it can't be moved to another one.
+
+use anyhow::{Context as _, Result};
+use argon2::Argon2;
+use base64::Engine as _;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use chacha20poly1305::aead::rand_core::RngCore as _;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use super::types::{ConnectionColor, ConnectionInfo, Environment, SslMode};
+use crate::services::database::{ConnectionSettings, QueryTimeouts, SshTunnelConfig};
+
+/// Version written to new files
+const FILE_VERSION: u32 = 1;
+const CIPHER: &str = "argon2id-chacha20poly1305";
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+
+/// Serialization of a connections file, picked by its extension
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionsFileFormat {
+    Json,
+    Toml,
+}
+
+impl ConnectionsFileFormat {
+    /// TOML for `.toml` files, JSON otherwise
+    pub fn from_path(path: &Path) -> Self {
+        if path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("toml"))
+        {
+            Self::Toml
+        } else {
+            Self::Json
+        }
+    }
+}
+
+/// What to do with an imported connection whose name is already saved
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NameConflict {
+    /// Keep the saved connection
+    #[default]
+    Skip,
+    /// Overwrite the saved connection's settings
+    Replace,
+    /// Add the imported one as `name (2)`
+    Rename,
+}
+
+/// Counts of what an import did
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ConnectionsImport {
+    pub added: usize,
+    pub replaced: usize,
+    pub renamed: usize,
+    pub skipped: usize,
+}
+
+/// A shareable list of connections
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConnectionsFile {
+    pub version: u32,
+    /// Set when passwords are included
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encryption: Option<FileEncryption>,
+    #[serde(default)]
+    pub connections: Vec<SharedConnection>,
+}
+
+/// How the passwords of a file are encrypted
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileEncryption {
+    pub cipher: String,
+    /// Base64 salt of the key derivation
+    pub salt: String,
+}
+
+/// A connection as written to a shared file, without its local id
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SharedConnection {
+    pub name: String,
+    pub hostname: String,
+    pub username: String,
+    pub database: String,
+    pub port: usize,
+    /// libpq name, e.g. `verify-full`
+    #[serde(default = "default_ssl_mode")]
+    pub ssl_mode: String,
+    /// Certificate paths, as they are on the exporting machine
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ssl_root_cert: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ssl_client_cert: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ssl_client_key: Option<String>,
+    /// Jump host settings; its secret is never written
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ssh_tunnel: Option<SshTunnelConfig>,
+    #[serde(default, skip_serializing_if = "QueryTimeouts::is_empty")]
+    pub timeouts: QueryTimeouts,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub read_only: bool,
+    #[serde(default, skip_serializing_if = "ConnectionSettings::is_empty")]
+    pub settings: ConnectionSettings,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub group: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<ConnectionColor>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub environment: Option<Environment>,
+    /// Base64 nonce and ciphertext of the password
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub password: Option<String>,
+}
+
+fn default_ssl_mode() -> String {
+    SslMode::default().to_db_str().to_string()
+}
+
+impl ConnectionsFile {
+    /// A file of `connections`; their passwords are encrypted under
+    /// `passphrase` when one is given and left out otherwise
+    pub fn new(connections: &[ConnectionInfo], passphrase: Option<&str>) -> Result<Self> {
+        let (encryption, cipher) = match passphrase {
+            Some(passphrase) => {
+                let mut salt = [0u8; SALT_LEN];
+                OsRng.fill_bytes(&mut salt);
+                let encryption = FileEncryption {
+                    cipher: CIPHER.to_string(),
+                    salt: BASE64.encode(salt),
+                };
+                let cipher = PasswordCipher::new(passphrase, &salt)?;
+                (Some(encryption), Some(cipher))
+            }
+            None => (None, None),
+        };
+
+        let connections = connections
+            .iter()
+            .map(|connection| {
+                let password = cipher
+                    .as_ref()
+                    .filter(|_| !connection.password.is_empty())
+                    .map(|cipher| cipher.encrypt(&connection.name, &connection.password))
+                    .transpose()?;
+                Ok(SharedConnection {
+                    name: connection.name.clone(),
+                    hostname: connection.hostname.clone(),
+                    username: connection.username.clone(),
+                    database: connection.database.clone(),
+                    port: connection.port,
+                    ssl_mode: connection.ssl_mode.to_db_str().to_string(),
+                    ssl_root_cert: connection.ssl_root_cert.clone(),
+                    ssl_client_cert: connection.ssl_client_cert.clone(),
+                    ssl_client_key: connection.ssl_client_key.clone(),
+                    ssh_tunnel: connection.ssh_tunnel.clone(),
+                    timeouts: connection.timeouts,
+                    read_only: connection.read_only,
+                    settings: connection.settings.clone(),
+                    group: connection.group.clone(),
+                    color: connection.color,
+                    environment: connection.environment,
+                    password,
+                })
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(Self {
+            version: FILE_VERSION,
+            encryption,
+            connections,
+        })
+    }
+
+    pub fn parse(contents: &str, format: ConnectionsFileFormat) -> Result<Self> {
+        let file: Self = match format {
+            ConnectionsFileFormat::Json => {
+                serde_json::from_str(contents).context("Invalid connections file")?
+            }
+            ConnectionsFileFormat::Toml => {
+                toml::from_str(contents).context("Invalid connections file")?
+            }
+        };
+        anyhow::ensure!(
+            file.version <= FILE_VERSION,
+            "The connections file was written by a newer version of pgui"
+        );
+        if let Some(encryption) = &file.encryption {
+            anyhow::ensure!(
+                encryption.cipher == CIPHER,
+                "Unsupported password encryption '{}'",
+                encryption.cipher
+            );
+        }
+        Ok(file)
+    }
+
+    pub fn to_string(&self, format: ConnectionsFileFormat) -> Result<String> {
+        Ok(match format {
+            ConnectionsFileFormat::Json => serde_json::to_string_pretty(self)?,
+            ConnectionsFileFormat::Toml => toml::to_string_pretty(self)?,
+        })
+    }
+
+    /// Whether any connection carries an encrypted password
+    pub fn has_passwords(&self) -> bool {
+        self.encryption.is_some()
+            && self
+                .connections
+                .iter()
+                .any(|connection| connection.password.is_some())
+    }
+
+    /// The connections with new ids. Passwords are decrypted with
+    /// `passphrase`, or left empty without one.
+    pub fn connections(&self, passphrase: Option<&str>) -> Result<Vec<ConnectionInfo>> {
+        let cipher = match (&self.encryption, passphrase) {
+            (Some(encryption), Some(passphrase)) => {
+                let salt = BASE64
+                    .decode(&encryption.salt)
+                    .context("Invalid salt in the connections file")?;
+                Some(PasswordCipher::new(passphrase, &salt)?)
+            }
+            _ => None,
+        };
+
+        self.connections
+            .iter()
+            .map(|shared| {
+                let password = match (&cipher, &shared.password) {
+                    (Some(cipher), Some(password)) => cipher.decrypt(&shared.name, password)?,
+                    _ => String::new(),
+                };
+                let mut connection = ConnectionInfo::new(
+                    shared.name.clone(),
+                    shared.hostname.clone(),
+                    shared.username.clone(),
+                    password,
+                    shared.database.clone(),
+                    shared.port,
+                    SslMode::from_db_str(&shared.ssl_mode),
+                );
+                connection.ssl_root_cert = shared.ssl_root_cert.clone();
+                connection.ssl_client_cert = shared.ssl_client_cert.clone();
+                connection.ssl_client_key = shared.ssl_client_key.clone();
+                connection.ssh_tunnel = shared.ssh_tunnel.clone();
+                connection.timeouts = shared.timeouts;
+                connection.read_only = shared.read_only;
+                connection.settings = shared.settings.clone();
+                connection.group = shared.group.clone();
+                connection.color = shared.color;
+                connection.environment = shared.environment;
+                Ok(connection)
+            })
+            .collect()
+    }
+}
+
+struct PasswordCipher {
+    cipher: ChaCha20Poly1305,
+}
+
+impl PasswordCipher {
+    fn new(passphrase: &str, salt: &[u8]) -> Result<Self> {
+        let mut key = Key::default();
+        Argon2::default()
+            .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+            .map_err(|e| anyhow::anyhow!("Failed to derive the key: {}", e))?;
+        Ok(Self {
+            cipher: ChaCha20Poly1305::new(&key),
+        })
+    }
+
+    /// Encrypts the password of the connection called `name`
+    fn encrypt(&self, name: &str, password: &str) -> Result<String> {
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let payload = Payload {
+            msg: password.as_bytes(),
+            aad: name.as_bytes(),
+        };
+        let ciphertext = self
+            .cipher
+            .encrypt(&nonce, payload)
+            .map_err(|_| anyhow::anyhow!("Failed to encrypt a password"))?;
+        Ok(BASE64.encode([nonce.as_slice(), &ciphertext].concat()))
+    }
+
+    fn decrypt(&self, name: &str, encoded: &str) -> Result<String> {
+        let bytes = BASE64
+            .decode(encoded)
+            .context("Invalid password in the connections file")?;
+        anyhow::ensure!(
+            bytes.len() > NONCE_LEN,
+            "Invalid password in the connections file"
+        );
+        let (nonce, ciphertext) = bytes.split_at(NONCE_LEN);
+        let payload = Payload {
+            msg: ciphertext,
+            aad: name.as_bytes(),
+        };
+        let password = self
+            .cipher
+            .decrypt(Nonce::from_slice(nonce), payload)
+            .map_err(|_| anyhow::anyhow!("Wrong passphrase"))?;
+        String::from_utf8(password).context("Invalid password in the connections file")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn connections() -> Vec<ConnectionInfo> {
+        let mut prod = ConnectionInfo::new(
+            "prod".to_string(),
+            "db.internal".to_string(),
+            "app".to_string(),
+            "s3cret".to_string(),
+            "orders".to_string(),
+            5433,
+            SslMode::VerifyFull,
+        );
+        prod.read_only = true;
+        prod.timeouts.statement_timeout_ms = Some(30_000);
+        prod.settings.role = Some("reporting".to_string());
+        prod.group = Some("Orders".to_string());
+        prod.environment = Some(Environment::Prod);
+        let mut local = ConnectionInfo::default();
+        local.password = String::new();
+        vec![prod, local]
+    }
+
+    #[test]
+    fn test_round_trip_without_passwords() {
+        for format in [ConnectionsFileFormat::Json, ConnectionsFileFormat::Toml] {
+            let file = ConnectionsFile::new(&connections(), None).unwrap();
+            let contents = file.to_string(format).unwrap();
+            assert!(!contents.contains("s3cret"));
+
+            let parsed = ConnectionsFile::parse(&contents, format).unwrap();
+            assert!(!parsed.has_passwords());
+            let imported = parsed.connections(None).unwrap();
+            assert_eq!(imported.len(), 2);
+            assert_eq!(imported[0].name, "prod");
+            assert_eq!(imported[0].port, 5433);
+            assert_eq!(imported[0].ssl_mode, SslMode::VerifyFull);
+            assert_eq!(imported[0].timeouts.statement_timeout_ms, Some(30_000));
+            assert!(imported[0].read_only);
+            assert_eq!(imported[0].settings.role.as_deref(), Some("reporting"));
+            assert_eq!(imported[0].group.as_deref(), Some("Orders"));
+            assert_eq!(imported[0].environment, Some(Environment::Prod));
+            assert_eq!(imported[0].stripe_color(), Some(ConnectionColor::Red));
+            assert!(imported[0].password.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_encrypted_passwords() {
+        let file = ConnectionsFile::new(&connections(), Some("correct horse")).unwrap();
+        let contents = file.to_string(ConnectionsFileFormat::Toml).unwrap();
+        assert!(!contents.contains("s3cret"));
+
+        let parsed = ConnectionsFile::parse(&contents, ConnectionsFileFormat::Toml).unwrap();
+        assert!(parsed.has_passwords());
+        let imported = parsed.connections(Some("correct horse")).unwrap();
+        assert_eq!(imported[0].password, "s3cret");
+        assert!(imported[1].password.is_empty());
+
+        assert!(parsed.connections(Some("wrong")).is_err());
+        assert!(parsed.connections(None).unwrap()[0].password.is_empty());
+
+        // A password copied onto another connection doesn't decrypt
+        let mut moved = parsed.clone();
+        moved.connections[1].password = moved.connections[0].password.clone();
+        assert!(moved.connections(Some("correct horse")).is_err());
+    }
+
+    #[test]
+    fn test_unknown_cipher() {
+        let mut file = ConnectionsFile::new(&connections(), Some("correct horse")).unwrap();
+        file.encryption.as_mut().unwrap().cipher = "rot13".to_string();
+        let contents = file.to_string(ConnectionsFileFormat::Json).unwrap();
+
+        let error = ConnectionsFile::parse(&contents, ConnectionsFileFormat::Json).unwrap_err();
+        assert_eq!(error.to_string(), "Unsupported password encryption 'rot13'");
+    }
+}
@@ -0,0 +1,87 @@
+use anyhow::{Context, Result};
+use sqlx::SqlitePool;
+use uuid::Uuid;
+
+use super::types::EditorTab;
+
+/// Repository for the editor tabs of each connection.
+#[derive(Debug, Clone)]
+pub struct EditorTabsRepository {
+    pool: SqlitePool,
+}
+
+impl EditorTabsRepository {
+    pub(crate) fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// Load a connection's tabs in display order
+    pub async fn load_for_connection(&self, connection_id: &Uuid) -> Result<Vec<EditorTab>> {
+        let rows = sqlx::query_as::<_, (String, String, String, i64, String, i64, Option<String>)>(
+            r#"
+            SELECT id, connection_id, name, position, content, cursor, last_result
+            FROM editor_tabs
+            WHERE connection_id = ?
+            ORDER BY position
+            "#,
+        )
+        .bind(connection_id.to_string())
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.into_iter()
+            .map(
+                |(id, conn_id, name, position, content, cursor, last_result)| {
+                    Ok(EditorTab {
+                        id: Uuid::parse_str(&id).context("Invalid UUID")?,
+                        connection_id: Uuid::parse_str(&conn_id)
+                            .context("Invalid connection UUID")?,
+                        name,
+                        position,
+                        content,
+                        cursor,
+                        last_result,
+                    })
+                },
+            )
+            .collect()
+    }
+
+    /// Insert a tab or replace its stored state
+    pub async fn save(&self, tab: &EditorTab) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO editor_tabs
+                (id, connection_id, name, position, content, cursor, last_result, updated_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?, datetime('now'))
+            ON CONFLICT(id) DO UPDATE SET
+                name = excluded.name,
+                position = excluded.position,
+                content = excluded.content,
+                cursor = excluded.cursor,
+                last_result = excluded.last_result,
+                updated_at = excluded.updated_at
+            "#,
+        )
+        .bind(tab.id.to_string())
+        .bind(tab.connection_id.to_string())
+        .bind(&tab.name)
+        .bind(tab.position)
+        .bind(&tab.content)
+        .bind(tab.cursor)
+        .bind(&tab.last_result)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Remove a closed tab
+    pub async fn delete(&self, id: &Uuid) -> Result<()> {
+        sqlx::query("DELETE FROM editor_tabs WHERE id = ?")
+            .bind(id.to_string())
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+}
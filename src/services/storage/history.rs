@@ -1,9 +1,30 @@
 use anyhow::{Context, Result};
 use chrono::{NaiveDateTime, Utc};
-use sqlx::SqlitePool;
+use sqlx::{QueryBuilder, Sqlite, SqlitePool};
 use uuid::Uuid;
 
-use super::types::QueryHistoryEntry;
+use super::types::{HistoryFilter, HistoryPage, NewHistoryEntry, QueryHistoryEntry};
+
+/// Columns of a history entry, in the order `entry_from_row` reads them
+const HISTORY_COLUMNS: &str = "h.id, h.connection_id, h.sql, h.execution_time_ms, h.rows_affected, h.rows_returned, h.success, h.error_message, h.sql_state, h.database, h.notices, h.executed_at";
+
+type HistoryRow = (
+    String,
+    String,
+    String,
+    i64,
+    Option<i64>,
+    Option<i64>,
+    bool,
+    Option<String>,
+    Option<String>,
+    Option<String>,
+    Option<String>,
+    String,
+);
+
+/// Timestamp format of `datetime('now')`
+const TIMESTAMP_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
 
 /// Repository for query history operations.
 #[derive(Debug, Clone)]
@@ -18,29 +39,31 @@ impl QueryHistoryRepository {
     }
 
     /// Record a query execution
-    pub async fn record(
-        &self,
-        connection_id: &Uuid,
-        sql: &str,
-        execution_time_ms: i64,
-        rows_affected: Option<i64>,
-        success: bool,
-        error_message: Option<&str>,
-    ) -> Result<()> {
+    pub async fn record(&self, connection_id: &Uuid, entry: &NewHistoryEntry) -> Result<()> {
+        let notices = if entry.notices.is_empty() {
+            None
+        } else {
+            Some(serde_json::to_string(&entry.notices)?)
+        };
+
         sqlx::query(
             r#"
             INSERT INTO query_history
-                (id, connection_id, sql, execution_time_ms, rows_affected, success, error_message, executed_at)
-            VALUES (?, ?, ?, ?, ?, ?, ?, datetime('now'))
+                (id, connection_id, sql, execution_time_ms, rows_affected, rows_returned, success, error_message, sql_state, database, notices, executed_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, datetime('now'))
             "#,
         )
         .bind(Uuid::new_v4().to_string())
         .bind(connection_id.to_string())
-        .bind(sql)
-        .bind(execution_time_ms)
-        .bind(rows_affected)
-        .bind(success)
-        .bind(error_message)
+        .bind(&entry.sql)
+        .bind(entry.execution_time_ms)
+        .bind(entry.rows_affected)
+        .bind(entry.rows_returned)
+        .bind(entry.success)
+        .bind(&entry.error_message)
+        .bind(&entry.sql_state)
+        .bind(&entry.database)
+        .bind(notices)
         .execute(&self.pool)
         .await?;
 
@@ -53,42 +76,47 @@ impl QueryHistoryRepository {
         connection_id: &Uuid,
         limit: u32,
     ) -> Result<Vec<QueryHistoryEntry>> {
-        let rows = sqlx::query_as::<_, (String, String, String, i64, Option<i64>, bool, Option<String>, String)>(
-            r#"
-            SELECT id, connection_id, sql, execution_time_ms, rows_affected, success, error_message, executed_at
-            FROM query_history
-            WHERE connection_id = ?
-            ORDER BY executed_at DESC
-            LIMIT ?
-            "#,
-        )
-        .bind(connection_id.to_string())
-        .bind(limit)
-        .fetch_all(&self.pool)
-        .await?;
-
-        rows.into_iter()
-            .map(
-                |(id, conn_id, sql, exec_time, rows, success, err, executed_at)| {
-                    Ok(QueryHistoryEntry {
-                        id: Uuid::parse_str(&id).context("Invalid UUID")?,
-                        connection_id: Uuid::parse_str(&conn_id)
-                            .context("Invalid connection UUID")?,
-                        sql,
-                        execution_time_ms: exec_time,
-                        rows_affected: rows,
-                        success,
-                        error_message: err,
-                        executed_at: NaiveDateTime::parse_from_str(
-                            &executed_at,
-                            "%Y-%m-%d %H:%M:%S",
-                        )
-                        .map(|dt| dt.and_utc())
-                        .unwrap_or_else(|_| Utc::now()),
-                    })
+        let page = self
+            .search(
+                &HistoryFilter {
+                    connection_id: Some(*connection_id),
+                    ..Default::default()
                 },
+                limit,
+                0,
             )
-            .collect()
+            .await?;
+        Ok(page.entries)
+    }
+
+    /// Entries matching `filter`, most recent first, skipping `offset` of them
+    pub async fn search(
+        &self,
+        filter: &HistoryFilter,
+        limit: u32,
+        offset: u32,
+    ) -> Result<HistoryPage> {
+        let mut count = QueryBuilder::<Sqlite>::new("SELECT COUNT(*) FROM query_history h");
+        push_filter(&mut count, filter);
+        let (total,): (i64,) = count.build_query_as().fetch_one(&self.pool).await?;
+
+        let mut select =
+            QueryBuilder::<Sqlite>::new(format!("SELECT {} FROM query_history h", HISTORY_COLUMNS));
+        push_filter(&mut select, filter);
+        select
+            .push(" ORDER BY h.executed_at DESC, h.rowid DESC LIMIT ")
+            .push_bind(limit)
+            .push(" OFFSET ")
+            .push_bind(offset);
+        let rows: Vec<HistoryRow> = select.build_query_as().fetch_all(&self.pool).await?;
+
+        Ok(HistoryPage {
+            entries: rows
+                .into_iter()
+                .map(entry_from_row)
+                .collect::<Result<_>>()?,
+            total,
+        })
     }
 
     /// Clear history for a connection
@@ -124,3 +152,124 @@ impl QueryHistoryRepository {
         Ok(result.rows_affected())
     }
 }
+
+/// Appends the WHERE clause for `filter` to a query over `query_history h`
+fn push_filter(query: &mut QueryBuilder<'_, Sqlite>, filter: &HistoryFilter) {
+    query.push(" WHERE 1 = 1");
+    if let Some(text) = fts_query(&filter.text) {
+        query
+            .push(" AND h.rowid IN (SELECT rowid FROM query_history_fts WHERE query_history_fts MATCH ")
+            .push_bind(text)
+            .push(")");
+    }
+    if let Some(connection_id) = filter.connection_id {
+        query
+            .push(" AND h.connection_id = ")
+            .push_bind(connection_id.to_string());
+    }
+    if let Some(success) = filter.success {
+        query.push(" AND h.success = ").push_bind(success);
+    }
+    if let Some(after) = filter.executed_after {
+        query
+            .push(" AND h.executed_at >= ")
+            .push_bind(after.format(TIMESTAMP_FORMAT).to_string());
+    }
+    if let Some(before) = filter.executed_before {
+        query
+            .push(" AND h.executed_at < ")
+            .push_bind(before.format(TIMESTAMP_FORMAT).to_string());
+    }
+    if let Some(min) = filter.min_execution_time_ms {
+        query.push(" AND h.execution_time_ms >= ").push_bind(min);
+    }
+}
+
+/// An FTS5 query matching every word of `text` as a prefix, with each word
+/// quoted so punctuation in SQL isn't read as query syntax
+fn fts_query(text: &str) -> Option<String> {
+    let terms: Vec<String> = text
+        .split_whitespace()
+        .map(|word| format!("\"{}\"*", word.replace('"', "\"\"")))
+        .collect();
+    (!terms.is_empty()).then(|| terms.join(" "))
+}
+
+fn entry_from_row(row: HistoryRow) -> Result<QueryHistoryEntry> {
+    let (
+        id,
+        conn_id,
+        sql,
+        exec_time,
+        rows,
+        rows_returned,
+        success,
+        err,
+        sql_state,
+        database,
+        notices,
+        executed_at,
+    ) = row;
+    Ok(QueryHistoryEntry {
+        id: Uuid::parse_str(&id).context("Invalid UUID")?,
+        connection_id: Uuid::parse_str(&conn_id).context("Invalid connection UUID")?,
+        sql,
+        execution_time_ms: exec_time,
+        rows_affected: rows,
+        rows_returned,
+        success,
+        error_message: err,
+        sql_state,
+        database,
+        notices: notices
+            .and_then(|n| serde_json::from_str(&n).ok())
+            .unwrap_or_default(),
+        executed_at: NaiveDateTime::parse_from_str(&executed_at, TIMESTAMP_FORMAT)
+            .map(|dt| dt.and_utc())
+            .unwrap_or_else(|_| Utc::now()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::database::{DatabaseErrorDetails, ErrorResult, QueryExecutionResult};
+
+    #[test]
+    fn test_fts_query() {
+        assert_eq!(fts_query("   "), None);
+        assert_eq!(
+            fts_query("sel users.id"),
+            Some("\"sel\"* \"users.id\"*".to_string())
+        );
+        assert_eq!(fts_query("a\"b"), Some("\"a\"\"b\"*".to_string()));
+    }
+
+    #[test]
+    fn test_entry_from_error() {
+        let result = QueryExecutionResult::Error(ErrorResult {
+            message: "relation \"missing\" does not exist".to_string(),
+            execution_time_ms: 3,
+            database_error: Some(Box::new(DatabaseErrorDetails {
+                code: "42P01".to_string(),
+                ..Default::default()
+            })),
+            timeout: None,
+        });
+
+        let entry = NewHistoryEntry::from_result(
+            "SELECT * FROM missing",
+            &result,
+            Some("app".to_string()),
+            &[],
+        );
+        assert!(!entry.success);
+        assert_eq!(entry.sql_state.as_deref(), Some("42P01"));
+        assert_eq!(
+            entry.error_message.as_deref(),
+            Some("relation \"missing\" does not exist")
+        );
+        assert_eq!(entry.database.as_deref(), Some("app"));
+        assert_eq!(entry.rows_returned, None);
+    }
+}
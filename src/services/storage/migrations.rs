@@ -0,0 +1,477 @@
+//! Numbered schema migrations for the app store.
+//!
+//! The applied versions are recorded in `schema_version`. Stores created
+//! before it existed start at version 0, so every step must also work on a
+//! layout that an older release already partly upgraded: tables and indexes
+//! use `IF NOT EXISTS` and columns are added with [`Step::AddColumn`].
+
+use anyhow::Result;
+use sqlx::SqliteConnection;
+use sqlx::sqlite::SqlitePool;
+
+/// One change to the schema
+enum Step {
+    /// A statement that is safe to run again
+    Sql(&'static str),
+    /// `ALTER TABLE <table> ADD COLUMN <definition>`, skipped when a column
+    /// of that name exists
+    AddColumn {
+        table: &'static str,
+        definition: &'static str,
+    },
+}
+
+struct Migration {
+    version: i64,
+    description: &'static str,
+    steps: &'static [Step],
+}
+
+/// Every migration, in the order they are applied. Never edit a released
+/// migration; add a new one instead.
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "connections and query history",
+        steps: &[
+            Step::Sql(
+                r#"
+                CREATE TABLE IF NOT EXISTS connections (
+                    id TEXT PRIMARY KEY,
+                    name TEXT NOT NULL UNIQUE,
+                    hostname TEXT NOT NULL,
+                    username TEXT NOT NULL,
+                    database TEXT NOT NULL,
+                    port INTEGER NOT NULL,
+                    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+                    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+                )
+                "#,
+            ),
+            Step::Sql("CREATE INDEX IF NOT EXISTS idx_connections_name ON connections(name)"),
+            Step::Sql(
+                r#"
+                CREATE TABLE IF NOT EXISTS query_history (
+                    id TEXT PRIMARY KEY,
+                    connection_id TEXT NOT NULL,
+                    sql TEXT NOT NULL,
+                    execution_time_ms INTEGER NOT NULL,
+                    rows_affected INTEGER,
+                    success INTEGER NOT NULL,
+                    error_message TEXT,
+                    executed_at TIMESTAMP NOT NULL,
+                    FOREIGN KEY (connection_id) REFERENCES connections(id) ON DELETE CASCADE
+                )
+                "#,
+            ),
+            Step::Sql(
+                "CREATE INDEX IF NOT EXISTS idx_history_connection ON query_history(connection_id, executed_at DESC)",
+            ),
+        ],
+    },
+    Migration {
+        version: 2,
+        description: "connection SSL mode",
+        steps: &[Step::AddColumn {
+            table: "connections",
+            definition: "ssl_mode TEXT NOT NULL DEFAULT 'prefer'",
+        }],
+    },
+    Migration {
+        version: 3,
+        description: "remembered query parameters",
+        steps: &[Step::Sql(
+            r#"
+            CREATE TABLE IF NOT EXISTS query_parameters (
+                sql TEXT PRIMARY KEY,
+                params TEXT NOT NULL,
+                updated_at TIMESTAMP NOT NULL
+            )
+            "#,
+        )],
+    },
+    Migration {
+        version: 4,
+        description: "server notices in query history",
+        steps: &[Step::AddColumn {
+            table: "query_history",
+            definition: "notices TEXT",
+        }],
+    },
+    Migration {
+        version: 5,
+        description: "connection timeouts",
+        steps: &[Step::AddColumn {
+            table: "connections",
+            definition: "timeouts TEXT",
+        }],
+    },
+    Migration {
+        version: 6,
+        description: "read-only connections",
+        steps: &[Step::AddColumn {
+            table: "connections",
+            definition: "read_only INTEGER NOT NULL DEFAULT 0",
+        }],
+    },
+    Migration {
+        version: 7,
+        description: "editor tabs",
+        steps: &[
+            Step::Sql(
+                r#"
+                CREATE TABLE IF NOT EXISTS editor_tabs (
+                    id TEXT PRIMARY KEY,
+                    connection_id TEXT NOT NULL,
+                    name TEXT NOT NULL,
+                    position INTEGER NOT NULL,
+                    content TEXT NOT NULL DEFAULT '',
+                    cursor INTEGER NOT NULL DEFAULT 0,
+                    last_result TEXT,
+                    updated_at TIMESTAMP NOT NULL,
+                    FOREIGN KEY (connection_id) REFERENCES connections(id) ON DELETE CASCADE
+                )
+                "#,
+            ),
+            Step::Sql(
+                "CREATE INDEX IF NOT EXISTS idx_editor_tabs_connection ON editor_tabs(connection_id, position)",
+            ),
+        ],
+    },
+    Migration {
+        version: 8,
+        description: "saved queries library",
+        // A query outlives the connection it was scoped to
+        steps: &[Step::Sql(
+            r#"
+            CREATE TABLE IF NOT EXISTS saved_queries (
+                id TEXT PRIMARY KEY,
+                connection_id TEXT,
+                name TEXT NOT NULL,
+                description TEXT,
+                tags TEXT NOT NULL DEFAULT '[]',
+                sql TEXT NOT NULL,
+                created_at TIMESTAMP NOT NULL,
+                updated_at TIMESTAMP NOT NULL,
+                FOREIGN KEY (connection_id) REFERENCES connections(id) ON DELETE SET NULL
+            )
+            "#,
+        )],
+    },
+    Migration {
+        version: 9,
+        description: "query history search",
+        // Full-text index over the history SQL, kept in sync by triggers and
+        // filled from the existing rows
+        steps: &[
+            Step::Sql(
+                "CREATE INDEX IF NOT EXISTS idx_history_executed_at ON query_history(executed_at DESC)",
+            ),
+            Step::Sql(
+                r#"
+                CREATE VIRTUAL TABLE IF NOT EXISTS query_history_fts USING fts5(
+                    sql,
+                    content = 'query_history',
+                    content_rowid = 'rowid'
+                )
+                "#,
+            ),
+            Step::Sql(
+                r#"
+                CREATE TRIGGER IF NOT EXISTS query_history_fts_insert AFTER INSERT ON query_history BEGIN
+                    INSERT INTO query_history_fts (rowid, sql) VALUES (new.rowid, new.sql);
+                END
+                "#,
+            ),
+            Step::Sql(
+                r#"
+                CREATE TRIGGER IF NOT EXISTS query_history_fts_delete AFTER DELETE ON query_history BEGIN
+                    INSERT INTO query_history_fts (query_history_fts, rowid, sql)
+                    VALUES ('delete', old.rowid, old.sql);
+                END
+                "#,
+            ),
+            Step::Sql(
+                r#"
+                CREATE TRIGGER IF NOT EXISTS query_history_fts_update AFTER UPDATE OF sql ON query_history BEGIN
+                    INSERT INTO query_history_fts (query_history_fts, rowid, sql)
+                    VALUES ('delete', old.rowid, old.sql);
+                    INSERT INTO query_history_fts (rowid, sql) VALUES (new.rowid, new.sql);
+                END
+                "#,
+            ),
+            Step::Sql("INSERT INTO query_history_fts (query_history_fts) VALUES ('rebuild')"),
+        ],
+    },
+    Migration {
+        version: 10,
+        description: "query outcomes in history",
+        steps: &[
+            Step::AddColumn {
+                table: "query_history",
+                definition: "rows_returned INTEGER",
+            },
+            Step::AddColumn {
+                table: "query_history",
+                definition: "sql_state TEXT",
+            },
+            Step::AddColumn {
+                table: "query_history",
+                definition: "database TEXT",
+            },
+        ],
+    },
+    Migration {
+        version: 11,
+        description: "connection SSL certificates",
+        steps: &[
+            Step::AddColumn {
+                table: "connections",
+                definition: "ssl_root_cert TEXT",
+            },
+            Step::AddColumn {
+                table: "connections",
+                definition: "ssl_client_cert TEXT",
+            },
+            Step::AddColumn {
+                table: "connections",
+                definition: "ssl_client_key TEXT",
+            },
+        ],
+    },
+    Migration {
+        version: 12,
+        description: "connection SSH tunnels",
+        steps: &[Step::AddColumn {
+            table: "connections",
+            definition: "ssh_tunnel TEXT",
+        }],
+    },
+    Migration {
+        version: 13,
+        description: "connection pool and session settings",
+        steps: &[Step::AddColumn {
+            table: "connections",
+            definition: "settings TEXT",
+        }],
+    },
+    Migration {
+        version: 14,
+        description: "connection groups, colors and environments",
+        steps: &[
+            Step::AddColumn {
+                table: "connections",
+                definition: "group_name TEXT",
+            },
+            Step::AddColumn {
+                table: "connections",
+                definition: "color TEXT",
+            },
+            Step::AddColumn {
+                table: "connections",
+                definition: "environment TEXT",
+            },
+        ],
+    },
+];
+
+/// Apply every migration newer than the store's version, each in its own
+/// transaction together with its `schema_version` row
+pub(super) async fn migrate(pool: &SqlitePool) -> Result<()> {
+    sqlx::query(
+        r#"
+            CREATE TABLE IF NOT EXISTS schema_version (
+                version INTEGER PRIMARY KEY,
+                description TEXT NOT NULL,
+                applied_at TIMESTAMP NOT NULL
+            )
+            "#,
+    )
+    .execute(pool)
+    .await?;
+
+    let current = schema_version(pool).await?;
+    if current > latest_version() {
+        tracing::warn!(
+            "Store schema version {} is newer than this release ({})",
+            current,
+            latest_version()
+        );
+        return Ok(());
+    }
+
+    for migration in MIGRATIONS
+        .iter()
+        .filter(|migration| migration.version > current)
+    {
+        tracing::debug!("Migration {}: {}", migration.version, migration.description);
+
+        let mut tx = pool.begin().await?;
+        for step in migration.steps {
+            apply(&mut tx, step).await?;
+        }
+        sqlx::query(
+            "INSERT INTO schema_version (version, description, applied_at) VALUES (?, ?, datetime('now'))",
+        )
+        .bind(migration.version)
+        .bind(migration.description)
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+    }
+
+    Ok(())
+}
+
+async fn apply(conn: &mut SqliteConnection, step: &Step) -> Result<()> {
+    match step {
+        Step::Sql(sql) => {
+            sqlx::query(sql).execute(&mut *conn).await?;
+        }
+        Step::AddColumn { table, definition } => {
+            let column = definition.split_whitespace().next().unwrap_or_default();
+            let exists: bool = sqlx::query_scalar(
+                "SELECT EXISTS (SELECT 1 FROM pragma_table_info(?) WHERE name = ?)",
+            )
+            .bind(table)
+            .bind(column)
+            .fetch_one(&mut *conn)
+            .await?;
+
+            if !exists {
+                sqlx::query(&format!("ALTER TABLE {} ADD COLUMN {}", table, definition))
+                    .execute(&mut *conn)
+                    .await?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Highest applied migration, 0 for a new or unversioned store
+async fn schema_version(pool: &SqlitePool) -> Result<i64> {
+    let version: Option<i64> = sqlx::query_scalar("SELECT MAX(version) FROM schema_version")
+        .fetch_one(pool)
+        .await?;
+    Ok(version.unwrap_or(0))
+}
+
+fn latest_version() -> i64 {
+    MIGRATIONS.last().map_or(0, |migration| migration.version)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::storage::AppStore;
+    use std::path::Path;
+
+    /// Tables, indexes and triggers with their columns, in a stable order
+    async fn layout(pool: &SqlitePool) -> Vec<(String, String, Vec<String>)> {
+        let objects: Vec<(String, String)> = sqlx::query_as(
+            r#"
+            SELECT type, name FROM sqlite_master
+            WHERE name NOT LIKE 'sqlite_%' AND name != 'schema_version'
+            ORDER BY type, name
+            "#,
+        )
+        .fetch_all(pool)
+        .await
+        .unwrap();
+
+        let mut layout = vec![];
+        for (kind, name) in objects {
+            let columns: Vec<String> = sqlx::query_scalar(
+                "SELECT name || ' ' || type || ' ' || \"notnull\" || ' ' || IFNULL(dflt_value, '') FROM pragma_table_info(?) ORDER BY name",
+            )
+            .bind(&name)
+            .fetch_all(pool)
+            .await
+            .unwrap();
+            layout.push((kind, name, columns));
+        }
+        layout
+    }
+
+    async fn open(path: &Path) -> SqlitePool {
+        let options = sqlx::sqlite::SqliteConnectOptions::new()
+            .filename(path)
+            .create_if_missing(true);
+        SqlitePool::connect_with(options).await.unwrap()
+    }
+
+    /// Schemas written by earlier releases, as read back from their
+    /// `sqlite_master`. Add the layout of each release that changes the
+    /// schema here.
+    const RELEASES: [(&str, &str); 2] = [
+        ("pre-ssl-mode", include_str!("./schemas/pre-ssl-mode.sql")),
+        ("baseline", include_str!("./schemas/baseline.sql")),
+    ];
+
+    #[test]
+    fn test_upgrade_from_each_release() {
+        smol::block_on(async {
+            let dir = tempfile::tempdir().unwrap();
+            let current = AppStore::from_path(dir.path().join("current.db"))
+                .await
+                .unwrap();
+            let expected = layout(&current.pool).await;
+            assert_eq!(
+                schema_version(&current.pool).await.unwrap(),
+                latest_version()
+            );
+
+            for (release, schema) in RELEASES {
+                let path = dir.path().join(format!("{}.db", release));
+                let pool = open(&path).await;
+                sqlx::raw_sql(schema).execute(&pool).await.unwrap();
+                pool.close().await;
+
+                let store = AppStore::from_path(path).await.unwrap();
+                assert_eq!(layout(&store.pool).await, expected, "from {}", release);
+                assert_eq!(
+                    schema_version(&store.pool).await.unwrap(),
+                    latest_version(),
+                    "from {}",
+                    release
+                );
+            }
+        });
+    }
+
+    #[test]
+    fn test_upgrade_keeps_rows() {
+        smol::block_on(async {
+            let dir = tempfile::tempdir().unwrap();
+            let path = dir.path().join("pgui.db");
+
+            // The baseline layout, from before migrations were numbered
+            let pool = open(&path).await;
+            for sql in [
+                "CREATE TABLE connections (id TEXT PRIMARY KEY, name TEXT NOT NULL UNIQUE, hostname TEXT NOT NULL, username TEXT NOT NULL, database TEXT NOT NULL, port INTEGER NOT NULL, ssl_mode TEXT NOT NULL DEFAULT 'prefer', created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP, updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP)",
+                "CREATE TABLE query_history (id TEXT PRIMARY KEY, connection_id TEXT NOT NULL, sql TEXT NOT NULL, execution_time_ms INTEGER NOT NULL, rows_affected INTEGER, success INTEGER NOT NULL, error_message TEXT, executed_at TIMESTAMP NOT NULL, FOREIGN KEY (connection_id) REFERENCES connections(id) ON DELETE CASCADE)",
+                "INSERT INTO connections (id, name, hostname, username, database, port, ssl_mode) VALUES ('7f1d8c1e-6d2a-4c55-9a4e-0c1f2b3a4d5e', 'local', 'localhost', 'postgres', 'app', 5432, 'require')",
+                "INSERT INTO query_history (id, connection_id, sql, execution_time_ms, success, executed_at) VALUES ('0b0f4f4e-2f8a-4a9b-8c1d-2e3f4a5b6c7d', '7f1d8c1e-6d2a-4c55-9a4e-0c1f2b3a4d5e', 'SELECT * FROM orders', 4, 1, '2024-01-02 03:04:05')",
+            ] {
+                sqlx::query(sql).execute(&pool).await.unwrap();
+            }
+            pool.close().await;
+
+            let store = AppStore::from_path(path).await.unwrap();
+            let (ssl_mode, read_only): (String, bool) =
+                sqlx::query_as("SELECT ssl_mode, read_only FROM connections")
+                    .fetch_one(&store.pool)
+                    .await
+                    .unwrap();
+            assert_eq!((ssl_mode.as_str(), read_only), ("require", false));
+
+            let matches: i64 = sqlx::query_scalar(
+                "SELECT COUNT(*) FROM query_history_fts WHERE query_history_fts MATCH 'orders'",
+            )
+            .fetch_one(&store.pool)
+            .await
+            .unwrap();
+            assert_eq!(matches, 1);
+        });
+    }
+}
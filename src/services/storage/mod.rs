@@ -1,11 +1,20 @@
 //! Unified SQLite storage for the application.
 
 mod connections;
+mod connections_file;
+mod editor_tabs;
 mod history;
+mod migrations;
+mod parameters;
+mod saved_queries;
 mod types;
 
 pub use connections::ConnectionsRepository;
+pub use connections_file::*;
+pub use editor_tabs::EditorTabsRepository;
 pub use history::QueryHistoryRepository;
+pub use parameters::QueryParametersRepository;
+pub use saved_queries::SavedQueriesRepository;
 pub use types::*;
 
 use anyhow::Result;
@@ -25,7 +34,7 @@ static STORE: OnceCell<AppStore> = OnceCell::new();
 
 impl AppStore {
     /// Get or initialize the global AppStore singleton.
-    /// Schema initialization and migration only run once.
+    /// Schema migrations only run once.
     pub async fn singleton() -> Result<&'static Self> {
         STORE.get_or_try_init(|| Self::init()).await
     }
@@ -48,10 +57,8 @@ impl AppStore {
             .connect_with(options)
             .await?;
 
-        let store = Self { pool };
-        store.initialize_schema().await?;
-        store.migrate_schema().await?;
-        Ok(store)
+        migrations::migrate(&pool).await?;
+        Ok(Self { pool })
     }
 
     fn get_db_path() -> Result<PathBuf> {
@@ -71,90 +78,18 @@ impl AppStore {
         QueryHistoryRepository::new(self.pool.clone())
     }
 
-    /// Initialize the database schema
-    async fn initialize_schema(&self) -> Result<()> {
-        sqlx::query(
-            r#"
-                CREATE TABLE IF NOT EXISTS connections (
-                    id TEXT PRIMARY KEY,
-                    name TEXT NOT NULL UNIQUE,
-                    hostname TEXT NOT NULL,
-                    username TEXT NOT NULL,
-                    database TEXT NOT NULL,
-                    port INTEGER NOT NULL,
-                    ssl_mode TEXT NOT NULL DEFAULT 'prefer',
-                    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
-                    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
-                )
-                "#,
-        )
-        .execute(&self.pool)
-        .await?;
-
-        // Create index on name for faster lookups
-        sqlx::query("CREATE INDEX IF NOT EXISTS idx_connections_name ON connections(name)")
-            .execute(&self.pool)
-            .await?;
-
-        // Query history table
-        sqlx::query(
-            r#"
-                CREATE TABLE IF NOT EXISTS query_history (
-                    id TEXT PRIMARY KEY,
-                    connection_id TEXT NOT NULL,
-                    sql TEXT NOT NULL,
-                    execution_time_ms INTEGER NOT NULL,
-                    rows_affected INTEGER,
-                    success INTEGER NOT NULL,
-                    error_message TEXT,
-                    executed_at TIMESTAMP NOT NULL,
-                    FOREIGN KEY (connection_id) REFERENCES connections(id) ON DELETE CASCADE
-                )
-                "#,
-        )
-        .execute(&self.pool)
-        .await?;
-
-        // Index for fast lookups by connection
-        sqlx::query(
-                "CREATE INDEX IF NOT EXISTS idx_history_connection ON query_history(connection_id, executed_at DESC)"
-            )
-            .execute(&self.pool)
-            .await?;
-
-        Ok(())
+    /// Get a repository for the editor tabs of each connection
+    pub fn editor_tabs(&self) -> EditorTabsRepository {
+        EditorTabsRepository::new(self.pool.clone())
     }
 
-    /// Migrate schema for existing databases
-    async fn migrate_schema(&self) -> Result<()> {
-        // Try to check if ssl_mode column exists by querying a single row
-        let has_ssl_mode = sqlx::query("SELECT ssl_mode FROM connections LIMIT 1")
-            .fetch_optional(&self.pool)
-            .await
-            .is_ok();
-
-        if !has_ssl_mode {
-            tracing::debug!("Migration: ssl_mode column not found, adding it...");
-
-            // Add ssl_mode column with default value
-            match sqlx::query(
-                "ALTER TABLE connections ADD COLUMN ssl_mode TEXT NOT NULL DEFAULT 'prefer'",
-            )
-            .execute(&self.pool)
-            .await
-            {
-                Ok(_) => {
-                    tracing::debug!("Migration: Successfully added ssl_mode column");
-                }
-                Err(e) => {
-                    // If column already exists, SQLite will error - that's okay
-                    tracing::warn!("Migration: Column may already exist: {}", e);
-                }
-            }
-        } else {
-            tracing::debug!("Migration: ssl_mode column already exists");
-        }
+    /// Get a repository for remembered query parameter values
+    pub fn parameters(&self) -> QueryParametersRepository {
+        QueryParametersRepository::new(self.pool.clone())
+    }
 
-        Ok(())
+    /// Get a repository for the saved queries library
+    pub fn saved_queries(&self) -> SavedQueriesRepository {
+        SavedQueriesRepository::new(self.pool.clone())
     }
 }
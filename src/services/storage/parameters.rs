@@ -0,0 +1,49 @@
+use anyhow::{Context, Result};
+use sqlx::SqlitePool;
+
+use crate::services::database::QueryParam;
+
+/// Repository for the last parameter values used with each query.
+#[derive(Debug, Clone)]
+pub struct QueryParametersRepository {
+    pool: SqlitePool,
+}
+
+impl QueryParametersRepository {
+    pub(crate) fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// Load the values last bound to this exact query text
+    pub async fn load(&self, sql: &str) -> Result<Option<Vec<QueryParam>>> {
+        let row =
+            sqlx::query_as::<_, (String,)>("SELECT params FROM query_parameters WHERE sql = ?")
+                .bind(sql)
+                .fetch_optional(&self.pool)
+                .await?;
+
+        row.map(|(params,)| {
+            serde_json::from_str(&params).context("Failed to parse saved query parameters")
+        })
+        .transpose()
+    }
+
+    /// Remember the values bound to a query, replacing earlier ones
+    pub async fn save(&self, sql: &str, params: &[QueryParam]) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO query_parameters (sql, params, updated_at)
+            VALUES (?, ?, datetime('now'))
+            ON CONFLICT(sql) DO UPDATE SET
+                params = excluded.params,
+                updated_at = excluded.updated_at
+            "#,
+        )
+        .bind(sql)
+        .bind(serde_json::to_string(params)?)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+}
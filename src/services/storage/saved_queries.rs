@@ -0,0 +1,92 @@
+use anyhow::{Context, Result};
+use sqlx::SqlitePool;
+use uuid::Uuid;
+
+use super::types::SavedQuery;
+
+/// Repository for the saved queries library.
+#[derive(Debug, Clone)]
+pub struct SavedQueriesRepository {
+    pool: SqlitePool,
+}
+
+impl SavedQueriesRepository {
+    pub(crate) fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// Load every saved query, ordered by name
+    pub async fn load_all(&self) -> Result<Vec<SavedQuery>> {
+        let rows = sqlx::query_as::<
+            _,
+            (
+                String,
+                Option<String>,
+                String,
+                Option<String>,
+                String,
+                String,
+            ),
+        >(
+            r#"
+            SELECT id, connection_id, name, description, tags, sql
+            FROM saved_queries
+            ORDER BY name COLLATE NOCASE
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.into_iter()
+            .map(|(id, connection_id, name, description, tags, sql)| {
+                Ok(SavedQuery {
+                    id: Uuid::parse_str(&id).context("Invalid UUID")?,
+                    connection_id: connection_id
+                        .map(|id| Uuid::parse_str(&id))
+                        .transpose()
+                        .context("Invalid connection UUID")?,
+                    name,
+                    description,
+                    tags: serde_json::from_str(&tags).unwrap_or_default(),
+                    sql,
+                })
+            })
+            .collect()
+    }
+
+    /// Insert a query or replace the one with the same id
+    pub async fn save(&self, query: &SavedQuery) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO saved_queries
+                (id, connection_id, name, description, tags, sql, created_at, updated_at)
+            VALUES (?, ?, ?, ?, ?, ?, datetime('now'), datetime('now'))
+            ON CONFLICT(id) DO UPDATE SET
+                connection_id = excluded.connection_id,
+                name = excluded.name,
+                description = excluded.description,
+                tags = excluded.tags,
+                sql = excluded.sql,
+                updated_at = excluded.updated_at
+            "#,
+        )
+        .bind(query.id.to_string())
+        .bind(query.connection_id.map(|id| id.to_string()))
+        .bind(&query.name)
+        .bind(&query.description)
+        .bind(serde_json::to_string(&query.tags)?)
+        .bind(&query.sql)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn delete(&self, id: &Uuid) -> Result<()> {
+        sqlx::query("DELETE FROM saved_queries WHERE id = ?")
+            .bind(id.to_string())
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,28 @@
+-- Store layout of releases before connections had an SSL mode
+
+CREATE TABLE connections (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL UNIQUE,
+    hostname TEXT NOT NULL,
+    username TEXT NOT NULL,
+    database TEXT NOT NULL,
+    port INTEGER NOT NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_connections_name ON connections(name);
+
+CREATE TABLE query_history (
+    id TEXT PRIMARY KEY,
+    connection_id TEXT NOT NULL,
+    sql TEXT NOT NULL,
+    execution_time_ms INTEGER NOT NULL,
+    rows_affected INTEGER,
+    success INTEGER NOT NULL,
+    error_message TEXT,
+    executed_at TIMESTAMP NOT NULL,
+    FOREIGN KEY (connection_id) REFERENCES connections(id) ON DELETE CASCADE
+);
+
+CREATE INDEX idx_history_connection ON query_history(connection_id, executed_at DESC);
@@ -2,14 +2,19 @@
 //!
 //! This module contains:
 //! - `SslMode` - SSL mode options for PostgreSQL connections
+//! - `Environment` and `ConnectionColor` - how a connection is tagged in the UI
 //! - `ConnectionInfo` - PostgreSQL connection configuration
 use chrono::{DateTime, Utc};
-use gpui::SharedString;
+use gpui::{Hsla, SharedString, rgb};
 use gpui_component::select::SelectItem;
 use serde::{Deserialize, Serialize};
 use sqlx::postgres::{PgConnectOptions, PgSslMode};
 use uuid::Uuid;
 
+use crate::services::database::{
+    ConnectionSettings, QueryExecutionResult, QueryTimeouts, ServerNotice, SshTunnelConfig,
+};
+
 /// SSL mode options for PostgreSQL connections
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub enum SslMode {
@@ -137,6 +142,126 @@ impl SslMode {
     }
 }
 
+/// The kind of server a connection points at
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Environment {
+    Local,
+    Dev,
+    Staging,
+    Prod,
+}
+
+impl Environment {
+    pub const ALL: [Environment; 4] = [
+        Environment::Local,
+        Environment::Dev,
+        Environment::Staging,
+        Environment::Prod,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Environment::Local => "Local",
+            Environment::Dev => "Dev",
+            Environment::Staging => "Staging",
+            Environment::Prod => "Prod",
+        }
+    }
+
+    /// Color of connections tagged with this environment and no color of
+    /// their own
+    pub fn default_color(&self) -> ConnectionColor {
+        match self {
+            Environment::Local => ConnectionColor::Green,
+            Environment::Dev => ConnectionColor::Blue,
+            Environment::Staging => ConnectionColor::Orange,
+            Environment::Prod => ConnectionColor::Red,
+        }
+    }
+
+    pub fn from_db_str(s: &str) -> Option<Self> {
+        match s {
+            "local" => Some(Environment::Local),
+            "dev" => Some(Environment::Dev),
+            "staging" => Some(Environment::Staging),
+            "prod" => Some(Environment::Prod),
+            _ => None,
+        }
+    }
+
+    pub fn to_db_str(&self) -> &'static str {
+        match self {
+            Environment::Local => "local",
+            Environment::Dev => "dev",
+            Environment::Staging => "staging",
+            Environment::Prod => "prod",
+        }
+    }
+}
+
+/// Palette a connection's color is picked from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConnectionColor {
+    Red,
+    Orange,
+    Yellow,
+    Green,
+    Teal,
+    Blue,
+    Purple,
+    Gray,
+}
+
+impl ConnectionColor {
+    pub const ALL: [ConnectionColor; 8] = [
+        ConnectionColor::Red,
+        ConnectionColor::Orange,
+        ConnectionColor::Yellow,
+        ConnectionColor::Green,
+        ConnectionColor::Teal,
+        ConnectionColor::Blue,
+        ConnectionColor::Purple,
+        ConnectionColor::Gray,
+    ];
+
+    /// The color as `0xRRGGBB`, readable on light and dark themes
+    pub fn rgb(&self) -> u32 {
+        match self {
+            ConnectionColor::Red => 0xdc2626,
+            ConnectionColor::Orange => 0xea580c,
+            ConnectionColor::Yellow => 0xca8a04,
+            ConnectionColor::Green => 0x16a34a,
+            ConnectionColor::Teal => 0x0d9488,
+            ConnectionColor::Blue => 0x2563eb,
+            ConnectionColor::Purple => 0x9333ea,
+            ConnectionColor::Gray => 0x6b7280,
+        }
+    }
+
+    pub fn hsla(&self) -> Hsla {
+        rgb(self.rgb()).into()
+    }
+
+    pub fn from_db_str(s: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|color| color.to_db_str() == s)
+    }
+
+    pub fn to_db_str(&self) -> &'static str {
+        match self {
+            ConnectionColor::Red => "red",
+            ConnectionColor::Orange => "orange",
+            ConnectionColor::Yellow => "yellow",
+            ConnectionColor::Green => "green",
+            ConnectionColor::Teal => "teal",
+            ConnectionColor::Blue => "blue",
+            ConnectionColor::Purple => "purple",
+            ConnectionColor::Gray => "gray",
+        }
+    }
+}
+
 /// PostgreSQL connection configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ConnectionInfo {
@@ -151,6 +276,35 @@ pub struct ConnectionInfo {
     pub port: usize,
     #[serde(default)]
     pub ssl_mode: SslMode,
+    /// CA certificate file the server certificate is verified against
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ssl_root_cert: Option<String>,
+    /// Client certificate file sent to the server
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ssl_client_cert: Option<String>,
+    /// Private key file of the client certificate
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ssl_client_key: Option<String>,
+    /// Jump host the server is reached through
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ssh_tunnel: Option<SshTunnelConfig>,
+    /// Limits every statement on this connection starts with
+    #[serde(default)]
+    pub timeouts: QueryTimeouts,
+    /// Open every session with `default_transaction_read_only = on` and
+    /// refuse modifying statements
+    #[serde(default)]
+    pub read_only: bool,
+    /// Pool size and session setup
+    #[serde(default, skip_serializing_if = "ConnectionSettings::is_empty")]
+    pub settings: ConnectionSettings,
+    /// Folder the connection is listed under
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub group: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<ConnectionColor>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub environment: Option<Environment>,
 }
 
 impl ConnectionInfo {
@@ -173,18 +327,57 @@ impl ConnectionInfo {
             database,
             port,
             ssl_mode,
+            ssl_root_cert: None,
+            ssl_client_cert: None,
+            ssl_client_key: None,
+            ssh_tunnel: None,
+            timeouts: QueryTimeouts::default(),
+            read_only: false,
+            settings: ConnectionSettings::default(),
+            group: None,
+            color: None,
+            environment: None,
         }
     }
 
+    /// The color marking this connection's tabs: its own, or else its
+    /// environment's
+    pub fn stripe_color(&self) -> Option<ConnectionColor> {
+        self.color
+            .or_else(|| self.environment.map(|env| env.default_color()))
+    }
+
     /// Create connection options for sqlx without exposing password
     pub fn to_pg_connect_options(&self) -> PgConnectOptions {
-        PgConnectOptions::new()
+        let mut options = PgConnectOptions::new()
             .host(&self.hostname)
             .port(self.port as u16)
             .username(&self.username)
             .password(&self.password)
             .database(&self.database)
             .ssl_mode(self.ssl_mode.to_pg_ssl_mode())
+            .options(self.timeouts.settings());
+        options = self.settings.connect_options(options);
+
+        // Certificates only matter once SSL is on; sqlx reads the files when
+        // it connects
+        if self.ssl_mode != SslMode::Disable {
+            if let Some(path) = &self.ssl_root_cert {
+                options = options.ssl_root_cert(path);
+            }
+            if let Some(path) = &self.ssl_client_cert {
+                options = options.ssl_client_cert(path);
+            }
+            if let Some(path) = &self.ssl_client_key {
+                options = options.ssl_client_key(path);
+            }
+        }
+
+        if self.read_only {
+            options.options([("default_transaction_read_only", "on")])
+        } else {
+            options
+        }
     }
 }
 
@@ -199,6 +392,16 @@ impl Default for ConnectionInfo {
             database: "test".to_string(),
             port: 5432,
             ssl_mode: SslMode::default(),
+            ssl_root_cert: None,
+            ssl_client_cert: None,
+            ssl_client_key: None,
+            ssh_tunnel: None,
+            timeouts: QueryTimeouts::default(),
+            read_only: false,
+            settings: ConnectionSettings::default(),
+            group: None,
+            color: None,
+            environment: None,
         }
     }
 }
@@ -213,6 +416,70 @@ impl Drop for ConnectionInfo {
     }
 }
 
+/// Error message recorded in history for queries cancelled by the user
+pub const CANCELLED_QUERY_MESSAGE: &str = "Query cancelled by user";
+
+/// A finished query run to add to the history
+#[derive(Debug, Clone, Default)]
+pub struct NewHistoryEntry {
+    pub sql: String,
+    pub execution_time_ms: i64,
+    pub rows_affected: Option<i64>,
+    /// Rows a SELECT returned
+    pub rows_returned: Option<i64>,
+    pub success: bool,
+    pub error_message: Option<String>,
+    /// SQLSTATE of a server error, e.g. `42P01`
+    pub sql_state: Option<String>,
+    /// Database the statement ran against
+    pub database: Option<String>,
+    pub notices: Vec<ServerNotice>,
+}
+
+impl NewHistoryEntry {
+    /// The history entry for a statement that finished with `result`
+    pub fn from_result(
+        sql: &str,
+        result: &QueryExecutionResult,
+        database: Option<String>,
+        notices: &[ServerNotice],
+    ) -> Self {
+        let mut entry = NewHistoryEntry {
+            sql: sql.to_string(),
+            success: true,
+            database,
+            notices: notices.to_vec(),
+            ..Default::default()
+        };
+        match result {
+            QueryExecutionResult::Select(select) => {
+                entry.execution_time_ms = select.execution_time_ms as i64;
+                entry.rows_returned = Some(select.row_count as i64);
+            }
+            QueryExecutionResult::Modified(modified) => {
+                entry.execution_time_ms = modified.execution_time_ms as i64;
+                entry.rows_affected = Some(modified.rows_affected as i64);
+            }
+            QueryExecutionResult::Error(error) => {
+                entry.execution_time_ms = error.execution_time_ms as i64;
+                entry.success = false;
+                entry.error_message = Some(error.message.clone());
+                entry.sql_state = error
+                    .database_error
+                    .as_ref()
+                    .map(|details| details.code.clone())
+                    .filter(|code| !code.is_empty());
+            }
+            QueryExecutionResult::Cancelled(cancelled) => {
+                entry.execution_time_ms = cancelled.execution_time_ms as i64;
+                entry.success = false;
+                entry.error_message = Some(CANCELLED_QUERY_MESSAGE.to_string());
+            }
+        }
+        entry
+    }
+}
+
 /// Query history entry
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct QueryHistoryEntry {
@@ -221,7 +488,120 @@ pub struct QueryHistoryEntry {
     pub sql: String,
     pub execution_time_ms: i64,
     pub rows_affected: Option<i64>,
+    /// Rows a SELECT returned
+    #[serde(default)]
+    pub rows_returned: Option<i64>,
     pub success: bool,
     pub error_message: Option<String>,
+    /// SQLSTATE of a server error, e.g. `42P01`
+    #[serde(default)]
+    pub sql_state: Option<String>,
+    /// Database the statement ran against
+    #[serde(default)]
+    pub database: Option<String>,
+    /// Notices and warnings the server sent while the query ran
+    #[serde(default)]
+    pub notices: Vec<ServerNotice>,
     pub executed_at: DateTime<Utc>,
 }
+
+impl QueryHistoryEntry {
+    /// Whether this run was stopped by the user rather than failing
+    pub fn is_cancelled(&self) -> bool {
+        !self.success && self.error_message.as_deref() == Some(CANCELLED_QUERY_MESSAGE)
+    }
+}
+
+/// Conditions for searching the query history; unset fields don't filter
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HistoryFilter {
+    pub connection_id: Option<Uuid>,
+    /// Words the SQL must contain, matched as prefixes by full-text search
+    pub text: String,
+    /// Only successful runs (`true`) or only failed ones (`false`)
+    pub success: Option<bool>,
+    pub executed_after: Option<DateTime<Utc>>,
+    pub executed_before: Option<DateTime<Utc>>,
+    /// Only runs that took at least this long
+    pub min_execution_time_ms: Option<i64>,
+}
+
+/// One page of history search results
+#[derive(Debug, Clone, Default)]
+pub struct HistoryPage {
+    pub entries: Vec<QueryHistoryEntry>,
+    /// Entries matching the filter across all pages
+    pub total: i64,
+}
+
+/// A named SQL buffer kept for a connection across restarts
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EditorTab {
+    pub id: Uuid,
+    pub connection_id: Uuid,
+    pub name: String,
+    /// Order among the connection's tabs
+    pub position: i64,
+    pub content: String,
+    /// Byte offset of the cursor in `content`
+    pub cursor: i64,
+    /// Outcome of the last run, e.g. "42 rows in 12 ms"
+    pub last_result: Option<String>,
+}
+
+impl EditorTab {
+    pub fn new(connection_id: Uuid, name: impl Into<String>, position: i64) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            connection_id,
+            name: name.into(),
+            position,
+            content: String::new(),
+            cursor: 0,
+            last_result: None,
+        }
+    }
+}
+
+/// A named query kept in the saved queries library
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SavedQuery {
+    pub id: Uuid,
+    /// Connection the query belongs to; `None` shows it for every connection
+    pub connection_id: Option<Uuid>,
+    pub name: String,
+    pub description: Option<String>,
+    pub tags: Vec<String>,
+    pub sql: String,
+}
+
+impl SavedQuery {
+    pub fn new(name: impl Into<String>, sql: impl Into<String>) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            connection_id: None,
+            name: name.into(),
+            description: None,
+            tags: vec![],
+            sql: sql.into(),
+        }
+    }
+
+    /// Whether the query is offered for this connection
+    pub fn is_visible_for(&self, connection_id: &Uuid) -> bool {
+        self.connection_id.is_none_or(|id| &id == connection_id)
+    }
+
+    /// Case-insensitive match on name, description, tags or SQL;
+    /// `search` must already be lowercase
+    pub fn matches(&self, search: &str) -> bool {
+        search.is_empty()
+            || self.name.to_lowercase().contains(search)
+            || self
+                .description
+                .as_ref()
+                .is_some_and(|d| d.to_lowercase().contains(search))
+            || self.tags.iter().any(|t| t.to_lowercase().contains(search))
+            || self.sql.to_lowercase().contains(search)
+    }
+}
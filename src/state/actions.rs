@@ -6,59 +6,154 @@
 use std::time::Duration;
 
 use gpui::*;
+use uuid::Uuid;
 
-use crate::services::{AppStore, ConnectionInfo, ConnectionsRepository, DatabaseManager};
+use crate::services::storage::{ConnectionsFile, ConnectionsImport, NameConflict, SavedQuery};
+use crate::services::{
+    AppStore, ConnectionError, ConnectionInfo, ConnectionsRepository, DatabaseManager,
+    TransactionStatus, reconnect_delay,
+};
 
-use super::connection::{ConnectionState, ConnectionStatus};
-use super::database::DatabaseState;
-use super::editor::EditorState;
+use super::connection::{ConnectionState, ConnectionStatus, SessionId};
+use super::saved_queries::SavedQueriesState;
 
 // =============================================================================
 // Connection Lifecycle
 // =============================================================================
 
-/// Initiates a connection to the database.
-/// Updates ConnectionState, EditorState, and DatabaseState on success.
-pub fn connect(connection_info: &ConnectionInfo, cx: &mut App) {
-    cx.update_global::<ConnectionState, _>(|state, _cx| {
-        state.connection_state = ConnectionStatus::Connecting;
+/// Opens a connection in a new tab and brings it to the front.
+/// The session stays in the Connecting state until the catalog is loaded.
+/// The task resolves then, or with why connecting failed, after the tab
+/// was closed; the caller explains the error.
+pub fn connect(
+    connection_info: &ConnectionInfo,
+    cx: &mut App,
+) -> Task<Result<SessionId, ConnectionError>> {
+    let cic = connection_info.clone();
+    let (session, db_manager) = cx.update_global::<ConnectionState, _>(|state, _cx| {
+        let session = state.open_session(cic.clone());
+        state.active_session = Some(session);
+        let db_manager = state.session(session).map(|s| s.db_manager.clone());
+        (session, db_manager)
     });
 
-    let cic = connection_info.clone();
-    let db_manager = cx.global::<ConnectionState>().db_manager.clone();
+    cx.spawn(async move |cx| {
+        let Some(db_manager) = db_manager else {
+            return Err(ConnectionError::Other("The session was closed".to_string()));
+        };
+        connect_async(session, cic, db_manager, cx).await?;
+        Ok(session)
+    })
+}
 
-    cx.spawn(async move |cx| connect_async(cic, db_manager, cx).await)
+/// Disconnects a session and closes its tab.
+pub fn disconnect(session: SessionId, cx: &mut App) {
+    let Some(db_manager) = session_manager(session, cx) else {
+        return;
+    };
+    cx.spawn(async move |cx| disconnect_async(session, db_manager, cx).await)
         .detach();
 }
 
-/// Disconnects from the current database.
-/// Updates ConnectionState and LLMState.
-pub fn disconnect(cx: &mut App) {
-    let db_manager = cx.global::<ConnectionState>().db_manager.clone();
-    cx.spawn(async move |cx| disconnect_async(db_manager, cx).await)
-        .detach();
+/// Brings a tab to the front; `None` shows the connection manager.
+pub fn activate_session(session: Option<SessionId>, cx: &mut App) {
+    cx.update_global::<ConnectionState, _>(|state, _cx| {
+        state.active_session = session;
+    });
 }
 
-/// Changes to a different database on the same server.
-/// Disconnects from current database and reconnects to the new one.
-pub fn change_database(database_name: String, cx: &mut App) {
-    let current_connection = cx.global::<ConnectionState>().active_connection.clone();
+/// Changes a session to a different database on the same server.
+/// Disconnects from current database and reconnects to the new one; the
+/// task resolves with why that failed, if it did.
+pub fn change_database(
+    session: SessionId,
+    database_name: String,
+    cx: &mut App,
+) -> Task<Result<(), ConnectionError>> {
+    let new_connection = cx.update_global::<ConnectionState, _>(|state, _cx| {
+        let session = state.session_mut(session)?;
+        session.connection.database = database_name;
+        session.status = ConnectionStatus::Connecting;
+        session.transaction_status = TransactionStatus::Idle;
+        session.tables.clear();
+        session.schema = None;
+        Some((session.connection.clone(), session.db_manager.clone()))
+    });
 
-    if let Some(mut new_connection) = current_connection {
-        new_connection.database = database_name;
+    let Some((new_connection, db_manager)) = new_connection else {
+        return Task::ready(Ok(()));
+    };
+    cx.spawn(async move |cx| {
+        if let Err(e) = db_manager.disconnect().await {
+            tracing::warn!("Failed to disconnect before changing database: {}", e);
+        }
+        // Wait a brief moment for cleanup
+        cx.background_executor()
+            .timer(Duration::from_millis(100))
+            .await;
+        // Connect to the new database
+        connect_async(session, new_connection, db_manager, cx).await
+    })
+}
 
-        let db_manager = cx.global::<ConnectionState>().db_manager.clone();
-        cx.spawn(async move |cx| {
-            disconnect_async(db_manager.clone(), cx).await;
-            // Wait a brief moment for cleanup
-            cx.background_executor()
-                .timer(Duration::from_millis(100))
-                .await;
-            // Connect to the new database
-            connect_async(new_connection, db_manager, cx).await;
-        })
-        .detach();
-    }
+// =============================================================================
+// Transaction Control
+// =============================================================================
+
+/// Starts an explicit transaction on a pinned connection.
+pub fn begin_transaction(session: SessionId, cx: &mut App) {
+    let Some(db_manager) = session_manager(session, cx) else {
+        return;
+    };
+    cx.spawn(async move |cx| {
+        if let Err(e) = db_manager.begin_transaction().await {
+            tracing::error!("Failed to begin transaction: {}", e);
+        }
+        refresh_transaction_status(session, &db_manager, cx).await;
+    })
+    .detach();
+}
+
+/// Commits the open transaction.
+pub fn commit_transaction(session: SessionId, cx: &mut App) {
+    let Some(db_manager) = session_manager(session, cx) else {
+        return;
+    };
+    cx.spawn(async move |cx| {
+        if let Err(e) = db_manager.commit_transaction().await {
+            tracing::error!("Failed to commit transaction: {}", e);
+        }
+        refresh_transaction_status(session, &db_manager, cx).await;
+    })
+    .detach();
+}
+
+/// Rolls back the open transaction.
+pub fn rollback_transaction(session: SessionId, cx: &mut App) {
+    let Some(db_manager) = session_manager(session, cx) else {
+        return;
+    };
+    cx.spawn(async move |cx| {
+        if let Err(e) = db_manager.rollback_transaction().await {
+            tracing::error!("Failed to roll back transaction: {}", e);
+        }
+        refresh_transaction_status(session, &db_manager, cx).await;
+    })
+    .detach();
+}
+
+/// Copies the manager's transaction status into the session.
+pub async fn refresh_transaction_status(
+    session: SessionId,
+    db_manager: &DatabaseManager,
+    cx: &mut AsyncApp,
+) {
+    let status = db_manager.transaction_status().await;
+    let _ = cx.update_global::<ConnectionState, _>(|state, _cx| {
+        if let Some(session) = state.session_mut(session) {
+            session.transaction_status = status;
+        }
+    });
 }
 
 // =============================================================================
@@ -73,7 +168,6 @@ pub fn add_connection(connection: ConnectionInfo, cx: &mut App) {
                 if let Ok(connections) = store.connections().load_all().await {
                     let _ = cx.update_global::<ConnectionState, _>(|app_state, _cx| {
                         app_state.saved_connections = connections;
-                        app_state.active_connection = None;
                     });
                 }
             }
@@ -83,6 +177,8 @@ pub fn add_connection(connection: ConnectionInfo, cx: &mut App) {
 }
 
 /// Updates an existing connection in the saved connections store.
+/// Open tabs of the connection show its new name and tags right away; the
+/// other settings apply when they next connect.
 pub fn update_connection(connection: ConnectionInfo, cx: &mut App) {
     cx.spawn(async move |cx| {
         if let Ok(store) = AppStore::singleton().await {
@@ -90,7 +186,14 @@ pub fn update_connection(connection: ConnectionInfo, cx: &mut App) {
                 if let Ok(connections) = store.connections().load_all().await {
                     let _ = cx.update_global::<ConnectionState, _>(|app_state, _cx| {
                         app_state.saved_connections = connections;
-                        app_state.active_connection = Some(connection);
+                        for session in &mut app_state.sessions {
+                            if session.connection.id == connection.id {
+                                session.connection.name = connection.name.clone();
+                                session.connection.group = connection.group.clone();
+                                session.connection.color = connection.color;
+                                session.connection.environment = connection.environment;
+                            }
+                        }
                     });
                 }
             }
@@ -99,6 +202,62 @@ pub fn update_connection(connection: ConnectionInfo, cx: &mut App) {
     .detach();
 }
 
+/// Adds imported connections, storing their passwords in the keyring.
+/// Names must already be unique; a connection that fails is skipped. The
+/// task resolves to how many were added and why the others failed.
+pub fn import_connections(
+    connections: Vec<ConnectionInfo>,
+    cx: &mut App,
+) -> Task<anyhow::Result<(usize, Vec<String>)>> {
+    cx.spawn(async move |cx| {
+        let store = AppStore::singleton().await?;
+        let mut added = 0;
+        let mut failures = vec![];
+        for connection in &connections {
+            match store.connections().create(connection).await {
+                Ok(_) => added += 1,
+                Err(e) => {
+                    tracing::error!("Failed to import connection {}: {}", connection.name, e);
+                    failures.push(format!("{}: {}", connection.name, e));
+                }
+            }
+        }
+        if let Ok(connections) = store.connections().load_all().await {
+            let _ = cx.update_global::<ConnectionState, _>(|app_state, _cx| {
+                app_state.saved_connections = connections;
+            });
+        }
+        Ok((added, failures))
+    })
+}
+
+/// Adds the connections of a shared file, resolving name conflicts with
+/// `on_conflict`. The task resolves to what was imported, for the caller
+/// to report.
+pub fn import_connections_file(
+    file: ConnectionsFile,
+    passphrase: Option<String>,
+    on_conflict: NameConflict,
+    cx: &mut App,
+) -> Task<anyhow::Result<ConnectionsImport>> {
+    cx.spawn(async move |cx| {
+        let store = AppStore::singleton().await?;
+        let result = store
+            .connections()
+            .import(&file, passphrase.as_deref(), on_conflict)
+            .await;
+        if let Err(e) = &result {
+            tracing::error!("Failed to import connections file: {}", e);
+        }
+        if let Ok(connections) = store.connections().load_all().await {
+            let _ = cx.update_global::<ConnectionState, _>(|app_state, _cx| {
+                app_state.saved_connections = connections;
+            });
+        }
+        result
+    })
+}
+
 /// Deletes a connection from the saved connections store.
 pub fn delete_connection(connection: ConnectionInfo, cx: &mut App) {
     let conn = connection.clone();
@@ -110,6 +269,40 @@ pub fn delete_connection(connection: ConnectionInfo, cx: &mut App) {
                         app_state.saved_connections = connections;
                     });
                 }
+                // Queries scoped to the connection are now shared
+                reload_saved_queries(store, cx).await;
+            }
+        }
+    })
+    .detach();
+}
+
+// =============================================================================
+// Saved Queries
+// =============================================================================
+
+/// Adds queries to the library, replacing those with the same id.
+pub fn save_queries(queries: Vec<SavedQuery>, cx: &mut App) {
+    cx.spawn(async move |cx| {
+        if let Ok(store) = AppStore::singleton().await {
+            for query in &queries {
+                if let Err(e) = store.saved_queries().save(query).await {
+                    tracing::error!("Failed to save query {}: {}", query.name, e);
+                }
+            }
+            reload_saved_queries(store, cx).await;
+        }
+    })
+    .detach();
+}
+
+/// Removes a query from the library.
+pub fn delete_saved_query(id: Uuid, cx: &mut App) {
+    cx.spawn(async move |cx| {
+        if let Ok(store) = AppStore::singleton().await {
+            match store.saved_queries().delete(&id).await {
+                Ok(()) => reload_saved_queries(store, cx).await,
+                Err(e) => tracing::error!("Failed to delete saved query: {}", e),
             }
         }
     })
@@ -120,88 +313,212 @@ pub fn delete_connection(connection: ConnectionInfo, cx: &mut App) {
 // Private Async Helpers
 // =============================================================================
 
-async fn connect_async(mut cic: ConnectionInfo, db_manager: DatabaseManager, cx: &mut AsyncApp) {
-    // Load password from keychain on-demand
-    if let Ok(password) = ConnectionsRepository::get_connection_password(&cic.id) {
-        cic.password = password;
-    } else {
+async fn reload_saved_queries(store: &AppStore, cx: &mut AsyncApp) {
+    match store.saved_queries().load_all().await {
+        Ok(queries) => {
+            let _ = cx.update_global::<SavedQueriesState, _>(|state, _cx| {
+                state.queries = queries;
+            });
+        }
+        Err(e) => tracing::error!("Failed to load saved queries: {}", e),
+    }
+}
+
+fn session_manager(session: SessionId, cx: &App) -> Option<DatabaseManager> {
+    cx.global::<ConnectionState>()
+        .session(session)
+        .map(|session| session.db_manager.clone())
+}
+
+/// Connects a session's manager, closing the tab if that fails, then keeps
+/// watching the connection in the background
+async fn connect_async(
+    session: SessionId,
+    mut cic: ConnectionInfo,
+    db_manager: DatabaseManager,
+    cx: &mut AsyncApp,
+) -> Result<(), ConnectionError> {
+    if let Err(error) = establish_session(session, &mut cic, &db_manager, cx).await {
+        tracing::error!("Failed to connect to {}: {}", cic.name, error);
         let _ = cx.update_global::<ConnectionState, _>(|state, _cx| {
-            state.connection_state = ConnectionStatus::Disconnected;
+            state.close_session(session);
         });
-        return;
+        return Err(error);
+    }
+
+    cx.spawn(async move |cx| monitor_session(session, cic, db_manager, cx).await)
+        .detach();
+    Ok(())
+}
+
+/// Opens the pool with the connection's secrets, then loads the catalog
+async fn establish_session(
+    session: SessionId,
+    cic: &mut ConnectionInfo,
+    db_manager: &DatabaseManager,
+    cx: &mut AsyncApp,
+) -> Result<(), ConnectionError> {
+    // Load password from keychain on-demand, unless one was just entered.
+    // Without a saved one, connect without a password as trust, peer and
+    // pgpass logins do.
+    let mut password_missing = false;
+    if cic.password.is_empty() {
+        match ConnectionsRepository::get_connection_password(&cic.id) {
+            Ok(password) => cic.password = password,
+            Err(e) => match e.downcast_ref::<keyring::Error>() {
+                Some(keyring::Error::NoEntry) => password_missing = true,
+                _ => return Err(ConnectionError::Keyring(format!("{:#}", e))),
+            },
+        }
+    }
+    // Agent logins and unencrypted keys have no SSH secret
+    if let Some(tunnel) = cic.ssh_tunnel.as_mut() {
+        tunnel.secret = ConnectionsRepository::get_ssh_secret(&cic.id).unwrap_or_default();
     }
 
     // Use secure connection options instead of string
     let connect_options = cic.to_pg_connect_options();
+    db_manager.set_read_only(cic.read_only);
 
-    if let Ok(_) = db_manager.connect_with_options(connect_options).await {
-        if let Ok(tables) = db_manager.get_tables().await {
-            let _ = cx.update_global::<EditorState, _>(|state, _cx| {
-                state.tables = tables;
-            });
-        }
+    db_manager
+        .connect_with_options(connect_options, cic.ssh_tunnel.clone(), &cic.settings)
+        .await
+        .map_err(|error| match error {
+            // The server asked for a password after all
+            ConnectionError::Authentication(_) if password_missing => {
+                ConnectionError::MissingPassword
+            }
+            error => error,
+        })?;
 
-        if let Ok(schema) = db_manager.get_schema(None).await {
-            let _ = cx.update_global::<EditorState, _>(|state, _cx| {
-                state.schema = Some(schema);
-            });
+    let tables = db_manager.get_tables().await.unwrap_or_default();
+    let schema = db_manager.get_schema(None).await.ok();
+    let databases = db_manager.get_databases().await.unwrap_or_default();
+
+    let _ = cx.update_global::<ConnectionState, _>(|state, _cx| {
+        if let Some(session) = state.session_mut(session) {
+            session.connection = cic.clone();
+            session.tables = tables;
+            session.schema = schema;
+            session.databases = databases;
+            session.status = ConnectionStatus::Connected;
         }
+    });
+    Ok(())
+}
 
-        if let Ok(databases) = db_manager.get_databases().await {
-            let _ = cx.update_global::<DatabaseState, _>(|state, _cx| {
-                state.databases = databases;
-            });
+/// Connection monitoring loop; it ends once the session is closed or
+/// switched to another database. A dropped connection is retried.
+async fn monitor_session(
+    session: SessionId,
+    cic: ConnectionInfo,
+    db_manager: DatabaseManager,
+    cx: &mut AsyncApp,
+) {
+    loop {
+        let connected = db_manager.is_connected().await;
+        let monitored = cx
+            .try_read_global::<ConnectionState, _>(|state, _cx| {
+                state
+                    .session(session)
+                    .is_some_and(|session| session.status == ConnectionStatus::Connected)
+            })
+            .unwrap_or(false);
+
+        if !monitored {
+            break;
         }
+        if !connected && !reconnect_async(session, &cic, &db_manager, cx).await {
+            break;
+        }
+
+        cx.background_executor()
+            .timer(Duration::from_millis(1000))
+            .await;
+    }
+}
+
+/// Retries a dropped session with exponential backoff until it is back,
+/// or until the tab is closed or switched to another database. Returns
+/// whether the session is connected again.
+async fn reconnect_async(
+    session: SessionId,
+    cic: &ConnectionInfo,
+    db_manager: &DatabaseManager,
+    cx: &mut AsyncApp,
+) -> bool {
+    let is_reconnecting = |cx: &mut AsyncApp| {
+        cx.try_read_global::<ConnectionState, _>(|state, _cx| {
+            state.session(session).is_some_and(|session| {
+                matches!(session.status, ConnectionStatus::Reconnecting { .. })
+            })
+        })
+        .unwrap_or(false)
+    };
 
+    tracing::warn!("Lost the connection to {}, reconnecting", cic.name);
+    let mut error = "The connection to the server was lost".to_string();
+    for attempt in 1.. {
         let _ = cx.update_global::<ConnectionState, _>(|state, _cx| {
-            state.active_connection = Some(cic);
-            state.connection_state = ConnectionStatus::Connected;
+            if let Some(session) = state.session_mut(session) {
+                session.status = ConnectionStatus::Reconnecting {
+                    attempt,
+                    error: error.clone(),
+                };
+                session.transaction_status = TransactionStatus::Idle;
+            }
         });
 
-        // Connection monitoring loop
-        loop {
-            let mut connected = db_manager.is_connected().await;
-            if !connected {
+        cx.background_executor()
+            .timer(reconnect_delay(attempt))
+            .await;
+        if !is_reconnecting(cx) {
+            return false;
+        }
+
+        let result = db_manager
+            .reconnect_with_options(
+                cic.to_pg_connect_options(),
+                cic.ssh_tunnel.clone(),
+                &cic.settings,
+            )
+            .await;
+        match result {
+            Ok(()) => {
+                tracing::info!("Reconnected to {} after {} attempts", cic.name, attempt);
+                let reconnected = is_reconnecting(cx);
                 let _ = cx.update_global::<ConnectionState, _>(|state, _cx| {
-                    state.active_connection = None;
-                    state.connection_state = ConnectionStatus::Disconnected;
+                    if reconnected && let Some(session) = state.session_mut(session) {
+                        session.status = ConnectionStatus::Connected;
+                    }
                 });
-                break;
+                return reconnected;
             }
-
-            let _ = cx.try_read_global::<ConnectionState, _>(|state, _cx| {
-                if state.active_connection.is_none() {
-                    connected = false;
-                }
-            });
-
-            if !connected {
-                break;
+            Err(e) => {
+                tracing::warn!(
+                    "Reconnect attempt {} to {} failed: {}",
+                    attempt,
+                    cic.name,
+                    e
+                );
+                error = e.to_string();
             }
-
-            cx.background_executor()
-                .timer(Duration::from_millis(1000))
-                .await;
         }
-    } else {
-        tracing::warn!("No Connect :(");
-        let _ = cx.update_global::<ConnectionState, _>(|state, _cx| {
-            state.active_connection = None;
-            state.connection_state = ConnectionStatus::Disconnected;
-        });
     }
+    false
 }
 
-async fn disconnect_async(db_manager: DatabaseManager, cx: &mut AsyncApp) {
+async fn disconnect_async(session: SessionId, db_manager: DatabaseManager, cx: &mut AsyncApp) {
     let _ = cx.update_global::<ConnectionState, _>(|state, _cx| {
-        state.active_connection = None;
-        state.connection_state = ConnectionStatus::Disconnecting;
+        if let Some(session) = state.session_mut(session) {
+            session.status = ConnectionStatus::Disconnecting;
+        }
     });
 
-    if let Ok(_) = db_manager.disconnect().await {
-        let _ = cx.update_global::<ConnectionState, _>(|state, _cx| {
-            state.active_connection = None;
-            state.connection_state = ConnectionStatus::Disconnected;
-        });
+    if let Err(e) = db_manager.disconnect().await {
+        tracing::warn!("Failed to disconnect: {}", e);
     }
+    let _ = cx.update_global::<ConnectionState, _>(|state, _cx| {
+        state.close_session(session);
+    });
 }
@@ -1,6 +1,9 @@
 use gpui::*;
 
-use crate::services::{AppStore, ConnectionInfo, DatabaseManager};
+use crate::services::{
+    AppStore, ConnectionInfo, DatabaseInfo, DatabaseManager, DatabaseSchema, TableInfo,
+    TransactionStatus,
+};
 
 #[derive(Clone, PartialEq)]
 pub enum ConnectionStatus {
@@ -8,25 +11,116 @@ pub enum ConnectionStatus {
     Disconnecting,
     Connecting,
     Connected,
+    /// The connection dropped; waiting before reconnect `attempt`, which
+    /// counts from 1. `error` is why the session was lost or the last
+    /// attempt failed.
+    Reconnecting {
+        attempt: u32,
+        error: String,
+    },
+}
+
+impl ConnectionStatus {
+    /// Connected, or reconnecting with the tab's tables and editors kept
+    pub fn is_open(&self) -> bool {
+        matches!(self, Self::Connected | Self::Reconnecting { .. })
+    }
+}
+
+/// Identifies an open connection; each one is shown in its own tab
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SessionId(u64);
+
+/// An open connection with its own database manager and catalog
+pub struct ConnectionSession {
+    pub id: SessionId,
+    pub connection: ConnectionInfo,
+    pub db_manager: DatabaseManager,
+    pub status: ConnectionStatus,
+    pub transaction_status: TransactionStatus,
+    /// Tables for autocomplete and the tables tree
+    pub tables: Vec<TableInfo>,
+    pub schema: Option<DatabaseSchema>,
+    /// Databases available on the server
+    pub databases: Vec<DatabaseInfo>,
+}
+
+impl ConnectionSession {
+    /// Whether an explicit transaction is holding a pinned connection
+    pub fn has_open_transaction(&self) -> bool {
+        self.transaction_status != TransactionStatus::Idle
+    }
+
+    /// Tab title: connection name and database
+    pub fn title(&self) -> String {
+        format!("{} · {}", self.connection.name, self.connection.database)
+    }
 }
 
 pub struct ConnectionState {
     pub saved_connections: Vec<ConnectionInfo>,
-    pub active_connection: Option<ConnectionInfo>,
-    pub db_manager: DatabaseManager,
-    pub connection_state: ConnectionStatus,
+    /// Open connections in tab order
+    pub sessions: Vec<ConnectionSession>,
+    /// The tab in front; `None` shows the connection manager
+    pub active_session: Option<SessionId>,
+    next_session_id: u64,
 }
 
 impl Global for ConnectionState {}
 
+impl ConnectionState {
+    pub fn session(&self, id: SessionId) -> Option<&ConnectionSession> {
+        self.sessions.iter().find(|session| session.id == id)
+    }
+
+    pub fn session_mut(&mut self, id: SessionId) -> Option<&mut ConnectionSession> {
+        self.sessions.iter_mut().find(|session| session.id == id)
+    }
+
+    /// The session of the tab in front
+    pub fn active(&self) -> Option<&ConnectionSession> {
+        self.session(self.active_session?)
+    }
+
+    /// Adds a session for `connection` in the Connecting state
+    pub(crate) fn open_session(&mut self, connection: ConnectionInfo) -> SessionId {
+        let id = SessionId(self.next_session_id);
+        self.next_session_id += 1;
+        self.sessions.push(ConnectionSession {
+            id,
+            connection,
+            db_manager: DatabaseManager::new(),
+            status: ConnectionStatus::Connecting,
+            transaction_status: TransactionStatus::Idle,
+            tables: vec![],
+            schema: None,
+            databases: vec![],
+        });
+        id
+    }
+
+    /// Removes a session, bringing its neighbour to the front if it was active
+    pub(crate) fn close_session(&mut self, id: SessionId) {
+        let Some(ix) = self.sessions.iter().position(|session| session.id == id) else {
+            return;
+        };
+        self.sessions.remove(ix);
+        if self.active_session == Some(id) {
+            self.active_session = self
+                .sessions
+                .get(ix.min(self.sessions.len().saturating_sub(1)))
+                .map(|session| session.id);
+        }
+    }
+}
+
 impl ConnectionState {
     pub fn init(cx: &mut App) {
-        let db_manager = DatabaseManager::new();
         let this = ConnectionState {
             saved_connections: vec![],
-            active_connection: None,
-            db_manager,
-            connection_state: ConnectionStatus::Disconnected,
+            sessions: vec![],
+            active_session: None,
+            next_session_id: 0,
         };
         cx.set_global(this);
 
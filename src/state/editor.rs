@@ -1,24 +1,5 @@
 use gpui::*;
 
-use crate::services::{DatabaseSchema, TableInfo};
-
-pub struct EditorState {
-    pub tables: Vec<TableInfo>,
-    pub schema: Option<DatabaseSchema>,
-}
-
-impl Global for EditorState {}
-
-impl EditorState {
-    pub fn init(cx: &mut App) {
-        let this = EditorState {
-            tables: vec![],
-            schema: None,
-        };
-        cx.set_global(this);
-    }
-}
-
 pub struct EditorCodeActions {
     pub loading: bool,
 }
@@ -5,24 +5,28 @@
 //!
 //! ## Structure
 //!
-//! - `connection` - Connection status and saved connections
-//! - `database` - Available databases on the connected server
-//! - `editor` - Editor-related state (tables for autocomplete, etc.)
+//! - `connection` - Saved connections and the open sessions, one per tab,
+//!   each with its status, tables and databases
+//! - `editor` - Editor-related state (assistant loading flags)
+//! - `saved_queries` - The saved queries library
 //! - `actions` - Cross-cutting operations (connect, disconnect, etc.)
 
 mod actions;
 mod connection;
-mod database;
 mod editor;
+mod saved_queries;
 
 // Re-export state structs
-pub use connection::{ConnectionState, ConnectionStatus};
-pub use database::DatabaseState;
-pub use editor::{EditorCodeActions, EditorInlineCompletions, EditorState};
+pub use connection::{ConnectionSession, ConnectionState, ConnectionStatus, SessionId};
+pub use editor::{EditorCodeActions, EditorInlineCompletions};
+pub use saved_queries::SavedQueriesState;
 
 // Re-export actions for orchestration
 pub use actions::{
-    add_connection, change_database, connect, delete_connection, disconnect, update_connection,
+    activate_session, add_connection, begin_transaction, change_database, commit_transaction,
+    connect, delete_connection, delete_saved_query, disconnect, import_connections,
+    import_connections_file, refresh_transaction_status, rollback_transaction, save_queries,
+    update_connection,
 };
 
 use gpui::App;
@@ -30,8 +34,7 @@ use gpui::App;
 /// Initialize all global state.
 pub fn init(cx: &mut App) {
     ConnectionState::init(cx);
-    DatabaseState::init(cx);
-    EditorState::init(cx);
     EditorCodeActions::init(cx);
     EditorInlineCompletions::init(cx);
+    SavedQueriesState::init(cx);
 }
@@ -0,0 +1,31 @@
+use gpui::*;
+
+use crate::services::AppStore;
+use crate::services::storage::SavedQuery;
+
+/// The saved queries library, shared by every connection tab
+pub struct SavedQueriesState {
+    pub queries: Vec<SavedQuery>,
+}
+
+impl Global for SavedQueriesState {}
+
+impl SavedQueriesState {
+    pub fn init(cx: &mut App) {
+        cx.set_global(SavedQueriesState { queries: vec![] });
+
+        cx.spawn(async move |cx| {
+            if let Ok(store) = AppStore::singleton().await {
+                match store.saved_queries().load_all().await {
+                    Ok(queries) => {
+                        let _ = cx.update_global::<SavedQueriesState, _>(|state, _cx| {
+                            state.queries = queries;
+                        });
+                    }
+                    Err(e) => tracing::error!("Failed to load saved queries: {}", e),
+                }
+            }
+        })
+        .detach();
+    }
+}
@@ -1,11 +1,21 @@
 use gpui::AsyncApp;
 
-use crate::services::{ColumnDetail, DatabaseSchema, QueryExecutionResult, TableSchema};
+use crate::services::{
+    ColumnDetail, DatabaseManager, DatabaseSchema, QueryExecutionResult, TableSchema,
+};
 use crate::{
     services::agent::{ToolCallData, ToolResultData},
     state::ConnectionState,
 };
 
+/// Manager of the tab in front, which the agent works against
+fn active_db_manager(cx: &AsyncApp) -> anyhow::Result<DatabaseManager> {
+    cx.read_global::<ConnectionState, _>(|state, _cx| {
+        state.active().map(|session| session.db_manager.clone())
+    })?
+    .ok_or_else(|| anyhow::anyhow!("No active connection"))
+}
+
 /// Execute tools with access to context
 /// This is where you'll add database access, file system, etc.
 pub async fn execute_tools(tool_calls: Vec<ToolCallData>, cx: &AsyncApp) -> Vec<ToolResultData> {
@@ -32,7 +42,7 @@ pub async fn execute_tools(tool_calls: Vec<ToolCallData>, cx: &AsyncApp) -> Vec<
                     is_error: true,
                 };
 
-                match cx.read_global::<ConnectionState, _>(|state, _cx| state.db_manager.clone()) {
+                match active_db_manager(cx) {
                     Ok(db) => match db.get_schema(filter_tables).await {
                         Ok(schema) => {
                             let formatted = format_schema_for_llm(&schema);
@@ -55,7 +65,7 @@ pub async fn execute_tools(tool_calls: Vec<ToolCallData>, cx: &AsyncApp) -> Vec<
                     is_error: true,
                 };
 
-                match cx.read_global::<ConnectionState, _>(|state, _cx| state.db_manager.clone()) {
+                match active_db_manager(cx) {
                     Ok(db) => match db.get_tables().await {
                         Ok(tables) => {
                             let formatted = tables
@@ -95,24 +105,20 @@ pub async fn execute_tools(tool_calls: Vec<ToolCallData>, cx: &AsyncApp) -> Vec<
                 };
 
                 match table_name {
-                    Some(name) => {
-                        match cx.read_global::<ConnectionState, _>(|state, _cx| {
-                            state.db_manager.clone()
-                        }) {
-                            Ok(db) => match db.get_table_columns(name, table_schema).await {
-                                Ok(result) => {
-                                    let formatted = format_query_result_as_markdown(result);
-                                    ToolResultData {
-                                        tool_use_id: call.id,
-                                        content: formatted,
-                                        is_error: false,
-                                    }
+                    Some(name) => match active_db_manager(cx) {
+                        Ok(db) => match db.get_table_columns(name, table_schema).await {
+                            Ok(result) => {
+                                let formatted = format_query_result_as_markdown(result);
+                                ToolResultData {
+                                    tool_use_id: call.id,
+                                    content: formatted,
+                                    is_error: false,
                                 }
-                                Err(e) => error_result(&format!("Failed to fetch columns: {}", e)),
-                            },
-                            Err(_) => error_result("Database not connected"),
-                        }
-                    }
+                            }
+                            Err(e) => error_result(&format!("Failed to fetch columns: {}", e)),
+                        },
+                        Err(_) => error_result("Database not connected"),
+                    },
                     None => error_result("table_name is required"),
                 }
             }
@@ -196,6 +202,8 @@ fn format_query_result_as_markdown(result: QueryExecutionResult) -> String {
         QueryExecutionResult::Error(error_result) => {
             format!("Error: {}", error_result.message)
         }
+
+        QueryExecutionResult::Cancelled(_) => "Query was cancelled.".to_string(),
     }
 }
 
@@ -0,0 +1,469 @@
+use super::history::{HistoryEvent, HistoryPanel};
+use super::query_tab::QueryTab;
+use super::saved_queries::{SavedQueriesPanel, SavedQueryEvent};
+use super::tables::{TableEvent, TablesTree};
+
+use crate::services::storage::EditorTab;
+use crate::services::{AppStore, DatabaseManager};
+use crate::state::{ConnectionState, ConnectionStatus, SessionId};
+use gpui::prelude::FluentBuilder as _;
+use gpui::*;
+
+use gpui_component::button::{Button, ButtonVariants as _};
+use gpui_component::input::{Input, InputState};
+use gpui_component::spinner::Spinner;
+use gpui_component::{
+    ActiveTheme, IconName, Selectable as _, Sizable as _, WindowExt as _, h_flex,
+};
+
+/// One open connection: its query tabs, tables and history, all bound to
+/// the session's own database manager
+pub struct ConnectionTab {
+    session: SessionId,
+    db_manager: DatabaseManager,
+    status: ConnectionStatus,
+    /// Named SQL buffers, restored from the app store once loaded
+    query_tabs: Vec<Entity<QueryTab>>,
+    active_query_tab: usize,
+    tables_tree: Entity<TablesTree>,
+    saved_queries_panel: Entity<SavedQueriesPanel>,
+    /// The sidebar lists saved queries instead of tables
+    show_saved_queries: bool,
+    history_panel: Entity<HistoryPanel>,
+    show_tables: bool,
+    show_history: bool,
+    _subscriptions: Vec<Subscription>,
+}
+
+impl ConnectionTab {
+    pub fn new(
+        session: SessionId,
+        db_manager: DatabaseManager,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) -> Self {
+        let tables_tree = TablesTree::view(session, window, cx);
+        let history_panel = HistoryPanel::view(session, window, cx);
+        let saved_queries_panel = SavedQueriesPanel::view(session, window, cx);
+
+        let _subscriptions = vec![
+            cx.observe_global::<ConnectionState>(move |this, cx| {
+                if let Some(session) = cx.global::<ConnectionState>().session(this.session)
+                    && session.status != this.status
+                {
+                    this.status = session.status.clone();
+                    cx.notify();
+                }
+            }),
+            cx.subscribe(&tables_tree, |this, _, event: &TableEvent, cx| {
+                this.handle_table_event(event, cx);
+            }),
+            cx.subscribe_in(
+                &history_panel,
+                window,
+                |this, _, event: &HistoryEvent, win, cx| match event {
+                    HistoryEvent::LoadQuery(sql) => {
+                        this.load_query_into_editor(sql.clone(), win, cx);
+                    }
+                    HistoryEvent::RunQuery(sql) => {
+                        this.run_query(sql.clone(), win, cx);
+                    }
+                },
+            ),
+            cx.subscribe_in(
+                &saved_queries_panel,
+                window,
+                |this, _, event: &SavedQueryEvent, win, cx| match event {
+                    SavedQueryEvent::LoadQuery(sql) => {
+                        this.load_query_into_editor(sql.clone(), win, cx);
+                    }
+                },
+            ),
+        ];
+
+        let state = cx.global::<ConnectionState>().session(session);
+        let status = state.map_or(ConnectionStatus::Connecting, |session| {
+            session.status.clone()
+        });
+        if let Some(connection_id) = state.map(|session| session.connection.id) {
+            cx.spawn_in(window, async move |this, cx| {
+                let mut tabs = match AppStore::singleton().await {
+                    Ok(store) => store
+                        .editor_tabs()
+                        .load_for_connection(&connection_id)
+                        .await
+                        .unwrap_or_else(|e| {
+                            tracing::error!("Failed to load editor tabs: {}", e);
+                            vec![]
+                        }),
+                    Err(e) => {
+                        tracing::error!("Failed to open app store: {}", e);
+                        vec![]
+                    }
+                };
+                if tabs.is_empty() {
+                    tabs.push(EditorTab::new(connection_id, next_tab_name(&[]), 0));
+                }
+
+                this.update_in(cx, |this, window, cx| {
+                    for tab in tabs {
+                        this.push_query_tab(tab, window, cx);
+                    }
+                    cx.notify();
+                })
+                .ok();
+            })
+            .detach();
+        }
+
+        Self {
+            session,
+            db_manager,
+            status,
+            query_tabs: vec![],
+            active_query_tab: 0,
+            tables_tree,
+            saved_queries_panel,
+            show_saved_queries: false,
+            history_panel,
+            show_tables: true,
+            show_history: false,
+            _subscriptions,
+        }
+    }
+
+    pub fn view(
+        session: SessionId,
+        db_manager: DatabaseManager,
+        window: &mut Window,
+        cx: &mut App,
+    ) -> Entity<Self> {
+        cx.new(|cx| Self::new(session, db_manager, window, cx))
+    }
+
+    pub fn session(&self) -> SessionId {
+        self.session
+    }
+
+    /// Shows or hides the tables sidebar and the history panel
+    pub fn set_panels(&mut self, show_tables: bool, show_history: bool, cx: &mut Context<Self>) {
+        self.show_tables = show_tables;
+        self.show_history = show_history;
+        cx.notify();
+    }
+
+    /// Loads a query into the front query tab and runs it, as the agent asks
+    pub fn run_query(&mut self, sql: String, window: &mut Window, cx: &mut Context<Self>) {
+        if let Some(tab) = self.active_query_tab() {
+            tab.update(cx, |tab, cx| tab.run_query(sql, window, cx));
+        }
+    }
+
+    fn active_query_tab(&self) -> Option<Entity<QueryTab>> {
+        self.query_tabs.get(self.active_query_tab).cloned()
+    }
+
+    fn push_query_tab(&mut self, tab: EditorTab, window: &mut Window, cx: &mut Context<Self>) {
+        let db_manager = self.db_manager.query_handle();
+        let query_tab = QueryTab::view(self.session, db_manager, tab, window, cx);
+        cx.observe(&query_tab, |_, _, cx| cx.notify()).detach();
+        self.query_tabs.push(query_tab);
+    }
+
+    /// Opens an empty query tab after the others and brings it to the front
+    fn add_query_tab(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        let Some(connection_id) = cx
+            .global::<ConnectionState>()
+            .session(self.session)
+            .map(|session| session.connection.id)
+        else {
+            return;
+        };
+
+        let names: Vec<String> = self
+            .query_tabs
+            .iter()
+            .map(|tab| tab.read(cx).tab().name.clone())
+            .collect();
+        let position = self
+            .query_tabs
+            .iter()
+            .map(|tab| tab.read(cx).tab().position + 1)
+            .max()
+            .unwrap_or(0);
+
+        let tab = EditorTab::new(connection_id, next_tab_name(&names), position);
+        let saved = tab.clone();
+        cx.background_spawn(async move {
+            if let Ok(store) = AppStore::singleton().await
+                && let Err(e) = store.editor_tabs().save(&saved).await
+            {
+                tracing::error!("Failed to save editor tab: {}", e);
+            }
+        })
+        .detach();
+
+        self.push_query_tab(tab, window, cx);
+        self.active_query_tab = self.query_tabs.len() - 1;
+        cx.notify();
+    }
+
+    /// Closes a query tab and forgets its contents; the last one stays open
+    fn close_query_tab(&mut self, ix: usize, cx: &mut Context<Self>) {
+        if self.query_tabs.len() <= 1 || ix >= self.query_tabs.len() {
+            return;
+        }
+
+        let closed = self.query_tabs.remove(ix);
+        let id = closed.read(cx).tab().id;
+        cx.background_spawn(async move {
+            if let Ok(store) = AppStore::singleton().await
+                && let Err(e) = store.editor_tabs().delete(&id).await
+            {
+                tracing::error!("Failed to delete editor tab: {}", e);
+            }
+        })
+        .detach();
+
+        for (position, tab) in self.query_tabs.iter().enumerate() {
+            tab.update(cx, |tab, cx| tab.set_position(position as i64, cx));
+        }
+        if self.active_query_tab > ix || self.active_query_tab >= self.query_tabs.len() {
+            self.active_query_tab -= 1;
+        }
+        cx.notify();
+    }
+
+    fn rename_query_tab(&mut self, ix: usize, window: &mut Window, cx: &mut Context<Self>) {
+        let Some(query_tab) = self.query_tabs.get(ix).cloned() else {
+            return;
+        };
+        let name = query_tab.read(cx).tab().name.clone();
+        let input = cx.new(|cx| InputState::new(window, cx).placeholder("Tab name"));
+        input.update(cx, |input, cx| input.set_value(name, window, cx));
+
+        window.open_dialog(cx, move |dialog, _win, _cx| {
+            let input = input.clone();
+            let query_tab = query_tab.clone();
+
+            dialog
+                .title("Rename tab")
+                .confirm()
+                .child(Input::new(&input))
+                .on_ok(move |_, _window, cx| {
+                    let name = input.read(cx).value().trim().to_string();
+                    if name.is_empty() {
+                        return false;
+                    }
+                    query_tab.update(cx, |tab, cx| tab.rename(name, cx));
+                    true
+                })
+        });
+    }
+
+    fn load_query_into_editor(&mut self, sql: String, window: &mut Window, cx: &mut App) {
+        if let Some(tab) = self.active_query_tab() {
+            tab.update(cx, |tab, cx| tab.load_query(sql, window, cx));
+        }
+    }
+
+    fn handle_table_event(&mut self, event: &TableEvent, cx: &mut Context<Self>) {
+        match event {
+            TableEvent::TableSelected(table) => {
+                if let Some(tab) = self.active_query_tab() {
+                    tab.update(cx, |tab, cx| tab.show_table_columns(table.clone(), cx));
+                }
+            }
+        }
+    }
+
+    /// Query tab buttons, double-click to rename, and the front tab's last
+    /// result
+    fn render_query_tabs(&mut self, cx: &mut Context<Self>) -> Stateful<Div> {
+        let closable = self.query_tabs.len() > 1;
+        let last_result = self
+            .active_query_tab()
+            .and_then(|tab| tab.read(cx).tab().last_result.clone());
+
+        h_flex()
+            .id("query-tabs")
+            .px_2()
+            .py_1()
+            .gap_1()
+            .items_center()
+            .border_b_1()
+            .border_color(cx.theme().border)
+            .children(self.query_tabs.iter().enumerate().map(|(ix, tab)| {
+                let name = tab.read(cx).tab().name.clone();
+
+                h_flex()
+                    .items_center()
+                    .child(
+                        Button::new(("query-tab", ix))
+                            .label(name)
+                            .xsmall()
+                            .ghost()
+                            .selected(ix == self.active_query_tab)
+                            .tooltip("Double-click to rename")
+                            .on_click(cx.listener(move |this, event: &ClickEvent, window, cx| {
+                                if event.click_count() > 1 {
+                                    this.rename_query_tab(ix, window, cx);
+                                } else {
+                                    this.active_query_tab = ix;
+                                    cx.notify();
+                                }
+                            })),
+                    )
+                    .when(closable, |d| {
+                        d.child(
+                            Button::new(("close-query-tab", ix))
+                                .icon(IconName::Close)
+                                .xsmall()
+                                .ghost()
+                                .tooltip("Close tab")
+                                .on_click(cx.listener(move |this, _, _window, cx| {
+                                    this.close_query_tab(ix, cx);
+                                })),
+                        )
+                    })
+            }))
+            .child(
+                Button::new("new-query-tab")
+                    .icon(IconName::Plus)
+                    .xsmall()
+                    .ghost()
+                    .tooltip("New query tab")
+                    .on_click(cx.listener(|this, _, window, cx| {
+                        this.add_query_tab(window, cx);
+                    })),
+            )
+            .when_some(last_result, |d, summary| {
+                d.child(
+                    div()
+                        .ml_auto()
+                        .text_xs()
+                        .text_color(cx.theme().muted_foreground)
+                        .child(format!("Last run: {}", summary)),
+                )
+            })
+    }
+
+    /// Switches the sidebar between the tables tree and the saved queries
+    fn render_sidebar_switch(&mut self, cx: &mut Context<Self>) -> Div {
+        h_flex()
+            .gap_1()
+            .px_2()
+            .pt_2()
+            .child(
+                Button::new("sidebar-tables")
+                    .label("Tables")
+                    .xsmall()
+                    .ghost()
+                    .selected(!self.show_saved_queries)
+                    .on_click(cx.listener(|this, _, _window, cx| {
+                        this.show_saved_queries = false;
+                        cx.notify();
+                    })),
+            )
+            .child(
+                Button::new("sidebar-saved-queries")
+                    .label("Saved")
+                    .xsmall()
+                    .ghost()
+                    .selected(self.show_saved_queries)
+                    .on_click(cx.listener(|this, _, _window, cx| {
+                        this.show_saved_queries = true;
+                        cx.notify();
+                    })),
+            )
+    }
+
+    fn render_connected(&mut self, cx: &mut Context<Self>) -> Stateful<Div> {
+        let sidebar = div()
+            .id("connected-sidebar")
+            .flex()
+            .flex_col()
+            .h_full()
+            .border_color(cx.theme().border)
+            .border_r_1()
+            .min_w(px(300.0))
+            .child(self.render_sidebar_switch(cx))
+            .map(|d| {
+                if self.show_saved_queries {
+                    d.child(self.saved_queries_panel.clone())
+                } else {
+                    d.child(self.tables_tree.clone())
+                }
+            });
+
+        let history = div()
+            .id("connected-history")
+            .flex()
+            .flex_col()
+            .h_full()
+            .w(px(400.))
+            .border_color(cx.theme().border)
+            .border_l_1()
+            .child(self.history_panel.clone());
+
+        let main = div()
+            .id("connected-main")
+            .flex()
+            .flex_col()
+            .flex_1()
+            .h_full()
+            .w_full()
+            .overflow_hidden()
+            .child(self.render_query_tabs(cx))
+            .children(self.active_query_tab());
+
+        div()
+            .id("connected-content")
+            .flex()
+            .flex_row()
+            .flex_1()
+            .h_full()
+            .bg(cx.theme().background)
+            .when(self.show_tables, |d| d.child(sidebar))
+            .child(main)
+            .when(self.show_history, |d| d.child(history))
+    }
+
+    fn render_loading(&mut self, cx: &mut Context<Self>) -> Stateful<Div> {
+        let content = div()
+            .id("loading-content")
+            .flex()
+            .flex_grow()
+            .bg(cx.theme().background)
+            .justify_center()
+            .items_center()
+            .child(
+                div()
+                    .flex()
+                    .flex_col()
+                    .items_center()
+                    .child(Spinner::new())
+                    .child("Loading"),
+            );
+
+        content
+    }
+}
+
+/// "Query N" with the smallest N not already taken
+fn next_tab_name(taken: &[String]) -> String {
+    (1..)
+        .map(|n| format!("Query {}", n))
+        .find(|name| !taken.contains(name))
+        .unwrap_or_default()
+}
+
+impl Render for ConnectionTab {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        if self.status.is_open() && !self.query_tabs.is_empty() {
+            self.render_connected(cx)
+        } else {
+            self.render_loading(cx)
+        }
+    }
+}
@@ -0,0 +1,287 @@
+use std::collections::HashSet;
+
+use gpui::{prelude::FluentBuilder as _, *};
+use gpui_component::{
+    ActiveTheme as _, Icon, Selectable as _, Sizable as _, StyledExt as _,
+    button::{Button, ButtonVariants as _},
+    h_flex,
+    input::{Input, InputState},
+    label::Label,
+    v_flex,
+};
+
+use crate::{
+    services::{
+        ConnectionInfo,
+        import::{
+            ImportedConnection, discover_connections, import_file, parse_connection_string,
+            unique_connection_name,
+        },
+    },
+    state::ConnectionState,
+};
+
+/// Picks connections to add from `~/.pgpass`, `pg_service.conf`, other
+/// files of those formats and pasted URLs
+pub struct ConnectionImport {
+    url: Entity<InputState>,
+    /// Found connections and whether each is picked
+    candidates: Vec<(ImportedConnection, bool)>,
+    error: Option<String>,
+    is_loading: bool,
+}
+
+impl ConnectionImport {
+    pub fn view(window: &mut Window, cx: &mut App) -> Entity<Self> {
+        cx.new(|cx| {
+            let url = cx.new(|cx| {
+                InputState::new(window, cx)
+                    .placeholder("postgres://user@host/database or host=... dbname=...")
+                    .clean_on_escape()
+            });
+
+            cx.spawn(async move |this, cx| {
+                let found = cx
+                    .background_executor()
+                    .spawn(async move { discover_connections() })
+                    .await;
+                let _ = this.update(cx, |this, cx| {
+                    this.is_loading = false;
+                    this.add_candidates(found, cx);
+                });
+            })
+            .detach();
+
+            Self {
+                url,
+                candidates: vec![],
+                error: None,
+                is_loading: true,
+            }
+        })
+    }
+
+    /// The picked connections, renamed where a name is already saved or
+    /// picked twice
+    pub fn selected(&self, cx: &App) -> Vec<ConnectionInfo> {
+        let mut taken: HashSet<String> = cx
+            .global::<ConnectionState>()
+            .saved_connections
+            .iter()
+            .map(|conn| conn.name.clone())
+            .collect();
+
+        self.candidates
+            .iter()
+            .filter(|(_, picked)| *picked)
+            .map(|(imported, _)| {
+                let mut connection = imported.connection.clone();
+                connection.name = unique_connection_name(&connection.name, &taken);
+                taken.insert(connection.name.clone());
+                connection
+            })
+            .collect()
+    }
+
+    /// Adds new candidates, picked unless a saved connection already points
+    /// at the same server, database and user
+    fn add_candidates(&mut self, found: Vec<ImportedConnection>, cx: &mut Context<Self>) {
+        let saved = &cx.global::<ConnectionState>().saved_connections;
+        for imported in found {
+            let conn = &imported.connection;
+            let known = saved.iter().any(|saved| {
+                saved.hostname == conn.hostname
+                    && saved.port == conn.port
+                    && saved.database == conn.database
+                    && saved.username == conn.username
+            });
+            self.candidates.push((imported, !known));
+        }
+        cx.notify();
+    }
+
+    fn add_url(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        let url = self.url.read(cx).value().trim().to_string();
+        if url.is_empty() {
+            return;
+        }
+
+        match parse_connection_string(&url) {
+            Ok(connection) => {
+                self.error = None;
+                self.add_candidates(
+                    vec![ImportedConnection {
+                        connection,
+                        source: "Pasted URL".to_string(),
+                    }],
+                    cx,
+                );
+                self.url
+                    .update(cx, |input, cx| input.set_value("", window, cx));
+            }
+            Err(e) => {
+                self.error = Some(e.to_string());
+                cx.notify();
+            }
+        }
+    }
+
+    fn open_file(&mut self, _: &ClickEvent, _window: &mut Window, cx: &mut Context<Self>) {
+        let receiver = cx.prompt_for_paths(PathPromptOptions {
+            files: true,
+            directories: false,
+            multiple: true,
+            prompt: Some("Import".into()),
+        });
+
+        cx.spawn(async move |this, cx| {
+            let Ok(Ok(Some(paths))) = receiver.await else {
+                return;
+            };
+
+            let results = cx
+                .background_executor()
+                .spawn(async move {
+                    paths
+                        .iter()
+                        .map(|path| import_file(path))
+                        .collect::<Vec<_>>()
+                })
+                .await;
+
+            let _ = this.update(cx, |this, cx| {
+                for result in results {
+                    match result {
+                        Ok(found) => this.add_candidates(found, cx),
+                        Err(e) => {
+                            tracing::error!("Failed to import connections: {}", e);
+                            this.error = Some(e.to_string());
+                        }
+                    }
+                }
+                cx.notify();
+            });
+        })
+        .detach();
+    }
+
+    fn render_candidate(
+        &self,
+        ix: usize,
+        imported: &ImportedConnection,
+        picked: bool,
+        cx: &mut Context<Self>,
+    ) -> Stateful<Div> {
+        let conn = &imported.connection;
+        let details = format!(
+            "{}@{}:{}/{} • sslmode {}{}",
+            conn.username,
+            conn.hostname,
+            conn.port,
+            conn.database,
+            conn.ssl_mode.to_db_str(),
+            if conn.password.is_empty() {
+                ""
+            } else {
+                " • password"
+            }
+        );
+
+        h_flex()
+            .id(("import-candidate", ix))
+            .gap_2()
+            .px_2()
+            .py_1()
+            .rounded(cx.theme().radius)
+            .cursor_pointer()
+            .when(picked, |el| el.bg(cx.theme().list_active))
+            .hover(|s| s.bg(cx.theme().list_hover))
+            .on_click(cx.listener(move |this, _, _window, cx| {
+                if let Some((_, picked)) = this.candidates.get_mut(ix) {
+                    *picked = !*picked;
+                }
+                cx.notify();
+            }))
+            .child(
+                Button::new(("import-candidate-pick", ix))
+                    .icon(Icon::empty().path("icons/check.svg"))
+                    .xsmall()
+                    .ghost()
+                    .selected(picked),
+            )
+            .child(
+                v_flex()
+                    .flex_1()
+                    .overflow_hidden()
+                    .child(Label::new(conn.name.clone()).text_sm().font_medium())
+                    .child(
+                        Label::new(details)
+                            .text_xs()
+                            .text_color(cx.theme().muted_foreground),
+                    )
+                    .child(
+                        Label::new(imported.source.clone())
+                            .text_xs()
+                            .italic()
+                            .text_color(cx.theme().muted_foreground),
+                    ),
+            )
+    }
+}
+
+impl Render for ConnectionImport {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let candidates: Vec<Stateful<Div>> = self
+            .candidates
+            .iter()
+            .enumerate()
+            .map(|(ix, (imported, picked))| self.render_candidate(ix, imported, *picked, cx))
+            .collect();
+
+        let empty_message = if self.is_loading {
+            "Looking for ~/.pgpass and pg_service.conf..."
+        } else {
+            "No connections found. Paste a URL or open a file."
+        };
+
+        v_flex()
+            .gap_2()
+            .child(
+                h_flex()
+                    .gap_2()
+                    .child(div().flex_1().child(Input::new(&self.url).small()))
+                    .child(
+                        Button::new("import-add-url")
+                            .label("Add")
+                            .small()
+                            .on_click(cx.listener(|this, _, window, cx| this.add_url(window, cx))),
+                    )
+                    .child(
+                        Button::new("import-open-file")
+                            .label("Open file...")
+                            .small()
+                            .ghost()
+                            .tooltip("A pgpass or pg_service.conf file")
+                            .on_click(cx.listener(Self::open_file)),
+                    ),
+            )
+            .when_some(self.error.clone(), |el, error| {
+                el.child(Label::new(error).text_xs().text_color(cx.theme().danger))
+            })
+            .child(
+                v_flex()
+                    .id("import-candidates")
+                    .gap_1()
+                    .max_h(px(320.))
+                    .overflow_y_scroll()
+                    .when(candidates.is_empty(), |el| {
+                        el.child(
+                            Label::new(empty_message)
+                                .text_sm()
+                                .text_color(cx.theme().muted_foreground),
+                        )
+                    })
+                    .children(candidates),
+            )
+    }
+}
@@ -1,22 +1,41 @@
+use std::collections::BTreeMap;
+
 use gpui::*;
 use gpui_component::{
-    IndexPath,
+    ActiveTheme as _, IndexPath, h_flex,
+    label::Label,
     list::{ListDelegate, ListState},
 };
 
 use crate::{services::*, workspace::connections::ConnectionListItem};
 
+/// Connections listed under one group heading; `name` is `None` for the
+/// ungrouped ones
+struct ConnectionGroup {
+    name: Option<String>,
+    connections: Vec<ConnectionInfo>,
+}
+
 pub struct ConnectionListDelegate {
     connections: Vec<ConnectionInfo>,
-    pub matched_connections: Vec<ConnectionInfo>,
+    /// Matching connections, one section per group
+    groups: Vec<ConnectionGroup>,
+    query: String,
+    environment: Option<Environment>,
     selected_index: Option<IndexPath>,
 }
 
 impl ListDelegate for ConnectionListDelegate {
     type Item = ConnectionListItem;
 
-    fn items_count(&self, _section: usize, _app: &App) -> usize {
-        self.matched_connections.len()
+    fn sections_count(&self, _app: &App) -> usize {
+        self.groups.len().max(1)
+    }
+
+    fn items_count(&self, section: usize, _app: &App) -> usize {
+        self.groups
+            .get(section)
+            .map_or(0, |group| group.connections.len())
     }
 
     fn confirm(
@@ -26,7 +45,7 @@ impl ListDelegate for ConnectionListDelegate {
         _cx: &mut Context<ListState<Self>>,
     ) {
         if let Some(selected) = self.selected_index {
-            if let Some(conn) = self.matched_connections.get(selected.row) {
+            if let Some(conn) = self.connection(selected) {
                 tracing::debug!("Selected connection: {}@{}", conn.username, conn.hostname);
             }
         }
@@ -42,6 +61,36 @@ impl ListDelegate for ConnectionListDelegate {
         cx.notify();
     }
 
+    fn render_section_header(
+        &mut self,
+        section: usize,
+        _: &mut Window,
+        cx: &mut Context<ListState<Self>>,
+    ) -> Option<impl IntoElement> {
+        // Headings only help once some connection is in a group
+        if self.groups.iter().all(|group| group.name.is_none()) {
+            return None;
+        }
+        let group = self.groups.get(section)?;
+
+        Some(
+            h_flex()
+                .px_2()
+                .pt_2()
+                .pb_1()
+                .gap_2()
+                .text_xs()
+                .text_color(cx.theme().muted_foreground)
+                .child(Label::new(
+                    group
+                        .name
+                        .clone()
+                        .unwrap_or_else(|| "Ungrouped".to_string()),
+                ))
+                .child(Label::new(group.connections.len().to_string())),
+        )
+    }
+
     fn render_item(
         &mut self,
         ix: IndexPath,
@@ -49,7 +98,7 @@ impl ListDelegate for ConnectionListDelegate {
         _cx: &mut Context<ListState<Self>>,
     ) -> Option<Self::Item> {
         let selected = Some(ix) == self.selected_index;
-        if let Some(conn) = self.matched_connections.get(ix.row) {
+        if let Some(conn) = self.connection(ix) {
             return Some(ConnectionListItem::new(ix, conn.clone(), ix, selected));
         }
         None
@@ -60,16 +109,155 @@ impl ConnectionListDelegate {
     pub fn new() -> Self {
         Self {
             connections: vec![],
-            matched_connections: vec![],
+            groups: vec![],
+            query: String::new(),
+            environment: None,
             selected_index: None,
         }
     }
 
+    pub fn connection(&self, ix: IndexPath) -> Option<&ConnectionInfo> {
+        self.groups.get(ix.section)?.connections.get(ix.row)
+    }
+
     pub fn update_connections(&mut self, connections: Vec<ConnectionInfo>) {
         self.connections = connections;
-        self.matched_connections = self.connections.clone();
-        if !self.matched_connections.is_empty() && self.selected_index.is_none() {
-            self.selected_index = Some(IndexPath::default());
+        self.regroup();
+    }
+
+    /// Lists only connections whose name, host, database or group contains
+    /// `query`, and that are tagged `environment` when one is given
+    pub fn set_filter(&mut self, query: String, environment: Option<Environment>) {
+        self.query = query.to_lowercase();
+        self.environment = environment;
+        self.regroup();
+    }
+
+    fn regroup(&mut self) {
+        let matches = |conn: &ConnectionInfo| {
+            let text_matches = self.query.is_empty()
+                || [
+                    Some(&conn.name),
+                    Some(&conn.hostname),
+                    Some(&conn.database),
+                    conn.group.as_ref(),
+                ]
+                .into_iter()
+                .flatten()
+                .any(|text| text.to_lowercase().contains(&self.query));
+            text_matches && (self.environment.is_none() || conn.environment == self.environment)
+        };
+
+        let mut grouped: BTreeMap<String, Vec<ConnectionInfo>> = BTreeMap::new();
+        let mut ungrouped = vec![];
+        for conn in self.connections.iter().filter(|conn| matches(conn)) {
+            match &conn.group {
+                Some(group) => grouped.entry(group.clone()).or_default().push(conn.clone()),
+                None => ungrouped.push(conn.clone()),
+            }
+        }
+
+        // Groups in name order, ungrouped connections last
+        self.groups = grouped
+            .into_iter()
+            .map(|(name, connections)| ConnectionGroup {
+                name: Some(name),
+                connections,
+            })
+            .collect();
+        if !ungrouped.is_empty() {
+            self.groups.push(ConnectionGroup {
+                name: None,
+                connections: ungrouped,
+            });
         }
+
+        if self
+            .selected_index
+            .is_none_or(|ix| self.connection(ix).is_none())
+        {
+            self.selected_index = (!self.groups.is_empty()).then(IndexPath::default);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn connection(
+        name: &str,
+        group: Option<&str>,
+        environment: Option<Environment>,
+    ) -> ConnectionInfo {
+        let mut connection = ConnectionInfo::new(
+            name.to_string(),
+            format!("{}.internal", name),
+            "app".to_string(),
+            String::new(),
+            "orders".to_string(),
+            5432,
+            SslMode::Prefer,
+        );
+        connection.group = group.map(str::to_string);
+        connection.environment = environment;
+        connection
+    }
+
+    fn names(delegate: &ConnectionListDelegate) -> Vec<(Option<&str>, Vec<&str>)> {
+        delegate
+            .groups
+            .iter()
+            .map(|group| {
+                (
+                    group.name.as_deref(),
+                    group
+                        .connections
+                        .iter()
+                        .map(|conn| conn.name.as_str())
+                        .collect(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_regroup() {
+        let mut delegate = ConnectionListDelegate::new();
+        delegate.update_connections(vec![
+            connection("orders-prod", Some("orders"), Some(Environment::Prod)),
+            connection("scratch", None, Some(Environment::Local)),
+            connection("billing", Some("billing"), Some(Environment::Staging)),
+            connection("orders-dev", Some("orders"), Some(Environment::Dev)),
+        ]);
+
+        // Groups in name order with the ungrouped last; connections keep
+        // their order within a group
+        assert_eq!(
+            names(&delegate),
+            vec![
+                (Some("billing"), vec!["billing"]),
+                (Some("orders"), vec!["orders-prod", "orders-dev"]),
+                (None, vec!["scratch"]),
+            ]
+        );
+        assert_eq!(delegate.selected_index, Some(IndexPath::default()));
+
+        delegate.set_filter("ORDERS".to_string(), None);
+        assert_eq!(
+            names(&delegate),
+            vec![(Some("orders"), vec!["orders-prod", "orders-dev"])]
+        );
+        delegate.set_filter("orders".to_string(), Some(Environment::Dev));
+        assert_eq!(names(&delegate), vec![(Some("orders"), vec!["orders-dev"])]);
+        delegate.set_filter(String::new(), Some(Environment::Local));
+        assert_eq!(names(&delegate), vec![(None, vec!["scratch"])]);
+
+        // Nothing left to select, then the first row once there is again
+        delegate.set_filter("nothing".to_string(), None);
+        assert!(delegate.groups.is_empty());
+        assert_eq!(delegate.selected_index, None);
+        delegate.set_filter(String::new(), None);
+        assert_eq!(delegate.selected_index, Some(IndexPath::default()));
     }
 }
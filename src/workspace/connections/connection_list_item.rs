@@ -4,7 +4,33 @@ use gpui_component::{
     v_flex,
 };
 
-use crate::services::ConnectionInfo;
+use crate::services::{ConnectionInfo, Environment};
+
+/// Marks a connection whose sessions refuse writes
+pub fn read_only_badge(cx: &App) -> impl IntoElement {
+    div()
+        .px_1p5()
+        .rounded(cx.theme().radius)
+        .bg(cx.theme().warning)
+        .text_color(cx.theme().warning_foreground)
+        .text_xs()
+        .font_semibold()
+        .whitespace_nowrap()
+        .child("READ ONLY")
+}
+
+/// Names the environment a connection is tagged with, in its stripe color
+pub fn environment_badge(environment: Environment, color: Hsla, cx: &App) -> impl IntoElement {
+    div()
+        .px_1p5()
+        .rounded(cx.theme().radius)
+        .bg(color)
+        .text_color(gpui::white())
+        .text_xs()
+        .font_semibold()
+        .whitespace_nowrap()
+        .child(environment.label().to_uppercase())
+}
 
 #[derive(IntoElement)]
 pub struct ConnectionListItem {
@@ -56,6 +82,7 @@ impl RenderOnce for ConnectionListItem {
         } else {
             cx.theme().list_even
         };
+        let stripe = self.connection.stripe_color().map(|color| color.hsla());
 
         self.base
             .px_3()
@@ -73,15 +100,35 @@ impl RenderOnce for ConnectionListItem {
                     .items_center()
                     .gap_3()
                     .text_color(text_color)
+                    .child(
+                        div()
+                            .w(px(3.))
+                            .h(px(32.))
+                            .rounded(px(2.))
+                            .when_some(stripe, |d, color| d.bg(color)),
+                    )
                     .child(
                         v_flex()
                             .gap_1()
                             .flex_1()
                             .overflow_x_hidden()
                             .child(
-                                Label::new(self.connection.name.clone())
-                                    .font_semibold()
-                                    .whitespace_nowrap(),
+                                h_flex()
+                                    .gap_2()
+                                    .child(
+                                        Label::new(self.connection.name.clone())
+                                            .font_semibold()
+                                            .whitespace_nowrap(),
+                                    )
+                                    .when_some(
+                                        self.connection.environment.zip(stripe),
+                                        |d, (environment, color)| {
+                                            d.child(environment_badge(environment, color, cx))
+                                        },
+                                    )
+                                    .when(self.connection.read_only, |d| {
+                                        d.child(read_only_badge(cx))
+                                    }),
                             )
                             .child(
                                 Label::new(format!(
@@ -1,16 +1,28 @@
 use gpui::{prelude::FluentBuilder as _, *};
 use gpui_component::{
-    ActiveTheme as _, Icon, IconName, Sizable as _, StyledExt, WindowExt as _,
+    ActiveTheme as _, Icon, IconName, Selectable as _, Sizable as _, StyledExt, WindowExt as _,
     button::{Button, ButtonVariants as _},
+    h_flex,
+    input::{Input, InputEvent, InputState},
     label::Label,
     list::{List, ListEvent, ListState},
+    notification::NotificationType,
     v_flex,
 };
 
 use crate::{
-    services::ConnectionInfo,
-    state::{ConnectionState, connect, delete_connection},
-    workspace::connections::{ConnectionForm, ConnectionListDelegate},
+    services::{
+        AppStore, ConnectionError, ConnectionInfo, Environment,
+        storage::{ConnectionsFile, ConnectionsFileFormat, ConnectionsImport},
+    },
+    state::{
+        ConnectionState, connect, delete_connection, import_connections, import_connections_file,
+        update_connection,
+    },
+    workspace::connections::{
+        ConnectionForm, ConnectionImport, ConnectionListDelegate, ConnectionsExportForm,
+        ConnectionsImportForm,
+    },
 };
 
 const VERSION: &str = env!("CARGO_PKG_VERSION");
@@ -21,6 +33,9 @@ pub struct ConnectionManager {
     selected_connection: Option<ConnectionInfo>,
     connection_form: Entity<ConnectionForm>,
     connection_list: Entity<ListState<ConnectionListDelegate>>,
+    /// Filters the list by name, host, database or group
+    search: Entity<InputState>,
+    environment_filter: Option<Environment>,
     _subscriptions: Vec<Subscription>,
 }
 
@@ -28,6 +43,11 @@ impl ConnectionManager {
     pub fn new(window: &mut Window, cx: &mut Context<Self>) -> Self {
         let connection_list =
             cx.new(|cx| ListState::new(ConnectionListDelegate::new(), window, cx));
+        let search = cx.new(|cx| {
+            InputState::new(window, cx)
+                .placeholder("Filter connections")
+                .clean_on_escape()
+        });
 
         let conn_list_clone = connection_list.clone();
         let _subscriptions = vec![
@@ -40,6 +60,11 @@ impl ConnectionManager {
 
                 cx.notify();
             }),
+            cx.subscribe_in(&search, window, |this, _, event: &InputEvent, _, cx| {
+                if let InputEvent::Change = event {
+                    this.apply_filter(cx);
+                }
+            }),
             cx.subscribe_in(
                 &connection_list.clone(),
                 window,
@@ -47,7 +72,7 @@ impl ConnectionManager {
                     match evt.clone() {
                         ListEvent::Confirm(ix) => {
                             let list_del = list.read(cx).delegate();
-                            if let Some(conn) = list_del.matched_connections.clone().get(ix.row) {
+                            if let Some(conn) = list_del.connection(ix).cloned() {
                                 this.selected_connection = Some(conn.clone());
                                 this.is_creating = false;
                                 this.is_editing = false;
@@ -83,6 +108,8 @@ impl ConnectionManager {
             selected_connection: None,
             connection_form,
             connection_list,
+            search,
+            environment_filter: None,
             _subscriptions,
         }
     }
@@ -91,6 +118,364 @@ impl ConnectionManager {
         cx.new(|cx| Self::new(window, cx))
     }
 
+    fn apply_filter(&mut self, cx: &mut Context<Self>) {
+        let query = self.search.read(cx).value().trim().to_string();
+        let environment = self.environment_filter;
+        self.connection_list.update(cx, |list, cx| {
+            list.delegate_mut().set_filter(query, environment);
+            cx.notify();
+        });
+        cx.notify();
+    }
+
+    fn render_filters(&self, cx: &mut Context<Self>) -> impl IntoElement {
+        let environments = [None].into_iter().chain(Environment::ALL.map(Some));
+
+        v_flex()
+            .w_full()
+            .gap_1()
+            .child(Input::new(&self.search).small())
+            .child(
+                h_flex()
+                    .gap_1()
+                    .children(environments.enumerate().map(|(ix, environment)| {
+                        Button::new(("environment-filter", ix))
+                            .label(environment.map_or("All", |env| env.label()))
+                            .xsmall()
+                            .ghost()
+                            .selected(self.environment_filter == environment)
+                            .on_click(cx.listener(move |this, _, _, cx| {
+                                this.environment_filter = environment;
+                                this.apply_filter(cx);
+                            }))
+                    })),
+            )
+    }
+
+    /// Connects to a saved connection, explaining a failure once the
+    /// connect comes back
+    fn open_connection(
+        &mut self,
+        connection: ConnectionInfo,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let task = connect(&connection, cx);
+        cx.spawn_in(window, async move |this, cx| {
+            if let Err(error) = task.await {
+                let _ = this.update_in(cx, |this, window, cx| {
+                    this.show_connection_failure(connection, error, window, cx);
+                });
+            }
+        })
+        .detach();
+    }
+
+    /// Explains a failed connect and opens the connection's settings with
+    /// the error next to them. A missing password is asked for instead.
+    fn show_connection_failure(
+        &mut self,
+        connection: ConnectionInfo,
+        error: ConnectionError,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let message: SharedString =
+            format!("Could not connect to {}: {}", connection.name, error).into();
+        let Some(connection) = cx
+            .global::<ConnectionState>()
+            .saved_connections
+            .iter()
+            .find(|saved| saved.id == connection.id)
+            .cloned()
+        else {
+            window.push_notification((NotificationType::Error, message), cx);
+            return;
+        };
+
+        if error == ConnectionError::MissingPassword {
+            self.prompt_password(connection, window, cx);
+            return;
+        }
+
+        window.push_notification((NotificationType::Error, message), cx);
+
+        self.selected_connection = Some(connection.clone());
+        self.is_creating = false;
+        self.is_editing = true;
+        cx.update_entity(&self.connection_form, |form, cx| {
+            form.set_connection(connection, window, cx);
+            form.set_error(error.to_string(), cx);
+        });
+        cx.notify();
+    }
+
+    /// Asks for the password of a connection the keyring has none for,
+    /// saves it and connects. Left empty, it connects without one again.
+    fn prompt_password(
+        &mut self,
+        connection: ConnectionInfo,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let password = cx.new(|cx| {
+            InputState::new(window, cx)
+                .masked(true)
+                .placeholder("Password")
+        });
+
+        let manager = cx.entity();
+        window.open_dialog(cx, move |dialog, _win, _cx| {
+            let password = password.clone();
+            let connection = connection.clone();
+            let manager = manager.clone();
+
+            dialog
+                .title(format!("Password for {}", connection.name))
+                .confirm()
+                .child(
+                    v_flex()
+                        .gap_2()
+                        .child(
+                            div()
+                                .text_sm()
+                                .child("The server asked for a password, and none is saved for this connection."),
+                        )
+                        .child(Input::new(&password).small()),
+                )
+                .on_ok(move |_, window, cx| {
+                    let password = password.read(cx).value().to_string();
+                    let mut connection = connection.clone();
+                    if !password.is_empty() {
+                        connection.password = password;
+                        update_connection(connection.clone(), cx);
+                    }
+                    manager.update(cx, |manager, cx| {
+                        manager.open_connection(connection, window, cx);
+                    });
+                    true
+                })
+        });
+    }
+
+    /// Opens the dialog that adds connections from libpq files and URLs
+    fn open_import(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        let import = ConnectionImport::view(window, cx);
+
+        window.open_dialog(cx, move |dialog, _win, _cx| {
+            let import = import.clone();
+
+            dialog
+                .title("Import connections")
+                .confirm()
+                .child(import.clone())
+                .on_ok(move |_, window, cx| {
+                    let connections = import.read(cx).selected(cx);
+                    if !connections.is_empty() {
+                        let import = import_connections(connections, cx);
+                        Self::report_import(import, window, cx);
+                    }
+                    true
+                })
+        });
+    }
+
+    /// Asks about passwords, then writes the saved connections to a JSON or
+    /// TOML file
+    fn open_export(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        let form = ConnectionsExportForm::view(window, cx);
+
+        window.open_dialog(cx, move |dialog, _win, _cx| {
+            let form = form.clone();
+
+            dialog
+                .title("Export connections")
+                .confirm()
+                .child(form.clone())
+                .on_ok(move |_, window, cx| match form.read(cx).passphrase(cx) {
+                    Ok(passphrase) => {
+                        Self::export_to_file(passphrase, window, cx);
+                        true
+                    }
+                    Err(message) => {
+                        window.push_notification((NotificationType::Error, message), cx);
+                        false
+                    }
+                })
+        });
+    }
+
+    fn export_to_file(passphrase: Option<String>, window: &mut Window, cx: &mut App) {
+        let home = dirs::home_dir().unwrap_or_default();
+        let receiver = cx.prompt_for_new_path(&home, Some("pgui-connections.json"));
+
+        window
+            .spawn(cx, async move |cx| {
+                let Ok(Ok(Some(path))) = receiver.await else {
+                    return;
+                };
+
+                let result: anyhow::Result<usize> = cx
+                    .background_executor()
+                    .spawn(async move {
+                        let store = AppStore::singleton().await?;
+                        let file = store.connections().export(passphrase.as_deref()).await?;
+                        let contents = file.to_string(ConnectionsFileFormat::from_path(&path))?;
+                        std::fs::write(&path, contents)?;
+                        Ok(file.connections.len())
+                    })
+                    .await;
+
+                let _ = cx.update(|window, cx| match result {
+                    Ok(count) => {
+                        let info: SharedString = format!("Exported {} connections", count).into();
+                        window.push_notification((NotificationType::Info, info), cx);
+                    }
+                    Err(e) => {
+                        tracing::error!("Failed to export connections: {}", e);
+                        window.push_notification((NotificationType::Error, "Export failed"), cx);
+                    }
+                });
+            })
+            .detach();
+    }
+
+    /// Reads a connections file, then asks for its passphrase and how to
+    /// handle names that are already saved
+    fn open_import_file(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        let receiver = cx.prompt_for_paths(PathPromptOptions {
+            files: true,
+            directories: false,
+            multiple: false,
+            prompt: Some("Import".into()),
+        });
+
+        cx.spawn_in(window, async move |_this, cx| {
+            let Ok(Ok(Some(paths))) = receiver.await else {
+                return;
+            };
+            let Some(path) = paths.into_iter().next() else {
+                return;
+            };
+
+            let result = cx
+                .background_executor()
+                .spawn(async move {
+                    let contents = std::fs::read_to_string(&path)?;
+                    ConnectionsFile::parse(&contents, ConnectionsFileFormat::from_path(&path))
+                })
+                .await;
+
+            let _ = cx.update(|window, cx| match result {
+                Ok(file) => {
+                    let saved_names: Vec<String> = cx
+                        .global::<ConnectionState>()
+                        .saved_connections
+                        .iter()
+                        .map(|conn| conn.name.clone())
+                        .collect();
+                    let form = ConnectionsImportForm::view(file, &saved_names, window, cx);
+
+                    window.open_dialog(cx, move |dialog, _win, _cx| {
+                        let form = form.clone();
+
+                        dialog
+                            .title("Import connections file")
+                            .confirm()
+                            .child(form.clone())
+                            .on_ok(move |_, window, cx| {
+                                let form = form.read(cx);
+                                let (file, on_conflict) = (form.file().clone(), form.on_conflict());
+                                match form.passphrase(cx) {
+                                    Ok(passphrase) => {
+                                        let import = import_connections_file(
+                                            file,
+                                            passphrase,
+                                            on_conflict,
+                                            cx,
+                                        );
+                                        Self::report_file_import(import, window, cx);
+                                        true
+                                    }
+                                    Err(message) => {
+                                        window.push_notification(
+                                            (NotificationType::Error, message),
+                                            cx,
+                                        );
+                                        false
+                                    }
+                                }
+                            })
+                    });
+                }
+                Err(e) => {
+                    tracing::error!("Failed to read connections file: {}", e);
+                    let message: SharedString = format!("Import failed: {}", e).into();
+                    window.push_notification((NotificationType::Error, message), cx);
+                }
+            });
+        })
+        .detach();
+    }
+
+    fn report_import(
+        import: Task<anyhow::Result<(usize, Vec<String>)>>,
+        window: &mut Window,
+        cx: &mut App,
+    ) {
+        window
+            .spawn(cx, async move |cx| {
+                let result = import.await;
+                let _ = cx.update(|window, cx| match result {
+                    Ok((added, failures)) if failures.is_empty() => {
+                        let info: SharedString = format!("Imported {} connections", added).into();
+                        window.push_notification((NotificationType::Info, info), cx);
+                    }
+                    Ok((added, failures)) => {
+                        let message: SharedString = format!(
+                            "Imported {} connections; {} failed: {}",
+                            added,
+                            failures.len(),
+                            failures.join("; ")
+                        )
+                        .into();
+                        window.push_notification((NotificationType::Error, message), cx);
+                    }
+                    Err(e) => {
+                        let message: SharedString = format!("Import failed: {}", e).into();
+                        window.push_notification((NotificationType::Error, message), cx);
+                    }
+                });
+            })
+            .detach();
+    }
+
+    fn report_file_import(
+        import: Task<anyhow::Result<ConnectionsImport>>,
+        window: &mut Window,
+        cx: &mut App,
+    ) {
+        window
+            .spawn(cx, async move |cx| {
+                let result = import.await;
+                let _ = cx.update(|window, cx| match result {
+                    Ok(summary) => {
+                        let info: SharedString = format!(
+                            "Added {}, replaced {}, renamed {} and skipped {} connections",
+                            summary.added, summary.replaced, summary.renamed, summary.skipped
+                        )
+                        .into();
+                        window.push_notification((NotificationType::Info, info), cx);
+                    }
+                    Err(e) => {
+                        let message: SharedString = format!("Import failed: {}", e).into();
+                        window.push_notification((NotificationType::Error, message), cx);
+                    }
+                });
+            })
+            .detach();
+    }
+
     fn render_connections_list(&mut self, cx: &mut Context<Self>) -> impl IntoElement {
         let title = div()
             .pl_1()
@@ -101,21 +486,55 @@ impl ConnectionManager {
             .items_center()
             .child(Label::new("Connections").font_bold().text_base())
             .child(
-                Button::new("new")
-                    .icon(Icon::empty().path("icons/plus.svg"))
-                    .tooltip("New Connection")
-                    .ghost()
-                    .small()
-                    .on_click(cx.listener(|this, _evt, win, cx| {
-                        this.is_creating = true;
-                        this.is_editing = false;
-                        this.selected_connection = None;
-                        cx.update_entity(&this.connection_form, |form, cx| {
-                            form.clear(win, cx);
-                            cx.notify();
-                        });
-                        cx.notify();
-                    })),
+                h_flex()
+                    .gap_1()
+                    .child(
+                        Button::new("export")
+                            .icon(Icon::empty().path("icons/external-link.svg"))
+                            .tooltip("Export connections to a file")
+                            .ghost()
+                            .small()
+                            .on_click(cx.listener(|this, _evt, win, cx| {
+                                this.open_export(win, cx);
+                            })),
+                    )
+                    .child(
+                        Button::new("import-file")
+                            .icon(Icon::empty().path("icons/file-braces.svg"))
+                            .tooltip("Import a connections file")
+                            .ghost()
+                            .small()
+                            .on_click(cx.listener(|this, _evt, win, cx| {
+                                this.open_import_file(win, cx);
+                            })),
+                    )
+                    .child(
+                        Button::new("import")
+                            .icon(Icon::empty().path("icons/inbox.svg"))
+                            .tooltip("Import from pgpass, pg_service.conf or URLs")
+                            .ghost()
+                            .small()
+                            .on_click(cx.listener(|this, _evt, win, cx| {
+                                this.open_import(win, cx);
+                            })),
+                    )
+                    .child(
+                        Button::new("new")
+                            .icon(Icon::empty().path("icons/plus.svg"))
+                            .tooltip("New Connection")
+                            .ghost()
+                            .small()
+                            .on_click(cx.listener(|this, _evt, win, cx| {
+                                this.is_creating = true;
+                                this.is_editing = false;
+                                this.selected_connection = None;
+                                cx.update_entity(&this.connection_form, |form, cx| {
+                                    form.clear(win, cx);
+                                    cx.notify();
+                                });
+                                cx.notify();
+                            })),
+                    ),
             );
         v_flex()
             .gap_2()
@@ -123,6 +542,7 @@ impl ConnectionManager {
             .flex_1()
             .items_start()
             .child(title)
+            .child(self.render_filters(cx))
             .child(
                 List::new(&self.connection_list)
                     .p(px(8.))
@@ -259,7 +679,7 @@ impl Render for ConnectionManager {
                                             });
 
                                             if let Some(conn) = this.selected_connection.clone() {
-                                                connect(&conn, cx);
+                                                this.open_connection(conn, win, cx);
                                             }
 
                                             this.selected_connection = None;
@@ -0,0 +1,188 @@
+use gpui::{prelude::FluentBuilder as _, *};
+use gpui_component::{
+    ActiveTheme as _, Selectable as _, Sizable as _,
+    button::{Button, ButtonVariants as _},
+    h_flex,
+    input::{Input, InputState},
+    v_flex,
+};
+
+use crate::services::storage::{ConnectionsFile, NameConflict};
+
+/// Asks whether an exported connections file includes passwords, and the
+/// passphrase they are encrypted under
+pub struct ConnectionsExportForm {
+    include_passwords: bool,
+    passphrase: Entity<InputState>,
+    confirm: Entity<InputState>,
+}
+
+impl ConnectionsExportForm {
+    pub fn view(window: &mut Window, cx: &mut App) -> Entity<Self> {
+        cx.new(|cx| Self {
+            include_passwords: false,
+            passphrase: cx.new(|cx| {
+                InputState::new(window, cx)
+                    .masked(true)
+                    .placeholder("Passphrase")
+            }),
+            confirm: cx.new(|cx| {
+                InputState::new(window, cx)
+                    .masked(true)
+                    .placeholder("Repeat passphrase")
+            }),
+        })
+    }
+
+    /// The passphrase to encrypt passwords with, `None` to leave them out
+    pub fn passphrase(&self, cx: &App) -> Result<Option<String>, String> {
+        if !self.include_passwords {
+            return Ok(None);
+        }
+
+        let passphrase = self.passphrase.read(cx).value().to_string();
+        if passphrase.is_empty() {
+            return Err("Enter a passphrase to encrypt the passwords.".to_string());
+        }
+        let confirm = self.confirm.read(cx).value().to_string();
+        if passphrase != confirm {
+            return Err("The passphrases don't match.".to_string());
+        }
+        Ok(Some(passphrase))
+    }
+}
+
+impl Render for ConnectionsExportForm {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        v_flex()
+            .gap_2()
+            .child(
+                div()
+                    .text_sm()
+                    .text_color(cx.theme().muted_foreground)
+                    .child(
+                        "Save as .json or .toml. Without passwords the file can be shared as is.",
+                    ),
+            )
+            .child(
+                Button::new("export-include-passwords")
+                    .label("Include passwords, encrypted")
+                    .xsmall()
+                    .ghost()
+                    .selected(self.include_passwords)
+                    .on_click(cx.listener(|this, _, _window, cx| {
+                        this.include_passwords = !this.include_passwords;
+                        cx.notify();
+                    })),
+            )
+            .when(self.include_passwords, |el| {
+                el.child(Input::new(&self.passphrase).small())
+                    .child(Input::new(&self.confirm).small())
+            })
+    }
+}
+
+const CONFLICTS: [(NameConflict, &str); 3] = [
+    (NameConflict::Skip, "Keep mine"),
+    (NameConflict::Replace, "Replace"),
+    (NameConflict::Rename, "Keep both"),
+];
+
+/// Shows what a connections file holds and asks how to import it
+pub struct ConnectionsImportForm {
+    file: ConnectionsFile,
+    /// Names in the file that are already saved
+    conflicts: Vec<String>,
+    passphrase: Entity<InputState>,
+    on_conflict: NameConflict,
+}
+
+impl ConnectionsImportForm {
+    pub fn view(
+        file: ConnectionsFile,
+        saved_names: &[String],
+        window: &mut Window,
+        cx: &mut App,
+    ) -> Entity<Self> {
+        let conflicts = file
+            .connections
+            .iter()
+            .filter(|conn| saved_names.contains(&conn.name))
+            .map(|conn| conn.name.clone())
+            .collect();
+
+        cx.new(|cx| Self {
+            file,
+            conflicts,
+            passphrase: cx.new(|cx| {
+                InputState::new(window, cx)
+                    .masked(true)
+                    .placeholder("Leave empty to import without passwords")
+            }),
+            on_conflict: NameConflict::default(),
+        })
+    }
+
+    pub fn file(&self) -> &ConnectionsFile {
+        &self.file
+    }
+
+    pub fn on_conflict(&self) -> NameConflict {
+        self.on_conflict
+    }
+
+    /// The passphrase, checked against the file's passwords
+    pub fn passphrase(&self, cx: &App) -> Result<Option<String>, String> {
+        let passphrase = self.passphrase.read(cx).value().to_string();
+        if passphrase.is_empty() || !self.file.has_passwords() {
+            return Ok(None);
+        }
+        self.file
+            .connections(Some(&passphrase))
+            .map(|_| Some(passphrase))
+            .map_err(|e| e.to_string())
+    }
+}
+
+impl Render for ConnectionsImportForm {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let summary = format!(
+            "{} connections{}",
+            self.file.connections.len(),
+            if self.file.has_passwords() {
+                " with encrypted passwords"
+            } else {
+                ""
+            }
+        );
+
+        v_flex()
+            .gap_2()
+            .child(div().text_sm().child(summary))
+            .when(self.file.has_passwords(), |el| {
+                el.child(Input::new(&self.passphrase).small())
+            })
+            .when(!self.conflicts.is_empty(), |el| {
+                el.child(
+                    div()
+                        .text_sm()
+                        .text_color(cx.theme().muted_foreground)
+                        .child(format!("Already saved: {}", self.conflicts.join(", "))),
+                )
+                .child(h_flex().gap_1().children(
+                    CONFLICTS.iter().enumerate().map(|(ix, (conflict, label))| {
+                        let conflict = *conflict;
+                        Button::new(("import-conflict", ix))
+                            .label(*label)
+                            .xsmall()
+                            .ghost()
+                            .selected(self.on_conflict == conflict)
+                            .on_click(cx.listener(move |this, _, _window, cx| {
+                                this.on_conflict = conflict;
+                                cx.notify();
+                            }))
+                    }),
+                ))
+            })
+    }
+}
@@ -1,10 +1,14 @@
 mod connection_form;
+mod connection_import;
 mod connection_list;
 mod connection_list_item;
 mod connection_manager;
+mod connections_file_form;
 
 pub(crate) use connection_form::*;
+pub(crate) use connection_import::*;
 pub(crate) use connection_list::*;
 pub(crate) use connection_list_item::*;
+pub(crate) use connections_file_form::*;
 
 pub use connection_manager::ConnectionManager;
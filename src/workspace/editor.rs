@@ -1,34 +1,60 @@
 use std::rc::Rc;
 
-use crate::services::sql::{SqlCodeActionProvider, SqlQuery, SqlQueryAnalyzer};
+use crate::services::sql::{
+    SqlCodeActionProvider, SqlQuery, SqlQueryAnalyzer, parameterize, tokenize,
+};
+use crate::services::{AppStore, DatabaseErrorDetails, QueryParam, QueryTimeouts};
 use crate::state::{EditorCodeActions, EditorInlineCompletions};
 use crate::workspace::agent::format_schema_for_llm;
+use crate::workspace::parameters_form::ParametersForm;
+use crate::workspace::saved_query_form::SavedQueryForm;
+use crate::workspace::timeouts_form::TimeoutsForm;
 use crate::{
-    services::{ConnectionInfo, SqlCompletionProvider},
-    state::{ConnectionState, DatabaseState, EditorState, change_database, disconnect},
+    services::{ConnectionInfo, DatabaseSchema, SqlCompletionProvider, TableInfo},
+    state::{
+        ConnectionState, ConnectionStatus, SessionId, begin_transaction, change_database,
+        disconnect, save_queries,
+    },
 };
 use gpui::{prelude::FluentBuilder as _, *};
 use gpui_component::spinner::Spinner;
 use gpui_component::{
-    ActiveTheme as _, Disableable as _, Icon, Sizable as _,
+    ActiveTheme as _, Disableable as _, Icon, Sizable as _, WindowExt as _,
     button::{Button, ButtonVariants as _},
     divider::Divider,
     h_flex,
+    highlighter::{Diagnostic, DiagnosticSeverity},
     input::{Input, InputState, TabSize},
+    notification::NotificationType,
     select::{Select, SelectEvent, SelectState},
     v_flex,
 };
 use gpui_component::{Selectable as _, input};
-use lsp_types::CompletionItem;
+use lsp_types::{CompletionItem, Position};
 use sqlformat::{FormatOptions, QueryParams, format};
 
 pub enum EditorEvent {
-    ExecuteQuery(String),
+    /// Run one statement; `params` bind its placeholders in order
+    ExecuteQuery {
+        sql: String,
+        params: Vec<QueryParam>,
+        timeouts: QueryTimeouts,
+    },
+    /// Run every statement in the editor in order
+    ExecuteScript {
+        statements: Vec<String>,
+        stop_on_error: bool,
+        timeouts: QueryTimeouts,
+    },
+    CancelQuery,
+    /// The text was edited or the cursor moved
+    BufferChanged,
 }
 
 impl EventEmitter<EditorEvent> for Editor {}
 
 pub struct Editor {
+    session: SessionId,
     input_state: Entity<InputState>,
     _subscriptions: Vec<Subscription>,
     completion_provider: Rc<SqlCompletionProvider>,
@@ -36,13 +62,22 @@ pub struct Editor {
     is_executing: bool,
     is_formatting: bool,
     active_connection: Option<ConnectionInfo>,
+    /// Database whose tables and schema feed the completions
+    loaded_database: Option<String>,
+    in_transaction: bool,
     db_select: Entity<SelectState<Vec<SharedString>>>,
     analyzer: SqlQueryAnalyzer,
     parsed_queries: Vec<SqlQuery>,
     current_query_index: Option<usize>,
+    /// Statements of the last run and their byte offsets in the text, for
+    /// placing the errors they report
+    executed: Vec<(usize, String)>,
     inline_completions_enabled: bool,
     code_actions_loading: bool,
     inline_completions_loading: bool,
+    stop_script_on_error: bool,
+    /// Limits overriding the connection's for the following runs
+    timeout_overrides: QueryTimeouts,
 }
 
 impl Editor {
@@ -53,7 +88,30 @@ impl Editor {
         });
     }
 
-    pub fn new(window: &mut Window, cx: &mut Context<Self>) -> Self {
+    pub fn contents(&self, cx: &App) -> String {
+        self.input_state.read(cx).value().to_string()
+    }
+
+    /// Byte offset of the cursor
+    pub fn cursor(&self, cx: &App) -> usize {
+        self.input_state.read(cx).cursor()
+    }
+
+    /// Puts back a saved buffer and its cursor
+    pub fn restore(&mut self, content: String, cursor: usize, window: &mut Window, cx: &mut App) {
+        let mut cursor = cursor.min(content.len());
+        while !content.is_char_boundary(cursor) {
+            cursor -= 1;
+        }
+        let position = text_position(&content, cursor);
+        cx.update_entity(&self.input_state, |input, cx| {
+            input.set_value(content, window, cx);
+            input.set_cursor_position(position, window, cx);
+            cx.notify();
+        });
+    }
+
+    pub fn new(session: SessionId, window: &mut Window, cx: &mut Context<Self>) -> Self {
         let default_language = "sql".to_string();
         let completion_provider = Rc::new(SqlCompletionProvider::new());
         let code_action_provider = Rc::new(SqlCodeActionProvider::new());
@@ -76,62 +134,39 @@ impl Editor {
         let db_select = cx.new(|cx| SelectState::new(Vec::<SharedString>::new(), None, window, cx));
 
         let _subscriptions = vec![
-            cx.observe_global::<EditorState>(move |this, cx| {
-                let tables = cx.global::<EditorState>().tables.clone();
-                let schema = cx.global::<EditorState>().schema.clone();
-                let completions = tables
-                    .iter()
-                    .map(|table| {
-                        let table = table.clone();
-                        CompletionItem {
-                            label: table.table_name.into(),
-                            kind: Some(lsp_types::CompletionItemKind::CLASS), // Better kind for tables
-                            detail: Some(
-                                format!("{}:{}", table.table_schema, table.table_type).into(),
-                            ),
-                            ..Default::default()
-                        }
-                    })
-                    .collect::<Vec<_>>();
-                this.completion_provider.add_schema_completions(completions);
-                if let Some(schema) = schema {
-                    let formatted = format_schema_for_llm(&schema);
-                    this.completion_provider.add_schema(formatted.clone());
-                    this.code_action_provider.set_schema(formatted);
-                }
-                cx.notify();
-            }),
             cx.observe_global_in::<ConnectionState>(window, move |this, win, cx| {
-                let state = cx.global::<ConnectionState>();
-                let active_connection = state.active_connection.clone();
-
-                this.active_connection = active_connection.clone();
-
-                if let Some(conn) = active_connection.clone() {
+                let Some(session) = cx.global::<ConnectionState>().session(this.session) else {
+                    return;
+                };
+                let connection = session.connection.clone();
+                this.in_transaction = session.has_open_transaction();
+
+                // Refresh completions and the database list once per database
+                let loaded = (session.status == ConnectionStatus::Connected)
+                    .then(|| connection.database.clone());
+                if loaded.is_some() && loaded != this.loaded_database {
+                    let tables = session.tables.clone();
+                    let schema = session.schema.clone();
+                    let databases: Vec<SharedString> = session
+                        .databases
+                        .iter()
+                        .map(|db| db.datname.clone().into())
+                        .collect();
+
+                    this.loaded_database = loaded;
+                    this.set_completions(tables, schema);
                     cx.update_entity(&this.db_select.clone(), |select, cx| {
-                        select.set_selected_value(&conn.database.clone().into(), win, cx);
+                        select.set_items(databases, win, cx);
+                        select.set_selected_value(&connection.database.clone().into(), win, cx);
                     });
                 }
 
-                cx.notify();
-            }),
-            cx.observe_global_in::<DatabaseState>(window, move |this, win, cx| {
-                let state = cx.global::<DatabaseState>();
-                let databases = state.databases.clone();
-
-                let databases: Vec<SharedString> = databases
-                    .iter()
-                    .map(|db| db.datname.clone().into())
-                    .collect();
-
-                cx.update_entity(&this.db_select.clone(), |select, cx| {
-                    select.set_items(databases, win, cx);
-                });
-
+                this.active_connection = Some(connection);
                 cx.notify();
             }),
             cx.subscribe(&input_state, |this, _, _: &input::InputEvent, cx| {
                 this.reparse_queries(cx);
+                cx.emit(EditorEvent::BufferChanged);
             }),
             cx.observe_global::<EditorCodeActions>(move |this, cx| {
                 this.code_actions_loading = cx.global::<EditorCodeActions>().loading.clone();
@@ -147,20 +182,26 @@ impl Editor {
             .detach();
 
         Self {
+            session,
             input_state,
             completion_provider,
             code_action_provider,
             is_executing: false,
             is_formatting: false,
             active_connection: None,
+            loaded_database: None,
+            in_transaction: false,
             db_select,
             _subscriptions,
             analyzer: SqlQueryAnalyzer::new(),
             parsed_queries: vec![],
             current_query_index: None,
+            executed: vec![],
             inline_completions_enabled: false,
             code_actions_loading: false,
             inline_completions_loading: false,
+            stop_script_on_error: true,
+            timeout_overrides: QueryTimeouts::default(),
         }
     }
 
@@ -182,26 +223,117 @@ impl Editor {
         );
     }
 
-    pub fn view(window: &mut Window, cx: &mut App) -> Entity<Self> {
-        cx.new(|cx| Self::new(window, cx))
+    pub fn view(session: SessionId, window: &mut Window, cx: &mut App) -> Entity<Self> {
+        cx.new(|cx| Self::new(session, window, cx))
+    }
+
+    /// Feeds the session's tables and schema to the completion providers
+    fn set_completions(&mut self, tables: Vec<TableInfo>, schema: Option<DatabaseSchema>) {
+        let completions = tables
+            .into_iter()
+            .map(|table| CompletionItem {
+                label: table.table_name,
+                kind: Some(lsp_types::CompletionItemKind::CLASS), // Better kind for tables
+                detail: Some(format!("{}:{}", table.table_schema, table.table_type)),
+                ..Default::default()
+            })
+            .collect::<Vec<_>>();
+        self.completion_provider.add_schema_completions(completions);
+        if let Some(schema) = schema {
+            let formatted = format_schema_for_llm(&schema);
+            self.completion_provider.add_schema(formatted.clone());
+            self.code_action_provider.set_schema(formatted);
+        }
     }
 
     fn on_select_database_event(
         &mut self,
         _: &Entity<SelectState<Vec<SharedString>>>,
         event: &SelectEvent<Vec<SharedString>>,
-        _window: &mut Window,
+        window: &mut Window,
         cx: &mut Context<Self>,
     ) {
         match event {
             SelectEvent::Confirm(value) => {
                 if let Some(database) = value {
-                    change_database(database.to_string(), cx)
+                    let database = database.to_string();
+                    let editor = cx.entity();
+                    let session = self.session;
+                    Self::confirm_if_in_transaction(
+                        self.in_transaction,
+                        "A transaction is open. Switching databases will roll it back.",
+                        window,
+                        cx,
+                        move |window, cx| {
+                            let task = change_database(session, database.clone(), cx);
+                            window
+                                .spawn(cx, async move |cx| {
+                                    if let Err(error) = task.await {
+                                        let message: SharedString =
+                                            format!("Could not switch databases: {}", error).into();
+                                        let _ = cx.update(|window, cx| {
+                                            window.push_notification(
+                                                (NotificationType::Error, message),
+                                                cx,
+                                            );
+                                        });
+                                    }
+                                })
+                                .detach();
+                        },
+                        move |window, cx| {
+                            editor.update(cx, |editor, cx| {
+                                editor.reset_database_select(window, cx);
+                            });
+                        },
+                    );
                 }
             }
         }
     }
 
+    fn reset_database_select(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        if let Some(conn) = self.active_connection.clone() {
+            self.db_select.update(cx, |select, cx| {
+                select.set_selected_value(&conn.database.clone().into(), window, cx);
+            });
+        }
+    }
+
+    /// Runs `action` right away, or only after the user confirms when an
+    /// explicit transaction is open and would be rolled back.
+    pub(crate) fn confirm_if_in_transaction(
+        in_transaction: bool,
+        message: &'static str,
+        window: &mut Window,
+        cx: &mut App,
+        action: impl Fn(&mut Window, &mut App) + 'static,
+        on_cancel: impl Fn(&mut Window, &mut App) + 'static,
+    ) {
+        if !in_transaction {
+            action(window, cx);
+            return;
+        }
+
+        let action = Rc::new(action);
+        let on_cancel = Rc::new(on_cancel);
+        window.open_dialog(cx, move |dialog, _win, _cx| {
+            let action = action.clone();
+            let on_cancel = on_cancel.clone();
+            dialog
+                .confirm()
+                .child(message)
+                .on_ok(move |_, window, cx| {
+                    action(window, cx);
+                    true
+                })
+                .on_cancel(move |_, window, cx| {
+                    on_cancel(window, cx);
+                    true
+                })
+        });
+    }
+
     pub fn toggle_inline_completions(
         &mut self,
         _: &ClickEvent,
@@ -229,30 +361,308 @@ impl Editor {
         })
     }
 
-    pub fn execute_query(&mut self, _: &ClickEvent, _window: &mut Window, cx: &mut Context<Self>) {
+    /// The statement under the cursor, the only statement, or everything,
+    /// with its byte offset in the text
+    fn current_query(&mut self, cx: &App) -> (usize, String) {
+        let text = self.input_state.read(cx).value().to_string();
         let cursor = self.input_state.read(cx).cursor();
         self.current_query_index = self.find_query_at_cursor(cursor);
 
-        let query = if let Some(idx) = self.current_query_index {
+        let query = match self.current_query_index {
             // Execute just the current query
-            self.parsed_queries[idx].query_text.clone()
-        } else if self.parsed_queries.len() == 1 {
+            Some(idx) => self.parsed_queries.get(idx),
             // Only one query, run it
-            self.parsed_queries[0].query_text.clone()
-        } else {
-            // Fallback to full editor content
-            self.input_state.read(cx).value().to_string()
+            None if self.parsed_queries.len() == 1 => self.parsed_queries.first(),
+            None => None,
         };
+        match query {
+            Some(query) => (query_offset(&text, query), query.query_text.clone()),
+            // Fallback to full editor content
+            None => (0, text),
+        }
+    }
+
+    pub fn execute_query(&mut self, _: &ClickEvent, window: &mut Window, cx: &mut Context<Self>) {
+        let (offset, query) = self.current_query(cx);
+
+        if query.trim().is_empty() {
+            return;
+        }
+        self.executed = vec![(offset, query.clone())];
+
+        let names = parameterize(&query).names;
+        if names.is_empty() {
+            cx.emit(EditorEvent::ExecuteQuery {
+                sql: query,
+                params: vec![],
+                timeouts: self.timeout_overrides,
+            });
+        } else {
+            self.prompt_for_parameters(query, names, window, cx);
+        }
+    }
+
+    /// Asks for placeholder values, prefilled with the ones last used for
+    /// this query, then runs it
+    fn prompt_for_parameters(
+        &mut self,
+        query: String,
+        names: Vec<String>,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        cx.spawn_in(window, async move |this, cx| {
+            let saved = match AppStore::singleton().await {
+                Ok(store) => store.parameters().load(&query).await.unwrap_or_else(|e| {
+                    tracing::error!("Failed to load saved query parameters: {}", e);
+                    None
+                }),
+                Err(e) => {
+                    tracing::error!("Failed to open app store: {}", e);
+                    None
+                }
+            };
+
+            this.update_in(cx, |_this, window, cx| {
+                let form = ParametersForm::view(names, saved.unwrap_or_default(), window, cx);
+                let editor = cx.entity();
+
+                window.open_dialog(cx, move |dialog, _win, _cx| {
+                    let form = form.clone();
+                    let editor = editor.clone();
+                    let query = query.clone();
+
+                    dialog
+                        .title("Query parameters")
+                        .confirm()
+                        .child(form.clone())
+                        .on_ok(move |_, _window, cx| {
+                            let params = form.read(cx).params(cx);
+                            editor.update(cx, |editor, cx| {
+                                editor.run_with_params(query.clone(), params, cx);
+                            });
+                            true
+                        })
+                });
+            })
+            .ok();
+        })
+        .detach();
+    }
+
+    fn run_with_params(&mut self, query: String, params: Vec<QueryParam>, cx: &mut Context<Self>) {
+        let sql = query.clone();
+        let saved_params = params.clone();
+        cx.background_spawn(async move {
+            if let Ok(store) = AppStore::singleton().await
+                && let Err(e) = store.parameters().save(&sql, &saved_params).await
+            {
+                tracing::error!("Failed to save query parameters: {}", e);
+            }
+        })
+        .detach();
+
+        cx.emit(EditorEvent::ExecuteQuery {
+            sql: query,
+            params,
+            timeouts: self.timeout_overrides,
+        });
+    }
+
+    pub fn execute_script(&mut self, _: &ClickEvent, _window: &mut Window, cx: &mut Context<Self>) {
+        self.reparse_queries(cx);
+
+        let text = self.input_state.read(cx).value().to_string();
+        self.executed = self
+            .parsed_queries
+            .iter()
+            .filter(|q| !q.query_text.trim().is_empty())
+            .map(|q| (query_offset(&text, q), q.query_text.clone()))
+            .collect();
+        let statements: Vec<String> = self.executed.iter().map(|(_, sql)| sql.clone()).collect();
+
+        if !statements.is_empty() {
+            cx.emit(EditorEvent::ExecuteScript {
+                statements,
+                stop_on_error: self.stop_script_on_error,
+                timeouts: self.timeout_overrides,
+            });
+        }
+    }
+
+    fn toggle_stop_on_error(
+        &mut self,
+        _: &ClickEvent,
+        _window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        self.stop_script_on_error = !self.stop_script_on_error;
+        cx.notify();
+    }
+
+    pub fn timeout_overrides(&self) -> QueryTimeouts {
+        self.timeout_overrides
+    }
+
+    /// Opens the per-run timeout overrides
+    fn edit_timeouts(&mut self, _: &ClickEvent, window: &mut Window, cx: &mut Context<Self>) {
+        let defaults = self
+            .active_connection
+            .as_ref()
+            .map(|conn| conn.timeouts)
+            .unwrap_or_default();
+        let form = TimeoutsForm::view(self.timeout_overrides, defaults, window, cx);
+        let editor = cx.entity();
+
+        window.open_dialog(cx, move |dialog, _win, _cx| {
+            let form = form.clone();
+            let editor = editor.clone();
+
+            dialog
+                .title("Timeouts for the next runs")
+                .confirm()
+                .child(form.clone())
+                .on_ok(move |_, window, cx| match form.read(cx).timeouts(cx) {
+                    Ok(timeouts) => {
+                        editor.update(cx, |editor, cx| {
+                            editor.timeout_overrides = timeouts;
+                            cx.notify();
+                        });
+                        true
+                    }
+                    Err(message) => {
+                        window.push_notification((NotificationType::Error, message), cx);
+                        false
+                    }
+                })
+        });
+    }
 
-        if !query.trim().is_empty() {
-            cx.emit(EditorEvent::ExecuteQuery(query));
+    /// Adds the current query to the saved queries library
+    fn save_query(&mut self, _: &ClickEvent, window: &mut Window, cx: &mut Context<Self>) {
+        let (_, sql) = self.current_query(cx);
+        if sql.trim().is_empty() {
+            return;
+        }
+
+        let connection = self
+            .active_connection
+            .as_ref()
+            .map(|conn| (conn.id, conn.name.clone()));
+        let form = SavedQueryForm::view(connection, window, cx);
+
+        window.open_dialog(cx, move |dialog, _win, _cx| {
+            let form = form.clone();
+            let sql = sql.clone();
+
+            dialog
+                .title("Save query")
+                .confirm()
+                .child(form.clone())
+                .on_ok(move |_, window, cx| match form.read(cx).query(&sql, cx) {
+                    Ok(query) => {
+                        save_queries(vec![query], cx);
+                        true
+                    }
+                    Err(message) => {
+                        window.push_notification((NotificationType::Error, message), cx);
+                        false
+                    }
+                })
+        });
+    }
+
+    pub fn cancel_query(&mut self, _: &ClickEvent, _window: &mut Window, cx: &mut Context<Self>) {
+        if self.is_executing {
+            cx.emit(EditorEvent::CancelQuery);
         }
     }
 
     pub fn set_executing(&mut self, executing: bool, cx: &mut Context<Self>) {
         self.is_executing = executing;
+        if executing {
+            // Errors from the previous run no longer apply
+            self.input_state.update(cx, |input, cx| {
+                if let Some(diagnostics) = input.diagnostics_mut() {
+                    diagnostics.clear();
+                }
+                cx.notify();
+            });
+        }
         cx.notify();
     }
+
+    /// Underlines the token the error of the last run's `statement`th
+    /// statement points at, if the statement is still where it ran from
+    pub fn show_error_position(
+        &mut self,
+        statement: usize,
+        details: &DatabaseErrorDetails,
+        cx: &mut Context<Self>,
+    ) {
+        let Some((base, sql)) = self
+            .executed
+            .get(statement)
+            .map(|(base, sql)| (*base, sql.as_str()))
+        else {
+            return;
+        };
+        let Some(offset) = details
+            .position
+            .and_then(|position| sql.char_indices().nth(position.checked_sub(1)?))
+            .map(|(ix, _)| ix)
+        else {
+            return;
+        };
+
+        // Underline the whole token, or a single character between tokens
+        let (start, end) = tokenize(sql)
+            .into_iter()
+            .find(|token| token.start <= offset && offset < token.end())
+            .map_or_else(
+                || {
+                    (
+                        offset,
+                        offset + sql[offset..].chars().next().map_or(0, char::len_utf8),
+                    )
+                },
+                |token| (token.start, token.end()),
+            );
+
+        self.input_state.update(cx, |input, cx| {
+            let text = input.value().to_string();
+            if text.get(base..base + sql.len()) != Some(sql) {
+                return;
+            }
+
+            let range = text_position(&text, base + start)..text_position(&text, base + end);
+            if let Some(diagnostics) = input.diagnostics_mut() {
+                diagnostics.push(
+                    Diagnostic::new(range, details.message.clone())
+                        .with_severity(DiagnosticSeverity::Error),
+                );
+            }
+            cx.notify();
+        });
+    }
+}
+
+/// Line and column of a byte offset
+/// Where a detected statement's trimmed text starts
+fn query_offset(text: &str, query: &SqlQuery) -> usize {
+    text.get(query.start_byte..)
+        .map_or(query.start_byte, |rest| {
+            query.start_byte + rest.len() - rest.trim_start().len()
+        })
+}
+
+fn text_position(text: &str, offset: usize) -> Position {
+    let before = &text[..offset];
+    let line_start = before.rfind('\n').map_or(0, |ix| ix + 1);
+    Position::new(
+        before.matches('\n').count() as u32,
+        before[line_start..].chars().count() as u32,
+    )
 }
 
 impl Render for Editor {
@@ -262,26 +672,89 @@ impl Render for Editor {
         let show_ai_loading =
             self.code_actions_loading.clone() || self.inline_completions_loading.clone();
 
+        let session = self.session;
+        let in_transaction = self.in_transaction;
         let disconnect_button = Button::new("disconnect_button")
             .icon(Icon::empty().path("icons/power.svg"))
             .small()
             .danger()
             .ghost()
             .tooltip("Disconnect")
-            .on_click(|_evt, _win, cx| disconnect(cx));
-
-        let execute_button = Button::new("execute-query")
-            .tooltip(if self.is_executing {
-                "Executing..."
+            .on_click(move |_evt, win, cx| {
+                Self::confirm_if_in_transaction(
+                    in_transaction,
+                    "A transaction is open. Disconnecting will roll it back.",
+                    win,
+                    cx,
+                    move |_window, cx| disconnect(session, cx),
+                    |_window, _cx| {},
+                );
+            });
+
+        let transaction_button = Button::new("begin-transaction")
+            .tooltip(if self.in_transaction {
+                "Transaction open"
             } else {
-                "Execute"
+                "Begin transaction"
             })
-            .icon(Icon::empty().path("icons/play.svg"))
+            .icon(Icon::empty().path("icons/database-zap.svg"))
+            .small()
+            .primary()
+            .ghost()
+            .selected(self.in_transaction)
+            .disabled(self.in_transaction || self.is_executing)
+            .on_click(move |_evt, _win, cx| begin_transaction(session, cx));
+
+        let execute_button = if self.is_executing {
+            Button::new("cancel-query")
+                .tooltip("Cancel query")
+                .icon(Icon::empty().path("icons/circle-x.svg"))
+                .small()
+                .danger()
+                .ghost()
+                .on_click(cx.listener(Self::cancel_query))
+        } else {
+            Button::new("execute-query")
+                .tooltip("Execute")
+                .icon(Icon::empty().path("icons/play.svg"))
+                .small()
+                .primary()
+                .ghost()
+                .on_click(cx.listener(Self::execute_query))
+        };
+
+        let script_button = Button::new("execute-script")
+            .tooltip("Run script")
+            .icon(Icon::empty().path("icons/gallery-vertical-end.svg"))
             .small()
             .primary()
             .ghost()
             .disabled(self.is_executing)
-            .on_click(cx.listener(Self::execute_query));
+            .on_click(cx.listener(Self::execute_script));
+
+        let stop_on_error_button = Button::new("script-stop-on-error")
+            .tooltip(if self.stop_script_on_error {
+                "Script stops on error"
+            } else {
+                "Script continues on error"
+            })
+            .icon(Icon::empty().path("icons/triangle-alert.svg"))
+            .small()
+            .ghost()
+            .selected(self.stop_script_on_error)
+            .on_click(cx.listener(Self::toggle_stop_on_error));
+
+        let timeouts_button = Button::new("query-timeouts")
+            .tooltip(if self.timeout_overrides.is_empty() {
+                "Timeouts"
+            } else {
+                "Timeouts (overridden)"
+            })
+            .icon(Icon::empty().path("icons/timer.svg"))
+            .small()
+            .ghost()
+            .selected(!self.timeout_overrides.is_empty())
+            .on_click(cx.listener(Self::edit_timeouts));
 
         let format_button = Button::new("execute-format")
             .tooltip(if self.is_formatting {
@@ -296,6 +769,13 @@ impl Render for Editor {
             .disabled(self.is_formatting)
             .on_click(cx.listener(Self::format_query));
 
+        let save_button = Button::new("save-query")
+            .tooltip("Save query")
+            .icon(Icon::empty().path("icons/star.svg"))
+            .small()
+            .ghost()
+            .on_click(cx.listener(Self::save_query));
+
         let inline_completions_button = Button::new("inline-completions")
             .tooltip("Toggle inline assist")
             .icon(Icon::empty().path("icons/sparkles.svg"))
@@ -333,6 +813,11 @@ impl Render for Editor {
                     .items_center()
                     .child(inline_completions_button)
                     .child(format_button)
+                    .child(save_button)
+                    .child(transaction_button)
+                    .child(stop_on_error_button)
+                    .child(timeouts_button)
+                    .child(script_button)
                     .child(execute_button)
                     .child(Divider::vertical())
                     .child(disconnect_button),
@@ -2,17 +2,26 @@ use gpui::prelude::FluentBuilder as _;
 use gpui::*;
 use gpui_component::button::{Button, ButtonVariants as _};
 use gpui_component::label::Label;
-use gpui_component::{ActiveTheme, Icon, IconName, Selectable as _, Sizable as _};
+use gpui_component::{
+    ActiveTheme, Disableable as _, Icon, IconName, Selectable as _, Sizable as _,
+};
 
-use crate::services::ConnectionInfo;
-use crate::state::{ConnectionState, ConnectionStatus};
+use crate::services::{ConnectionInfo, TransactionStatus};
+use crate::state::{
+    ConnectionState, ConnectionStatus, SessionId, commit_transaction, rollback_transaction,
+};
 
 pub struct FooterBar {
+    /// Session of the tab in front
+    active_session: Option<SessionId>,
     active_connection: Option<ConnectionInfo>,
     tables_active: bool,
     agent_active: bool,
     history_active: bool,
     is_connected: bool,
+    /// Attempt and last error while a dropped connection is retried
+    reconnecting: Option<(u32, String)>,
+    transaction_status: TransactionStatus,
     _subscriptions: Vec<Subscription>,
 }
 
@@ -27,18 +36,32 @@ impl EventEmitter<FooterBarEvent> for FooterBar {}
 impl FooterBar {
     pub fn new(_window: &mut Window, cx: &mut Context<Self>) -> Self {
         let _subscriptions = vec![cx.observe_global::<ConnectionState>(move |this, cx| {
-            let state = cx.global::<ConnectionState>();
-            this.is_connected = state.connection_state.clone() == ConnectionStatus::Connected;
-            this.active_connection = state.active_connection.clone();
+            let active = cx.global::<ConnectionState>().active();
+            this.active_session = active.map(|session| session.id);
+            this.is_connected =
+                active.is_some_and(|session| session.status == ConnectionStatus::Connected);
+            this.reconnecting = active.and_then(|session| match &session.status {
+                ConnectionStatus::Reconnecting { attempt, error } => {
+                    Some((*attempt, error.clone()))
+                }
+                _ => None,
+            });
+            this.active_connection = active.map(|session| session.connection.clone());
+            this.transaction_status = active
+                .map(|session| session.transaction_status)
+                .unwrap_or(TransactionStatus::Idle);
             cx.notify();
         })];
 
         Self {
+            active_session: None,
             active_connection: None,
             tables_active: true,
             agent_active: false,
             history_active: false,
             is_connected: false,
+            reconnecting: None,
+            transaction_status: TransactionStatus::Idle,
             _subscriptions,
         }
     }
@@ -47,6 +70,80 @@ impl FooterBar {
     }
 }
 
+impl FooterBar {
+    fn render_transaction_controls(&self, cx: &mut Context<Self>) -> impl IntoElement {
+        let (label, color) = match self.transaction_status {
+            TransactionStatus::Failed => ("Failed transaction", cx.theme().danger),
+            _ => ("In transaction", cx.theme().warning),
+        };
+        let session = self.active_session;
+
+        div()
+            .flex()
+            .flex_row()
+            .items_center()
+            .gap_1()
+            .child(
+                Icon::new(IconName::TriangleAlert)
+                    .xsmall()
+                    .text_color(color),
+            )
+            .child(Label::new(label).text_xs().text_color(color))
+            .child(
+                Button::new("commit-transaction")
+                    .label("Commit")
+                    .xsmall()
+                    .ghost()
+                    .disabled(self.transaction_status == TransactionStatus::Failed)
+                    .tooltip("Commit transaction")
+                    .on_click(move |_evt, _win, cx| {
+                        if let Some(session) = session {
+                            commit_transaction(session, cx);
+                        }
+                    }),
+            )
+            .child(
+                Button::new("rollback-transaction")
+                    .label("Rollback")
+                    .xsmall()
+                    .ghost()
+                    .tooltip("Roll back transaction")
+                    .on_click(move |_evt, _win, cx| {
+                        if let Some(session) = session {
+                            rollback_transaction(session, cx);
+                        }
+                    }),
+            )
+    }
+}
+
+impl FooterBar {
+    fn render_reconnecting(&self, attempt: u32, error: String, cx: &Context<Self>) -> Div {
+        div()
+            .flex()
+            .flex_row()
+            .items_center()
+            .gap_1()
+            .overflow_hidden()
+            .child(
+                Icon::empty()
+                    .path("icons/power.svg")
+                    .xsmall()
+                    .text_color(cx.theme().warning),
+            )
+            .child(
+                Label::new(format!("Reconnecting, attempt {}", attempt))
+                    .text_xs()
+                    .text_color(cx.theme().warning),
+            )
+            .child(
+                Label::new(error)
+                    .text_xs()
+                    .text_color(cx.theme().muted_foreground),
+            )
+    }
+}
+
 impl Render for FooterBar {
     fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
         let tables_button = Button::new("tables_button")
@@ -101,6 +198,12 @@ impl Render for FooterBar {
                 cx.notify();
             }));
 
+        let stripe = self
+            .active_connection
+            .as_ref()
+            .and_then(|connection| connection.stripe_color())
+            .map(|color| color.hsla());
+
         let connection_url = self
             .active_connection
             .clone()
@@ -152,6 +255,8 @@ impl Render for FooterBar {
             .text_xs()
             .bg(cx.theme().title_bar)
             .border_color(cx.theme().border)
+            // The connection's color, so the server in use is always in view
+            .when_some(stripe, |d, color| d.border_t_2().border_color(color))
             .w_full()
             .py_1()
             .px_2()
@@ -160,6 +265,13 @@ impl Render for FooterBar {
             .justify_between()
             .items_center()
             .child(left_controls)
+            .when(
+                self.is_connected && self.transaction_status != TransactionStatus::Idle,
+                |d| d.child(self.render_transaction_controls(cx)),
+            )
+            .when_some(self.reconnecting.clone(), |d, (attempt, error)| {
+                d.child(self.render_reconnecting(attempt, error, cx))
+            })
             .child(right_controls);
 
         footer
@@ -5,21 +5,29 @@ use gpui_component::{
     button::{Button, ButtonVariants as _},
     h_flex,
     label::Label,
+    v_flex,
 };
 
 use crate::{
     services::{check_for_update, updates::UpdateInfo},
+    state::ConnectionState,
     themes::*,
+    workspace::connections::{environment_badge, read_only_badge},
 };
 
 pub struct HeaderBar {
     update_available: Option<UpdateInfo>,
+    _subscriptions: Vec<Subscription>,
 }
 
 impl HeaderBar {
     pub fn new(_window: &mut Window, cx: &mut Context<Self>) -> Self {
+        // The badges and color stripe follow the tab in front
+        let _subscriptions = vec![cx.observe_global::<ConnectionState>(|_, cx| cx.notify())];
+
         let this = Self {
             update_available: None,
+            _subscriptions,
         };
 
         // Check for updates on startup
@@ -97,12 +105,30 @@ impl Render for HeaderBar {
                 .on_click(cx.listener(Self::open_release_page))
         });
 
-        TitleBar::new().child(
+        let active = cx
+            .global::<ConnectionState>()
+            .active()
+            .map(|session| &session.connection);
+        let read_only = active.is_some_and(|connection| connection.read_only);
+        let environment = active.and_then(|connection| connection.environment);
+        let stripe = active
+            .and_then(|connection| connection.stripe_color())
+            .map(|color| color.hsla());
+
+        let title_bar = TitleBar::new().child(
             h_flex()
                 .w_full()
                 .pr_2()
                 .justify_between()
-                .child(Label::new("PGUI").text_xs())
+                .child(
+                    h_flex()
+                        .gap_2()
+                        .child(Label::new("PGUI").text_xs())
+                        .when_some(environment.zip(stripe), |d, (environment, color)| {
+                            d.child(environment_badge(environment, color, cx))
+                        })
+                        .when(read_only, |d| d.child(read_only_badge(cx))),
+                )
                 .child(
                     div()
                         .pr(px(5.0))
@@ -114,6 +140,13 @@ impl Render for HeaderBar {
                         .child(theme_toggle)
                         .child(github_button),
                 ),
-        )
+        );
+
+        v_flex()
+            .w_full()
+            .child(title_bar)
+            .when_some(stripe, |d, color| {
+                d.child(div().w_full().h(px(2.)).bg(color))
+            })
     }
 }
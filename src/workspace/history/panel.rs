@@ -1,134 +1,209 @@
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Days, Local, NaiveDate, TimeZone as _, Utc};
 use gpui::{
     AnyElement, App, AppContext, ClickEvent, Context, Entity, EventEmitter,
     InteractiveElement as _, IntoElement, ListAlignment, ListState, ParentElement, Render,
-    StatefulInteractiveElement as _, Styled, Subscription, Window, div, list,
+    StatefulInteractiveElement as _, Styled, Subscription, Task, Window, div, list,
     prelude::FluentBuilder as _, px,
 };
 use gpui_component::{
-    ActiveTheme as _, Disableable, Icon, IconName, Sizable as _, StyledExt as _,
+    ActiveTheme as _, Disableable, Icon, IconName, Selectable as _, Sizable as _, StyledExt as _,
     button::{Button, ButtonVariants as _},
     h_flex,
+    input::{Input, InputEvent, InputState},
     label::Label,
     v_flex,
 };
+use uuid::Uuid;
 
 use crate::{
-    services::{AppStore, ConnectionInfo, storage::QueryHistoryEntry},
-    state::ConnectionState,
+    services::{
+        AppStore,
+        storage::{HistoryFilter, QueryHistoryEntry},
+    },
+    state::{ConnectionState, SessionId},
 };
 
+/// Entries shown per page
+const PAGE_SIZE: u32 = 50;
+
 /// Event emitted when a history entry is selected
 pub enum HistoryEvent {
     /// User wants to load this SQL into the editor
     LoadQuery(String),
+    /// User wants to run this SQL again in the active query tab
+    RunQuery(String),
 }
 
 impl EventEmitter<HistoryEvent> for HistoryPanel {}
 
 pub struct HistoryPanel {
+    session: SessionId,
     list_state: ListState,
-    history_entries: Vec<QueryHistoryEntry>,
-    filtered_entries: Vec<QueryHistoryEntry>,
-    active_connection: Option<ConnectionInfo>,
+    /// The current page of matching entries
+    entries: Vec<QueryHistoryEntry>,
+    /// Matching entries across all pages
+    total: i64,
+    page: u32,
+    connection_id: Option<Uuid>,
+    search: Entity<InputState>,
+    executed_after: Entity<InputState>,
+    executed_before: Entity<InputState>,
+    min_duration: Entity<InputState>,
+    /// Only successful (`true`) or only failed (`false`) runs
+    success: Option<bool>,
+    all_connections: bool,
+    /// Why the filter can't be applied, e.g. a malformed date
+    filter_error: Option<String>,
     is_loading: bool,
+    /// Running search; replacing it drops results of an outdated filter
+    search_task: Option<Task<()>>,
     _subscriptions: Vec<Subscription>,
 }
 
 #[allow(dead_code)]
 impl HistoryPanel {
-    pub fn new(_window: &mut Window, cx: &mut Context<Self>) -> Self {
+    pub fn new(session: SessionId, window: &mut Window, cx: &mut Context<Self>) -> Self {
         let list_state = ListState::new(0, ListAlignment::Top, px(20.));
+        let input = |placeholder: &'static str, window: &mut Window, cx: &mut Context<Self>| {
+            cx.new(|cx| {
+                InputState::new(window, cx)
+                    .placeholder(placeholder)
+                    .clean_on_escape()
+            })
+        };
+        let search = input("Search SQL", window, cx);
+        let executed_after = input("From YYYY-MM-DD", window, cx);
+        let executed_before = input("To YYYY-MM-DD", window, cx);
+        let min_duration = input("Min ms", window, cx);
 
-        let _subscriptions = vec![cx.observe_global::<ConnectionState>(move |this, cx| {
+        let mut _subscriptions = vec![cx.observe_global::<ConnectionState>(move |this, cx| {
             let state = cx.global::<ConnectionState>();
-            let new_connection = state.active_connection.clone();
+            let Some(session) = state.session(this.session) else {
+                return;
+            };
+            let connection_id = Some(session.connection.id);
 
             // Only reload if connection changed
-            if this.active_connection.as_ref().map(|c| &c.id)
-                != new_connection.as_ref().map(|c| &c.id)
-            {
-                this.active_connection = new_connection;
-                this.load_history(cx);
+            if this.connection_id != connection_id {
+                this.connection_id = connection_id;
+                this.apply_filter(cx);
             }
             cx.notify();
         })];
+        for input in [&search, &executed_after, &executed_before, &min_duration] {
+            _subscriptions.push(cx.subscribe(input, |this, _, _: &InputEvent, cx| {
+                this.apply_filter(cx);
+            }));
+        }
 
         Self {
+            session,
             list_state,
-            history_entries: Vec::new(),
-            filtered_entries: Vec::new(),
-            active_connection: None,
+            entries: Vec::new(),
+            total: 0,
+            page: 0,
+            connection_id: None,
+            search,
+            executed_after,
+            executed_before,
+            min_duration,
+            success: None,
+            all_connections: false,
+            filter_error: None,
             is_loading: false,
+            search_task: None,
             _subscriptions,
         }
     }
 
-    pub fn view(window: &mut Window, cx: &mut App) -> Entity<Self> {
-        cx.new(|cx| Self::new(window, cx))
+    pub fn view(session: SessionId, window: &mut Window, cx: &mut App) -> Entity<Self> {
+        cx.new(|cx| Self::new(session, window, cx))
     }
 
-    fn filter_entries(&mut self, search_text: &str) {
-        if search_text.is_empty() {
-            self.filtered_entries = self.history_entries.clone();
+    /// The filter entered in the panel, or a message naming the bad field
+    fn filter(&self, cx: &App) -> Result<HistoryFilter, String> {
+        let value = |input: &Entity<InputState>| input.read(cx).value().trim().to_string();
+
+        let min_duration = value(&self.min_duration);
+        let min_execution_time_ms = if min_duration.is_empty() {
+            None
         } else {
-            self.filtered_entries = self
-                .history_entries
-                .iter()
-                .filter(|entry| entry.sql.to_lowercase().contains(search_text))
-                .cloned()
-                .collect();
-        }
+            Some(
+                min_duration
+                    .parse::<i64>()
+                    .map_err(|_| "Min duration must be a number of milliseconds.".to_string())?,
+            )
+        };
+
+        Ok(HistoryFilter {
+            connection_id: if self.all_connections {
+                None
+            } else {
+                self.connection_id
+            },
+            text: value(&self.search),
+            success: self.success,
+            executed_after: parse_day(&value(&self.executed_after), "From", 0)?,
+            // The "to" day is included
+            executed_before: parse_day(&value(&self.executed_before), "To", 1)?,
+            min_execution_time_ms,
+        })
+    }
+
+    /// Searches again from the first page
+    fn apply_filter(&mut self, cx: &mut Context<Self>) {
+        self.page = 0;
+        self.load_history(cx);
     }
 
     fn load_history(&mut self, cx: &mut Context<Self>) {
-        let Some(connection) = self.active_connection.clone() else {
-            self.history_entries.clear();
-            self.filtered_entries.clear();
+        if self.connection_id.is_none() {
+            self.entries.clear();
+            self.total = 0;
             self.list_state = ListState::new(0, ListAlignment::Top, px(20.));
             cx.notify();
             return;
-        };
+        }
 
+        let filter = match self.filter(cx) {
+            Ok(filter) => filter,
+            Err(message) => {
+                self.filter_error = Some(message);
+                cx.notify();
+                return;
+            }
+        };
+        self.filter_error = None;
         self.is_loading = true;
         cx.notify();
 
-        let connection_id = connection.id;
+        let offset = self.page * PAGE_SIZE;
 
-        cx.spawn(async move |this, cx| {
+        self.search_task = Some(cx.spawn(async move |this, cx| {
             let result = async {
                 let store = AppStore::singleton().await?;
-                store
-                    .history()
-                    .load_for_connection(&connection_id, 100)
-                    .await
+                store.history().search(&filter, PAGE_SIZE, offset).await
             }
             .await;
 
             this.update(cx, |this, cx| {
                 this.is_loading = false;
                 match result {
-                    Ok(entries) => {
-                        this.history_entries = entries;
-                        this.filter_entries("");
-                        this.list_state = ListState::new(
-                            this.filtered_entries.len(),
-                            ListAlignment::Top,
-                            px(20.),
-                        );
+                    Ok(page) => {
+                        this.entries = page.entries;
+                        this.total = page.total;
                     }
                     Err(e) => {
                         tracing::error!("Failed to load history: {}", e);
-                        this.history_entries.clear();
-                        this.filtered_entries.clear();
-                        this.list_state = ListState::new(0, ListAlignment::Top, px(20.));
+                        this.entries.clear();
+                        this.total = 0;
                     }
                 }
+                this.list_state = ListState::new(this.entries.len(), ListAlignment::Top, px(20.));
                 cx.notify();
             })
             .ok();
-        })
-        .detach();
+        }));
     }
 
     /// Reload history - can be called after executing a query
@@ -140,13 +215,31 @@ impl HistoryPanel {
         self.load_history(cx);
     }
 
+    fn set_success_filter(&mut self, success: Option<bool>, cx: &mut Context<Self>) {
+        self.success = success;
+        self.apply_filter(cx);
+    }
+
+    fn toggle_all_connections(
+        &mut self,
+        _: &ClickEvent,
+        _window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        self.all_connections = !self.all_connections;
+        self.apply_filter(cx);
+    }
+
+    fn show_page(&mut self, page: u32, cx: &mut Context<Self>) {
+        self.page = page;
+        self.load_history(cx);
+    }
+
     fn on_clear_history(&mut self, _: &ClickEvent, _window: &mut Window, cx: &mut Context<Self>) {
-        let Some(connection) = self.active_connection.clone() else {
+        let Some(connection_id) = self.connection_id else {
             return;
         };
 
-        let connection_id = connection.id;
-
         cx.spawn(async move |this, cx| {
             let result = async {
                 let store = AppStore::singleton().await?;
@@ -156,9 +249,7 @@ impl HistoryPanel {
 
             this.update(cx, |this, cx| {
                 if result.is_ok() {
-                    this.history_entries.clear();
-                    this.filtered_entries.clear();
-                    this.list_state = ListState::new(0, ListAlignment::Top, px(20.));
+                    this.apply_filter(cx);
                 }
                 cx.notify();
             })
@@ -207,31 +298,84 @@ impl HistoryPanel {
         _window: &mut Window,
         cx: &mut Context<Self>,
     ) -> AnyElement {
-        let Some(entry) = self.filtered_entries.get(ix).cloned() else {
+        let Some(entry) = self.entries.get(ix).cloned() else {
             return div().into_any_element();
         };
 
         let sql = entry.sql.clone();
+        let (rerun_sql, open_sql) = (sql.clone(), sql.clone());
+        let failed = !entry.success && !entry.is_cancelled();
         let truncated_sql = Self::truncate_sql(&sql, 40);
         let relative_time = Self::format_relative_time(entry.executed_at);
+        let connection_name = self
+            .all_connections
+            .then(|| {
+                cx.global::<ConnectionState>()
+                    .saved_connections
+                    .iter()
+                    .find(|conn| conn.id == entry.connection_id)
+                    .map(|conn| conn.name.clone())
+            })
+            .flatten();
 
-        let execution_info = if let Some(rows) = entry.rows_affected {
-            format!("{}ms • {} rows", entry.execution_time_ms, rows)
-        } else {
-            format!("{}ms", entry.execution_time_ms)
-        };
+        let mut execution_info = format!("{}ms", entry.execution_time_ms);
+        if let Some(rows) = entry.rows_returned {
+            execution_info.push_str(&format!(" • {} rows returned", rows));
+        } else if let Some(rows) = entry.rows_affected {
+            execution_info.push_str(&format!(" • {} rows", rows));
+        }
+        if let Some(database) = &entry.database {
+            execution_info.push_str(&format!(" • {}", database));
+        }
+        if !entry.notices.is_empty() {
+            execution_info.push_str(&format!(" • {} messages", entry.notices.len()));
+        }
 
         let status_icon = if entry.success {
             Icon::new(IconName::CircleCheck).text_color(cx.theme().success)
+        } else if entry.is_cancelled() {
+            Icon::new(IconName::CircleX).text_color(cx.theme().warning)
         } else {
             Icon::new(IconName::CircleX).text_color(cx.theme().danger)
         };
 
-        let bg_color = if ix % 2 == 0 {
+        let bg_color = if failed {
+            cx.theme().danger.opacity(0.08)
+        } else if ix % 2 == 0 {
             cx.theme().list
         } else {
             cx.theme().list_even
         };
+        let border_color = if failed {
+            cx.theme().danger.opacity(0.5)
+        } else {
+            cx.theme().border
+        };
+
+        let actions = h_flex()
+            .gap_1()
+            .child(
+                Button::new(("history-rerun", ix))
+                    .icon(Icon::empty().path("icons/play.svg"))
+                    .xsmall()
+                    .ghost()
+                    .tooltip("Re-run")
+                    .on_click(cx.listener(move |_this, _, _window, cx| {
+                        cx.stop_propagation();
+                        cx.emit(HistoryEvent::RunQuery(rerun_sql.clone()));
+                    })),
+            )
+            .child(
+                Button::new(("history-open", ix))
+                    .icon(Icon::empty().path("icons/pencil-line.svg"))
+                    .xsmall()
+                    .ghost()
+                    .tooltip("Open in editor")
+                    .on_click(cx.listener(move |_this, _, _window, cx| {
+                        cx.stop_propagation();
+                        cx.emit(HistoryEvent::LoadQuery(open_sql.clone()));
+                    })),
+            );
 
         div()
             .p_1()
@@ -242,7 +386,7 @@ impl HistoryPanel {
                     .p_2()
                     .bg(bg_color)
                     .border_1()
-                    .border_color(cx.theme().border)
+                    .border_color(border_color)
                     .rounded(cx.theme().radius)
                     .cursor_pointer()
                     .hover(|s| {
@@ -261,11 +405,14 @@ impl HistoryPanel {
                                     .items_center()
                                     .child(status_icon.size_4())
                                     .child(
-                                        Label::new(truncated_sql)
-                                            .text_sm()
-                                            .font_medium()
-                                            .line_height(px(18.)),
-                                    ),
+                                        div().flex_1().overflow_hidden().child(
+                                            Label::new(truncated_sql)
+                                                .text_sm()
+                                                .font_medium()
+                                                .line_height(px(18.)),
+                                        ),
+                                    )
+                                    .child(actions),
                             )
                             .child(
                                 h_flex()
@@ -285,24 +432,58 @@ impl HistoryPanel {
                                         Label::new(relative_time)
                                             .text_xs()
                                             .text_color(cx.theme().muted_foreground),
-                                    ),
+                                    )
+                                    .when_some(connection_name, |el, name| {
+                                        el.child(
+                                            Label::new(format!("• {}", name))
+                                                .text_xs()
+                                                .text_color(cx.theme().muted_foreground),
+                                        )
+                                    }),
                             )
-                            .when(!entry.success && entry.error_message.is_some(), |el| {
+                            .when(entry.is_cancelled(), |el| {
                                 el.child(
                                     h_flex().pl(px(24.)).child(
-                                        Label::new(
-                                            entry
-                                                .error_message
-                                                .clone()
-                                                .unwrap_or_default()
-                                                .chars()
-                                                .take(50)
-                                                .collect::<String>(),
-                                        )
-                                        .text_xs()
-                                        .text_color(cx.theme().danger),
+                                        Label::new("Cancelled")
+                                            .text_xs()
+                                            .italic()
+                                            .text_color(cx.theme().warning),
                                     ),
                                 )
+                            })
+                            .when(failed && entry.error_message.is_some(), |el| {
+                                el.child(
+                                    h_flex()
+                                        .gap_1()
+                                        .pl(px(24.))
+                                        .when_some(entry.sql_state.clone(), |el, code| {
+                                            el.child(
+                                                div()
+                                                    .px_1()
+                                                    .rounded(cx.theme().radius)
+                                                    .bg(cx.theme().danger.opacity(0.15))
+                                                    .text_xs()
+                                                    .text_color(cx.theme().danger)
+                                                    .child(code),
+                                            )
+                                        })
+                                        .child(
+                                            Label::new(
+                                                entry
+                                                    .error_message
+                                                    .clone()
+                                                    .unwrap_or_default()
+                                                    .lines()
+                                                    .next()
+                                                    .unwrap_or_default()
+                                                    .chars()
+                                                    .take(80)
+                                                    .collect::<String>(),
+                                            )
+                                            .text_xs()
+                                            .text_color(cx.theme().danger),
+                                        ),
+                                )
                             }),
                     ),
             )
@@ -310,10 +491,28 @@ impl HistoryPanel {
     }
 }
 
+/// Start of a local calendar day given as `YYYY-MM-DD`, `days` later;
+/// empty means no bound
+fn parse_day(value: &str, label: &str, days: u64) -> Result<Option<DateTime<Utc>>, String> {
+    if value.is_empty() {
+        return Ok(None);
+    }
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .ok()
+        .and_then(|date| date.checked_add_days(Days::new(days)))
+        .and_then(|date| {
+            Local
+                .from_local_datetime(&date.and_hms_opt(0, 0, 0)?)
+                .earliest()
+        })
+        .map(|start| Some(start.with_timezone(&Utc)))
+        .ok_or_else(|| format!("{} must be a date like 2024-01-31.", label))
+}
+
 impl Render for HistoryPanel {
     fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
-        let has_connection = self.active_connection.is_some();
-        let entry_count = self.filtered_entries.len();
+        let has_connection = self.connection_id.is_some();
+        let entry_count = self.entries.len();
 
         let refresh_button = Button::new("refresh-history")
             .icon(Icon::empty().path("icons/rotate-ccw.svg"))
@@ -328,7 +527,7 @@ impl Render for HistoryPanel {
             .small()
             .ghost()
             .tooltip("Clear History")
-            .disabled(!has_connection || self.history_entries.is_empty())
+            .disabled(!has_connection || self.total == 0)
             .on_click(cx.listener(Self::on_clear_history));
 
         let header = h_flex()
@@ -337,13 +536,69 @@ impl Render for HistoryPanel {
             .child(Label::new("History").font_bold().text_base())
             .child(h_flex().gap_1().child(refresh_button).child(clear_button));
 
+        let outcome_button = |id: &'static str, label: &'static str, success: Option<bool>| {
+            Button::new(id)
+                .label(label)
+                .xsmall()
+                .ghost()
+                .selected(self.success == success)
+                .on_click(cx.listener(move |this, _, _window, cx| {
+                    this.set_success_filter(success, cx);
+                }))
+        };
+
+        let filters = v_flex()
+            .gap_1()
+            .child(Input::new(&self.search).small())
+            .child(
+                h_flex()
+                    .gap_1()
+                    .child(outcome_button("history-all", "All", None))
+                    .child(outcome_button("history-succeeded", "Succeeded", Some(true)))
+                    .child(outcome_button("history-failed", "Failed", Some(false)))
+                    .child(
+                        Button::new("history-all-connections")
+                            .label("All connections")
+                            .xsmall()
+                            .ghost()
+                            .selected(self.all_connections)
+                            .on_click(cx.listener(Self::toggle_all_connections)),
+                    ),
+            )
+            .child(
+                h_flex()
+                    .gap_1()
+                    .child(
+                        div()
+                            .flex_1()
+                            .child(Input::new(&self.executed_after).small()),
+                    )
+                    .child(
+                        div()
+                            .flex_1()
+                            .child(Input::new(&self.executed_before).small()),
+                    )
+                    .child(
+                        div()
+                            .w(px(80.))
+                            .child(Input::new(&self.min_duration).small()),
+                    ),
+            );
+
         let content = if !has_connection {
             div().flex_1().flex().items_center().justify_center().child(
                 Label::new("Connect to a database to see history")
                     .text_sm()
                     .text_color(cx.theme().muted_foreground),
             )
-        } else if self.is_loading {
+        } else if let Some(message) = self.filter_error.clone() {
+            div()
+                .flex_1()
+                .flex()
+                .items_center()
+                .justify_center()
+                .child(Label::new(message).text_sm().text_color(cx.theme().danger))
+        } else if self.is_loading && entry_count == 0 {
             div().flex_1().flex().items_center().justify_center().child(
                 Label::new("Loading...")
                     .text_sm()
@@ -361,13 +616,9 @@ impl Render for HistoryPanel {
                             .text_color(cx.theme().muted_foreground),
                     )
                     .child(
-                        Label::new(if self.history_entries.is_empty() {
-                            "No queries yet"
-                        } else {
-                            "No matching queries"
-                        })
-                        .text_sm()
-                        .text_color(cx.theme().muted_foreground),
+                        Label::new("No matching queries")
+                            .text_sm()
+                            .text_color(cx.theme().muted_foreground),
                     ),
             )
         } else {
@@ -380,21 +631,61 @@ impl Render for HistoryPanel {
             )
         };
 
+        let first = self.page as i64 * PAGE_SIZE as i64;
+        let has_previous = self.page > 0;
+        let has_next = first + (entry_count as i64) < self.total;
+        let page = self.page;
+
+        let pager = h_flex()
+            .justify_between()
+            .items_center()
+            .text_xs()
+            .text_color(cx.theme().muted_foreground)
+            .child(if entry_count == 0 {
+                format!("{} queries", self.total)
+            } else {
+                format!(
+                    "{}–{} of {} {}",
+                    first + 1,
+                    first + entry_count as i64,
+                    self.total,
+                    if self.total == 1 { "query" } else { "queries" }
+                )
+            })
+            .child(
+                h_flex()
+                    .gap_1()
+                    .child(
+                        Button::new("history-previous-page")
+                            .icon(IconName::ChevronLeft)
+                            .xsmall()
+                            .ghost()
+                            .tooltip("Newer")
+                            .disabled(!has_previous || self.is_loading)
+                            .on_click(cx.listener(move |this, _, _window, cx| {
+                                this.show_page(page.saturating_sub(1), cx);
+                            })),
+                    )
+                    .child(
+                        Button::new("history-next-page")
+                            .icon(IconName::ChevronRight)
+                            .xsmall()
+                            .ghost()
+                            .tooltip("Older")
+                            .disabled(!has_next || self.is_loading)
+                            .on_click(cx.listener(move |this, _, _window, cx| {
+                                this.show_page(page + 1, cx);
+                            })),
+                    ),
+            );
+
         v_flex()
             .size_full()
             .gap_2()
             .p_2()
             .child(header)
-            .child(
-                div()
-                    .text_xs()
-                    .text_color(cx.theme().muted_foreground)
-                    .child(format!(
-                        "{} {}",
-                        entry_count,
-                        if entry_count == 1 { "query" } else { "queries" }
-                    )),
-            )
+            .child(filters)
             .child(content)
+            .child(pager)
     }
 }
@@ -1,11 +1,17 @@
 mod agent;
+mod connection_tab;
 mod connections;
 mod editor;
 mod footer_bar;
 mod header_bar;
 mod history;
+mod parameters_form;
+mod query_tab;
 mod results;
+mod saved_queries;
+mod saved_query_form;
 mod tables;
+mod timeouts_form;
 mod workspace;
 
 pub use workspace::*;
@@ -0,0 +1,151 @@
+use gpui::*;
+use gpui_component::{
+    ActiveTheme as _, IndexPath, Selectable as _, Sizable as _,
+    button::{Button, ButtonVariants as _},
+    h_flex,
+    input::{Input, InputState},
+    select::{Select, SelectEvent, SelectState},
+    v_flex,
+};
+
+use crate::services::{ParamType, QueryParam};
+
+struct ParameterRow {
+    name: String,
+    value: Entity<InputState>,
+    type_select: Entity<SelectState<Vec<SharedString>>>,
+    param_type: ParamType,
+    is_null: bool,
+}
+
+/// Prompts for the values of a statement's bind placeholders
+pub struct ParametersForm {
+    rows: Vec<ParameterRow>,
+    _subscriptions: Vec<Subscription>,
+}
+
+impl ParametersForm {
+    /// One row per placeholder name, prefilled from the last saved values
+    pub fn view(
+        names: Vec<String>,
+        saved: Vec<QueryParam>,
+        window: &mut Window,
+        cx: &mut App,
+    ) -> Entity<Self> {
+        cx.new(|cx| {
+            let type_labels: Vec<SharedString> =
+                ParamType::ALL.iter().map(|t| t.label().into()).collect();
+            let mut _subscriptions = Vec::new();
+
+            let rows: Vec<ParameterRow> = names
+                .into_iter()
+                .enumerate()
+                .map(|(ix, name)| {
+                    let saved = saved.iter().find(|p| p.name == name);
+                    let param_type = saved.map(|p| p.param_type).unwrap_or_default();
+
+                    let value = cx.new(|cx| {
+                        InputState::new(window, cx)
+                            .placeholder("Value")
+                            .clean_on_escape()
+                    });
+                    if let Some(saved_value) = saved.and_then(|p| p.value.clone()) {
+                        value.update(cx, |input, cx| input.set_value(saved_value, window, cx));
+                    }
+
+                    let type_ix = ParamType::ALL
+                        .iter()
+                        .position(|t| *t == param_type)
+                        .unwrap_or(0);
+                    let type_select = cx.new(|cx| {
+                        SelectState::new(
+                            type_labels.clone(),
+                            Some(IndexPath::new(type_ix)),
+                            window,
+                            cx,
+                        )
+                    });
+                    _subscriptions.push(cx.subscribe_in(
+                        &type_select,
+                        window,
+                        move |this: &mut Self, _, event: &SelectEvent<Vec<SharedString>>, _, cx| {
+                            if let SelectEvent::Confirm(Some(label)) = event
+                                && let Some(param_type) =
+                                    ParamType::ALL.iter().find(|t| t.label() == label.as_ref())
+                            {
+                                this.rows[ix].param_type = *param_type;
+                                cx.notify();
+                            }
+                        },
+                    ));
+
+                    ParameterRow {
+                        name,
+                        value,
+                        type_select,
+                        param_type,
+                        is_null: saved.is_some_and(|p| p.value.is_none()),
+                    }
+                })
+                .collect();
+
+            if let Some(first) = rows.first() {
+                first.value.read(cx).focus_handle(cx).focus(window);
+            }
+
+            Self {
+                rows,
+                _subscriptions,
+            }
+        })
+    }
+
+    /// Current values in bind order
+    pub fn params(&self, cx: &App) -> Vec<QueryParam> {
+        self.rows
+            .iter()
+            .map(|row| QueryParam {
+                name: row.name.clone(),
+                param_type: row.param_type,
+                value: (!row.is_null).then(|| row.value.read(cx).value().to_string()),
+            })
+            .collect()
+    }
+}
+
+impl Render for ParametersForm {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        v_flex()
+            .gap_2()
+            .children(self.rows.iter().enumerate().map(|(ix, row)| {
+                h_flex()
+                    .gap_2()
+                    .items_center()
+                    .child(
+                        div()
+                            .w(px(120.))
+                            .text_sm()
+                            .text_color(cx.theme().muted_foreground)
+                            .child(row.name.clone()),
+                    )
+                    .child(div().flex_1().child(Input::new(&row.value).small()))
+                    .child(
+                        div()
+                            .w(px(130.))
+                            .child(Select::new(&row.type_select).small()),
+                    )
+                    .child(
+                        Button::new(("param-null", ix))
+                            .label("NULL")
+                            .small()
+                            .ghost()
+                            .selected(row.is_null)
+                            .tooltip("Bind NULL instead of the value")
+                            .on_click(cx.listener(move |this, _, _, cx| {
+                                this.rows[ix].is_null = !this.rows[ix].is_null;
+                                cx.notify();
+                            })),
+                    )
+            }))
+    }
+}
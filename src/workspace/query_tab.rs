@@ -0,0 +1,503 @@
+use super::editor::{Editor, EditorEvent};
+use super::results::ResultsPanel;
+
+use crate::services::sql::{DestructiveStatement, destructive_statement};
+use crate::services::storage::{EditorTab, NewHistoryEntry};
+use crate::services::{AppStore, ConnectionInfo, DatabaseManager, QueryParam, QueryTimeouts};
+use crate::services::{ErrorResult, QueryExecutionResult, ServerNotice, TableInfo};
+use crate::state::{ConnectionState, SessionId, refresh_transaction_status};
+use gpui::*;
+
+use gpui_component::ActiveTheme;
+use gpui_component::WindowExt as _;
+use gpui_component::resizable::{resizable_panel, v_resizable};
+use gpui_component::v_flex;
+use std::rc::Rc;
+use std::time::Duration;
+
+/// Delay after the last keystroke before the buffer is saved
+const SAVE_DELAY: Duration = Duration::from_millis(500);
+
+/// A named SQL buffer of a connection with its own results, saved to the
+/// app store as it changes
+pub struct QueryTab {
+    session: SessionId,
+    db_manager: DatabaseManager,
+    tab: EditorTab,
+    editor: Entity<Editor>,
+    results_panel: Entity<ResultsPanel>,
+    /// Set when the user cancels, so a running script stops between statements
+    script_cancelled: bool,
+    /// Pending save; replacing it debounces typing
+    save_task: Option<Task<()>>,
+    _subscriptions: Vec<Subscription>,
+}
+
+impl QueryTab {
+    pub fn new(
+        session: SessionId,
+        db_manager: DatabaseManager,
+        tab: EditorTab,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) -> Self {
+        let editor = Editor::view(session, window, cx);
+        let results_panel = ResultsPanel::view(db_manager.clone(), window, cx);
+
+        if !tab.content.is_empty() {
+            let (content, cursor) = (tab.content.clone(), tab.cursor.max(0) as usize);
+            editor.update(cx, |editor, cx| editor.restore(content, cursor, window, cx));
+        }
+
+        let _subscriptions = vec![cx.subscribe_in(
+            &editor,
+            window,
+            |this, _, event: &EditorEvent, window, cx| match event {
+                EditorEvent::ExecuteQuery {
+                    sql,
+                    params,
+                    timeouts,
+                } => {
+                    let (sql, params, timeouts) = (sql.clone(), params.clone(), *timeouts);
+                    this.confirm_destructive(
+                        vec![sql.clone()],
+                        params.clone(),
+                        window,
+                        cx,
+                        move |this, cx| {
+                            this.execute_query(sql.clone(), params.clone(), timeouts, cx)
+                        },
+                    );
+                }
+                EditorEvent::ExecuteScript {
+                    statements,
+                    stop_on_error,
+                    timeouts,
+                } => {
+                    let (statements, stop_on_error, timeouts) =
+                        (statements.clone(), *stop_on_error, *timeouts);
+                    this.confirm_destructive(
+                        statements.clone(),
+                        vec![],
+                        window,
+                        cx,
+                        move |this, cx| {
+                            this.execute_script(statements.clone(), stop_on_error, timeouts, cx)
+                        },
+                    );
+                }
+                EditorEvent::CancelQuery => {
+                    this.cancel_query(cx);
+                }
+                EditorEvent::BufferChanged => {
+                    this.buffer_changed(cx);
+                }
+            },
+        )];
+
+        Self {
+            session,
+            db_manager,
+            tab,
+            editor,
+            results_panel,
+            script_cancelled: false,
+            save_task: None,
+            _subscriptions,
+        }
+    }
+
+    pub fn view(
+        session: SessionId,
+        db_manager: DatabaseManager,
+        tab: EditorTab,
+        window: &mut Window,
+        cx: &mut App,
+    ) -> Entity<Self> {
+        cx.new(|cx| Self::new(session, db_manager, tab, window, cx))
+    }
+
+    pub fn tab(&self) -> &EditorTab {
+        &self.tab
+    }
+
+    pub fn rename(&mut self, name: String, cx: &mut Context<Self>) {
+        self.tab.name = name;
+        self.save(Duration::ZERO, cx);
+        cx.notify();
+    }
+
+    pub fn set_position(&mut self, position: i64, cx: &mut Context<Self>) {
+        if self.tab.position != position {
+            self.tab.position = position;
+            self.save(Duration::ZERO, cx);
+        }
+    }
+
+    /// Loads a query into the editor and runs it, as the agent asks
+    pub fn run_query(&mut self, sql: String, window: &mut Window, cx: &mut Context<Self>) {
+        self.load_query(sql.clone(), window, cx);
+        let timeouts = self.editor.read(cx).timeout_overrides();
+        self.confirm_destructive(vec![sql.clone()], vec![], window, cx, move |this, cx| {
+            this.execute_query(sql.clone(), vec![], timeouts, cx)
+        });
+    }
+
+    pub fn load_query(&mut self, sql: String, window: &mut Window, cx: &mut App) {
+        self.editor.update(cx, |editor, cx| {
+            editor.set_query(sql, window, cx);
+        });
+    }
+
+    /// Picks up the editor's text and cursor and saves them once typing pauses
+    fn buffer_changed(&mut self, cx: &mut Context<Self>) {
+        let editor = self.editor.read(cx);
+        let (content, cursor) = (editor.contents(cx), editor.cursor(cx) as i64);
+        if content != self.tab.content || cursor != self.tab.cursor {
+            self.tab.content = content;
+            self.tab.cursor = cursor;
+            self.save(SAVE_DELAY, cx);
+        }
+    }
+
+    fn set_last_result(&mut self, result: &QueryExecutionResult, cx: &mut Context<Self>) {
+        self.tab.last_result = Some(result.summary());
+        self.save(Duration::ZERO, cx);
+        cx.notify();
+    }
+
+    /// Writes the tab to the app store after `delay`, replacing any pending save
+    fn save(&mut self, delay: Duration, cx: &mut Context<Self>) {
+        let tab = self.tab.clone();
+        self.save_task = Some(cx.spawn(async move |_this, cx| {
+            if !delay.is_zero() {
+                cx.background_executor().timer(delay).await;
+            }
+            if let Ok(store) = AppStore::singleton().await
+                && let Err(e) = store.editor_tabs().save(&tab).await
+            {
+                tracing::error!("Failed to save editor tab: {}", e);
+            }
+        }));
+    }
+
+    /// The session's connection, following database changes
+    fn connection(&self, cx: &App) -> Option<ConnectionInfo> {
+        cx.global::<ConnectionState>()
+            .session(self.session)
+            .map(|session| session.connection.clone())
+    }
+
+    /// Calls `run` right away, or once the user confirms when any of the
+    /// statements drops, truncates, or updates or deletes every row.
+    /// Read-only connections refuse such statements anyway, so they skip it.
+    fn confirm_destructive(
+        &mut self,
+        statements: Vec<String>,
+        params: Vec<QueryParam>,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+        run: impl Fn(&mut Self, &mut Context<Self>) + 'static,
+    ) {
+        let db_manager = self.db_manager.clone();
+        let flagged: Vec<(String, DestructiveStatement)> = statements
+            .into_iter()
+            .filter_map(|sql| destructive_statement(&sql).map(|destructive| (sql, destructive)))
+            .collect();
+
+        if flagged.is_empty() || db_manager.is_read_only() {
+            run(self, cx);
+            return;
+        }
+
+        let run = Rc::new(run);
+        cx.spawn_in(window, async move |this, cx| {
+            let mut warnings = Vec::new();
+            for (sql, destructive) in flagged {
+                let estimate = if destructive.action.affects_rows() {
+                    db_manager.estimate_affected_rows(&sql, &params).await
+                } else {
+                    None
+                };
+                warnings.push(destructive_warning(&destructive, estimate));
+            }
+
+            this.update_in(cx, |_this, window, cx| {
+                let tab = cx.entity();
+                window.open_dialog(cx, move |dialog, _win, cx| {
+                    let run = run.clone();
+                    let tab = tab.clone();
+                    dialog
+                        .title("Run destructive statement?")
+                        .confirm()
+                        .child(
+                            v_flex().gap_2().children(
+                                warnings
+                                    .iter()
+                                    .map(|warning| div().text_sm().child(warning.clone())),
+                            ),
+                        )
+                        .child(
+                            div()
+                                .mt_2()
+                                .text_xs()
+                                .text_color(cx.theme().muted_foreground)
+                                .child("This can't be undone outside a transaction."),
+                        )
+                        .on_ok(move |_, _window, cx| {
+                            tab.update(cx, |this, cx| run(this, cx));
+                            true
+                        })
+                });
+            })
+            .ok();
+        })
+        .detach();
+    }
+
+    fn execute_query(
+        &mut self,
+        query: String,
+        params: Vec<QueryParam>,
+        timeouts: QueryTimeouts,
+        cx: &mut Context<Self>,
+    ) {
+        // Set editor to executing state
+        self.editor.update(cx, |editor, cx| {
+            editor.set_executing(true, cx);
+            cx.notify();
+        });
+
+        tracing::debug!("execute_query");
+
+        let db_manager = self.db_manager.clone();
+        let session = self.session;
+        let active_connection = self.connection(cx);
+
+        cx.spawn(async move |this, cx| {
+            tracing::debug!("execute_query spawn - before execute_query_with_params");
+            let (result, notices) = db_manager
+                .execute_query_with_params(&query, &params, &timeouts)
+                .await;
+            tracing::debug!("execute_query_with_params result");
+
+            if let Some(conn) = &active_connection {
+                record_history(conn, &query, &result, &notices).await;
+            }
+
+            this.update(cx, |this, cx| {
+                // Set editor back to normal state
+                this.editor.update(cx, |editor, cx| {
+                    editor.set_executing(false, cx);
+                    if let QueryExecutionResult::Error(ErrorResult {
+                        database_error: Some(details),
+                        ..
+                    }) = &result
+                    {
+                        editor.show_error_position(0, details, cx);
+                    }
+                });
+
+                this.set_last_result(&result, cx);
+
+                // Update results panel
+                this.results_panel.update(cx, |results, cx| {
+                    results.update_result(result, notices, cx);
+                });
+
+                cx.notify();
+            })
+            .ok();
+
+            refresh_transaction_status(session, &db_manager, cx).await;
+        })
+        .detach();
+    }
+
+    /// Run each statement in order, giving each its own result tab
+    fn execute_script(
+        &mut self,
+        statements: Vec<String>,
+        stop_on_error: bool,
+        timeouts: QueryTimeouts,
+        cx: &mut Context<Self>,
+    ) {
+        self.script_cancelled = false;
+        self.editor.update(cx, |editor, cx| {
+            editor.set_executing(true, cx);
+        });
+        self.results_panel.update(cx, |results, cx| {
+            results.clear(cx);
+        });
+
+        let db_manager = self.db_manager.clone();
+        let session = self.session;
+        let active_connection = self.connection(cx);
+
+        cx.spawn(async move |this, cx| {
+            let total = statements.len();
+
+            for (ix, statement) in statements.into_iter().enumerate() {
+                let cancelled = this
+                    .read_with(cx, |this, _| this.script_cancelled)
+                    .unwrap_or(true);
+                if cancelled {
+                    this.update(cx, |this, cx| {
+                        this.results_panel.update(cx, |results, cx| {
+                            results.set_skipped_statements(total - ix, cx);
+                        });
+                    })
+                    .ok();
+                    break;
+                }
+
+                let (result, notices) = db_manager
+                    .execute_query_with_params(&statement, &[], &timeouts)
+                    .await;
+
+                if let Some(conn) = &active_connection {
+                    record_history(conn, &statement, &result, &notices).await;
+                }
+
+                // A cancelled statement always ends the script
+                let stop = match &result {
+                    QueryExecutionResult::Cancelled(_) => true,
+                    QueryExecutionResult::Error(_) => stop_on_error,
+                    _ => false,
+                };
+
+                this.update(cx, |this, cx| {
+                    if let QueryExecutionResult::Error(ErrorResult {
+                        database_error: Some(details),
+                        ..
+                    }) = &result
+                    {
+                        this.editor.update(cx, |editor, cx| {
+                            editor.show_error_position(ix, details, cx);
+                        });
+                    }
+                    this.set_last_result(&result, cx);
+                    this.results_panel.update(cx, |results, cx| {
+                        results.push_result(statement, result, notices, cx);
+                        if stop {
+                            results.set_skipped_statements(total - ix - 1, cx);
+                        }
+                    });
+                })
+                .ok();
+
+                if stop {
+                    break;
+                }
+            }
+
+            this.update(cx, |this, cx| {
+                this.editor.update(cx, |editor, cx| {
+                    editor.set_executing(false, cx);
+                });
+                cx.notify();
+            })
+            .ok();
+
+            refresh_transaction_status(session, &db_manager, cx).await;
+        })
+        .detach();
+    }
+
+    fn cancel_query(&mut self, cx: &mut Context<Self>) {
+        self.script_cancelled = true;
+        let db_manager = self.db_manager.clone();
+
+        cx.spawn(async move |_this, _cx| {
+            if let Err(e) = db_manager.cancel_query().await {
+                tracing::error!("Failed to cancel query: {}", e);
+            }
+        })
+        .detach();
+    }
+
+    pub fn show_table_columns(&mut self, table: TableInfo, cx: &mut Context<Self>) {
+        let db_manager = self.db_manager.clone();
+
+        cx.spawn(async move |this, cx| {
+            let result = db_manager
+                .get_table_columns(&table.table_name, &table.table_schema)
+                .await;
+
+            this.update(cx, |this, cx| {
+                match result {
+                    Ok(query_result) => {
+                        this.results_panel.update(cx, |results, cx| {
+                            results.update_result(query_result, vec![], cx);
+                        });
+                    }
+                    Err(e) => {
+                        this.results_panel.update(cx, |results, cx| {
+                            results.update_result(
+                                QueryExecutionResult::Error(ErrorResult {
+                                    message: format!("Failed to load table columns: {}", e),
+                                    ..Default::default()
+                                }),
+                                vec![],
+                                cx,
+                            );
+                        });
+                    }
+                }
+                cx.notify();
+            })
+            .ok();
+        })
+        .detach();
+    }
+}
+
+/// Record a finished statement in the query history
+async fn record_history(
+    connection: &ConnectionInfo,
+    query: &str,
+    result: &QueryExecutionResult,
+    notices: &[ServerNotice],
+) {
+    let entry =
+        NewHistoryEntry::from_result(query, result, Some(connection.database.clone()), notices);
+
+    if let Ok(store) = AppStore::singleton().await
+        && let Err(e) = store.history().record(&connection.id, &entry).await
+    {
+        tracing::error!("Failed to record query history: {}", e);
+    }
+}
+
+/// One line of the destructive statement confirmation
+fn destructive_warning(destructive: &DestructiveStatement, estimate: Option<u64>) -> String {
+    let mut warning = destructive.action.label();
+    if !destructive.objects.is_empty() {
+        warning.push_str(&format!(" on {}", destructive.objects.join(", ")));
+    }
+    if destructive.action.affects_rows() {
+        match estimate {
+            Some(1) => warning.push_str(", about 1 row"),
+            Some(rows) => warning.push_str(&format!(", about {} rows", rows)),
+            None => warning.push_str(", row count unknown"),
+        }
+    }
+    warning
+}
+
+impl Render for QueryTab {
+    fn render(&mut self, _window: &mut Window, _cx: &mut Context<Self>) -> impl IntoElement {
+        v_resizable("resizable-results")
+            .child(
+                resizable_panel()
+                    .size(px(400.))
+                    .size_range(px(200.)..px(800.))
+                    .child(self.editor.clone()),
+            )
+            .child(
+                resizable_panel()
+                    .size(px(200.))
+                    .child(self.results_panel.clone()),
+            )
+    }
+}
@@ -1,15 +1,16 @@
 use crate::{
     services::{
-        QueryExecutionResult,
+        DatabaseManager, ErrorResult, NoticeSeverity, QueryExecutionResult, ServerNotice,
         export::{stream_to_csv, stream_to_ndjson},
         export_to_csv, export_to_json,
     },
-    state::ConnectionState,
     workspace::results::EnhancedResultsTableDelegate,
 };
+use gpui::prelude::FluentBuilder as _;
 use gpui::*;
 use gpui_component::{
-    ActiveTheme as _, Icon, Sizable as _, WindowExt as _,
+    ActiveTheme as _, Icon, IconName, Selectable as _, Sizable as _, StyledExt as _,
+    WindowExt as _,
     button::{Button, ButtonVariants as _},
     h_flex,
     label::Label,
@@ -23,35 +24,212 @@ pub enum ExportFormat {
     Json,
 }
 
+/// The outcome of one statement; a script produces one tab per statement
+struct ResultTab {
+    sql: String,
+    result: QueryExecutionResult,
+    /// Notices and warnings the server sent while the statement ran
+    notices: Vec<ServerNotice>,
+    /// Grid for SELECT results, created the first time the tab is shown
+    table: Option<Entity<TableState<EnhancedResultsTableDelegate>>>,
+    /// Whether this result still owns the open server-side cursor
+    owns_cursor: bool,
+    _subscription: Option<Subscription>,
+}
+
 pub struct ResultsPanel {
-    current_result: Option<QueryExecutionResult>,
-    table: Entity<TableState<EnhancedResultsTableDelegate>>,
+    /// Manager of the session whose results are shown, for paging and export
+    db_manager: DatabaseManager,
+    tabs: Vec<ResultTab>,
+    active_tab: usize,
+    /// Statements left unrun after a script stopped early
+    skipped_statements: usize,
+    is_fetching_all: bool,
+    /// Show the Messages view instead of the result
+    show_messages: bool,
 }
 
 impl ResultsPanel {
-    pub fn new(window: &mut Window, cx: &mut Context<Self>) -> Self {
-        let delegate = EnhancedResultsTableDelegate::new();
-        let table = cx.new(|cx| TableState::new(delegate, window, cx).sortable(false));
-
+    pub fn new(db_manager: DatabaseManager, _window: &mut Window, _cx: &mut Context<Self>) -> Self {
         Self {
-            current_result: None,
-            table,
+            db_manager,
+            tabs: Vec::new(),
+            active_tab: 0,
+            skipped_statements: 0,
+            is_fetching_all: false,
+            show_messages: false,
+        }
+    }
+
+    pub fn view(db_manager: DatabaseManager, window: &mut Window, cx: &mut App) -> Entity<Self> {
+        cx.new(|cx| Self::new(db_manager, window, cx))
+    }
+
+    /// Replace all tabs with a single result
+    pub fn update_result(
+        &mut self,
+        result: QueryExecutionResult,
+        notices: Vec<ServerNotice>,
+        cx: &mut Context<Self>,
+    ) {
+        self.clear(cx);
+        self.push_result(String::new(), result, notices, cx);
+    }
+
+    /// Remove all tabs, e.g. before a script starts
+    pub fn clear(&mut self, cx: &mut Context<Self>) {
+        self.tabs.clear();
+        self.active_tab = 0;
+        self.skipped_statements = 0;
+        self.is_fetching_all = false;
+        cx.notify();
+    }
+
+    /// Add the result of the statement that just ran and show it
+    pub fn push_result(
+        &mut self,
+        sql: String,
+        result: QueryExecutionResult,
+        notices: Vec<ServerNotice>,
+        cx: &mut Context<Self>,
+    ) {
+        // Running a statement closes the previous result cursor, so earlier
+        // grids can no longer page
+        for tab in &mut self.tabs {
+            tab.owns_cursor = false;
+            if let Some(table) = &tab.table {
+                table.update(cx, |table, cx| {
+                    table.delegate_mut().detach_cursor();
+                    table.refresh(cx);
+                });
+            }
+        }
+
+        let sql = if sql.is_empty() {
+            match &result {
+                QueryExecutionResult::Select(r) => r.original_query.clone(),
+                _ => sql,
+            }
+        } else {
+            sql
+        };
+
+        // Statements without rows (DO blocks, DDL) say everything in notices
+        self.show_messages =
+            !notices.is_empty() && !matches!(result, QueryExecutionResult::Select(_));
+
+        self.tabs.push(ResultTab {
+            sql,
+            result,
+            notices,
+            table: None,
+            owns_cursor: true,
+            _subscription: None,
+        });
+        self.active_tab = self.tabs.len() - 1;
+        cx.notify();
+    }
+
+    pub fn set_skipped_statements(&mut self, count: usize, cx: &mut Context<Self>) {
+        self.skipped_statements = count;
+        cx.notify();
+    }
+
+    fn select_tab(&mut self, ix: usize, cx: &mut Context<Self>) {
+        if ix < self.tabs.len() {
+            self.active_tab = ix;
+            cx.notify();
         }
     }
 
-    pub fn view(window: &mut Window, cx: &mut App) -> Entity<Self> {
-        cx.new(|cx| Self::new(window, cx))
+    fn current_notices(&self) -> &[ServerNotice] {
+        self.tabs
+            .get(self.active_tab)
+            .map(|tab| tab.notices.as_slice())
+            .unwrap_or_default()
+    }
+
+    fn current_result(&self) -> Option<&QueryExecutionResult> {
+        self.tabs.get(self.active_tab).map(|tab| &tab.result)
+    }
+
+    fn current_table(&self) -> Option<Entity<TableState<EnhancedResultsTableDelegate>>> {
+        self.tabs
+            .get(self.active_tab)
+            .and_then(|tab| tab.table.clone())
+    }
+
+    /// Create the grid for the active tab if it is a SELECT shown for the first time
+    fn ensure_table(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        let Some(tab) = self.tabs.get_mut(self.active_tab) else {
+            return;
+        };
+        let QueryExecutionResult::Select(result) = &tab.result else {
+            return;
+        };
+        if tab.table.is_some() {
+            return;
+        }
+
+        let mut delegate = EnhancedResultsTableDelegate::new(self.db_manager.clone());
+        delegate.update(result.clone());
+        if !tab.owns_cursor {
+            delegate.detach_cursor();
+        }
+
+        let table = cx.new(|cx| TableState::new(delegate, window, cx).sortable(false));
+        // Row counts change as pages are loaded on scroll, and the pages may
+        // bring notices
+        let ix = self.active_tab;
+        tab._subscription = Some(cx.observe(&table, move |this, table, cx| {
+            let notices = table.update(cx, |table, _| table.delegate_mut().take_notices());
+            if let Some(tab) = this.tabs.get_mut(ix) {
+                tab.notices.extend(notices);
+            }
+            cx.notify();
+        }));
+        tab.table = Some(table);
     }
 
-    pub fn update_result(&mut self, result: QueryExecutionResult, cx: &mut Context<Self>) {
-        self.current_result = Some(result.clone());
-        if let QueryExecutionResult::Select(x) = result {
-            self.table.update(cx, |table, cx| {
-                table.delegate_mut().update(x.clone());
-                table.refresh(cx);
-            });
+    fn fetch_all_rows(&mut self, cx: &mut Context<Self>) {
+        if self.is_fetching_all {
+            return;
         }
+        let Some(table) = self.current_table() else {
+            return;
+        };
+
+        self.is_fetching_all = true;
         cx.notify();
+
+        let ix = self.active_tab;
+        let db_manager = self.db_manager.clone();
+
+        cx.spawn(async move |this, cx| {
+            let page = db_manager.fetch_more(None).await;
+
+            this.update(cx, |this, cx| {
+                this.is_fetching_all = false;
+                match page {
+                    Ok(page) => {
+                        let notices = table.update(cx, |table, cx| {
+                            table.delegate_mut().append_page(page);
+                            table.refresh(cx);
+                            table.delegate_mut().take_notices()
+                        });
+                        if let Some(tab) = this.tabs.get_mut(ix) {
+                            tab.notices.extend(notices);
+                        }
+                    }
+                    Err(e) => {
+                        tracing::error!("Failed to fetch all rows: {}", e);
+                    }
+                }
+                cx.notify();
+            })
+            .ok();
+        })
+        .detach();
     }
 
     fn stream_export_results(
@@ -60,7 +238,7 @@ impl ResultsPanel {
         window: &mut Window,
         cx: &mut Context<Self>,
     ) {
-        let Some(QueryExecutionResult::Select(result)) = &self.current_result else {
+        let Some(QueryExecutionResult::Select(result)) = self.current_result() else {
             return;
         };
 
@@ -73,29 +251,24 @@ impl ResultsPanel {
 
         let home = dirs::home_dir().unwrap_or_default();
         let receiver = cx.prompt_for_new_path(&home, Some(&suggested_name));
+        let db_manager = self.db_manager.clone();
 
         cx.spawn_in(window, async move |_this, cx| {
             if let Ok(Ok(Some(path))) = receiver.await {
-                let db_manager_result =
-                    cx.read_global::<ConnectionState, _>(|state, _, _| state.db_manager.clone());
-
-                let result: anyhow::Result<u64> = if let Ok(db_manager) = db_manager_result {
-                    cx.background_executor()
-                        .spawn(async move {
-                            let stream = db_manager
-                                .stream_query(&sql)
-                                .await
-                                .map_err(|e| anyhow::anyhow!(e))?;
-
-                            match format {
-                                ExportFormat::Csv => stream_to_csv(stream, &path).await,
-                                ExportFormat::Json => stream_to_ndjson(stream, &path).await,
-                            }
-                        })
-                        .await
-                } else {
-                    Ok(0)
-                };
+                let result: anyhow::Result<u64> = cx
+                    .background_executor()
+                    .spawn(async move {
+                        let stream = db_manager
+                            .stream_query(&sql)
+                            .await
+                            .map_err(|e| anyhow::anyhow!(e))?;
+
+                        match format {
+                            ExportFormat::Csv => stream_to_csv(stream, &path).await,
+                            ExportFormat::Json => stream_to_ndjson(stream, &path).await,
+                        }
+                    })
+                    .await;
 
                 match result {
                     Ok(count) => {
@@ -124,7 +297,7 @@ impl ResultsPanel {
         window: &mut Window,
         cx: &mut Context<Self>,
     ) {
-        let Some(QueryExecutionResult::Select(result)) = &self.current_result else {
+        let Some(QueryExecutionResult::Select(result)) = self.current_result() else {
             return;
         };
 
@@ -176,45 +349,354 @@ impl ResultsPanel {
         .detach();
     }
 
-    fn render_toolbar(&self, cx: &mut Context<Self>) -> impl IntoElement {
+    fn render_toolbar(
+        &self,
+        table: &Entity<TableState<EnhancedResultsTableDelegate>>,
+        execution_time_ms: u128,
+        cx: &mut Context<Self>,
+    ) -> impl IntoElement {
+        let delegate = table.read(cx).delegate();
+        let loaded_rows = delegate.loaded_rows();
+        let has_more = delegate.has_more_rows();
+
+        let row_count = if has_more {
+            format!("{}+ rows loaded", loaded_rows)
+        } else if delegate.is_truncated() {
+            format!("first {} rows", loaded_rows)
+        } else {
+            format!(
+                "{} {}",
+                loaded_rows,
+                if loaded_rows == 1 { "row" } else { "rows" }
+            )
+        };
+        let row_count = format!("{} in {}ms", row_count, execution_time_ms);
+
+        h_flex()
+            .gap_1()
+            .justify_between()
+            .items_center()
+            .child(
+                h_flex()
+                    .gap_2()
+                    .items_center()
+                    .child(
+                        Label::new(row_count)
+                            .text_xs()
+                            .text_color(cx.theme().muted_foreground),
+                    )
+                    .when(has_more, |d| {
+                        d.child(
+                            Button::new("fetch-all")
+                                .label("Fetch all")
+                                .xsmall()
+                                .ghost()
+                                .loading(self.is_fetching_all)
+                                .tooltip("Load every remaining row")
+                                .on_click(cx.listener(|this, _, _win, cx| {
+                                    this.fetch_all_rows(cx);
+                                })),
+                        )
+                    }),
+            )
+            .child(
+                h_flex()
+                    .gap_1()
+                    .items_center()
+                    .child(
+                        Button::new("export-csv")
+                            .icon(Icon::empty().path("icons/file-spreadsheet.svg"))
+                            .small()
+                            .ghost()
+                            .tooltip("Export CSV")
+                            .on_click(cx.listener(|this, _, win, cx| {
+                                this.stream_export_results(ExportFormat::Csv, win, cx);
+                            })),
+                    )
+                    .child(
+                        Button::new("export-json")
+                            .icon(Icon::empty().path("icons/file-braces.svg"))
+                            .small()
+                            .ghost()
+                            .tooltip("Export JSON")
+                            .on_click(cx.listener(|this, _, win, cx| {
+                                this.stream_export_results(ExportFormat::Json, win, cx);
+                            })),
+                    ),
+            )
+    }
+
+    /// Switch between the result and the server messages of the active tab
+    fn render_view_switch(&self, cx: &mut Context<Self>) -> impl IntoElement {
+        let notice_count = self.current_notices().len();
+        let has_warnings = self
+            .current_notices()
+            .iter()
+            .any(|n| matches!(n.severity, NoticeSeverity::Warning | NoticeSeverity::Error));
+
         h_flex()
             .gap_1()
-            .justify_end()
             .items_center()
             .child(
-                Button::new("export-csv")
-                    .icon(Icon::empty().path("icons/file-spreadsheet.svg"))
-                    .small()
+                Button::new("show-result")
+                    .label("Results")
+                    .xsmall()
                     .ghost()
-                    .tooltip("Export CSV")
-                    .on_click(cx.listener(|this, _, win, cx| {
-                        this.stream_export_results(ExportFormat::Csv, win, cx);
+                    .selected(!self.show_messages)
+                    .on_click(cx.listener(|this, _, _win, cx| {
+                        this.show_messages = false;
+                        cx.notify();
                     })),
             )
             .child(
-                Button::new("export-json")
-                    .icon(Icon::empty().path("icons/file-braces.svg"))
-                    .small()
+                Button::new("show-messages")
+                    .label(if notice_count > 0 {
+                        format!("Messages ({})", notice_count)
+                    } else {
+                        "Messages".to_string()
+                    })
+                    .xsmall()
                     .ghost()
-                    .tooltip("Export JSON")
-                    .on_click(cx.listener(|this, _, win, cx| {
-                        this.stream_export_results(ExportFormat::Json, win, cx);
+                    .selected(self.show_messages)
+                    .when(has_warnings, |b| {
+                        b.icon(Icon::new(IconName::TriangleAlert).text_color(cx.theme().warning))
+                    })
+                    .on_click(cx.listener(|this, _, _win, cx| {
+                        this.show_messages = true;
+                        cx.notify();
                     })),
             )
     }
-}
 
-impl Render for ResultsPanel {
-    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
-        match &self.current_result {
-            Some(QueryExecutionResult::Select(_result)) => v_flex()
+    fn render_messages(&self, cx: &mut Context<Self>) -> Div {
+        let notices = self.current_notices();
+        if notices.is_empty() {
+            return h_flex().size_full().items_center().justify_center().child(
+                Label::new("The server sent no messages")
+                    .text_sm()
+                    .text_color(cx.theme().muted_foreground),
+            );
+        }
+
+        v_flex().size_full().child(
+            v_flex()
+                .id("result-messages")
+                .size_full()
+                .overflow_y_scroll()
+                .gap_1()
+                .p_1()
+                .children(notices.iter().map(|notice| {
+                    let color = match notice.severity {
+                        NoticeSeverity::Error => cx.theme().danger,
+                        NoticeSeverity::Warning => cx.theme().warning,
+                        _ => cx.theme().muted_foreground,
+                    };
+
+                    h_flex()
+                        .gap_2()
+                        .items_start()
+                        .child(
+                            Label::new(notice.severity.label())
+                                .text_xs()
+                                .font_semibold()
+                                .text_color(color),
+                        )
+                        .child(
+                            Label::new(notice.message.clone())
+                                .text_sm()
+                                .text_color(cx.theme().foreground),
+                        )
+                })),
+        )
+    }
+
+    fn render_error(&self, error: &ErrorResult, cx: &mut Context<Self>) -> Div {
+        let Some(details) = &error.database_error else {
+            return v_flex().size_full().p_2().child(
+                div()
+                    .p_4()
+                    .bg(cx.theme().danger)
+                    .border_1()
+                    .border_color(cx.theme().danger)
+                    .rounded(cx.theme().radius)
+                    .child(
+                        Label::new(format!(
+                            "Error: {} ({}ms)",
+                            error.message, error.execution_time_ms
+                        ))
+                        .text_sm()
+                        .text_color(cx.theme().danger_foreground),
+                    ),
+            );
+        };
+
+        let object = [
+            ("Schema", &details.schema),
+            ("Table", &details.table),
+            ("Column", &details.column),
+            ("Type", &details.data_type),
+            ("Constraint", &details.constraint),
+        ]
+        .into_iter()
+        .filter_map(|(label, value)| value.as_ref().map(|v| format!("{}: {}", label, v)))
+        .collect::<Vec<_>>()
+        .join("  ");
+
+        let field = |label: &'static str, value: String| {
+            h_flex()
+                .gap_2()
+                .items_start()
+                .child(
+                    Label::new(label)
+                        .text_xs()
+                        .font_semibold()
+                        .w(px(80.))
+                        .text_color(cx.theme().muted_foreground),
+                )
+                .child(
+                    Label::new(value)
+                        .text_sm()
+                        .text_color(cx.theme().foreground),
+                )
+        };
+
+        v_flex().size_full().p_2().child(
+            v_flex()
+                .id("result-error")
                 .size_full()
-                .p_2()
-                .flex()
-                .flex_col()
+                .overflow_y_scroll()
                 .gap_1()
-                .child(self.render_toolbar(cx))
-                .child(Table::new(&self.table.clone()).stripe(true)),
+                .p_4()
+                .border_1()
+                .border_color(cx.theme().danger)
+                .rounded(cx.theme().radius)
+                .child(
+                    h_flex()
+                        .gap_2()
+                        .items_center()
+                        .child(Icon::new(IconName::CircleX).text_color(cx.theme().danger))
+                        .when_some(error.timeout, |d, kind| {
+                            d.child(
+                                Label::new(kind.label())
+                                    .text_xs()
+                                    .font_semibold()
+                                    .text_color(cx.theme().warning),
+                            )
+                        })
+                        .child(
+                            Label::new(format!("{} {}", details.severity, details.code))
+                                .text_xs()
+                                .font_semibold()
+                                .text_color(cx.theme().danger),
+                        )
+                        .child(
+                            Label::new(format!("{}ms", error.execution_time_ms))
+                                .text_xs()
+                                .text_color(cx.theme().muted_foreground),
+                        ),
+                )
+                .child(
+                    Label::new(details.message.clone())
+                        .text_sm()
+                        .font_semibold()
+                        .text_color(cx.theme().foreground),
+                )
+                .when_some(details.detail.clone(), |d, detail| {
+                    d.child(field("Detail", detail))
+                })
+                .when_some(details.hint.clone(), |d, hint| d.child(field("Hint", hint)))
+                .when_some(details.position, |d, position| {
+                    d.child(field("Position", format!("character {}", position)))
+                })
+                .when_some(details.internal_query.clone(), |d, query| {
+                    let query = match details.internal_position {
+                        Some(position) => format!("{} (at character {})", query, position),
+                        None => query,
+                    };
+                    d.child(field("Query", query))
+                })
+                .when_some(details.context.clone(), |d, context| {
+                    d.child(field("Context", context))
+                })
+                .when(!object.is_empty(), |d| d.child(field("Object", object))),
+        )
+    }
+
+    fn render_tab_strip(&self, cx: &mut Context<Self>) -> impl IntoElement {
+        h_flex()
+            .id("result-tabs")
+            .gap_1()
+            .items_center()
+            .overflow_x_scroll()
+            .children(self.tabs.iter().enumerate().map(|(ix, tab)| {
+                let summary: String = tab.sql.split_whitespace().collect::<Vec<_>>().join(" ");
+                let title = if summary.chars().count() > 24 {
+                    format!(
+                        "{}. {}...",
+                        ix + 1,
+                        summary.chars().take(24).collect::<String>()
+                    )
+                } else {
+                    format!("{}. {}", ix + 1, summary)
+                };
+
+                let button = Button::new(("result-tab", ix))
+                    .label(title)
+                    .xsmall()
+                    .ghost()
+                    .selected(ix == self.active_tab)
+                    .tooltip(summary.chars().take(200).collect::<String>())
+                    .on_click(cx.listener(move |this, _, _win, cx| {
+                        this.select_tab(ix, cx);
+                    }));
+
+                match &tab.result {
+                    QueryExecutionResult::Error(_) => {
+                        button.icon(Icon::new(IconName::CircleX).text_color(cx.theme().danger))
+                    }
+                    QueryExecutionResult::Cancelled(_) => {
+                        button.icon(Icon::new(IconName::CircleX).text_color(cx.theme().warning))
+                    }
+                    _ => button,
+                }
+            }))
+            .when(self.skipped_statements > 0, |d| {
+                d.child(
+                    Label::new(format!(
+                        "{} {} not run",
+                        self.skipped_statements,
+                        if self.skipped_statements == 1 {
+                            "statement"
+                        } else {
+                            "statements"
+                        }
+                    ))
+                    .text_xs()
+                    .text_color(cx.theme().muted_foreground),
+                )
+            })
+    }
+}
+
+impl Render for ResultsPanel {
+    fn render(&mut self, window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        self.ensure_table(window, cx);
+
+        let show_tabs = self.tabs.len() > 1 || self.skipped_statements > 0;
+        let has_result = self.current_result().is_some();
+
+        let content = match self.current_result() {
+            Some(_) if self.show_messages => self.render_messages(cx),
+            Some(QueryExecutionResult::Select(result)) => match self.current_table() {
+                Some(table) => v_flex()
+                    .size_full()
+                    .flex()
+                    .flex_col()
+                    .gap_1()
+                    .child(self.render_toolbar(&table, result.execution_time_ms, cx))
+                    .child(Table::new(&table).stripe(true)),
+                None => v_flex().size_full(),
+            },
             Some(QueryExecutionResult::Modified(modified)) => {
                 h_flex().size_full().items_center().justify_center().child(
                     Label::new(format!(
@@ -225,24 +707,30 @@ impl Render for ResultsPanel {
                     .text_color(cx.theme().accent_foreground),
                 )
             }
-            Some(QueryExecutionResult::Error(error)) => v_flex().size_full().p_4().child(
-                div()
-                    .p_4()
-                    .bg(cx.theme().danger)
-                    .border_1()
-                    .border_color(cx.theme().danger)
-                    .rounded(cx.theme().radius)
-                    .child(
-                        Label::new(format!("Error: {}", error.message))
-                            .text_sm()
-                            .text_color(cx.theme().danger_foreground),
-                    ),
-            ),
-            _ => h_flex().size_full().items_center().justify_center().child(
+            Some(QueryExecutionResult::Error(error)) => self.render_error(error, cx),
+            Some(QueryExecutionResult::Cancelled(cancelled)) => {
+                h_flex().size_full().items_center().justify_center().child(
+                    Label::new(format!(
+                        "Query cancelled after {}ms",
+                        cancelled.execution_time_ms
+                    ))
+                    .text_sm()
+                    .text_color(cx.theme().warning),
+                )
+            }
+            None => h_flex().size_full().items_center().justify_center().child(
                 Label::new("Execute a query to see results here")
                     .text_sm()
                     .text_color(cx.theme().muted_foreground),
             ),
-        }
+        };
+
+        v_flex()
+            .size_full()
+            .p_2()
+            .gap_1()
+            .when(show_tabs, |d| d.child(self.render_tab_strip(cx)))
+            .when(has_result, |d| d.child(self.render_view_switch(cx)))
+            .child(content)
     }
 }
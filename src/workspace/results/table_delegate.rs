@@ -1,6 +1,8 @@
 use std::ops::Range;
 
-use crate::services::{QueryResult, ResultCell};
+use crate::services::{
+    DatabaseManager, QueryResult, RESULT_PAGE_SIZE, ResultCell, ResultPage, ServerNotice,
+};
 use gpui::*;
 use gpui_component::{
     ActiveTheme as _,
@@ -9,19 +11,34 @@ use gpui_component::{
 };
 
 pub struct EnhancedResultsTableDelegate {
+    /// Manager holding the cursor more rows are fetched from
+    db_manager: DatabaseManager,
     columns: Vec<Column>,
     // Store the full ResultCell data with metadata
     rows: Vec<Vec<ResultCell>>,
+    /// Original column index for each displayed column, so appended pages
+    /// follow any columns the user has moved
+    column_order: Vec<usize>,
     loading: bool,
+    has_more: bool,
+    /// Rows stopped arriving because the cursor was closed by a later statement
+    truncated: bool,
+    /// Notices from pages loaded on scroll, until the results panel takes them
+    notices: Vec<ServerNotice>,
     visible_rows: Range<usize>,
 }
 
 impl EnhancedResultsTableDelegate {
-    pub fn new() -> Self {
+    pub fn new(db_manager: DatabaseManager) -> Self {
         Self {
+            db_manager,
             rows: vec![],
             columns: vec![],
+            column_order: vec![],
             loading: false,
+            has_more: false,
+            truncated: false,
+            notices: vec![],
             visible_rows: Range::default(),
         }
     }
@@ -45,8 +62,50 @@ impl EnhancedResultsTableDelegate {
             })
             .collect();
 
+        self.column_order = (0..columns.len()).collect();
         self.rows = rows;
         self.columns = columns;
+        self.has_more = result.has_more;
+        self.truncated = false;
+        self.loading = false;
+    }
+
+    /// Stop paging once another statement has replaced the result cursor
+    pub fn detach_cursor(&mut self) {
+        if self.has_more {
+            self.has_more = false;
+            self.truncated = true;
+        }
+    }
+
+    /// Appends a page fetched from the result cursor
+    pub fn append_page(&mut self, page: ResultPage) {
+        for row in page.rows {
+            let cells = self
+                .column_order
+                .iter()
+                .filter_map(|&ix| row.cells.get(ix).cloned())
+                .collect();
+            self.rows.push(cells);
+        }
+        self.has_more = page.has_more;
+        self.notices.extend(page.notices);
+    }
+
+    pub fn take_notices(&mut self) -> Vec<ServerNotice> {
+        std::mem::take(&mut self.notices)
+    }
+
+    pub fn loaded_rows(&self) -> usize {
+        self.rows.len()
+    }
+
+    pub fn has_more_rows(&self) -> bool {
+        self.has_more
+    }
+
+    pub fn is_truncated(&self) -> bool {
+        self.truncated
     }
 }
 
@@ -173,6 +232,11 @@ impl TableDelegate for EnhancedResultsTableDelegate {
         let col = self.columns.remove(col_ix);
         self.columns.insert(to_ix, col);
 
+        if col_ix < self.column_order.len() && to_ix < self.column_order.len() {
+            let original_ix = self.column_order.remove(col_ix);
+            self.column_order.insert(to_ix, original_ix);
+        }
+
         // Also move the cells in each row
         for row in &mut self.rows {
             if col_ix < row.len() && to_ix < row.len() {
@@ -190,19 +254,37 @@ impl TableDelegate for EnhancedResultsTableDelegate {
         150
     }
 
-    // fn load_more(&mut self, _: &mut Window, cx: &mut Context<TableState<Self>>) {
-    //     self.loading = true;
-    //     cx.spawn(async move |view, cx| {
-    //         // Simulate network request
-    //         Timer::after(Duration::from_secs(1)).await;
-    //         cx.update(|cx| {
-    //             let _ = view.update(cx, |view, _| {
-    //                 view.delegate_mut().loading = false;
-    //             });
-    //         })
-    //     })
-    //     .detach();
-    // }
+    fn has_more(&self, _: &App) -> bool {
+        self.has_more
+    }
+
+    fn load_more(&mut self, _: &mut Window, cx: &mut Context<TableState<Self>>) {
+        if self.loading || !self.has_more {
+            return;
+        }
+
+        self.loading = true;
+        let db_manager = self.db_manager.clone();
+
+        cx.spawn(async move |view, cx| {
+            let page = db_manager.fetch_more(Some(RESULT_PAGE_SIZE)).await;
+
+            let _ = view.update(cx, |view, cx| {
+                let delegate = view.delegate_mut();
+                delegate.loading = false;
+                match page {
+                    Ok(page) => delegate.append_page(page),
+                    Err(e) => {
+                        tracing::error!("Failed to fetch more rows: {}", e);
+                        delegate.has_more = false;
+                    }
+                }
+                view.refresh(cx);
+                cx.notify();
+            });
+        })
+        .detach();
+    }
 
     fn visible_rows_changed(
         &mut self,
@@ -0,0 +1,4 @@
+mod panel;
+
+pub use panel::SavedQueriesPanel;
+pub use panel::SavedQueryEvent;
@@ -0,0 +1,381 @@
+use gpui::{
+    AnyElement, App, AppContext, ClickEvent, Context, Entity, EventEmitter,
+    InteractiveElement as _, IntoElement, ListAlignment, ListState, ParentElement,
+    PathPromptOptions, Render, SharedString, StatefulInteractiveElement as _, Styled, Subscription,
+    Window, div, list, prelude::FluentBuilder as _, px,
+};
+use gpui_component::{
+    ActiveTheme as _, Icon, Sizable as _, StyledExt as _, WindowExt as _,
+    button::{Button, ButtonVariants as _},
+    h_flex,
+    input::{Input, InputEvent, InputState},
+    label::Label,
+    notification::NotificationType,
+    v_flex,
+};
+use uuid::Uuid;
+
+use crate::{
+    services::{
+        export::{export_saved_queries, import_saved_queries},
+        storage::SavedQuery,
+    },
+    state::{ConnectionState, SavedQueriesState, SessionId, delete_saved_query, save_queries},
+};
+
+/// Event emitted when a saved query is picked
+pub enum SavedQueryEvent {
+    /// User wants to load this SQL into the editor
+    LoadQuery(String),
+}
+
+impl EventEmitter<SavedQueryEvent> for SavedQueriesPanel {}
+
+/// Browses the saved queries shared by every connection and those limited
+/// to the session's connection
+pub struct SavedQueriesPanel {
+    session: SessionId,
+    connection_id: Option<Uuid>,
+    search: Entity<InputState>,
+    list_state: ListState,
+    filtered_queries: Vec<SavedQuery>,
+    _subscriptions: Vec<Subscription>,
+}
+
+impl SavedQueriesPanel {
+    pub fn new(session: SessionId, window: &mut Window, cx: &mut Context<Self>) -> Self {
+        let search = cx.new(|cx| {
+            InputState::new(window, cx)
+                .placeholder("Search name, tags or SQL")
+                .clean_on_escape()
+        });
+
+        let _subscriptions = vec![
+            cx.observe_global::<SavedQueriesState>(|this, cx| this.filter_queries(cx)),
+            cx.observe_global::<ConnectionState>(|this, cx| {
+                let connection_id = cx
+                    .global::<ConnectionState>()
+                    .session(this.session)
+                    .map(|session| session.connection.id);
+                if connection_id != this.connection_id {
+                    this.connection_id = connection_id;
+                    this.filter_queries(cx);
+                }
+            }),
+            cx.subscribe(&search, |this, _, _: &InputEvent, cx| {
+                this.filter_queries(cx)
+            }),
+        ];
+
+        let mut this = Self {
+            session,
+            connection_id: cx
+                .global::<ConnectionState>()
+                .session(session)
+                .map(|session| session.connection.id),
+            search,
+            list_state: ListState::new(0, ListAlignment::Top, px(20.)),
+            filtered_queries: vec![],
+            _subscriptions,
+        };
+        this.filter_queries(cx);
+        this
+    }
+
+    pub fn view(session: SessionId, window: &mut Window, cx: &mut App) -> Entity<Self> {
+        cx.new(|cx| Self::new(session, window, cx))
+    }
+
+    fn filter_queries(&mut self, cx: &mut Context<Self>) {
+        let search = self.search.read(cx).value().trim().to_lowercase();
+        self.filtered_queries = cx
+            .global::<SavedQueriesState>()
+            .queries
+            .iter()
+            .filter(|query| {
+                self.connection_id
+                    .is_none_or(|id| query.is_visible_for(&id))
+                    && query.matches(&search)
+            })
+            .cloned()
+            .collect();
+        self.list_state = ListState::new(self.filtered_queries.len(), ListAlignment::Top, px(20.));
+        cx.notify();
+    }
+
+    fn on_delete(&mut self, query: SavedQuery, window: &mut Window, cx: &mut Context<Self>) {
+        window.open_dialog(cx, move |dialog, _win, _cx| {
+            let id = query.id;
+            dialog
+                .title("Delete saved query?")
+                .confirm()
+                .child(format!(
+                    "\"{}\" will be removed from the library.",
+                    query.name
+                ))
+                .on_ok(move |_, _window, cx| {
+                    delete_saved_query(id, cx);
+                    true
+                })
+        });
+    }
+
+    /// Adds every `.sql` file of a folder to the library. A file replaces
+    /// the saved query with the same name and connection.
+    fn on_import(&mut self, _: &ClickEvent, window: &mut Window, cx: &mut Context<Self>) {
+        let receiver = cx.prompt_for_paths(PathPromptOptions {
+            files: false,
+            directories: true,
+            multiple: false,
+            prompt: Some("Import".into()),
+        });
+
+        cx.spawn_in(window, async move |_this, cx| {
+            let Ok(Ok(Some(paths))) = receiver.await else {
+                return;
+            };
+            let Some(dir) = paths.into_iter().next() else {
+                return;
+            };
+
+            let result = cx
+                .background_executor()
+                .spawn(async move { import_saved_queries(&dir) })
+                .await;
+
+            let _ = cx.update(|window, cx| match result {
+                Ok(mut imported) => {
+                    let existing = &cx.global::<SavedQueriesState>().queries;
+                    let connections = &cx.global::<ConnectionState>().saved_connections;
+                    for query in &mut imported {
+                        // Scopes to connections that aren't saved here are dropped
+                        if query
+                            .connection_id
+                            .is_some_and(|id| !connections.iter().any(|conn| conn.id == id))
+                        {
+                            query.connection_id = None;
+                        }
+                        if let Some(same) = existing.iter().find(|saved| {
+                            saved.name == query.name && saved.connection_id == query.connection_id
+                        }) {
+                            query.id = same.id;
+                        }
+                    }
+
+                    let info: SharedString = format!("Imported {} queries", imported.len()).into();
+                    save_queries(imported, cx);
+                    window.push_notification((NotificationType::Info, info), cx);
+                }
+                Err(e) => {
+                    tracing::error!("Failed to import saved queries: {}", e);
+                    window.push_notification((NotificationType::Error, "Import failed"), cx);
+                }
+            });
+        })
+        .detach();
+    }
+
+    /// Writes the whole library to a folder, one `.sql` file per query
+    fn on_export(&mut self, _: &ClickEvent, window: &mut Window, cx: &mut Context<Self>) {
+        let queries = cx.global::<SavedQueriesState>().queries.clone();
+        if queries.is_empty() {
+            return;
+        }
+
+        let receiver = cx.prompt_for_paths(PathPromptOptions {
+            files: false,
+            directories: true,
+            multiple: false,
+            prompt: Some("Export".into()),
+        });
+
+        cx.spawn_in(window, async move |_this, cx| {
+            let Ok(Ok(Some(paths))) = receiver.await else {
+                return;
+            };
+            let Some(dir) = paths.into_iter().next() else {
+                return;
+            };
+
+            let result = cx
+                .background_executor()
+                .spawn(async move { export_saved_queries(&queries, &dir) })
+                .await;
+
+            let _ = cx.update(|window, cx| match result {
+                Ok(count) => {
+                    let info: SharedString = format!("Exported {} queries", count).into();
+                    window.push_notification((NotificationType::Info, info), cx);
+                }
+                Err(e) => {
+                    tracing::error!("Failed to export saved queries: {}", e);
+                    window.push_notification((NotificationType::Error, "Export failed"), cx);
+                }
+            });
+        })
+        .detach();
+    }
+
+    fn render_entry(
+        &mut self,
+        ix: usize,
+        _window: &mut Window,
+        cx: &mut Context<Self>,
+    ) -> AnyElement {
+        let Some(query) = self.filtered_queries.get(ix).cloned() else {
+            return div().into_any_element();
+        };
+
+        let sql = query.sql.clone();
+        let deleted = query.clone();
+        let collapsed: String = sql.split_whitespace().collect::<Vec<_>>().join(" ");
+        let preview: String = collapsed.chars().take(60).collect();
+
+        let bg_color = if ix % 2 == 0 {
+            cx.theme().list
+        } else {
+            cx.theme().list_even
+        };
+
+        div()
+            .p_1()
+            .child(
+                div()
+                    .id(("saved-query", ix))
+                    .w_full()
+                    .p_2()
+                    .bg(bg_color)
+                    .border_1()
+                    .border_color(cx.theme().border)
+                    .rounded(cx.theme().radius)
+                    .cursor_pointer()
+                    .hover(|s| {
+                        s.bg(cx.theme().list_active)
+                            .border_color(cx.theme().list_active_border)
+                    })
+                    .on_click(cx.listener(move |_this, _, _window, cx| {
+                        cx.emit(SavedQueryEvent::LoadQuery(sql.clone()));
+                    }))
+                    .child(
+                        v_flex()
+                            .gap_1()
+                            .child(
+                                h_flex()
+                                    .justify_between()
+                                    .items_center()
+                                    .child(
+                                        Label::new(query.name.clone())
+                                            .text_sm()
+                                            .font_medium()
+                                            .line_height(px(18.)),
+                                    )
+                                    .child(
+                                        Button::new(("delete-saved-query", ix))
+                                            .icon(Icon::empty().path("icons/trash.svg"))
+                                            .xsmall()
+                                            .ghost()
+                                            .tooltip("Delete")
+                                            .on_click(cx.listener(move |this, _, window, cx| {
+                                                cx.stop_propagation();
+                                                this.on_delete(deleted.clone(), window, cx);
+                                            })),
+                                    ),
+                            )
+                            .when_some(query.description.clone(), |el, description| {
+                                el.child(
+                                    Label::new(description)
+                                        .text_xs()
+                                        .text_color(cx.theme().muted_foreground),
+                                )
+                            })
+                            .child(
+                                Label::new(preview)
+                                    .text_xs()
+                                    .text_color(cx.theme().muted_foreground),
+                            )
+                            .when(
+                                !query.tags.is_empty() || query.connection_id.is_some(),
+                                |el| {
+                                    el.child(
+                                        h_flex()
+                                            .gap_1()
+                                            .flex_wrap()
+                                            .children(query.tags.iter().map(|tag| {
+                                                div()
+                                                    .px_1()
+                                                    .rounded(cx.theme().radius)
+                                                    .bg(cx.theme().secondary)
+                                                    .text_xs()
+                                                    .child(tag.clone())
+                                            }))
+                                            .when(query.connection_id.is_some(), |el| {
+                                                el.child(
+                                                    Label::new("this connection")
+                                                        .text_xs()
+                                                        .italic()
+                                                        .text_color(cx.theme().muted_foreground),
+                                                )
+                                            }),
+                                    )
+                                },
+                            ),
+                    ),
+            )
+            .into_any_element()
+    }
+}
+
+impl Render for SavedQueriesPanel {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let query_count = self.filtered_queries.len();
+        let library_empty = cx.global::<SavedQueriesState>().queries.is_empty();
+
+        let import_button = Button::new("import-saved-queries")
+            .icon(Icon::empty().path("icons/inbox.svg"))
+            .small()
+            .ghost()
+            .tooltip("Import a folder of .sql files")
+            .on_click(cx.listener(Self::on_import));
+
+        let export_button = Button::new("export-saved-queries")
+            .icon(Icon::empty().path("icons/external-link.svg"))
+            .small()
+            .ghost()
+            .tooltip("Export to a folder of .sql files")
+            .on_click(cx.listener(Self::on_export));
+
+        let header = h_flex()
+            .justify_between()
+            .items_center()
+            .child(Label::new("Saved Queries").font_bold().text_base())
+            .child(h_flex().gap_1().child(import_button).child(export_button));
+
+        let content = if query_count == 0 {
+            div().flex_1().flex().items_center().justify_center().child(
+                Label::new(if library_empty {
+                    "Save a query from the editor to keep it here"
+                } else {
+                    "No matching queries"
+                })
+                .text_sm()
+                .text_color(cx.theme().muted_foreground),
+            )
+        } else {
+            div().flex_1().overflow_hidden().child(
+                list(
+                    self.list_state.clone(),
+                    cx.processor(|this, ix, window, cx| this.render_entry(ix, window, cx)),
+                )
+                .size_full(),
+            )
+        };
+
+        v_flex()
+            .size_full()
+            .gap_2()
+            .p_2()
+            .child(header)
+            .child(Input::new(&self.search).small())
+            .child(content)
+    }
+}
@@ -0,0 +1,97 @@
+use gpui::*;
+use gpui_component::{
+    ActiveTheme as _, Selectable as _, Sizable as _,
+    button::{Button, ButtonVariants as _},
+    h_flex,
+    input::{Input, InputState},
+    v_flex,
+};
+use uuid::Uuid;
+
+use crate::services::storage::SavedQuery;
+
+/// Names a query for the saved queries library
+pub struct SavedQueryForm {
+    name: Entity<InputState>,
+    description: Entity<InputState>,
+    tags: Entity<InputState>,
+    /// Connection the query can be limited to, with its name
+    connection: Option<(Uuid, String)>,
+    connection_only: bool,
+}
+
+impl SavedQueryForm {
+    pub fn view(
+        connection: Option<(Uuid, String)>,
+        window: &mut Window,
+        cx: &mut App,
+    ) -> Entity<Self> {
+        cx.new(|cx| Self {
+            name: cx.new(|cx| InputState::new(window, cx).placeholder("Daily checks")),
+            description: cx.new(|cx| InputState::new(window, cx).placeholder("Optional")),
+            tags: cx.new(|cx| InputState::new(window, cx).placeholder("ops, reports")),
+            connection,
+            connection_only: false,
+        })
+    }
+
+    /// The query to save, or a message when the name is missing
+    pub fn query(&self, sql: &str, cx: &App) -> Result<SavedQuery, String> {
+        let name = self.name.read(cx).value().trim().to_string();
+        if name.is_empty() {
+            return Err("Give the query a name.".to_string());
+        }
+
+        let mut query = SavedQuery::new(name, sql.trim());
+        let description = self.description.read(cx).value().trim().to_string();
+        query.description = (!description.is_empty()).then_some(description);
+        query.tags = self
+            .tags
+            .read(cx)
+            .value()
+            .split(',')
+            .map(str::trim)
+            .filter(|tag| !tag.is_empty())
+            .map(str::to_string)
+            .collect();
+        if self.connection_only {
+            query.connection_id = self.connection.as_ref().map(|(id, _)| *id);
+        }
+        Ok(query)
+    }
+}
+
+impl Render for SavedQueryForm {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let field = |label: &'static str, input: &Entity<InputState>| {
+            h_flex()
+                .gap_2()
+                .items_center()
+                .child(
+                    div()
+                        .w(px(100.))
+                        .text_sm()
+                        .text_color(cx.theme().muted_foreground)
+                        .child(label),
+                )
+                .child(div().flex_1().child(Input::new(input).small()))
+        };
+
+        v_flex()
+            .gap_2()
+            .child(field("Name", &self.name))
+            .child(field("Description", &self.description))
+            .child(field("Tags", &self.tags))
+            .children(self.connection.as_ref().map(|(_, name)| {
+                Button::new("saved-query-connection-only")
+                    .label(format!("Only show for {}", name))
+                    .xsmall()
+                    .ghost()
+                    .selected(self.connection_only)
+                    .on_click(cx.listener(|this, _, _window, cx| {
+                        this.connection_only = !this.connection_only;
+                        cx.notify();
+                    }))
+            }))
+    }
+}
@@ -15,7 +15,7 @@ use gpui_component::{
 
 use crate::{
     services::{ConnectionInfo, DatabaseManager, TableInfo},
-    state::ConnectionState,
+    state::{ConnectionState, SessionId},
 };
 
 pub enum TableEvent {
@@ -27,6 +27,7 @@ impl EventEmitter<TableEvent> for TablesTree {}
 actions!(tables_tree, [SelectItem]);
 
 pub struct TablesTree {
+    session: SessionId,
     tree_state: Entity<TreeState>,
     selected_item: Option<TreeItem>,
     db_manager: Option<DatabaseManager>,
@@ -77,8 +78,8 @@ fn build_tree_items(tables: Vec<TableInfo>) -> Vec<TreeItem> {
 }
 
 impl TablesTree {
-    pub fn view(window: &mut Window, cx: &mut App) -> Entity<Self> {
-        cx.new(|cx| Self::new(window, cx))
+    pub fn view(session: SessionId, window: &mut Window, cx: &mut App) -> Entity<Self> {
+        cx.new(|cx| Self::new(session, window, cx))
     }
 
     fn load_tables(&mut self, cx: &mut Context<Self>) {
@@ -128,16 +129,27 @@ impl TablesTree {
         self.load_tables(cx);
     }
 
-    fn new(window: &mut Window, cx: &mut Context<Self>) -> Self {
+    fn new(session: SessionId, window: &mut Window, cx: &mut Context<Self>) -> Self {
         let tree_state = cx.new(|cx| TreeState::new(cx));
 
         let _subscriptions =
             vec![
                 cx.observe_global_in::<ConnectionState>(window, move |this, _win, cx| {
                     let state = cx.global::<ConnectionState>();
-                    let active_connection = state.active_connection.clone();
+                    let Some(session) = state.session(this.session) else {
+                        return;
+                    };
+                    let active_connection =
+                        session.status.is_open().then(|| session.connection.clone());
+
+                    // Reload only when connecting or switching databases
+                    let database =
+                        |c: &Option<ConnectionInfo>| c.as_ref().map(|c| c.database.clone());
+                    if database(&active_connection) == database(&this.active_connection) {
+                        return;
+                    }
 
-                    this.db_manager = Some(state.db_manager.clone());
+                    this.db_manager = Some(session.db_manager.clone());
                     this.active_connection = active_connection.clone();
                     if active_connection.is_some() {
                         this.load_tables(cx);
@@ -150,6 +162,7 @@ impl TablesTree {
             ];
 
         Self {
+            session,
             tree_state,
             selected_item: None,
             db_manager: None,
@@ -0,0 +1,106 @@
+use gpui::*;
+use gpui_component::{
+    ActiveTheme as _, Sizable as _, h_flex,
+    input::{Input, InputState},
+    v_flex,
+};
+
+use crate::services::QueryTimeouts;
+
+/// Parses a timeout field in milliseconds; empty means "not set"
+pub fn parse_timeout(value: &str, label: &str) -> Result<Option<u64>, String> {
+    let value = value.trim();
+    if value.is_empty() {
+        return Ok(None);
+    }
+    value
+        .parse::<u64>()
+        .map(Some)
+        .map_err(|_| format!("{} must be a number of milliseconds.", label))
+}
+
+const FIELDS: [&str; 3] = [
+    "Statement timeout",
+    "Lock timeout",
+    "Idle in transaction timeout",
+];
+
+/// Edits the limits overriding the connection's for the next runs
+pub struct TimeoutsForm {
+    inputs: [Entity<InputState>; 3],
+}
+
+impl TimeoutsForm {
+    /// Fields start from `current`; empty ones show the connection default
+    pub fn view(
+        current: QueryTimeouts,
+        defaults: QueryTimeouts,
+        window: &mut Window,
+        cx: &mut App,
+    ) -> Entity<Self> {
+        let values = |t: QueryTimeouts| {
+            [
+                t.statement_timeout_ms,
+                t.lock_timeout_ms,
+                t.idle_in_transaction_timeout_ms,
+            ]
+        };
+        let (current, defaults) = (values(current), values(defaults));
+
+        cx.new(|cx| {
+            let inputs = std::array::from_fn(|ix| {
+                let placeholder = match defaults[ix] {
+                    Some(ms) => format!("Connection default ({} ms)", ms),
+                    None => "Connection default".to_string(),
+                };
+                let input = cx.new(|cx| {
+                    InputState::new(window, cx)
+                        .placeholder(placeholder)
+                        .clean_on_escape()
+                });
+                if let Some(ms) = current[ix] {
+                    input.update(cx, |input, cx| input.set_value(ms.to_string(), window, cx));
+                }
+                input
+            });
+
+            Self { inputs }
+        })
+    }
+
+    /// The entered overrides, or a message naming the invalid field
+    pub fn timeouts(&self, cx: &App) -> Result<QueryTimeouts, String> {
+        let parse = |ix: usize| parse_timeout(&self.inputs[ix].read(cx).value(), FIELDS[ix]);
+        Ok(QueryTimeouts {
+            statement_timeout_ms: parse(0)?,
+            lock_timeout_ms: parse(1)?,
+            idle_in_transaction_timeout_ms: parse(2)?,
+        })
+    }
+}
+
+impl Render for TimeoutsForm {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        v_flex()
+            .gap_2()
+            .children(self.inputs.iter().zip(FIELDS).map(|(input, label)| {
+                h_flex()
+                    .gap_2()
+                    .items_center()
+                    .child(
+                        div()
+                            .w(px(180.))
+                            .text_sm()
+                            .text_color(cx.theme().muted_foreground)
+                            .child(format!("{} (ms)", label)),
+                    )
+                    .child(div().flex_1().child(Input::new(input).small()))
+            }))
+            .child(
+                div()
+                    .text_xs()
+                    .text_color(cx.theme().muted_foreground)
+                    .child("0 disables a limit. The idle limit applies to transactions begun while it is set."),
+            )
+    }
+}
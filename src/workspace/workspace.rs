@@ -1,36 +1,26 @@
+use super::connection_tab::ConnectionTab;
 use super::connections::ConnectionManager;
 use super::editor::Editor;
-use super::editor::EditorEvent;
 use super::footer_bar::{FooterBar, FooterBarEvent};
 use super::header_bar::HeaderBar;
-use super::tables::{TableEvent, TablesTree};
 
-use crate::services::AppStore;
-use crate::services::{ErrorResult, QueryExecutionResult, TableInfo};
-use crate::state::{ConnectionState, ConnectionStatus};
+use crate::state::{ConnectionState, ConnectionStatus, SessionId, activate_session, disconnect};
 use crate::workspace::agent::AgentPanel;
 use crate::workspace::agent::AgentPanelEvent;
-use crate::workspace::history::HistoryEvent;
-use crate::workspace::history::HistoryPanel;
-use crate::workspace::results::ResultsPanel;
 use gpui::prelude::FluentBuilder as _;
 use gpui::*;
 
-use gpui_component::ActiveTheme;
-use gpui_component::Root;
-use gpui_component::resizable::{resizable_panel, v_resizable};
-use gpui_component::spinner::Spinner;
+use gpui_component::button::{Button, ButtonVariants as _};
+use gpui_component::{ActiveTheme, IconName, Root, Selectable as _, Sizable as _, h_flex};
 
 pub struct Workspace {
-    connection_state: ConnectionStatus,
     header_bar: Entity<HeaderBar>,
     footer_bar: Entity<FooterBar>,
-    tables_tree: Entity<TablesTree>,
-    editor: Entity<Editor>,
     agent_panel: Entity<AgentPanel>,
-    history_panel: Entity<HistoryPanel>,
     connection_manager: Entity<ConnectionManager>,
-    results_panel: Entity<ResultsPanel>,
+    /// One tab per open session, in the order of `ConnectionState::sessions`
+    tabs: Vec<Entity<ConnectionTab>>,
+    active_session: Option<SessionId>,
     _subscriptions: Vec<Subscription>,
     show_tables: bool,
     show_agent: bool,
@@ -41,25 +31,12 @@ impl Workspace {
     pub fn new(window: &mut Window, cx: &mut Context<Self>) -> Self {
         let header_bar = HeaderBar::view(window, cx);
         let footer_bar = FooterBar::view(window, cx);
-        let tables_tree = TablesTree::view(window, cx);
         let agent_panel = AgentPanel::view(window, cx);
-        let history_panel = HistoryPanel::view(window, cx);
-        let editor = Editor::view(window, cx);
-        let results_panel = ResultsPanel::view(window, cx);
         let connection_manager = ConnectionManager::view(window, cx);
 
         let _subscriptions = vec![
-            cx.observe_global::<ConnectionState>(move |this, cx| {
-                this.connection_state = cx.global::<ConnectionState>().connection_state.clone();
-                cx.notify();
-            }),
-            cx.subscribe(&editor, |this, _, event: &EditorEvent, cx| match event {
-                EditorEvent::ExecuteQuery(query) => {
-                    this.execute_query(query.clone(), cx);
-                }
-            }),
-            cx.subscribe(&tables_tree, |this, _, event: &TableEvent, cx| {
-                this.handle_table_event(event, cx);
+            cx.observe_global_in::<ConnectionState>(window, move |this, window, cx| {
+                this.sync_tabs(window, cx);
             }),
             cx.subscribe(&footer_bar, |this, _, event: &FooterBarEvent, cx| {
                 match event {
@@ -73,26 +50,21 @@ impl Workspace {
                         this.show_history = *show;
                     }
                 }
+                let (show_tables, show_history) = (this.show_tables, this.show_history);
+                for tab in &this.tabs {
+                    tab.update(cx, |tab, cx| tab.set_panels(show_tables, show_history, cx));
+                }
                 cx.notify();
             }),
-            // Subscribe to history panel events
-            cx.subscribe_in(
-                &history_panel,
-                window,
-                |this, _, event: &HistoryEvent, win, cx| match event {
-                    HistoryEvent::LoadQuery(sql) => {
-                        this.load_query_into_editor(sql.clone(), win, cx);
-                    }
-                },
-            ),
             cx.subscribe_in(
                 &agent_panel,
                 window,
                 |this, _, event: &AgentPanelEvent, window, cx| match event {
                     AgentPanelEvent::RunQuery(sql) => {
-                        // Load into editor and execute
-                        this.load_query_into_editor(sql.clone().to_string(), window, cx);
-                        this.execute_query(sql.clone().to_string(), cx);
+                        // Load into the tab in front and execute
+                        if let Some(tab) = this.active_tab(cx) {
+                            tab.update(cx, |tab, cx| tab.run_query(sql.to_string(), window, cx));
+                        }
                     }
                 },
             ),
@@ -101,14 +73,11 @@ impl Workspace {
         Self {
             header_bar,
             footer_bar,
-            connection_manager,
-            tables_tree,
-            editor,
             agent_panel,
-            history_panel,
-            results_panel,
+            connection_manager,
+            tabs: Vec::new(),
+            active_session: None,
             _subscriptions,
-            connection_state: ConnectionStatus::Disconnected,
             show_tables: true,
             show_agent: false,
             show_history: false,
@@ -119,119 +88,45 @@ impl Workspace {
         cx.new(|cx| Self::new(window, cx))
     }
 
-    fn load_query_into_editor(&mut self, sql: String, window: &mut Window, cx: &mut App) {
-        self.editor.update(cx, |editor, cx| {
-            editor.set_query(sql, window, cx);
-        });
-    }
-
-    fn execute_query(&mut self, query: String, cx: &mut Context<Self>) {
-        // Set editor to executing state
-        self.editor.update(cx, |editor, cx| {
-            editor.set_executing(true, cx);
-            cx.notify();
-        });
-
-        tracing::debug!("execute_query");
-
-        // Get database manager from global state
-        let db_manager = cx.global::<ConnectionState>().db_manager.clone();
-        tracing::debug!("execute_query - db_manager");
-        let active_connection = cx.global::<ConnectionState>().active_connection.clone();
-        tracing::debug!("execute_query - active_connection");
-
-        cx.spawn(async move |this, cx| {
-            tracing::debug!("execute_query spawn - before execute_query_enhanced");
-            let result = db_manager.execute_query_enhanced(&query).await;
-            tracing::debug!("execute_query_enhanced result");
-            // Extract execution info before moving result
-            let (execution_time_ms, rows_affected) = match &result {
-                QueryExecutionResult::Modified(modified) => (
-                    Some(modified.execution_time_ms as i64),
-                    Some(modified.rows_affected as i64),
-                ),
-                QueryExecutionResult::Select(r) => (Some(r.execution_time_ms as i64), None),
-                QueryExecutionResult::Error(err) => (Some(err.execution_time_ms as i64), None),
-            };
-
-            this.update(cx, |this, cx| {
-                // Update results panel
-                this.results_panel.update(cx, |results, cx| {
-                    results.update_result(result, cx);
-                });
-
-                // Set editor back to normal state
-                this.editor.update(cx, |editor, cx| {
-                    editor.set_executing(false, cx);
+    /// Opens a tab for each new session and drops the tabs of closed ones
+    fn sync_tabs(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        let state = cx.global::<ConnectionState>();
+        let sessions: Vec<_> = state
+            .sessions
+            .iter()
+            .map(|session| (session.id, session.db_manager.clone()))
+            .collect();
+        self.active_session = state.active_session;
+
+        let mut tabs = Vec::with_capacity(sessions.len());
+        for (session, db_manager) in sessions {
+            let existing = self
+                .tabs
+                .iter()
+                .find(|tab| tab.read(cx).session() == session)
+                .cloned();
+            let tab = existing.unwrap_or_else(|| {
+                let tab = ConnectionTab::view(session, db_manager, window, cx);
+                tab.update(cx, |tab, cx| {
+                    tab.set_panels(self.show_tables, self.show_history, cx)
                 });
-
-                cx.notify();
-            })
-            .ok();
-
-            if let Some(conn) = active_connection {
-                if let Ok(store) = AppStore::singleton().await {
-                    let _ = store
-                        .history()
-                        .record(
-                            &conn.id,
-                            &query.clone(),
-                            execution_time_ms.unwrap_or(0),
-                            rows_affected,
-                            true,
-                            None,
-                        )
-                        .await;
-                }
-            }
-        })
-        .detach();
-    }
-
-    fn handle_table_event(&mut self, event: &TableEvent, cx: &mut Context<Self>) {
-        match event {
-            TableEvent::TableSelected(table) => {
-                self.show_table_columns(table.clone(), cx);
-            }
+                tab
+            });
+            tabs.push(tab);
         }
+        self.tabs = tabs;
+        cx.notify();
     }
 
-    fn show_table_columns(&mut self, table: TableInfo, cx: &mut Context<Self>) {
-        // Get database manager from global state
-        let db_manager = cx.global::<ConnectionState>().db_manager.clone();
-
-        cx.spawn(async move |this, cx| {
-            let result = db_manager
-                .get_table_columns(&table.table_name, &table.table_schema)
-                .await;
-
-            this.update(cx, |this, cx| {
-                match result {
-                    Ok(query_result) => {
-                        this.results_panel.update(cx, |results, cx| {
-                            results.update_result(query_result, cx);
-                        });
-                    }
-                    Err(e) => {
-                        this.results_panel.update(cx, |results, cx| {
-                            results.update_result(
-                                QueryExecutionResult::Error(ErrorResult {
-                                    execution_time_ms: 0,
-                                    message: format!("Failed to load table columns: {}", e),
-                                }),
-                                cx,
-                            );
-                        });
-                    }
-                }
-                cx.notify();
-            })
-            .ok();
-        })
-        .detach();
+    fn active_tab(&self, cx: &App) -> Option<Entity<ConnectionTab>> {
+        let session = self.active_session?;
+        self.tabs
+            .iter()
+            .find(|tab| tab.read(cx).session() == session)
+            .cloned()
     }
 
-    fn render_disconnected(&mut self, cx: &mut Context<Self>) -> Stateful<Div> {
+    fn render_connection_manager(&mut self, cx: &mut Context<Self>) -> Stateful<Div> {
         let content = div()
             .id("connection-manager")
             .flex()
@@ -242,17 +137,70 @@ impl Workspace {
         content
     }
 
-    fn render_connected(&mut self, cx: &mut Context<Self>) -> Stateful<Div> {
-        let sidebar = div()
-            .id("connected-sidebar")
-            .flex()
-            .flex_col()
-            .h_full()
+    /// Connection tabs, titled with the connection name and database, and a
+    /// button to open another connection
+    fn render_tab_strip(&mut self, cx: &mut Context<Self>) -> Stateful<Div> {
+        let state = cx.global::<ConnectionState>();
+
+        h_flex()
+            .id("connection-tabs")
+            .px_2()
+            .py_1()
+            .gap_1()
+            .items_center()
+            .overflow_x_scroll()
+            .border_b_1()
             .border_color(cx.theme().border)
-            .border_r_1()
-            .min_w(px(300.0))
-            .child(self.tables_tree.clone());
+            .children(state.sessions.iter().enumerate().map(|(ix, session)| {
+                let id = session.id;
+                let in_transaction = session.has_open_transaction();
+                let selected = self.active_session == Some(id);
 
+                h_flex()
+                    .items_center()
+                    .child(
+                        Button::new(("connection-tab", ix))
+                            .label(session.title())
+                            .xsmall()
+                            .ghost()
+                            .selected(selected)
+                            .loading(matches!(
+                                session.status,
+                                ConnectionStatus::Connecting
+                                    | ConnectionStatus::Reconnecting { .. }
+                            ))
+                            .on_click(move |_, _win, cx| activate_session(Some(id), cx)),
+                    )
+                    .child(
+                        Button::new(("close-connection-tab", ix))
+                            .icon(IconName::Close)
+                            .xsmall()
+                            .ghost()
+                            .tooltip("Disconnect")
+                            .on_click(move |_, window, cx| {
+                                Editor::confirm_if_in_transaction(
+                                    in_transaction,
+                                    "A transaction is open. Disconnecting will roll it back.",
+                                    window,
+                                    cx,
+                                    move |_window, cx| disconnect(id, cx),
+                                    |_window, _cx| {},
+                                );
+                            }),
+                    )
+            }))
+            .child(
+                Button::new("new-connection-tab")
+                    .icon(IconName::Plus)
+                    .xsmall()
+                    .ghost()
+                    .tooltip("Open another connection")
+                    .selected(self.active_session.is_none())
+                    .on_click(|_, _win, cx| activate_session(None, cx)),
+            )
+    }
+
+    fn render_connected(&mut self, cx: &mut Context<Self>) -> Stateful<Div> {
         let agent = div()
             .id("connected-agent")
             .flex()
@@ -263,82 +211,36 @@ impl Workspace {
             .border_l_1()
             .child(self.agent_panel.clone());
 
-        let history = div()
-            .id("connected-history")
-            .flex()
-            .flex_col()
-            .h_full()
-            .w(px(400.))
-            .border_color(cx.theme().border)
-            .border_l_1()
-            .child(self.history_panel.clone());
+        let content = match self.active_tab(cx) {
+            Some(tab) => div()
+                .id("connected-content")
+                .flex()
+                .flex_row()
+                .flex_1()
+                .h_full()
+                .bg(cx.theme().background)
+                .child(div().flex().flex_1().h_full().child(tab))
+                .when(self.show_agent, |d| d.child(agent)),
+            None => self.render_connection_manager(cx),
+        };
 
-        let main = div()
-            .id("connected-main")
+        div()
+            .id("connected-tabs")
             .flex()
             .flex_col()
             .flex_1()
-            .h_full()
-            .w_full()
             .overflow_hidden()
-            .child(
-                v_resizable("resizable-results")
-                    .child(
-                        resizable_panel()
-                            .size(px(400.))
-                            .size_range(px(200.)..px(800.))
-                            .child(self.editor.clone()),
-                    )
-                    .child(
-                        resizable_panel()
-                            .size(px(200.))
-                            .child(self.results_panel.clone()),
-                    ),
-            );
-
-        let content = div()
-            .id("connected-content")
-            .flex()
-            .flex_row()
-            .flex_1()
-            .h_full()
-            .bg(cx.theme().background)
-            .when(self.show_tables.clone(), |d| d.child(sidebar))
-            .child(main)
-            .when(self.show_agent.clone(), |d| d.child(agent))
-            .when(self.show_history.clone(), |d| d.child(history));
-
-        content
-    }
-
-    fn render_loading(&mut self, cx: &mut Context<Self>) -> Stateful<Div> {
-        let content = div()
-            .id("loading-content")
-            .flex()
-            .flex_grow()
-            .bg(cx.theme().background)
-            .justify_center()
-            .items_center()
-            .child(
-                div()
-                    .flex()
-                    .flex_col()
-                    .items_center()
-                    .child(Spinner::new())
-                    .child("Loading"),
-            );
-
-        content
+            .child(self.render_tab_strip(cx))
+            .child(content)
     }
 }
 
 impl Render for Workspace {
     fn render(&mut self, window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
-        let content = match self.connection_state.clone() {
-            ConnectionStatus::Disconnected => self.render_disconnected(cx),
-            ConnectionStatus::Connected => self.render_connected(cx),
-            ConnectionStatus::Disconnecting => self.render_loading(cx),
-            ConnectionStatus::Connecting => self.render_loading(cx),
+        let content = if self.tabs.is_empty() {
+            self.render_connection_manager(cx)
+        } else {
+            self.render_connected(cx)
         };
 
         div()